@@ -0,0 +1,87 @@
+//! Allocations per PING and per SET, counted by a global allocator that wraps the system one.
+//! Requests and their arguments reuse the connection's storage, so a pipelined flood should
+//! settle at a small, constant number of allocations per command.
+//!
+//! ```sh
+//! cargo bench --bench allocations
+//! ```
+
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const BATCH: usize = 1_000;
+const WARMUP: usize = 10;
+const BATCHES: usize = 100;
+
+/// Counts every allocation and reallocation, on any thread.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+// SAFETY: Every call is forwarded to the system allocator unchanged.
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        // SAFETY: The caller upholds `GlobalAlloc::alloc`'s contract.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: The caller upholds `GlobalAlloc::dealloc`'s contract.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        // SAFETY: The caller upholds `GlobalAlloc::realloc`'s contract.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn main() {
+    common::run(async |server| {
+        let mut local = common::stream(&server, 1 << 16);
+
+        // Requests are written and replies read as raw bytes into buffers made up front, so that
+        // every allocation counted below is the server's.
+        let tests: [(&str, &[u8], &[u8]); 2] = [
+            ("ping", b"*1\r\n$4\r\nPING\r\n", b"+PONG\r\n"),
+            (
+                "set",
+                b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n",
+                b"+OK\r\n",
+            ),
+        ];
+        for (name, request, reply) in tests {
+            let requests = request.repeat(BATCH);
+            let mut replies = vec![0; reply.len() * BATCH];
+
+            let mut allocations = 0;
+            for batch in 0..WARMUP + BATCHES {
+                let before = ALLOCATIONS.load(Relaxed);
+                local.write_all(&requests).await.unwrap();
+                local.read_exact(&mut replies).await.unwrap();
+                if batch >= WARMUP {
+                    allocations += ALLOCATIONS.load(Relaxed) - before;
+                }
+                assert!(replies.chunks(reply.len()).all(|chunk| chunk == reply));
+            }
+
+            let commands = BATCH * BATCHES;
+            #[allow(clippy::cast_precision_loss)]
+            let per_command = allocations as f64 / commands as f64;
+            println!(
+                "{name}: {allocations} allocations in {commands} commands \
+                 ({per_command:.2} per command)"
+            );
+        }
+    });
+}
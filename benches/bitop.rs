@@ -0,0 +1,68 @@
+//! BITOP over two 1MB strings, which are combined 16 bytes at a time rather than byte by byte.
+//! Each command is followed by the same operation done in process one byte at a time, which is
+//! the floor a byte loop would set before any of the server's own costs.
+//!
+//! ```sh
+//! cargo bench --bench bitop
+//! ```
+
+mod common;
+
+use common::Connection;
+use std::{hint::black_box, time::Instant};
+
+const SIZE: usize = 1 << 20;
+const BATCH: usize = 10;
+const BATCHES: usize = 20;
+
+/// The byte loop each command is compared against.
+type ByteOp = fn(u8, u8) -> u8;
+
+fn main() {
+    common::run(async |server| {
+        let mut connection = Connection::new(&server, 1 << 16);
+
+        // The bytes vary so that no operation is trivial.
+        let [a, b] = [7, 13].map(|seed| -> Vec<u8> {
+            (0..SIZE)
+                .map(|i| u8::try_from(i * seed % 251).unwrap())
+                .collect()
+        });
+        for (key, value) in [(&b"a"[..], &a), (b"b", &b)] {
+            connection.send_args(&[b"set", key, value]).await;
+            connection.reply().await;
+        }
+
+        let ops: [(&str, ByteOp); 6] = [
+            ("bitop and d a b", |a, b| a & b),
+            ("bitop or d a b", |a, b| a | b),
+            ("bitop xor d a b", |a, b| a ^ b),
+            ("bitop not d a", |a, _| !a),
+            ("bitop diff d a b", |a, b| a & !b),
+            // With two keys, ONE is the same as XOR.
+            ("bitop one d a b", |a, b| a ^ b),
+        ];
+        for (command, op) in ops {
+            let elapsed = connection.time(command, BATCH, BATCHES).await;
+            let commands = BATCH * BATCHES;
+            let rate = common::rate(commands, elapsed);
+            let throughput = common::rate(commands * SIZE, elapsed) / f64::from(1 << 30);
+            println!(
+                "{command}: {commands} commands in {elapsed:?} ({rate:.0} commands/sec, \
+                 {throughput:.2} GiB/sec of output)"
+            );
+
+            let mut result = vec![0; SIZE];
+            let start = Instant::now();
+            for _ in 0..commands {
+                for (result, (a, b)) in result.iter_mut().zip(a.iter().zip(&b)) {
+                    *result = op(black_box(*a), *b);
+                }
+                black_box(&mut result);
+            }
+            let elapsed = start.elapsed();
+            let throughput = common::rate(commands * SIZE, elapsed) / f64::from(1 << 30);
+            println!("  byte loop: {elapsed:?} ({throughput:.2} GiB/sec)");
+        }
+    });
+}
@@ -0,0 +1,122 @@
+//! Setup and reporting shared by the benches, which drive the server through in-process streams
+//! so that the numbers aren't dominated by TCP. Each bench uses a different part of this.
+#![allow(dead_code)]
+
+use bradis::Server;
+use respite::{RespConfig, RespReader, RespValue, RespWriter};
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tokio::io::{DuplexStream, ReadHalf, WriteHalf, duplex, split};
+
+pub type Reader = RespReader<ReadHalf<DuplexStream>>;
+pub type Writer = RespWriter<WriteHalf<DuplexStream>>;
+
+/// Start a server on a new runtime and run `f` with it to completion.
+pub fn run<F: Future>(f: impl FnOnce(Server) -> F) -> F::Output {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(async { Server::default() });
+    runtime.block_on(f(server))
+}
+
+/// Connect to `server` with an in-process stream that buffers up to `capacity` bytes each way.
+pub fn stream(server: &Server, capacity: usize) -> DuplexStream {
+    let (local, remote) = duplex(capacity);
+    server.connect(remote, None);
+    local
+}
+
+/// The first argument passed to the bench, or `default` without one.
+pub fn arg<T: FromStr>(default: T, name: &str) -> T {
+    std::env::args()
+        .skip(1)
+        .find(|arg| arg != "--bench")
+        .map_or(default, |arg| {
+            arg.parse()
+                .unwrap_or_else(|_| panic!("invalid number of {name}"))
+        })
+}
+
+/// How many of something happened per second.
+pub fn rate(count: usize, elapsed: Duration) -> f64 {
+    #[allow(clippy::cast_precision_loss)]
+    let count = count as f64;
+    count / elapsed.as_secs_f64()
+}
+
+/// Print how long `count` commands took, and how many that is per second.
+pub fn report(name: impl Display, count: usize, elapsed: Duration) {
+    let rate = rate(count, elapsed);
+    println!("{name}: {count} commands in {elapsed:?} ({rate:.0} commands/sec)");
+}
+
+/// A client connected to the server.
+pub struct Connection {
+    reader: Reader,
+    writer: Writer,
+}
+
+impl Connection {
+    pub fn new(server: &Server, capacity: usize) -> Self {
+        let (reader, writer) = split(stream(server, capacity));
+        Connection {
+            reader: RespReader::new(reader, RespConfig::default()),
+            writer: RespWriter::new(writer),
+        }
+    }
+
+    /// Send an inline command without waiting for its reply.
+    pub async fn send(&mut self, command: impl AsRef<[u8]>) {
+        self.writer.write_inline(command.as_ref()).await.unwrap();
+    }
+
+    /// Send a command as an array, for arguments that are too large or varied to be inline.
+    pub async fn send_args<A: AsRef<[u8]>>(&mut self, args: &[A]) {
+        self.writer.write_array(args.len()).await.unwrap();
+        for arg in args {
+            self.writer.write_blob_string(arg.as_ref()).await.unwrap();
+        }
+    }
+
+    /// Read the next reply.
+    pub async fn reply(&mut self) -> RespValue {
+        self.reader.value().await.unwrap().unwrap()
+    }
+
+    /// Send an inline command and read its reply.
+    pub async fn run(&mut self, command: impl AsRef<[u8]>) -> RespValue {
+        self.send(command).await;
+        self.reply().await
+    }
+
+    /// Send `command` `count` times before reading any of the replies.
+    pub async fn pipeline(&mut self, command: impl AsRef<[u8]>, count: usize) {
+        for _ in 0..count {
+            self.send(&command).await;
+        }
+        for _ in 0..count {
+            self.reply().await;
+        }
+    }
+
+    /// Send `command` in `batches` pipelines of `batch`, and return how long it took.
+    pub async fn time(
+        &mut self,
+        command: impl AsRef<[u8]>,
+        batch: usize,
+        batches: usize,
+    ) -> Duration {
+        let start = Instant::now();
+        for _ in 0..batches {
+            self.pipeline(&command, batch).await;
+        }
+        start.elapsed()
+    }
+
+    /// Split the connection, for a client that reads in a separate task.
+    pub fn split(self) -> (Reader, Writer) {
+        (self.reader, self.writer)
+    }
+}
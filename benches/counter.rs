@@ -0,0 +1,43 @@
+//! A hot counter workload: many clients pipelining INCR against a handful of keys, with GET,
+//! GETRANGE, and APPEND mixed in so that counters keep their integer representation.
+//!
+//! ```sh
+//! cargo bench --bench counter
+//! ```
+
+mod common;
+
+use common::Connection;
+use std::time::Instant;
+
+const CLIENTS: usize = 4;
+const KEYS: usize = 8;
+const BATCH: usize = 1_000;
+const BATCHES: usize = 50;
+
+async fn client(mut connection: Connection, index: usize) {
+    for batch in 0..BATCHES {
+        for i in 0..BATCH {
+            let key = format!("counter:{}", (index + i) % KEYS);
+            let command = match i % 100 {
+                0 => format!("get {key}"),
+                1 => format!("getrange {key} 0 -1"),
+                2 if batch == 0 => format!("append {key} 1"),
+                _ => format!("incr {key}"),
+            };
+            connection.send(command).await;
+        }
+        for _ in 0..BATCH {
+            connection.reply().await;
+        }
+    }
+}
+
+fn main() {
+    common::run(async |server| {
+        let start = Instant::now();
+        let clients = (0..CLIENTS).map(|index| client(Connection::new(&server, 1 << 16), index));
+        futures::future::join_all(clients).await;
+        common::report("incr", CLIENTS * BATCH * BATCHES, start.elapsed());
+    });
+}
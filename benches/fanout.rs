@@ -0,0 +1,51 @@
+//! PUBLISH to a channel with many subscribers. Each message is built once and shared by every
+//! subscriber's reply queue, so the cost of a publish is one send per subscriber.
+//!
+//! ```sh
+//! cargo bench --bench fanout
+//! cargo bench --bench fanout -- 1000
+//! ```
+
+mod common;
+
+use common::Connection;
+use std::time::Instant;
+
+const SUBSCRIBERS: usize = 100;
+const MESSAGES: usize = 10_000;
+const BATCH: usize = 1_000;
+
+fn main() {
+    let subscribers = common::arg(SUBSCRIBERS, "subscribers");
+
+    common::run(async |server| {
+        // Each subscriber reads every message, so the time includes delivering them all.
+        let mut tasks = Vec::with_capacity(subscribers);
+        for _ in 0..subscribers {
+            let mut subscriber = Connection::new(&server, 1 << 16);
+            subscriber.run("subscribe channel").await;
+            tasks.push(tokio::spawn(async move {
+                // Keep the connection open until every message is read.
+                for _ in 0..MESSAGES {
+                    subscriber.reply().await;
+                }
+            }));
+        }
+
+        let mut connection = Connection::new(&server, 1 << 16);
+        let published = connection
+            .time("publish channel hello", BATCH, MESSAGES / BATCH)
+            .await;
+        let start = Instant::now();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let delivered = published + start.elapsed();
+
+        let rate = common::rate(MESSAGES * subscribers, delivered);
+        println!(
+            "{MESSAGES} messages to {subscribers} subscribers: published in {published:?}, \
+             delivered in {delivered:?} ({rate:.0} deliveries/sec)"
+        );
+    });
+}
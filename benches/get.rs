@@ -0,0 +1,36 @@
+//! GET and GETRANGE of a 1MB string, which are written to the client straight from the stored
+//! bytes without copying them into a reply first.
+//!
+//! ```sh
+//! cargo bench --bench get
+//! ```
+
+mod common;
+
+use common::Connection;
+
+const SIZE: usize = 1 << 20;
+const BATCH: usize = 100;
+const BATCHES: usize = 20;
+
+fn main() {
+    common::run(async |server| {
+        let mut connection = Connection::new(&server, 1 << 20);
+
+        // Too large for an inline command.
+        let value = vec![b'x'; SIZE];
+        connection.send_args(&[&b"set"[..], b"s", &value]).await;
+        connection.reply().await;
+
+        for command in ["get s", "getrange s 1 -2"] {
+            let elapsed = connection.time(command, BATCH, BATCHES).await;
+            let commands = BATCH * BATCHES;
+            let rate = common::rate(commands, elapsed);
+            let throughput = common::rate(commands * SIZE, elapsed) / f64::from(1 << 30);
+            println!(
+                "{command}: {commands} commands in {elapsed:?} ({rate:.0} commands/sec, \
+                 {throughput:.2} GiB/sec)"
+            );
+        }
+    });
+}
@@ -0,0 +1,61 @@
+//! HGET, HSET, and HDEL on a 128-field hash, which is still small enough to be stored as a
+//! listpack. Larger listpack hashes keep a sorted index of their fields, so lookups binary search
+//! instead of comparing every field in turn.
+//!
+//! ```sh
+//! cargo bench --bench hget
+//! ```
+
+mod common;
+
+use common::Connection;
+use std::time::Instant;
+
+const FIELDS: usize = 128;
+const BATCH: usize = 1_000;
+const BATCHES: usize = 50;
+
+/// The commands to send for a field.
+type Workload = fn(&str) -> Vec<String>;
+
+fn main() {
+    common::run(async |server| {
+        let mut connection = Connection::new(&server, 1 << 16);
+
+        let fields: Vec<String> = (0..FIELDS).map(|i| format!("field:{i:03}")).collect();
+        let pairs: Vec<String> = fields.iter().map(|field| format!("{field} 1")).collect();
+        connection.run(format!("hset h {}", pairs.join(" "))).await;
+
+        let workloads: [(&str, Workload); 3] = [
+            ("hget", |field| vec![format!("hget h {field}")]),
+            ("hset", |field| vec![format!("hset h {field} 2")]),
+            ("hdel + hset", |field| {
+                vec![format!("hdel h {field}"), format!("hset h {field} 1")]
+            }),
+        ];
+
+        for (name, commands) in workloads {
+            let start = Instant::now();
+            let mut count: usize = 0;
+            for batch in 0..BATCHES {
+                let mut pending = 0;
+                for i in 0..BATCH {
+                    // Walk the fields with a stride so that lookups land all over the hash.
+                    let field = &fields[(batch * BATCH + i * 37) % FIELDS];
+                    for command in commands(field) {
+                        connection.send(command).await;
+                        pending += 1;
+                    }
+                }
+                for _ in 0..pending {
+                    connection.reply().await;
+                }
+                count += pending;
+            }
+            common::report(name, count, start.elapsed());
+        }
+
+        let encoding = connection.run("object encoding h").await;
+        println!("encoding: {encoding:?}");
+    });
+}
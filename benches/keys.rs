@@ -0,0 +1,57 @@
+//! KEYS with glob patterns over 1M keys, where each pattern is compiled once and then matched
+//! against every key.
+//!
+//! ```sh
+//! cargo bench --bench keys
+//! cargo bench --bench keys -- 100000
+//! ```
+
+mod common;
+
+use common::Connection;
+use respite::RespValue;
+use std::time::Instant;
+
+const KEYS: usize = 1_000_000;
+const BATCH: usize = 1000;
+const RUNS: usize = 10;
+
+fn main() {
+    let keys = common::arg(KEYS, "keys");
+
+    common::run(async |server| {
+        let mut connection = Connection::new(&server, 1 << 20);
+
+        for batch in (0..keys).step_by(BATCH) {
+            let end = keys.min(batch + BATCH);
+            for key in batch..end {
+                connection.send(format!("set user:{key}:name x")).await;
+            }
+            for _ in batch..end {
+                connection.reply().await;
+            }
+        }
+
+        // Each pattern matches only a few keys, so the time is spent matching rather than
+        // replying.
+        for pattern in [
+            "user:12345:*",
+            "user:*99:name",
+            "*:4?2?1:*",
+            "user:[0-4]*7[89]:name",
+            "*nomatch*",
+            "USER:*",
+        ] {
+            let command = format!("keys {pattern}");
+            let mut matches = 0;
+            let start = Instant::now();
+            for _ in 0..RUNS {
+                if let RespValue::Array(values) = connection.run(&command).await {
+                    matches = values.len();
+                }
+            }
+            let elapsed = start.elapsed() / u32::try_from(RUNS).unwrap();
+            println!("keys {pattern}: {matches} of {keys} keys in {elapsed:?}");
+        }
+    });
+}
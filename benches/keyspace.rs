@@ -0,0 +1,58 @@
+//! Latency while the keyspace, a set, and a sorted set each grow to 10M entries. Entries are
+//! written in pipelined batches and the time for each batch is recorded, so a stall while a table
+//! grows shows up as a slow batch.
+//!
+//! ```sh
+//! cargo bench --bench keyspace
+//! cargo bench --bench keyspace -- 1000000
+//! ```
+
+mod common;
+
+use common::Connection;
+use std::time::{Duration, Instant};
+
+const KEYS: usize = 10_000_000;
+const BATCH: usize = 1000;
+
+/// The command that adds entry `i`.
+type Workload = fn(usize) -> String;
+
+fn main() {
+    let keys = common::arg(KEYS, "keys");
+
+    common::run(async |server| {
+        let mut connection = Connection::new(&server, 1 << 20);
+
+        let workloads: [(&str, Workload); 3] = [
+            ("set", |i| format!("set key:{i} x")),
+            ("sadd", |i| format!("sadd set member:{i}")),
+            ("zadd", |i| format!("zadd zset {i} member:{i}")),
+        ];
+        for (name, command) in workloads {
+            let mut latencies = Vec::with_capacity(keys.div_ceil(BATCH));
+            let start = Instant::now();
+            for batch in (0..keys).step_by(BATCH) {
+                let batch_start = Instant::now();
+                let end = keys.min(batch + BATCH);
+                for i in batch..end {
+                    connection.send(command(i)).await;
+                }
+                for _ in batch..end {
+                    connection.reply().await;
+                }
+                latencies.push(batch_start.elapsed());
+            }
+            common::report(name, keys, start.elapsed());
+
+            latencies.sort_unstable();
+            let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+            let max = latencies.last().copied().unwrap_or(Duration::ZERO);
+            println!(
+                "  batches of {BATCH}: p50 {:?}, p99 {:?}, max {max:?}",
+                percentile(50),
+                percentile(99),
+            );
+        }
+    });
+}
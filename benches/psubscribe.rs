@@ -0,0 +1,45 @@
+//! PUBLISH with 10K pattern subscriptions, which are bucketed by literal prefix so that each
+//! message is only tested against the patterns that could match its channel.
+//!
+//! ```sh
+//! cargo bench --bench psubscribe
+//! cargo bench --bench psubscribe -- 100000
+//! ```
+
+mod common;
+
+use common::Connection;
+
+const PATTERNS: usize = 10_000;
+const BATCH: usize = 1_000;
+const BATCHES: usize = 20;
+
+fn main() {
+    let patterns = common::arg(PATTERNS, "patterns");
+
+    common::run(async |server| {
+        // Subscribe to every pattern, then throw away the messages they receive.
+        let mut subscriber = Connection::new(&server, 1 << 20);
+        for index in 0..patterns {
+            subscriber.send(format!("psubscribe news.{index}.*")).await;
+        }
+        for _ in 0..patterns {
+            subscriber.reply().await;
+        }
+        let (mut reader, _writer) = subscriber.split();
+        tokio::spawn(async move { while let Ok(Some(_)) = reader.value().await {} });
+
+        let mut connection = Connection::new(&server, 1 << 20);
+
+        // One channel that matches a single pattern, and one that matches none.
+        for channel in ["news.42.sport", "weather.today"] {
+            let command = format!("publish {channel} hello");
+            let elapsed = connection.time(&command, BATCH, BATCHES).await;
+            common::report(
+                format!("{command} with {patterns} patterns"),
+                BATCH * BATCHES,
+                elapsed,
+            );
+        }
+    });
+}
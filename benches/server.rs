@@ -0,0 +1,220 @@
+//! A small `redis-benchmark` for the hot paths of common commands, driving the server through
+//! in-process streams so that the numbers aren't dominated by TCP. Each test runs `-n` requests
+//! spread over `-c` clients, sending `-P` requests at a time.
+//!
+//! ```sh
+//! cargo bench --bench server
+//! cargo bench --bench server -- -c 8 -n 200000 -P 16 -d 64 -r 1000 -t set,get
+//! ```
+
+mod common;
+
+use bradis::Server;
+use common::Connection;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::time::{Duration, Instant};
+
+const TESTS: [&str; 7] = ["set", "get", "exists", "type", "lpush", "zadd", "publish"];
+
+const USAGE: &str = "\
+Usage: cargo bench --bench server -- [options]
+
+  -c <clients>    Number of parallel clients (default 50)
+  -n <requests>   Total number of requests for each test (default 100000)
+  -P <pipeline>   Requests to send at a time (default 1)
+  -d <size>       Size of values in bytes (default 3)
+  -r <keyspace>   Use random keys from 0 to keyspace - 1 (default 0, a single key)
+  -t <tests>      Comma separated tests to run (default set,get,exists,type,lpush,zadd,publish)";
+
+#[derive(Clone, Debug)]
+struct Options {
+    clients: usize,
+    requests: usize,
+    pipeline: usize,
+    size: usize,
+    keyspace: usize,
+    tests: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            clients: 50,
+            requests: 100_000,
+            pipeline: 1,
+            size: 3,
+            keyspace: 0,
+            tests: TESTS.iter().map(|test| (*test).to_owned()).collect(),
+        }
+    }
+}
+
+impl Options {
+    fn parse() -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            // Cargo passes `--bench` to benchmarks without a harness.
+            if arg == "--bench" {
+                continue;
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {arg}"))?;
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid value for {arg}: {value}"))
+            };
+
+            match &arg[..] {
+                "-c" => options.clients = number()?.max(1),
+                "-n" => options.requests = number()?,
+                "-P" => options.pipeline = number()?.max(1),
+                "-d" => options.size = number()?,
+                "-r" => options.keyspace = number()?,
+                "-t" => {
+                    options.tests = value.split(',').map(str::to_lowercase).collect();
+                    if let Some(test) = options.tests.iter().find(|t| !TESTS.contains(&&t[..])) {
+                        return Err(format!("unknown test: {test}"));
+                    }
+                }
+                _ => return Err(format!("unknown option: {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// The key for the next request, with a random suffix when there's a keyspace.
+    fn key(&self, prefix: &str, rng: &mut impl Rng) -> String {
+        let suffix = match self.keyspace {
+            0 => 0,
+            keyspace => rng.gen_range(0..keyspace),
+        };
+        format!("{prefix}:{suffix:012}")
+    }
+
+    /// The arguments for the next request in `test`.
+    fn command(&self, test: &str, value: &[u8], rng: &mut impl Rng) -> Vec<Vec<u8>> {
+        let arg = |s: &str| s.as_bytes().to_vec();
+        match test {
+            "set" => vec![arg("set"), arg(&self.key("key", rng)), value.to_vec()],
+            "get" => vec![arg("get"), arg(&self.key("key", rng))],
+            "exists" => vec![arg("exists"), arg(&self.key("key", rng))],
+            "type" => vec![arg("type"), arg(&self.key("key", rng))],
+            "lpush" => vec![arg("lpush"), arg("mylist"), value.to_vec()],
+            "zadd" => {
+                let score = rng.gen_range(0..1_000_000).to_string();
+                vec![
+                    arg("zadd"),
+                    arg("myzset"),
+                    arg(&score),
+                    arg(&self.key("element", rng)),
+                ]
+            }
+            "publish" => vec![arg("publish"), arg("channel"), value.to_vec()],
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Send `requests` requests for `test`, returning the latency of each pipeline.
+async fn client(
+    mut connection: Connection,
+    options: Options,
+    test: String,
+    requests: usize,
+) -> Vec<Duration> {
+    let value = vec![b'x'; options.size];
+    let mut rng = StdRng::from_entropy();
+    let mut latencies = Vec::with_capacity(requests / options.pipeline + 1);
+
+    let mut remaining = requests;
+    while remaining > 0 {
+        let count = remaining.min(options.pipeline);
+        let start = Instant::now();
+        for _ in 0..count {
+            let command = options.command(&test, &value, &mut rng);
+            connection.send_args(&command).await;
+        }
+        for _ in 0..count {
+            connection.reply().await;
+        }
+        latencies.push(start.elapsed());
+        remaining -= count;
+    }
+
+    latencies
+}
+
+/// Subscribe to the channel for the `publish` test, so that each message is delivered, and
+/// throw away everything the server sends.
+async fn subscriber(server: &Server) {
+    let mut connection = Connection::new(server, 1 << 16);
+    connection.run("subscribe channel").await;
+    let (mut reader, _writer) = connection.split();
+
+    tokio::spawn(async move { while let Ok(Some(_)) = reader.value().await {} });
+}
+
+/// Return the latency at a percentile of sorted latencies.
+fn percentile(latencies: &[Duration], percentile: usize) -> Duration {
+    let index = (latencies.len() * percentile / 100).min(latencies.len() - 1);
+    latencies[index]
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    common::run(async |server| {
+        for test in &options.tests {
+            if test == "publish" {
+                subscriber(&server).await;
+            }
+
+            let start = Instant::now();
+            let mut tasks = Vec::with_capacity(options.clients);
+            for index in 0..options.clients {
+                // Spread any remainder over the first few clients.
+                let requests = options.requests / options.clients
+                    + usize::from(index < options.requests % options.clients);
+                let connection = Connection::new(&server, 1 << 16);
+                let task = client(connection, options.clone(), test.clone(), requests);
+                tasks.push(tokio::spawn(task));
+            }
+
+            let mut latencies = Vec::new();
+            for task in tasks {
+                latencies.extend(task.await.unwrap());
+            }
+            let elapsed = start.elapsed();
+            latencies.sort_unstable();
+
+            let rate = common::rate(options.requests, elapsed);
+            println!("====== {} ======", test.to_uppercase());
+            println!("  {} requests completed in {elapsed:.2?}", options.requests);
+            println!(
+                "  {} parallel clients, pipeline {}, {} bytes payload",
+                options.clients, options.pipeline, options.size
+            );
+            if !latencies.is_empty() {
+                println!(
+                    "  latency p50 {:.2?}, p99 {:.2?}, max {:.2?}",
+                    percentile(&latencies, 50),
+                    percentile(&latencies, 99),
+                    latencies[latencies.len() - 1],
+                );
+            }
+            println!("  {rate:.0} requests per second\n");
+        }
+    });
+}
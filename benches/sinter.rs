@@ -0,0 +1,34 @@
+//! SINTER and SINTERCARD on two 128-element int sets, which are intersected in order without
+//! converting either one or looking up any values.
+//!
+//! ```sh
+//! cargo bench --bench sinter
+//! ```
+
+mod common;
+
+use common::Connection;
+
+const MEMBERS: usize = 128;
+const BATCH: usize = 1_000;
+const BATCHES: usize = 50;
+
+fn main() {
+    common::run(async |server| {
+        let mut connection = Connection::new(&server, 1 << 16);
+
+        // Half of the members of `a` are also in `b`.
+        let a: Vec<String> = (0..MEMBERS).map(|i| (i * 2).to_string()).collect();
+        let b: Vec<String> = (0..MEMBERS).map(|i| (i * 4).to_string()).collect();
+        for (key, members) in [("a", a), ("b", b)] {
+            connection
+                .run(format!("sadd {key} {}", members.join(" ")))
+                .await;
+        }
+
+        for command in ["sinter a b", "sintercard 2 a b", "sintercard 2 a b limit 8"] {
+            let elapsed = connection.time(command, BATCH, BATCHES).await;
+            common::report(command, BATCH * BATCHES, elapsed);
+        }
+    });
+}
@@ -0,0 +1,252 @@
+use std::{env, fmt::Write, fs, path::Path};
+
+/// The command table, relative to the manifest.
+const TABLE: &str = "src/command/commands.txt";
+
+/// Every command flag, in the order they're listed by `COMMAND INFO`.
+const FLAGS: [&str; 7] = [
+    "write", "readonly", "admin", "pubsub", "noscript", "blocking", "fast",
+];
+
+/// Every ACL category, in the order Redis lists them.
+const CATEGORIES: [&str; 21] = [
+    "@keyspace",
+    "@read",
+    "@write",
+    "@set",
+    "@sortedset",
+    "@list",
+    "@hash",
+    "@string",
+    "@bitmap",
+    "@hyperloglog",
+    "@geo",
+    "@stream",
+    "@pubsub",
+    "@admin",
+    "@fast",
+    "@slow",
+    "@blocking",
+    "@dangerous",
+    "@connection",
+    "@transaction",
+    "@scripting",
+];
+
+/// One line of the command table.
+struct Row<'a> {
+    name: &'a str,
+    run: &'a str,
+    arity: String,
+    keys: Option<String>,
+    flags: Vec<&'a str>,
+    categories: Vec<&'a str>,
+}
+
+impl Row<'_> {
+    fn has(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// The name of the generated static, like `CLIENT_KILL` for `client|kill`.
+    fn ident(&self) -> String {
+        self.name.to_uppercase().replace(['|', '-'], "_")
+    }
+
+    /// The ACL categories of this command, including those implied by its flags.
+    fn categories(&self) -> Vec<&str> {
+        let implied = [
+            ("readonly", "@read"),
+            ("write", "@write"),
+            ("admin", "@admin"),
+            ("admin", "@dangerous"),
+            ("pubsub", "@pubsub"),
+            ("fast", "@fast"),
+            ("blocking", "@blocking"),
+        ];
+        let mut categories = self.categories.clone();
+        for (flag, category) in implied {
+            if self.has(flag) {
+                categories.push(category);
+            }
+        }
+        if !self.has("fast") {
+            categories.push("@slow");
+        }
+        CATEGORIES
+            .into_iter()
+            .filter(|category| categories.contains(category))
+            .collect()
+    }
+}
+
+/// Generate the command statics, `CommandKind`, `ALL`, and perfect hashes for looking up commands
+/// and subcommands by name from the command table, so that each command is only described once.
+fn main() {
+    println!("cargo::rerun-if-changed={TABLE}");
+
+    let table = fs::read_to_string(TABLE).expect("read the command table");
+    let rows: Vec<Row> = table
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| parse(line).unwrap_or_else(|e| panic!("{TABLE}:{}: {e}", index + 1)))
+        .collect();
+
+    let (subcommands, commands): (Vec<&Row>, Vec<&Row>) =
+        rows.iter().partition(|row| row.name.contains('|'));
+
+    let mut out = String::new();
+
+    out.push_str("#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]\npub enum CommandKind {\n");
+    for row in &commands {
+        writeln!(out, "    {},", variant(row.name)).unwrap();
+    }
+    out.push_str("    Unknown,\n}\n\n");
+
+    for row in &subcommands {
+        writeln!(out, "static {}: Subcommand = Subcommand {{", row.ident()).unwrap();
+        writeln!(out, "    name: {:?},", row.name).unwrap();
+        writeln!(out, "    arity: {},", row.arity).unwrap();
+        writeln!(out, "    run: {},", row.run).unwrap();
+        let keys = row.keys.as_deref().unwrap_or("Keys::None");
+        writeln!(out, "    keys: {keys},").unwrap();
+        out.push_str("};\n\n");
+    }
+
+    for row in &commands {
+        let keys = row.keys.as_deref().unwrap_or_default();
+        let prefix = format!("{}|", row.name);
+        let children: Vec<String> = subcommands
+            .iter()
+            .filter(|subcommand| subcommand.name.starts_with(&prefix))
+            .map(|subcommand| format!("&{}", subcommand.ident()))
+            .collect();
+
+        writeln!(out, "pub static {}: Command = Command {{", row.ident()).unwrap();
+        writeln!(out, "    kind: CommandKind::{},", variant(row.name)).unwrap();
+        writeln!(out, "    name: {:?},", row.name).unwrap();
+        writeln!(out, "    arity: {},", row.arity).unwrap();
+        writeln!(out, "    run: {},", row.run).unwrap();
+        writeln!(out, "    keys: {keys},").unwrap();
+        for flag in FLAGS {
+            writeln!(out, "    {flag}: {},", row.has(flag)).unwrap();
+        }
+        writeln!(out, "    categories: &{:?},", row.categories()).unwrap();
+        writeln!(out, "    subcommands: &[{}],", children.join(", ")).unwrap();
+        out.push_str("};\n\n");
+    }
+
+    writeln!(out, "pub static ALL: [&Command; {}] = [", commands.len()).unwrap();
+    for row in &commands {
+        writeln!(out, "    &{},", row.ident()).unwrap();
+    }
+    out.push_str("];\n\n");
+
+    let longest = rows.iter().map(|row| row.name.len()).max().unwrap_or(0);
+    writeln!(out, "const MAX_NAME_LEN: usize = {longest};\n").unwrap();
+
+    let mut map = phf_codegen::Map::new();
+    for row in &commands {
+        map.entry(row.name.as_bytes(), &format!("&{}", row.ident()));
+    }
+    writeln!(
+        out,
+        "#[allow(clippy::unreadable_literal)]\nstatic COMMANDS: phf::Map<&'static [u8], &'static Command> = {};\n",
+        map.build()
+    )
+    .unwrap();
+
+    let mut map = phf_codegen::Map::new();
+    for row in &subcommands {
+        map.entry(row.name.as_bytes(), &format!("&{}", row.ident()));
+    }
+    writeln!(
+        out,
+        "#[allow(clippy::unreadable_literal)]\nstatic SUBCOMMANDS: phf::Map<&'static [u8], &'static Subcommand> = {};",
+        map.build()
+    )
+    .unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("commands.rs");
+    fs::write(path, out).expect("write the generated commands");
+}
+
+fn parse(line: &str) -> Result<Row<'_>, String> {
+    let mut columns = line.split_whitespace();
+    let name = columns.next().ok_or("missing name")?;
+    let run = columns.next().ok_or("missing run")?;
+    let arity = arity(columns.next().ok_or("missing arity")?)?;
+    let keys = columns.next();
+    let (categories, flags): (Vec<&str>, Vec<&str>) =
+        columns.partition(|column| column.starts_with('@'));
+
+    if name != name.to_lowercase() {
+        return Err(format!("{name} isn't lowercase"));
+    }
+    if name.contains('|') && !(flags.is_empty() && categories.is_empty()) {
+        return Err(format!("subcommand {name} has flags"));
+    }
+    if !name.contains('|') && keys.is_none() {
+        return Err(format!("command {name} is missing keys"));
+    }
+    if let Some(flag) = flags.iter().find(|flag| !FLAGS.contains(flag)) {
+        return Err(format!("unknown flag {flag}"));
+    }
+    if let Some(category) = categories.iter().find(|c| !CATEGORIES.contains(c)) {
+        return Err(format!("unknown category {category}"));
+    }
+    let keys = keys.map(self::keys).transpose()?;
+
+    Ok(Row {
+        name,
+        run,
+        arity,
+        keys,
+        flags,
+        categories,
+    })
+}
+
+/// The `CommandKind` variant for a command name, like `Bitfieldro` for `bitfield_ro`.
+fn variant(name: &str) -> String {
+    let name = name.replace('_', "");
+    name[..1].to_uppercase() + &name[1..]
+}
+
+fn arity(arity: &str) -> Result<String, String> {
+    let invalid = || format!("invalid arity {arity}");
+    if let Some((min, max)) = arity.split_once("..=") {
+        let min: u8 = min.parse().map_err(|_| invalid())?;
+        let max: u8 = max.parse().map_err(|_| invalid())?;
+        if min >= max {
+            return Err(invalid());
+        }
+        return Ok(format!("Arity::Range({min}, {max})"));
+    }
+    let arity: i8 = arity.parse().map_err(|_| invalid())?;
+    if arity < 0 {
+        Ok(format!("Arity::Minimum({})", arity.unsigned_abs()))
+    } else {
+        Ok(format!("Arity::Exact({arity})"))
+    }
+}
+
+fn keys(keys: &str) -> Result<String, String> {
+    if let Some(index) = keys.strip_prefix("argument:") {
+        let index: usize = index.parse().map_err(|_| format!("invalid keys {keys}"))?;
+        return Ok(format!("Keys::Argument({index})"));
+    }
+    let keys = match keys {
+        "all" => "All",
+        "double" => "Double",
+        "odd" => "Odd",
+        "none" => "None",
+        "second" => "Second",
+        "single" => "Single",
+        "skip-one" => "SkipOne",
+        "trailing" => "Trailing",
+        _ => return Err(format!("unknown keys {keys}")),
+    };
+    Ok(format!("Keys::{keys}"))
+}
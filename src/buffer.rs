@@ -10,7 +10,9 @@ pub use array::ArrayBuffer;
 /// `STRLEN`). In these cases, we can convert the value to bytes on the stack or a shared
 /// allocation.
 pub trait Buffer {
-    /// Write an f64 and return the written slice.
+    /// Write an f64 and return the written slice. Like the strings written by `INCRBYFLOAT` in
+    /// Redis, this is the shortest representation that round trips, without an exponent or
+    /// trailing zeros. Double replies are formatted with [`crate::reply::DoubleOutput`] instead.
     fn write_f64(&mut self, value: f64) -> &[u8];
 
     /// Write an i64 and return the written slice.
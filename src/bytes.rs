@@ -64,6 +64,36 @@ pub fn parse_i64_exact(item: &[u8]) -> Option<i64> {
     }
 }
 
+/// Parse an f64 the way Redis does with `strtod`. This means no surrounding space, no NaN, and
+/// no values that overflow to infinity or underflow to zero.
+pub fn parse_f64(bytes: &[u8]) -> Option<f64> {
+    if bytes.first().is_some_and(u8::is_ascii_whitespace) {
+        return None;
+    }
+
+    let value = parse_f64_bound(bytes)?;
+
+    // Only a literal "inf" or "infinity" may produce an infinite value.
+    if value.is_infinite() && !bytes.iter().any(|b| b.eq_ignore_ascii_case(&b'i')) {
+        return None;
+    }
+
+    // A non-zero mantissa can't produce zero.
+    let mantissa = bytes.split(|b| b.eq_ignore_ascii_case(&b'e')).next()?;
+    if value == 0.0 && mantissa.iter().any(|b| matches!(b, b'1'..=b'9')) {
+        return None;
+    }
+
+    Some(value)
+}
+
+/// Parse one end of a score range the way Redis does, with a bare `strtod`. Leading space is
+/// skipped and values out of range become infinite or zero, but NaN can't be compared and is
+/// never allowed. Like [`parse_f64`], `inf` and `infinity` may have any case and a sign.
+pub fn parse_f64_bound(bytes: &[u8]) -> Option<f64> {
+    parse(bytes.trim_ascii_start()).filter(|value: &f64| !value.is_nan())
+}
+
 /// An output wrapper for an arbitrary byte sequence. Printable ASCII characters are output
 /// directly and all others are escaped.
 pub struct Output<'a>(pub &'a [u8]);
@@ -137,6 +167,56 @@ mod tests {
         assert_eq!(parse_i64_exact(&buf), None);
     }
 
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn parse_f64_success() {
+        assert_eq!(parse_f64(b"0"), Some(0.0));
+        assert_eq!(parse_f64(b"-0.0"), Some(-0.0));
+        assert_eq!(parse_f64(b"1.5"), Some(1.5));
+        assert_eq!(parse_f64(b"+1.5"), Some(1.5));
+        assert_eq!(parse_f64(b".5"), Some(0.5));
+        assert_eq!(parse_f64(b"1e3"), Some(1000.0));
+        assert_eq!(parse_f64(b"0e500"), Some(0.0));
+        assert_eq!(parse_f64(b"inf"), Some(f64::INFINITY));
+        assert_eq!(parse_f64(b"-Infinity"), Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn parse_f64_failure() {
+        assert_eq!(parse_f64(b""), None);
+        assert_eq!(parse_f64(b" 1"), None);
+        assert_eq!(parse_f64(b"1 "), None);
+        assert_eq!(parse_f64(b"1x"), None);
+        assert_eq!(parse_f64(b"nan"), None);
+        assert_eq!(parse_f64(b"-NaN"), None);
+        assert_eq!(parse_f64(b"1e500"), None);
+        assert_eq!(parse_f64(b"-1e500"), None);
+        assert_eq!(parse_f64(b"1e-500"), None);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn parse_f64_bound_success() {
+        assert_eq!(parse_f64_bound(b"1.5"), Some(1.5));
+        assert_eq!(parse_f64_bound(b" 1"), Some(1.0));
+        assert_eq!(parse_f64_bound(b"+inf"), Some(f64::INFINITY));
+        assert_eq!(parse_f64_bound(b"-INF"), Some(f64::NEG_INFINITY));
+        assert_eq!(parse_f64_bound(b"+Infinity"), Some(f64::INFINITY));
+        assert_eq!(parse_f64_bound(b"1e500"), Some(f64::INFINITY));
+        assert_eq!(parse_f64_bound(b"-1e500"), Some(f64::NEG_INFINITY));
+        assert_eq!(parse_f64_bound(b"1e-500"), Some(0.0));
+    }
+
+    #[test]
+    fn parse_f64_bound_failure() {
+        assert_eq!(parse_f64_bound(b""), None);
+        assert_eq!(parse_f64_bound(b"1 "), None);
+        assert_eq!(parse_f64_bound(b"1x"), None);
+        assert_eq!(parse_f64_bound(b"nan"), None);
+        assert_eq!(parse_f64_bound(b"-NaN"), None);
+        assert_eq!(parse_f64_bound(b"+in"), None);
+    }
+
     #[test]
     fn lex_exact_bytes() {
         #[derive(Logos)]
@@ -1,29 +1,37 @@
 mod addr;
 mod id;
 mod info;
+mod reader;
 mod replier;
 mod reply_message;
+mod reply_sender;
 
-pub use addr::Addr;
+pub use addr::{Addr, Endpoint};
 pub use id::ClientId;
 pub use info::ClientInfo;
+pub use reader::RequestReader;
 pub use replier::Replier;
 pub use reply_message::ReplyMessage;
+pub use reply_sender::{ReplyReceiver, ReplySender};
 
 use crate::{
     BlockResult, BulkReply, Command, DBIndex, Reply, ReplyError, Store, StoreMessage, StringValue,
-    TaskHandle, epoch, request::Request,
+    TaskHandle, epoch,
+    request::Request,
+    store::{Connections, StoreSender},
 };
 use bytes::Bytes;
-use respite::{RespConfig, RespReader, RespRequest, RespVersion};
+use respite::{RespConfig, RespRequest, RespVersion};
 use std::{
     collections::VecDeque,
     io::Write,
+    panic::{self, AssertUnwindSafe},
     ptr,
     sync::{
         Mutex,
-        atomic::{AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicU64, AtomicUsize, Ordering},
     },
+    time::Duration,
 };
 use tokio::{
     io::{AsyncRead, AsyncWrite},
@@ -34,10 +42,7 @@ use tokio::{
     },
 };
 use triomphe::Arc;
-use web_time::{Duration, Instant};
-
-#[cfg(feature = "tokio-runtime")]
-use tokio::task::JoinHandle;
+use web_time::Instant;
 
 pub enum Argument {
     Push(Bytes),
@@ -53,30 +58,10 @@ pub enum ReplyMode {
     /// Send no replies.
     Off,
 
-    /// Skip the next reply, then turn them back on.
+    /// Skip the reply to the current command, then turn them back on.
     Skip,
 }
 
-/// The current timeout task
-#[derive(Debug)]
-#[cfg(feature = "tokio-runtime")]
-struct Timeout {
-    /// Has this timeout been canceled?
-    canceled: Arc<AtomicBool>,
-
-    /// The task for sending a timeout message.
-    task: JoinHandle<()>,
-}
-
-#[cfg(feature = "tokio-runtime")]
-impl Timeout {
-    /// Abort the task and mark this timeout as canceled to skip an existing message.
-    fn cancel(&mut self) {
-        self.canceled.store(true, Ordering::Relaxed);
-        self.task.abort();
-    }
-}
-
 /// The transaction state of a client.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Tx {
@@ -123,10 +108,10 @@ pub struct Client {
     pub name: Option<StringValue>,
 
     /// A channel for sending messages to the store
-    store_sender: mpsc::UnboundedSender<StoreMessage>,
+    store_sender: StoreSender,
 
     /// A channel for sending replies
-    pub reply_sender: mpsc::UnboundedSender<ReplyMessage>,
+    pub reply_sender: ReplySender,
 
     /// Current transaction status
     tx: Tx,
@@ -140,8 +125,14 @@ pub struct Client {
     /// A queue of commands to be executed with EXEC
     pub queue: VecDeque<Argument>,
 
+    /// The size of the current request's arguments, shared with the store
+    argv_mem: Arc<AtomicUsize>,
+
+    /// The size of the arguments queued for EXEC, shared with the store
+    multi_mem: Arc<AtomicUsize>,
+
     /// Are we currently running a script?
-    scripting: bool,
+    pub scripting: bool,
 
     /// A buffer for storing script replies during a command
     pub scripting_reply: VecDeque<Reply>,
@@ -155,35 +146,38 @@ pub struct Client {
     /// The current reply mode
     reply_mode: ReplyMode,
 
+    /// Skip replies to the next command, after `CLIENT REPLY SKIP`
+    skip_next: bool,
+
     /// Current multi state, shared with the store
     multi: Arc<AtomicIsize>,
 
     /// Current protocol version, shared with the store
     resp: Arc<AtomicU8>,
 
-    /// The number of subscribed channels, shared with the store
-    pub subscribers: Arc<AtomicUsize>,
-
-    /// The number of subscribed patterns, shared with the store
-    pub psubscribers: Arc<AtomicUsize>,
-
     /// The last command run by the client, shared with the store
     last_command: Arc<AtomicPtr<Command>>,
 
+    /// The instant the client was created
+    created_at: Instant,
+
+    /// Milliseconds from `created_at` to the start of the last command, shared with the store
+    last_interaction: Arc<AtomicU64>,
+
+    /// Connection settings, shared with the store
+    connections: Arc<Connections>,
+
     /// The reader task
     reader_task: TaskHandle<()>,
-
-    #[cfg(feature = "tokio-runtime")]
-    /// The current timeout
-    timeout: Option<Timeout>,
 }
 
 impl Client {
     /// Create a new client and wait for input
     pub fn spawn<S: AsyncRead + AsyncWrite + Send + 'static>(
         stream: S,
-        store_sender: mpsc::UnboundedSender<StoreMessage>,
+        store_sender: StoreSender,
         config: RespConfig,
+        connections: Arc<Connections>,
         addr: Option<Addr>,
     ) {
         // Set up various channels
@@ -193,50 +187,69 @@ impl Client {
         let quit_sender = Arc::new(Mutex::new(Some(quit_sender)));
 
         // Spawn the reader
-        let mut reader = RespReader::new(reader, config);
-        let reader_task = crate::spawn_with_handle(async move {
-            reader
-                .requests(|request| {
-                    _ = request_sender.send(request);
-                })
-                .await;
-        });
+        //
+        // TODO: Stream large bulks for APPEND and SET into the destination string. RespReader
+        // buffers each argument in full before the request is sent, and the argument shares the
+        // reader's buffer, so a value near the blob limit is held twice while it's stored. That
+        // needs a reader that yields partial arguments.
+        let id = ClientId::next();
+        let mut reader = RequestReader::new(reader, config.clone());
+        let reader_task =
+            crate::spawn_with_handle(format_args!("client {id} reader"), async move {
+                reader
+                    .requests(|request| {
+                        _ = request_sender.send(request);
+                    })
+                    .await;
+            });
 
         // Spawn the replier
-        let reply_sender = Replier::spawn(writer, quit_sender.clone());
+        let (reply_sender, reply_receiver) = reply_sender::channel(connections.clone());
+        let (evict_sender, evict_receiver) = oneshot::channel();
+        Replier::spawn(
+            id,
+            writer,
+            config,
+            quit_sender.clone(),
+            reply_receiver,
+            evict_receiver,
+        );
 
         // Create shared info state
         let db = Arc::new(AtomicUsize::new(0));
-        let id = ClientId::next();
         let multi = Arc::new(AtomicIsize::new(-1));
-        let subscribers = Arc::new(AtomicUsize::new(0));
-        let psubscribers = Arc::new(AtomicUsize::new(0));
         let last_command = Arc::new(AtomicPtr::new(ptr::null_mut()));
+        let created_at = Instant::now();
+        let last_interaction = Arc::new(AtomicU64::new(0));
         let protocol = RespVersion::V2;
         let resp = Arc::new(AtomicU8::new(protocol.into()));
         let monitor = Arc::new(AtomicBool::new(false));
         let blocking = Arc::new(AtomicBool::new(false));
+        let argv_mem = Arc::new(AtomicUsize::new(0));
+        let multi_mem = Arc::new(AtomicUsize::new(0));
 
         // Create an info instance
         let info = ClientInfo {
-            addr,
+            addr: addr.clone(),
             blocking: blocking.clone(),
             id,
             quit_sender,
+            evict_sender: Some(evict_sender),
             reply_sender: reply_sender.clone(),
             name: None,
             db: db.clone(),
-            created_at: Instant::now(),
+            created_at,
+            last_interaction: last_interaction.clone(),
             multi: multi.clone(),
-            subscribers: subscribers.clone(),
-            psubscribers: psubscribers.clone(),
             last_command: last_command.clone(),
             resp: resp.clone(),
             monitor: monitor.clone(),
+            argv_mem: argv_mem.clone(),
+            multi_mem: multi_mem.clone(),
         };
 
         // Notify the store about the connection
-        let message = StoreMessage::Connect(info);
+        let message = StoreMessage::Connect(Box::new(info));
         _ = store_sender.send(message);
 
         // Create the client
@@ -256,40 +269,61 @@ impl Client {
             in_exec: false,
             request: Request::default(),
             queue: VecDeque::new(),
+            argv_mem,
+            multi_mem,
             scripting: false,
             scripting_reply: VecDeque::new(),
             pubsub: false,
             protocol,
             reply_mode: ReplyMode::On,
-            subscribers,
-            psubscribers,
+            skip_next: false,
             last_command,
+            created_at,
+            last_interaction,
+            connections,
             resp,
             monitor,
             reader_task,
-            #[cfg(feature = "tokio-runtime")]
-            timeout: None,
         };
 
         // Wait for the first request
         client.wait();
     }
 
-    /// Set a transaction error and clear any queued requests.
+    /// Set a transaction error and clear any queued requests. Errors from commands run by EXEC
+    /// itself leave the rest of the queue alone.
     fn error(&mut self) {
         if let Tx::Some(len) = self.tx {
             self.set_tx(Tx::Error(len));
+            self.clear_queue();
         }
-        self.queue.clear();
     }
 
     /// Discard the current multi transaction
     pub fn discard(&mut self, store: &mut Store) {
         self.set_tx(Tx::None);
-        self.queue.clear();
+        self.clear_queue();
         store.unwatch(self.id);
     }
 
+    /// Clear the commands queued for EXEC.
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+        self.multi_mem.store(0, Ordering::Relaxed);
+    }
+
+    /// Add an argument to the current request.
+    fn push_argument(&mut self, argument: Bytes) {
+        self.request.push_back(argument);
+        self.argv_mem.store(self.request.size(), Ordering::Relaxed);
+    }
+
+    /// Clear the current request to free space in the request buffer.
+    pub fn clear_request(&mut self) {
+        self.request.clear();
+        self.argv_mem.store(0, Ordering::Relaxed);
+    }
+
     /// Get the currently selected database index.
     pub fn db(&self) -> DBIndex {
         DBIndex(self.db.load(Ordering::Relaxed))
@@ -311,13 +345,32 @@ impl Client {
         self.monitor.store(monitor, Ordering::Relaxed);
     }
 
-    /// Set the current reply mode and notify the replier
+    /// Set the current reply mode and notify the replier. Any pending skip is dropped.
     pub fn set_reply_mode(&mut self, reply_mode: ReplyMode) {
         if self.reply_mode != reply_mode {
             let message = ReplyMessage::On(reply_mode == ReplyMode::On);
-            _ = self.reply_sender.send(message);
+            self.reply_sender.send(message);
         }
         self.reply_mode = reply_mode;
+        self.skip_next = false;
+    }
+
+    /// Skip the replies to the next command, unless replies are already off.
+    pub fn skip_next_reply(&mut self) {
+        if self.reply_mode != ReplyMode::Off {
+            self.skip_next = true;
+        }
+    }
+
+    /// Update the reply mode once a command is finished, including one that was blocked and has
+    /// just been woken or timed out. A skipped command turns replies back on, and a skip
+    /// requested during the command applies to the next one.
+    pub fn finish_reply_mode(&mut self) {
+        if self.skip_next {
+            self.set_reply_mode(ReplyMode::Skip);
+        } else if self.reply_mode == ReplyMode::Skip {
+            self.set_reply_mode(ReplyMode::On);
+        }
     }
 
     /// Get the current transaction state.
@@ -342,7 +395,12 @@ impl Client {
     pub fn set_protocol(&mut self, version: RespVersion) {
         self.protocol = version;
         self.resp.store(version.into(), Ordering::Relaxed);
-        _ = self.reply_sender.send(version.into());
+        self.reply_sender.send(version);
+    }
+
+    /// The current protocol version
+    pub fn protocol(&self) -> RespVersion {
+        self.protocol
     }
 
     /// Is the client currently using the Resp3 protocol?
@@ -360,7 +418,7 @@ impl Client {
         if !self.is_quitting() {
             self.quit_receiver.close();
             // No more replies after quitting.
-            _ = self.reply_sender.send(ReplyMessage::Quit);
+            self.reply_sender.send(ReplyMessage::Quit);
         }
     }
 
@@ -380,7 +438,7 @@ impl Client {
         if self.scripting {
             self.scripting_reply.push_back(reply.into());
         } else {
-            _ = self.reply_sender.send(reply.into().into());
+            self.reply_sender.send(reply.into());
         }
     }
 
@@ -405,7 +463,19 @@ impl Client {
         let (sender, receiver) = oneshot::channel();
         self.reply(Reply::DeferredArray(receiver));
         let count = iter.map(|reply| self.reply(reply)).count();
-        _ = sender.send(count);
+        _ = sender.send(count.into());
+    }
+
+    /// Send a set reply for an iterator without an exact size.
+    pub fn deferred_set<I, T>(&mut self, iter: I)
+    where
+        T: Into<Reply>,
+        I: Iterator<Item = T>,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.reply(Reply::DeferredSet(receiver));
+        let count = iter.map(|reply| self.reply(reply)).count();
+        _ = sender.send(count.into());
     }
 
     /// Send a map reply for an iterator without an exact size.
@@ -423,7 +493,7 @@ impl Client {
                 self.reply(v);
             })
             .count();
-        _ = sender.send(count);
+        _ = sender.send(count.into());
     }
 
     /// Send a bulk reply.
@@ -449,12 +519,13 @@ impl Client {
 
     /// Run the currently loaded request, and then clear it to free space in the request buffer.
     pub fn run(&mut self, store: &mut Store) -> Option<BlockResult> {
-        // If the client is in SKIP mode when we begin, turn it off afterward.
-        let skipped = self.reply_mode == ReplyMode::Skip;
-
-        // Store the last command.
+        // Store the last command and when it ran.
         let command = std::ptr::from_ref(self.request.command).cast_mut();
         self.last_command.store(command, Ordering::Relaxed);
+        let millis = self.created_at.elapsed().as_millis();
+        let millis = u64::try_from(millis).unwrap_or(u64::MAX);
+        self.last_interaction.store(millis, Ordering::Relaxed);
+        self.argv_mem.store(self.request.size(), Ordering::Relaxed);
 
         let block = 'run: {
             if !self.request.is_valid() {
@@ -463,6 +534,12 @@ impl Client {
                 break 'run None;
             }
 
+            // A busy script can only be interrupted by a few commands, other than its own.
+            if store.busy && !self.scripting && !self.request.command.busy_allowed() {
+                self.reply(ReplyError::Busy);
+                break 'run None;
+            }
+
             if self.monitor() && !self.request.command.monitor_allowed() {
                 self.reply(ReplyError::Replica);
                 break 'run None;
@@ -487,6 +564,8 @@ impl Client {
                     // Queue the request and tell the client about it.
                     Tx::Some(count) => {
                         self.set_tx(Tx::Some(count + 1));
+                        self.multi_mem
+                            .fetch_add(self.request.size(), Ordering::Relaxed);
                         for argument in self.request.drain() {
                             self.queue.push_back(Argument::Push(argument));
                         }
@@ -499,13 +578,29 @@ impl Client {
                 }
             }
 
-            let block = match (self.request.command.run)(self, store) {
+            // A panic leaves a partial reply and possibly a half finished write, so the command
+            // fails and the client is disconnected rather than taking the whole server down.
+            let command = self.request.command;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| (command.run)(self, store)));
+            let panicked = result.is_err();
+            let result = result.unwrap_or_else(|payload| {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown panic");
+                tracing::error!(command = command.name, "command panicked: {message}");
+                Err(ReplyError::Panic(command).into())
+            });
+
+            let block = match result {
                 // The command has already replied.
                 Ok(block) => block,
 
                 // The command returned an actual error, so we should clear any queued requests and set
                 // a transaction error before replying.
                 Err(Reply::Error(reply)) => {
+                    store.count_error(&reply);
                     self.error();
                     self.reply(reply);
                     None
@@ -518,19 +613,33 @@ impl Client {
                 }
             };
 
+            if panicked {
+                self.quit();
+            }
+
             self.notify_monitors(store);
 
             store.numcommands += 1;
 
-            block
+            // A transaction or script can't wait, so every blocking command falls back to its
+            // timeout reply.
+            match block {
+                Some(block) if self.in_exec || self.scripting => {
+                    self.reply(block.timeout_reply);
+                    None
+                }
+                block => block,
+            }
         };
 
         if block.is_none() {
-            self.request.clear();
-        }
+            self.clear_request();
 
-        if skipped {
-            self.set_reply_mode(ReplyMode::On);
+            // Commands run by EXEC or a script are part of the one that ran them, and a blocked
+            // command isn't finished until it's woken or times out.
+            if !self.in_exec && !self.scripting {
+                self.finish_reply_mode();
+            }
         }
 
         block
@@ -543,19 +652,22 @@ impl Client {
     /// * The timeout for a blocking operation expires.
     /// * Receive a request or error from the arguments task.
     pub fn wait(self) {
-        crate::spawn(self.wait_inner());
+        let id = self.id;
+        crate::spawn(format_args!("client {id}"), self.wait_inner());
     }
 
     #[doc(hidden)]
     async fn wait_inner(mut self) {
         loop {
+            let timeout = self.idle_timeout();
             select! {
                 _ = &mut self.quit_receiver => break,
+                () = idle(timeout) => break,
                 message = self.requests.recv() => {
                     match message {
                         Some(RespRequest::Argument(argument)) => {
                             // Push arguments until the request is complete.
-                            self.request.push_back(argument);
+                            self.push_argument(argument);
                         }
                         Some(message) => {
                             // Buffer this message for the store.
@@ -572,48 +684,26 @@ impl Client {
         }
     }
 
-    #[cfg(not(feature = "tokio-runtime"))]
-    /// Mark this client as blocked and spawn a timeout if necessary.
-    pub fn block(&mut self, _timeout: Duration) {
-        self.blocking.store(true, Ordering::Relaxed);
+    /// How long this client can wait for input before it's disconnected. Subscribers and
+    /// monitors wait forever, and blocked clients aren't waiting for input at all.
+    fn idle_timeout(&self) -> Option<Duration> {
+        let timeout = self.connections.timeout();
+        if timeout.is_zero() || self.pubsub || self.monitor.load(Ordering::Relaxed) {
+            None
+        } else {
+            Some(timeout)
+        }
     }
 
-    #[cfg(feature = "tokio-runtime")]
-    /// Mark this client as blocked and spawn a timeout if necessary.
-    pub fn block(&mut self, timeout: Duration) {
+    /// Mark this client as blocked. The store is responsible for any timeout.
+    pub fn block(&mut self) {
         self.blocking.store(true, Ordering::Relaxed);
-
-        if timeout.is_zero() {
-            self.timeout = None;
-            return;
-        }
-
-        let id = self.id;
-        let sleep = tokio::time::sleep(timeout);
-        let store_sender = self.store_sender.clone();
-
-        // Use a shared value to ensure that a timeout message is from the most recent blocking
-        // operation.
-        let canceled = Arc::new(AtomicBool::new(false));
-
-        self.timeout = Some(Timeout {
-            canceled: canceled.clone(),
-            task: tokio::spawn(async move {
-                sleep.await;
-                let message = StoreMessage::Timeout(id, canceled);
-                _ = store_sender.send(message);
-            }),
-        });
     }
 
-    // Mark this client unblocked and cancel the timeout.
+    // Mark this client unblocked.
     pub fn unblock(&mut self) {
-        self.request.clear();
+        self.clear_request();
         self.blocking.store(false, Ordering::Relaxed);
-        #[cfg(feature = "tokio-runtime")]
-        if let Some(mut timeout) = self.timeout.take() {
-            timeout.cancel();
-        }
     }
 
     /// Process all requests from the queue and then wait.
@@ -626,7 +716,7 @@ impl Client {
             use RespRequest::*;
             match message {
                 Argument(argument) => {
-                    self.request.push_back(argument);
+                    self.push_argument(argument);
                 }
                 End => {
                     if let Some(block) = self.run(store) {
@@ -665,11 +755,13 @@ impl Client {
         let mut buffer = Vec::new();
         _ = write!(buffer, "{:.6}", epoch().as_secs_f64());
 
-        // TODO: Unix sockets…
         if self.scripting {
             _ = write!(buffer, " [{} lua]", self.db());
-        } else if let Some(addr) = self.addr {
-            _ = write!(buffer, " [{} {}]", self.db(), addr.peer);
+        } else if let Some(addr) = &self.addr {
+            match &addr.peer {
+                Endpoint::Tcp(peer) => _ = write!(buffer, " [{} {peer}]", self.db()),
+                Endpoint::Unix(path) => _ = write!(buffer, " [{} unix:{path}]", self.db()),
+            }
         }
 
         _ = write!(buffer, " {}", self.request);
@@ -681,6 +773,16 @@ impl Client {
     }
 }
 
+/// Wait out an idle timeout, or forever if there isn't one. Idle clients are only disconnected
+/// with a tokio runtime.
+async fn idle(timeout: Option<Duration>) {
+    match timeout {
+        #[cfg(feature = "tokio-runtime")]
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        _ => std::future::pending().await,
+    }
+}
+
 impl Drop for Client {
     /// Send messages to stop the reader and clean up store resources.
     fn drop(&mut self) {
@@ -1,7 +1,89 @@
-use std::net::SocketAddr;
+use std::{fmt, net::SocketAddr, str::from_utf8};
+use triomphe::Arc;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// The addresses of both ends of a connection. The local end identifies the listener that
+/// accepted it, so clients can be listed and killed per listener.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Addr {
-    pub local: SocketAddr,
-    pub peer: SocketAddr,
+    pub local: Endpoint,
+    pub peer: Endpoint,
+}
+
+/// One end of a connection.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Endpoint {
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+
+    /// The path of a unix socket. Both ends of a unix socket connection use the path that the
+    /// listener is bound to.
+    Unix(Arc<str>),
+}
+
+impl Endpoint {
+    /// Is this a unix socket or a loopback address?
+    #[must_use]
+    pub fn is_local(&self) -> bool {
+        match self {
+            Endpoint::Tcp(addr) => addr.ip().is_loopback(),
+            Endpoint::Unix(_) => true,
+        }
+    }
+
+    /// Parse an endpoint as it's formatted in `CLIENT LIST`, either `ip:port` or `path:0`.
+    #[must_use]
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        let value = from_utf8(value).ok()?;
+        if let Ok(addr) = value.parse() {
+            return Some(Endpoint::Tcp(addr));
+        }
+        let path = value.strip_suffix(":0").filter(|path| !path.is_empty())?;
+        Some(Endpoint::Unix(path.into()))
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{addr}"),
+            Endpoint::Unix(path) => write!(f, "{path}:0"),
+        }
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(value: SocketAddr) -> Self {
+        Endpoint::Tcp(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let tcp = Endpoint::parse(b"127.0.0.1:6379");
+        assert_eq!(tcp, Some(Endpoint::Tcp("127.0.0.1:6379".parse().unwrap())));
+        let unix = Endpoint::parse(b"/tmp/redis.sock:0");
+        assert_eq!(unix, Some(Endpoint::Unix("/tmp/redis.sock".into())));
+        assert_eq!(Endpoint::parse(b"/tmp/redis.sock"), None);
+        assert_eq!(Endpoint::parse(b":0"), None);
+    }
+
+    #[test]
+    fn display() {
+        let unix = Endpoint::Unix("/tmp/redis.sock".into());
+        assert_eq!(unix.to_string(), "/tmp/redis.sock:0");
+        let tcp = Endpoint::Tcp("[::1]:6379".parse().unwrap());
+        assert_eq!(tcp.to_string(), "[::1]:6379");
+    }
+
+    #[test]
+    fn is_local() {
+        assert!(Endpoint::parse(b"127.0.0.1:6379").unwrap().is_local());
+        assert!(Endpoint::parse(b"[::1]:6379").unwrap().is_local());
+        assert!(Endpoint::parse(b"/tmp/redis.sock:0").unwrap().is_local());
+        assert!(!Endpoint::parse(b"10.0.0.1:6379").unwrap().is_local());
+    }
 }
@@ -1,15 +1,18 @@
-use crate::{ClientId, Command, Reply, ReplyMessage, Store, StringValue, client::Addr};
+use crate::{
+    ClientId, Command, Reply, ReplyMessage, Store, StringValue,
+    client::{Addr, ReplySender},
+};
 use bytes::BufMut;
 use std::{
     io::Write,
     sync::{
         Mutex,
-        atomic::{AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicU64, AtomicUsize, Ordering},
     },
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::oneshot;
 use triomphe::Arc;
-use web_time::Instant;
+use web_time::{Duration, Instant};
 
 /// Clients are not owned by the store, but the store needs accurate data in several cases.
 ///
@@ -32,8 +35,11 @@ pub struct ClientInfo {
     /// A channel for asking the client to quit
     pub quit_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
 
+    /// A channel for stopping the replier when the client is evicted
+    pub evict_sender: Option<oneshot::Sender<()>>,
+
     /// A channel for sending replies
-    pub reply_sender: mpsc::UnboundedSender<ReplyMessage>,
+    pub reply_sender: ReplySender,
 
     /// Is this client currently blocking?
     pub blocking: Arc<AtomicBool>,
@@ -44,15 +50,12 @@ pub struct ClientInfo {
     /// The instant the client was created
     pub created_at: Instant,
 
+    /// Milliseconds from `created_at` to the start of the last command, shared with the client
+    pub last_interaction: Arc<AtomicU64>,
+
     /// The current transaction status, shared with the client
     pub multi: Arc<AtomicIsize>,
 
-    /// The number of subscribed channels, shared with the client
-    pub subscribers: Arc<AtomicUsize>,
-
-    /// The number of subscribed patterns, shared with the client
-    pub psubscribers: Arc<AtomicUsize>,
-
     /// The last command run by the client, shared with the client
     pub last_command: Arc<AtomicPtr<Command>>,
 
@@ -61,6 +64,12 @@ pub struct ClientInfo {
 
     /// Current monitor state, shared with the client
     pub monitor: Arc<AtomicBool>,
+
+    /// The size of the current request's arguments, shared with the client
+    pub argv_mem: Arc<AtomicUsize>,
+
+    /// The size of the arguments queued for EXEC, shared with the client
+    pub multi_mem: Arc<AtomicUsize>,
 }
 
 impl ClientInfo {
@@ -69,6 +78,12 @@ impl ClientInfo {
         self.created_at.elapsed().as_secs()
     }
 
+    /// The number of seconds since the last command
+    pub fn idle(&self) -> u64 {
+        let last = Duration::from_millis(self.last_interaction.load(Ordering::Relaxed));
+        self.created_at.elapsed().saturating_sub(last).as_secs()
+    }
+
     /// Ask the client to quit
     pub fn quit(&mut self) {
         let Ok(mut quit) = self.quit_sender.lock() else {
@@ -79,31 +94,42 @@ impl ClientInfo {
         };
         _ = quit.send(());
         // No more replies after quitting.
-        _ = self.reply_sender.send(ReplyMessage::Quit);
+        self.reply_sender.send(ReplyMessage::Quit);
+    }
+
+    /// Evict the client, closing the connection without waiting for queued replies
+    pub fn evict(&mut self) {
+        if let Some(evict) = self.evict_sender.take() {
+            _ = evict.send(());
+        }
     }
 
     /// Send a reply to the client
     pub fn reply(&mut self, reply: impl Into<Reply>) {
-        _ = self.reply_sender.send(reply.into().into());
+        self.reply_sender.send(reply.into());
     }
 
     /// Write client info to a buffer
     pub fn write_info(&self, store: &Store, buffer: &mut Vec<u8>) {
         let db = self.db.load(Ordering::Relaxed);
         let multi = self.multi.load(Ordering::Relaxed);
-        let psubscribers = self.psubscribers.load(Ordering::Relaxed);
-        let subscribers = self.subscribers.load(Ordering::Relaxed);
+        let subscribers = store.pubsub.subscribers(self.id);
+        let psubscribers = store.pubsub.psubscribers(self.id);
         let resp = self.resp.load(Ordering::Relaxed);
         let monitor = self.monitor.load(Ordering::Relaxed);
+        let argv_mem = self.argv_mem.load(Ordering::Relaxed);
+        let multi_mem = self.multi_mem.load(Ordering::Relaxed);
+        let omem = self.reply_sender.queued();
 
         _ = write!(buffer, "id={}", self.id);
         _ = write!(buffer, " db={db}");
         _ = write!(buffer, " age={}", self.age());
+        _ = write!(buffer, " idle={}", self.idle());
         _ = write!(buffer, " sub={subscribers}");
         _ = write!(buffer, " psub={psubscribers}");
         _ = write!(buffer, " resp={resp}");
 
-        if let Some(addr) = self.addr {
+        if let Some(addr) = &self.addr {
             _ = write!(buffer, " addr={}", addr.peer);
             _ = write!(buffer, " laddr={}", addr.local);
         }
@@ -122,6 +148,10 @@ impl ClientInfo {
         }
 
         _ = write!(buffer, " multi={multi}");
+        _ = write!(buffer, " argv-mem={argv_mem}");
+        _ = write!(buffer, " multi-mem={multi_mem}");
+        _ = write!(buffer, " omem={omem}");
+        _ = write!(buffer, " tot-mem={}", argv_mem + multi_mem + omem);
 
         buffer.extend_from_slice(b" flags=");
 
@@ -129,7 +159,7 @@ impl ClientInfo {
             buffer.put_u8(b'b');
         }
 
-        if subscribers > 0 || psubscribers > 0 {
+        if subscribers + psubscribers > 0 {
             buffer.put_u8(b'P');
         }
 
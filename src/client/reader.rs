@@ -0,0 +1,248 @@
+use bytes::{Buf, Bytes, BytesMut};
+use respite::{RespConfig, RespError, RespReader, RespRequest};
+use std::{cmp, io::Cursor};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The size of a connection's read buffer, like Redis's `PROTO_IOBUF_LEN`.
+const READ_BUFFER: usize = 16 * 1024;
+
+/// Reads requests from a connection, like [`RespReader::requests`].
+///
+/// Arguments are zero copy slices of the read buffer, so the buffer can only be reused once every
+/// argument read from it has been dropped. `RespReader` starts with an empty buffer, which means
+/// that while the previous arguments are still waiting for the store, every read allocates a new
+/// buffer just large enough for what's left to read, often a handful of commands. This buffer
+/// starts at [`READ_BUFFER`] and is replaced at that size, so a flood of small commands
+/// allocates once per buffer rather than once every few commands, and none at all once the store
+/// keeps up.
+pub struct RequestReader<R> {
+    buffer: BytesMut,
+    config: RespConfig,
+    inner: R,
+}
+
+impl<R: AsyncRead + Unpin> RequestReader<R> {
+    pub fn new(inner: R, config: RespConfig) -> Self {
+        RequestReader {
+            buffer: BytesMut::with_capacity(READ_BUFFER),
+            config,
+            inner,
+        }
+    }
+
+    /// Call `f` for each piece of each request received, until the connection is closed or sends
+    /// something invalid.
+    pub async fn requests(&mut self, mut f: impl FnMut(RespRequest)) {
+        if let Err(error) = self.requests_inner(&mut f).await {
+            f(RespRequest::Error(error));
+        }
+    }
+
+    async fn requests_inner(&mut self, f: &mut impl FnMut(RespRequest)) -> Result<(), RespError> {
+        while let Some(byte) = self.peek().await? {
+            if byte == b'*' {
+                self.require(b"*").await?;
+                let size = self.read_size().await?;
+                for _ in 0..size {
+                    self.require(b"$").await?;
+                    let size = self.read_size().await?;
+                    if size > self.config.blob_limit() {
+                        return Err(RespError::InvalidBlobLength);
+                    }
+                    let argument = self.read_exact(size).await?;
+                    self.require(b"\r\n").await?;
+                    f(RespRequest::Argument(argument));
+                }
+                f(RespRequest::End);
+                continue;
+            }
+
+            // Inline requests are rare outside of a terminal, so leave splitting them to
+            // `RespReader` rather than keep a second copy of its quoting rules.
+            let mut line = self.read_line().await?.to_vec();
+            line.extend_from_slice(b"\r\n");
+            RespReader::new(Cursor::new(line), self.config.clone())
+                .requests(&mut *f)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Read more input into the buffer, returning how many bytes were read.
+    async fn read(&mut self) -> Result<usize, RespError> {
+        Ok(self.inner.read_buf(&mut self.buffer).await?)
+    }
+
+    /// Read more input into the buffer, or fail at the end of the input.
+    async fn read_some(&mut self) -> Result<(), RespError> {
+        if self.read().await? == 0 {
+            return Err(RespError::EndOfInput);
+        }
+        Ok(())
+    }
+
+    /// Look at the next byte, or return `None` at the end of the input.
+    async fn peek(&mut self) -> Result<Option<u8>, RespError> {
+        if self.buffer.is_empty() && self.read().await? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.buffer[0]))
+    }
+
+    async fn pop(&mut self) -> Result<u8, RespError> {
+        if self.buffer.is_empty() {
+            self.read_some().await?;
+        }
+        Ok(self.buffer.get_u8())
+    }
+
+    /// Consume `expected`, or fail with the first byte that doesn't match.
+    async fn require(&mut self, expected: &[u8]) -> Result<(), RespError> {
+        for &expected in expected {
+            let byte = self.pop().await?;
+            if byte != expected {
+                return Err(RespError::Unexpected(expected, byte));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a size terminated by `\r\n`.
+    async fn read_size(&mut self) -> Result<usize, RespError> {
+        if self.peek().await? == Some(b'\r') {
+            return Err(RespError::InvalidBlobLength);
+        }
+
+        let mut size: usize = 0;
+        loop {
+            match self.pop().await? {
+                b'\r' => {
+                    self.require(b"\n").await?;
+                    return Ok(size);
+                }
+                byte @ b'0'..=b'9' => {
+                    size = size
+                        .checked_mul(10)
+                        .and_then(|size| size.checked_add((byte - b'0').into()))
+                        .ok_or(RespError::InvalidBlobLength)?;
+                }
+                _ => return Err(RespError::InvalidBlobLength),
+            }
+        }
+    }
+
+    /// Read a line terminated by `\r\n`, up to the inline limit.
+    async fn read_line(&mut self) -> Result<Bytes, RespError> {
+        let mut from = 0;
+        let line = loop {
+            let to = cmp::min(self.config.inline_limit(), self.buffer.len());
+            if let Some(index) = self.buffer[from..to].iter().position(|&b| b == b'\r') {
+                break self.buffer.split_to(from + index);
+            }
+            if self.buffer.len() > self.config.inline_limit() {
+                return Err(RespError::TooBigInline);
+            }
+            from = self.buffer.len();
+            self.read_some().await?;
+        };
+
+        self.require(b"\r\n").await?;
+        Ok(line.freeze())
+    }
+
+    async fn read_exact(&mut self, len: usize) -> Result<Bytes, RespError> {
+        self.buffer.reserve(len);
+        while self.buffer.len() < len {
+            self.read_some().await?;
+        }
+        Ok(self.buffer.split_to(len).freeze())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(miri))]
+#[cfg(feature = "tokio-runtime")]
+mod tests {
+    use super::*;
+
+    async fn requests(input: &'static [u8]) -> Vec<String> {
+        let mut reader = RequestReader::new(input, RespConfig::default());
+        let mut requests = Vec::new();
+        reader
+            .requests(|request| {
+                requests.push(match request {
+                    RespRequest::Argument(argument) => {
+                        String::from_utf8_lossy(&argument).into_owned()
+                    }
+                    RespRequest::InvalidArgument => "<invalid>".to_owned(),
+                    RespRequest::Error(error) => format!("<{error}>"),
+                    RespRequest::End => "<end>".to_owned(),
+                });
+            })
+            .await;
+        requests
+    }
+
+    #[tokio::test]
+    async fn multibulk() {
+        let input = b"*2\r\n$3\r\nget\r\n$1\r\nx\r\n*1\r\n$4\r\nping\r\n";
+        assert_eq!(
+            requests(input).await,
+            ["get", "x", "<end>", "ping", "<end>"]
+        );
+    }
+
+    #[tokio::test]
+    async fn inline() {
+        let input = b"set x 'a b'\r\n\r\nget \"x\r\n*1\r\n$4\r\nping\r\n";
+        assert_eq!(
+            requests(input).await,
+            [
+                "set",
+                "x",
+                "a b",
+                "<end>",
+                "<end>",
+                "<invalid>",
+                "ping",
+                "<end>"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid() {
+        let expected = format!("<{}>", RespError::InvalidBlobLength);
+        assert_eq!(requests(b"*1\r\n$x\r\n").await, [expected]);
+        let expected = format!("<{}>", RespError::Unexpected(b'\n', b'x'));
+        assert_eq!(requests(b"ping\rx").await, [expected]);
+        let expected = format!("<{}>", RespError::EndOfInput);
+        assert_eq!(requests(b"*2\r\n$3\r\nget\r\n").await, ["get", &expected]);
+    }
+
+    #[tokio::test]
+    async fn reuse_buffer() {
+        let (mut local, remote) = tokio::io::duplex(1 << 16);
+        let mut reader = RequestReader::new(remote, RespConfig::default());
+        let start = reader.buffer.as_ptr() as usize;
+
+        // Arguments are dropped as they're read, so once the buffer is full the next read goes
+        // back to its start instead of allocating a new one.
+        let request = b"*1\r\n$4\r\nping\r\n";
+        for _ in 0..READ_BUFFER * 3 / request.len() / 100 {
+            let requests = request.repeat(100);
+            tokio::io::AsyncWriteExt::write_all(&mut local, &requests)
+                .await
+                .unwrap();
+            for _ in 0..100 {
+                reader.require(b"*1\r\n$4\r\n").await.unwrap();
+                let argument = reader.read_exact(4).await.unwrap();
+                assert_eq!(&argument[..], b"ping");
+                let offset = argument.as_ptr() as usize - start;
+                assert!(offset < READ_BUFFER);
+                reader.require(b"\r\n").await.unwrap();
+            }
+        }
+    }
+}
@@ -1,19 +1,24 @@
-use crate::{Reply, ReplyMessage};
-use respite::{RespError, RespWriter};
+use super::ReplyReceiver;
+use crate::{
+    ClientId, Reply, ReplyError, ReplyMessage,
+    reply::{Deferred, DoubleOutput},
+};
+use respite::{RespConfig, RespError, RespVersion, RespWriter};
 use std::{io::Write as IoWrite, sync::Mutex};
 use thiserror::Error;
 use tokio::{
     io::{AsyncWrite, BufWriter},
-    sync::{
-        mpsc,
-        oneshot::{self, error::RecvError},
-    },
+    select,
+    sync::oneshot::{self, error::RecvError},
 };
 use triomphe::Arc;
 
 /// An error during writing replies
 #[derive(Debug, Error)]
 pub enum ReplierError {
+    #[error("client evicted")]
+    Evicted,
+
     #[error(transparent)]
     IO(#[from] std::io::Error),
 
@@ -29,14 +34,21 @@ pub struct Replier<W: AsyncWrite + Unpin> {
     /// A buffer for writing output
     buffer: Vec<u8>,
 
+    /// Protocol limits shared with the store, for checking bulk replies against
+    /// `proto-max-bulk-len`.
+    config: RespConfig,
+
     /// Are we currently sending requests, or ignoring them?
     on: bool,
 
     /// Is this client quitting?
     quitting: bool,
 
+    /// The number of replies left to drop from an attribute that RESP2 can't represent
+    skip: usize,
+
     /// A channel to receiver replies from
-    reply_receiver: mpsc::UnboundedReceiver<ReplyMessage>,
+    reply_receiver: ReplyReceiver,
 
     /// A writer for sending bytes to the client
     writer: RespWriter<W>,
@@ -46,27 +58,37 @@ pub struct Replier<W: AsyncWrite + Unpin> {
 }
 
 impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
-    /// Create a new Replier and wait for replies
+    /// Create a new Replier and wait for replies from `reply_receiver`, until the client quits or
+    /// `evict` is sent.
     pub fn spawn(
+        id: ClientId,
         writer: W,
+        config: RespConfig,
         quit_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
-    ) -> mpsc::UnboundedSender<ReplyMessage> {
-        let (reply_sender, reply_receiver) = mpsc::unbounded_channel();
+        reply_receiver: ReplyReceiver,
+        evict: oneshot::Receiver<()>,
+    ) {
         let replier = Replier {
             buffer: Vec::new(),
+            config,
             on: true,
             quitting: false,
+            skip: 0,
             reply_receiver,
             writer: RespWriter::new(BufWriter::new(writer)),
             quit_sender,
         };
-        crate::spawn(replier.listen());
-        reply_sender
+        crate::spawn(format_args!("client {id} replier"), replier.listen(evict));
     }
 
-    /// Listen for reply messages and handle them as quickly as possible.
-    async fn listen(mut self) {
-        if self.listen_inner().await.is_err() {
+    /// Listen for reply messages and handle them as quickly as possible. An evicted client stops
+    /// right away, even if it's waiting to write, dropping any replies that are still queued.
+    async fn listen(mut self, evict: oneshot::Receiver<()>) {
+        let result = select! {
+            result = self.listen_inner() => result,
+            Ok(()) = evict => Err(ReplierError::Evicted),
+        };
+        if result.is_err() {
             let Ok(mut quit) = self.quit_sender.lock() else {
                 return;
             };
@@ -83,7 +105,7 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
             self.message(message).await?;
 
             // Receive as many messages as possible before flushing the writer.
-            while let Ok(message) = self.reply_receiver.try_recv() {
+            while let Some(message) = self.reply_receiver.try_recv() {
                 self.message(message).await?;
             }
 
@@ -109,10 +131,36 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
             Reply(reply) => {
                 self.write(reply).await?;
             }
+            Message(message) => {
+                self.write_message(&message).await?;
+            }
         }
         Ok(())
     }
 
+    /// Write a published message as a single push reply.
+    async fn write_message(
+        &mut self,
+        message: &crate::pubsub::Message,
+    ) -> Result<(), ReplierError> {
+        if !self.on || self.quitting {
+            return Ok(());
+        }
+
+        if let Some(pattern) = &message.pattern {
+            self.writer.write_push(4).await?;
+            self.writer.write_simple_string(b"pmessage").await?;
+            self.writer.write_blob_string(pattern).await?;
+        } else {
+            self.writer.write_push(3).await?;
+            self.writer.write_simple_string(b"message").await?;
+        }
+        self.writer.write_blob_string(&message.channel).await?;
+        self.writer.write_blob_string(&message.message).await?;
+
+        Ok(())
+    }
+
     /// Write a reply to send to the client
     async fn write(&mut self, reply: Reply) -> Result<(), ReplierError> {
         use Reply::*;
@@ -121,7 +169,22 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
             return Ok(());
         }
 
+        if self.skip > 0 {
+            return self.discard(reply).await;
+        }
+
         match reply {
+            Attribute(len) => {
+                if self.writer.version == RespVersion::V2 {
+                    self.skip = 2 * len;
+                } else {
+                    // RespWriter::write_attribute expects a serialized body, so write the header
+                    // alone and let the following replies fill it in.
+                    self.buffer.clear();
+                    let _ = write!(self.buffer, "|{len}");
+                    self.writer.write_inline(&self.buffer[..]).await?;
+                }
+            }
             Boolean(value) => {
                 self.writer.write_boolean(value).await?;
             }
@@ -129,9 +192,7 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
                 self.writer.write_nil().await?;
             }
             Error(error) => {
-                self.buffer.clear();
-                let _ = write!(self.buffer, "{error}");
-                self.writer.write_simple_error(&self.buffer[..]).await?;
+                self.write_error(&error).await?;
             }
             Integer(value) => {
                 self.writer.write_integer(value).await?;
@@ -140,19 +201,28 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
                 self.writer.write_array(len).await?;
             }
             DeferredArray(len) => {
-                self.writer.write_array(len.await?).await?;
+                if let Some(len) = self.deferred(len).await? {
+                    self.writer.write_array(len).await?;
+                }
             }
             Set(len) => {
                 self.writer.write_set(len).await?;
             }
             DeferredSet(len) => {
-                self.writer.write_set(len.await?).await?;
+                if let Some(len) = self.deferred(len).await? {
+                    self.writer.write_set(len).await?;
+                }
             }
             Map(len) => {
                 self.writer.write_map(len).await?;
             }
             DeferredMap(len) => {
-                self.writer.write_map(len.await?).await?;
+                if let Some(len) = self.deferred(len).await? {
+                    self.writer.write_map(len).await?;
+                }
+            }
+            Bulk(bulk) if bulk.size() > self.config.blob_limit() => {
+                self.write_error(&ReplyError::ReplyLength).await?;
             }
             Bulk(bulk) => {
                 self.buffer.clear();
@@ -160,7 +230,18 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
                 self.writer.write_blob_string(value).await?;
             }
             Double(value) => {
-                self.writer.write_double(value).await?;
+                // RESP2 has no doubles, so they're sent as bulk strings like Redis does.
+                self.buffer.clear();
+                if self.writer.version == RespVersion::V2 {
+                    let _ = write!(self.buffer, "{}", DoubleOutput(value));
+                    self.writer.write_blob_string(&self.buffer[..]).await?;
+                } else {
+                    let _ = write!(self.buffer, ",{}", DoubleOutput(value));
+                    self.writer.write_inline(&self.buffer[..]).await?;
+                }
+            }
+            Verbatim(_, value) if value.size() > self.config.blob_limit() => {
+                self.write_error(&ReplyError::ReplyLength).await?;
             }
             Verbatim(format, value) => {
                 self.buffer.clear();
@@ -182,6 +263,53 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
 
         Ok(())
     }
+
+    /// Write an error reply.
+    async fn write_error(&mut self, error: &ReplyError) -> Result<(), ReplierError> {
+        self.buffer.clear();
+        let _ = write!(self.buffer, "{error}");
+        self.writer.write_simple_error(&self.buffer[..]).await?;
+        Ok(())
+    }
+
+    /// Wait for the length of a deferred reply. If it was abandoned, write its error instead and
+    /// drop the replies already sent for it, so the client never sees a partial aggregate.
+    async fn deferred(
+        &mut self,
+        len: oneshot::Receiver<Deferred>,
+    ) -> Result<Option<usize>, ReplierError> {
+        match len.await? {
+            Deferred::Len(len) => Ok(Some(len)),
+            Deferred::Abort { sent, error } => {
+                self.write_error(&error).await?;
+                self.skip = sent;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Drop a reply inside an attribute, along with any replies nested inside it.
+    async fn discard(&mut self, reply: Reply) -> Result<(), ReplierError> {
+        use Reply::*;
+
+        let nested = match reply {
+            Array(len) | Push(len) | Set(len) => len,
+            DeferredArray(len) | DeferredSet(len) => match len.await? {
+                Deferred::Len(len) => len,
+                Deferred::Abort { sent, .. } => sent,
+            },
+            Map(len) => 2 * len,
+            DeferredMap(len) => match len.await? {
+                Deferred::Len(len) => 2 * len,
+                Deferred::Abort { sent, .. } => sent,
+            },
+            // Attributes precede a reply rather than replacing one.
+            Attribute(len) => 2 * len + 1,
+            _ => 0,
+        };
+        self.skip = self.skip - 1 + nested;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -189,10 +317,20 @@ impl<W: AsyncWrite + Unpin + Send + 'static> Replier<W> {
 #[cfg(feature = "tokio-runtime")]
 mod tests {
     use super::*;
-    use crate::ReplyError;
+    use crate::{
+        ReplyError,
+        client::{ReplySender, reply_sender},
+        db::{Raw, StringValue},
+        pack::PackRef,
+        store::Connections,
+    };
     use bytes::Bytes;
-    use respite::RespVersion;
-    use std::{str::from_utf8, time::Duration};
+    use std::{
+        pin::Pin,
+        str::from_utf8,
+        task::{Context, Poll},
+        time::Duration,
+    };
     use tokio::{
         io::{AsyncReadExt, duplex},
         sync::oneshot,
@@ -207,8 +345,17 @@ mod tests {
         let quit_sender = Arc::new(Mutex::new(Some(quit_sender)));
 
         // Cause an error by dropping a deferred array reply.
-        let sender = Replier::spawn(remote, quit_sender);
-        _ = sender.send(ReplyMessage::Reply(Reply::DeferredArray(len_receiver)));
+        let (sender, receiver) = reply_sender::channel(Arc::new(Connections::default()));
+        let (_evict_sender, evict_receiver) = oneshot::channel();
+        Replier::spawn(
+            ClientId(0),
+            remote,
+            RespConfig::default(),
+            quit_sender,
+            receiver,
+            evict_receiver,
+        );
+        sender.send(ReplyMessage::Reply(Reply::DeferredArray(len_receiver)));
         drop(len_sender);
 
         let limit = Duration::from_millis(50);
@@ -216,15 +363,34 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn evict_while_writing() -> Result<(), ReplierError> {
+        let (mut local, remote) = duplex(2usize.pow(8));
+        let (sender, evict) = spawn(remote, RespConfig::default());
+
+        // The replier can't finish writing until the client reads.
+        sender.send(Reply::Bulk(vec![b'x'; 1_024].into()));
+        sender.send(Reply::Bulk(vec![b'x'; 1_024].into()));
+        tokio::task::yield_now().await;
+        _ = evict.send(());
+
+        let mut buffer = Vec::new();
+        let limit = Duration::from_millis(50);
+        timeout(limit, local.read_to_end(&mut buffer))
+            .await
+            .unwrap()?;
+        assert!(buffer.len() < 2_048);
+        assert_eq!(sender.queued(), 0);
+        Ok(())
+    }
+
     macro_rules! assert_replies {
         ($reply:expr, $output:expr, $version:expr) => {{
             let (mut local, remote) = duplex(2usize.pow(8));
-            let (quit_sender, _) = oneshot::channel();
-            let quit_sender = Arc::new(Mutex::new(Some(quit_sender)));
-            let sender = Replier::spawn(remote, quit_sender);
+            let (sender, _evict) = spawn(remote, RespConfig::default());
 
-            _ = sender.send(ReplyMessage::Protocol($version));
-            _ = sender.send(ReplyMessage::Reply($reply.into()));
+            sender.send(ReplyMessage::Protocol($version));
+            sender.send(ReplyMessage::Reply($reply.into()));
 
             // Drop the sender so that the replier task exits
             drop(sender);
@@ -249,6 +415,88 @@ mod tests {
         ($reply:expr, $output:expr) => {{ assert_replies!($reply, $output, RespVersion::V3) }};
     }
 
+    fn spawn(
+        remote: tokio::io::DuplexStream,
+        config: RespConfig,
+    ) -> (ReplySender, oneshot::Sender<()>) {
+        let (quit_sender, _) = oneshot::channel();
+        let (evict_sender, evict_receiver) = oneshot::channel();
+        let (sender, receiver) = reply_sender::channel(Arc::new(Connections::default()));
+        Replier::spawn(
+            ClientId(0),
+            remote,
+            config,
+            Arc::new(Mutex::new(Some(quit_sender))),
+            receiver,
+            evict_receiver,
+        );
+        (sender, evict_sender)
+    }
+
+    async fn write_message(
+        message: crate::pubsub::Message,
+        version: RespVersion,
+    ) -> Result<Vec<u8>, ReplierError> {
+        let (mut local, remote) = duplex(2usize.pow(8));
+        let (sender, _) = spawn(remote, RespConfig::default());
+        sender.send(ReplyMessage::Protocol(version));
+        sender.send(ReplyMessage::Message(Arc::new(message)));
+        drop(sender);
+
+        let mut buffer = Vec::new();
+        local.read_to_end(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    async fn write_replies(
+        replies: impl IntoIterator<Item = Reply>,
+        version: RespVersion,
+    ) -> Result<Vec<u8>, ReplierError> {
+        write_replies_with_config(replies, version, RespConfig::default()).await
+    }
+
+    async fn write_replies_with_config(
+        replies: impl IntoIterator<Item = Reply>,
+        version: RespVersion,
+        config: RespConfig,
+    ) -> Result<Vec<u8>, ReplierError> {
+        let (mut local, remote) = duplex(2usize.pow(8));
+        let (sender, _) = spawn(remote, config);
+        sender.send(ReplyMessage::Protocol(version));
+        for reply in replies {
+            sender.send(ReplyMessage::Reply(reply));
+        }
+        drop(sender);
+
+        let mut buffer = Vec::new();
+        local.read_to_end(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    #[tokio::test]
+    async fn write_published_message() -> Result<(), ReplierError> {
+        let message = || crate::pubsub::Message {
+            pattern: None,
+            channel: Bytes::from("x"),
+            message: Bytes::from("hi"),
+        };
+        let output = write_message(message(), RespVersion::V2).await?;
+        assert_eq!(output, b"*3\r\n+message\r\n$1\r\nx\r\n$2\r\nhi\r\n");
+        let output = write_message(message(), RespVersion::V3).await?;
+        assert_eq!(output, b">3\r\n+message\r\n$1\r\nx\r\n$2\r\nhi\r\n");
+
+        let message = crate::pubsub::Message {
+            pattern: Some(Bytes::from("x*")),
+            ..message()
+        };
+        let output = write_message(message, RespVersion::V3).await?;
+        assert_eq!(
+            output,
+            b">4\r\n+pmessage\r\n$2\r\nx*\r\n$1\r\nx\r\n$2\r\nhi\r\n"
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn write_nil() -> Result<(), ReplierError> {
         assert_v2!(Reply::Nil, b"$-1\r\n");
@@ -298,10 +546,10 @@ mod tests {
     #[tokio::test]
     async fn write_deferred_map() -> Result<(), ReplierError> {
         let (sender, receiver) = oneshot::channel();
-        _ = sender.send(5);
+        _ = sender.send(Deferred::Len(5));
         assert_v2!(Reply::DeferredMap(receiver), b"*10\r\n");
         let (sender, receiver) = oneshot::channel();
-        _ = sender.send(5);
+        _ = sender.send(Deferred::Len(5));
         assert_v3!(Reply::DeferredMap(receiver), b"%5\r\n");
         Ok(())
     }
@@ -309,10 +557,10 @@ mod tests {
     #[tokio::test]
     async fn write_deferred_set() -> Result<(), ReplierError> {
         let (sender, receiver) = oneshot::channel();
-        _ = sender.send(5);
+        _ = sender.send(Deferred::Len(5));
         assert_v2!(Reply::DeferredSet(receiver), b"*5\r\n");
         let (sender, receiver) = oneshot::channel();
-        _ = sender.send(5);
+        _ = sender.send(Deferred::Len(5));
         assert_v3!(Reply::DeferredSet(receiver), b"~5\r\n");
         Ok(())
     }
@@ -323,6 +571,113 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn write_bulk_over_limit() -> Result<(), ReplierError> {
+        let mut config = RespConfig::default();
+        config.set_blob_limit(3);
+        let replies = || {
+            [
+                Reply::Array(3),
+                Reply::Bulk(Bytes::from("abc").into()),
+                Reply::Bulk(Bytes::from("abcd").into()),
+                Reply::Verbatim(Bytes::from("txt"), Bytes::from("abcd").into()),
+            ]
+        };
+        let output = write_replies_with_config(replies(), RespVersion::V3, config.clone()).await?;
+        assert_eq!(
+            output,
+            b"*3\r\n$3\r\nabc\r\n-ERR reply exceeds maximum allowed size (proto-max-bulk-len)\r\n-ERR reply exceeds maximum allowed size (proto-max-bulk-len)\r\n"
+        );
+        Ok(())
+    }
+
+    /// A writer that records the address of every buffer passed to it.
+    #[derive(Clone, Default)]
+    struct Addresses(Arc<Mutex<Vec<usize>>>);
+
+    impl AsyncWrite for Addresses {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.0.lock().unwrap().push(buf.as_ptr().addr());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn write_bulk_without_copying() -> Result<(), ReplierError> {
+        let addresses = Addresses::default();
+        let (_, receiver) = reply_sender::channel(Arc::new(Connections::default()));
+        let mut replier = Replier {
+            buffer: Vec::new(),
+            config: RespConfig::default(),
+            on: true,
+            quitting: false,
+            skip: 0,
+            reply_receiver: receiver,
+            writer: RespWriter::new(BufWriter::new(addresses.clone())),
+            quit_sender: Arc::new(Mutex::new(None)),
+        };
+
+        // Values larger than the writer's buffer should be written straight from storage, as
+        // they are for GET, GETRANGE, and LRANGE.
+        let raw: Raw = vec![b'x'; 1 << 20].into();
+        let value = StringValue::Raw(raw.clone());
+        let range = 1..raw.len() - 1;
+        let replies = [
+            (Reply::from(&value), raw.as_ptr()),
+            (
+                value.slice(range.clone()).into(),
+                raw[range.clone()].as_ptr(),
+            ),
+            (
+                PackRef::Slice(raw.slice(range.clone())).into(),
+                raw[range].as_ptr(),
+            ),
+        ];
+
+        for (reply, address) in replies {
+            replier.write(reply).await?;
+            assert!(addresses.0.lock().unwrap().contains(&address.addr()));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn write_aborted_deferred_array() -> Result<(), ReplierError> {
+        let replies = || {
+            let (sender, receiver) = oneshot::channel();
+            _ = sender.send(Deferred::Abort {
+                sent: 2,
+                error: ReplyError::ReplyLength,
+            });
+            [
+                Reply::DeferredArray(receiver),
+                Reply::Array(2),
+                Reply::Integer(1),
+                Reply::Integer(2),
+                Reply::Integer(3),
+                Reply::Integer(4),
+            ]
+        };
+        let output = write_replies(replies(), RespVersion::V3).await?;
+        assert_eq!(
+            output,
+            b"-ERR reply exceeds maximum allowed size (proto-max-bulk-len)\r\n:4\r\n"
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn write_boolean() -> Result<(), ReplierError> {
         assert_v3!(Reply::Boolean(true), b"#t\r\n");
@@ -334,11 +689,13 @@ mod tests {
 
     #[tokio::test]
     async fn write_double() -> Result<(), ReplierError> {
-        assert_v2!(Reply::Double(3.2f64), b"+3.2\r\n");
-        assert_v2!(Reply::Double(f64::INFINITY), b"+inf\r\n");
-        assert_v2!(Reply::Double(f64::NEG_INFINITY), b"+-inf\r\n");
+        assert_v2!(Reply::Double(3.2f64), b"$3\r\n3.2\r\n");
+        assert_v2!(Reply::Double(1e20), b"$5\r\n1e+20\r\n");
+        assert_v2!(Reply::Double(f64::INFINITY), b"$3\r\ninf\r\n");
+        assert_v2!(Reply::Double(f64::NEG_INFINITY), b"$4\r\n-inf\r\n");
 
         assert_v3!(Reply::Double(3.2f64), b",3.2\r\n");
+        assert_v3!(Reply::Double(1e20), b",1e+20\r\n");
         assert_v3!(Reply::Double(f64::INFINITY), b",inf\r\n");
         assert_v3!(Reply::Double(f64::NEG_INFINITY), b",-inf\r\n");
         Ok(())
@@ -373,6 +730,35 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn write_attribute() -> Result<(), ReplierError> {
+        let replies = || {
+            [
+                Reply::Attribute(1),
+                "ttl".into(),
+                Reply::Array(2),
+                Reply::Integer(1),
+                Reply::Map(1),
+                "a".into(),
+                Reply::Attribute(1),
+                "b".into(),
+                "c".into(),
+                Reply::Bignum("5".into()),
+                Reply::Array(2),
+                Reply::Double(1.5),
+                Reply::Boolean(true),
+            ]
+        };
+        let output = write_replies(replies(), RespVersion::V2).await?;
+        assert_eq!(output, b"*2\r\n$3\r\n1.5\r\n:1\r\n");
+        let output = write_replies(replies(), RespVersion::V3).await?;
+        assert_eq!(
+            output,
+            b"|1\r\n+ttl\r\n*2\r\n:1\r\n%1\r\n+a\r\n|1\r\n+b\r\n+c\r\n(5\r\n*2\r\n,1.5\r\n#t\r\n"
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn write_status() -> Result<(), ReplierError> {
         assert_v2!(Reply::Status("PONG".into()), b"+PONG\r\n");
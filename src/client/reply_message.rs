@@ -1,5 +1,7 @@
-use crate::Reply;
+use crate::{Reply, pubsub::Message};
+use bytes::Bytes;
 use respite::RespVersion;
+use triomphe::Arc;
 
 /// A message from a client or the store to a [`crate::client::Replier`].
 /// Indicates what to reply with or how to reply.
@@ -16,6 +18,28 @@ pub enum ReplyMessage {
 
     /// Send a reply to the client.
     Reply(Reply),
+
+    /// Send a published message, shared with every other subscriber receiving it.
+    Message(Arc<Message>),
+}
+
+impl ReplyMessage {
+    /// Roughly how many bytes this message holds while it waits in a reply queue. Published
+    /// messages are shared, but count in full for every subscriber, since each one writes them.
+    pub fn size(&self) -> usize {
+        use ReplyMessage::*;
+        match self {
+            Reply(reply) => reply.size(),
+            Message(message) => {
+                let pattern = message.pattern.as_ref().map_or(0, Bytes::len);
+                std::mem::size_of::<ReplyMessage>()
+                    + pattern
+                    + message.channel.len()
+                    + message.message.len()
+            }
+            Protocol(_) | On(_) | Quit => std::mem::size_of::<ReplyMessage>(),
+        }
+    }
 }
 
 impl From<Reply> for ReplyMessage {
@@ -0,0 +1,136 @@
+use super::ReplyMessage;
+use crate::store::Connections;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use tokio::sync::mpsc;
+use triomphe::Arc;
+
+/// Sends messages to a client's [`super::Replier`], counting the bytes waiting in its queue and in
+/// the queues of every client, for `maxmemory-clients`.
+#[derive(Clone, Debug)]
+pub struct ReplySender {
+    /// The channel to the replier.
+    sender: mpsc::UnboundedSender<ReplyMessage>,
+
+    /// Bytes waiting in this client's queue, shared with the receiver.
+    queued: Arc<AtomicUsize>,
+
+    /// Keeps the total for every client.
+    connections: Arc<Connections>,
+}
+
+/// Receives messages sent by a [`ReplySender`], releasing their bytes as they're taken.
+pub struct ReplyReceiver {
+    /// The channel from every sender.
+    receiver: mpsc::UnboundedReceiver<ReplyMessage>,
+
+    /// Bytes waiting in this client's queue, shared with the senders.
+    queued: Arc<AtomicUsize>,
+
+    /// Keeps the total for every client.
+    connections: Arc<Connections>,
+}
+
+/// Create a reply queue for a client.
+pub fn channel(connections: Arc<Connections>) -> (ReplySender, ReplyReceiver) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let queued = Arc::new(AtomicUsize::new(0));
+    let sender = ReplySender {
+        sender,
+        queued: queued.clone(),
+        connections: connections.clone(),
+    };
+    let receiver = ReplyReceiver {
+        receiver,
+        queued,
+        connections,
+    };
+    (sender, receiver)
+}
+
+impl ReplySender {
+    /// Queue a message for the replier. Messages sent after the replier stops are dropped.
+    pub fn send(&self, message: impl Into<ReplyMessage>) {
+        let message = message.into();
+        let size = message.size();
+        self.queued.fetch_add(size, Relaxed);
+        self.connections.queue_output(size);
+        if self.sender.send(message).is_err() {
+            self.queued.fetch_sub(size, Relaxed);
+            self.connections.release_output(size);
+        }
+    }
+
+    /// The number of bytes waiting in this client's queue.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Relaxed)
+    }
+}
+
+impl ReplyReceiver {
+    /// Wait for the next message, or `None` once every sender is dropped.
+    pub async fn recv(&mut self) -> Option<ReplyMessage> {
+        let message = self.receiver.recv().await?;
+        self.release(&message);
+        Some(message)
+    }
+
+    /// Take the next message if one is waiting.
+    pub fn try_recv(&mut self) -> Option<ReplyMessage> {
+        let message = self.receiver.try_recv().ok()?;
+        self.release(&message);
+        Some(message)
+    }
+
+    fn release(&self, message: &ReplyMessage) {
+        let size = message.size();
+        self.queued.fetch_sub(size, Relaxed);
+        self.connections.release_output(size);
+    }
+}
+
+impl Drop for ReplyReceiver {
+    /// Release anything still waiting, so it isn't counted after the replier stops.
+    fn drop(&mut self) {
+        self.receiver.close();
+        while self.try_recv().is_some() {}
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(miri))]
+#[cfg(feature = "tokio-runtime")]
+mod tests {
+    use super::*;
+    use crate::Reply;
+
+    #[tokio::test]
+    async fn count_queued_bytes() {
+        let connections = Arc::new(Connections::default());
+        let (a, mut receiver) = channel(connections.clone());
+        let (b, _other) = channel(connections.clone());
+
+        a.send(Reply::Bulk("abc".into()));
+        b.send(Reply::Bulk("abcdef".into()));
+        assert_eq!(a.queued(), Reply::Bulk("abc".into()).size());
+        assert_eq!(connections.output(), a.queued() + b.queued());
+
+        receiver.recv().await.unwrap();
+        assert_eq!(a.queued(), 0);
+        assert_eq!(connections.output(), b.queued());
+    }
+
+    #[test]
+    fn release_on_drop() {
+        let connections = Arc::new(Connections::default());
+        let (sender, receiver) = channel(connections.clone());
+        sender.send(Reply::Nil);
+        drop(receiver);
+        assert_eq!(sender.queued(), 0);
+        assert_eq!(connections.output(), 0);
+
+        // Messages sent to a closed queue aren't counted.
+        sender.send(Reply::Nil);
+        assert_eq!(sender.queued(), 0);
+        assert_eq!(connections.output(), 0);
+    }
+}
@@ -13,23 +13,10 @@ mod set;
 mod sorted_set;
 mod string;
 
-pub use bitops::*;
 pub use client::*;
-pub use config::*;
-pub use db::*;
-pub use debug::*;
-pub use eval::*;
-pub use expire::*;
-pub use hash::*;
-pub use keys::*;
-pub use list::*;
-pub use pubsub::*;
-pub use set::*;
-pub use sorted_set::*;
-pub use string::*;
-
-use crate::{bytes::lex, client::Client, db::Edge, reply::Reply, store::Store};
-use logos::Logos;
+
+use crate::{client::Client, db::Edge, reply::Reply, store::Store};
+use arrayvec::ArrayVec;
 use std::{iter::StepBy, ops::Range, time::Duration};
 
 /// A description of the number of arguments a command accepts.
@@ -37,6 +24,20 @@ use std::{iter::StepBy, ops::Range, time::Duration};
 pub enum Arity {
     Exact(u8),
     Minimum(u8),
+
+    /// Between a minimum and a maximum, inclusive, which `COMMAND INFO` lists as the minimum.
+    Range(u8, u8),
+}
+
+impl Arity {
+    /// Does this arity accept `len` arguments, including the command name?
+    pub fn accepts(&self, len: usize) -> bool {
+        match *self {
+            Arity::Exact(arity) => len == usize::from(arity),
+            Arity::Minimum(arity) => len >= usize::from(arity),
+            Arity::Range(min, max) => (usize::from(min)..=usize::from(max)).contains(&len),
+        }
+    }
 }
 
 /// A description of where the keys are in the arguments to a command.
@@ -47,6 +48,7 @@ pub enum Keys {
     Double,
     Odd,
     None,
+    Second,
     Single,
     SkipOne,
     Trailing,
@@ -62,26 +64,99 @@ impl Keys {
             Double => (1, 2, 1),
             Odd => (1, -1, 2),
             None => (0, 0, 0),
+            Second => (2, 2, 1),
             Single => (1, 1, 1),
             SkipOne => (2, -1, 1),
             Trailing => (1, -2, 1),
         }
     }
+
+    /// Get the key specifications for the `COMMAND` command, as in Redis 7.
+    pub fn specs(&self) -> ArrayVec<KeySpec, 2> {
+        use Keys::*;
+        let range = |index, lastkey, keystep| KeySpec {
+            index,
+            find: FindKeys::Range { lastkey, keystep },
+        };
+        match *self {
+            All => [range(1, -1, 1)].into_iter().collect(),
+            Argument(index) => [KeySpec {
+                index,
+                find: FindKeys::Keynum,
+            }]
+            .into_iter()
+            .collect(),
+            Double => [range(1, 0, 1), range(2, 0, 1)].into(),
+            Odd => [range(1, -1, 2)].into_iter().collect(),
+            None => ArrayVec::new(),
+            Second => [range(2, 0, 1)].into_iter().collect(),
+            Single => [range(1, 0, 1)].into_iter().collect(),
+            SkipOne => [range(2, -1, 1)].into_iter().collect(),
+            Trailing => [range(1, -2, 1)].into_iter().collect(),
+        }
+    }
+}
+
+/// Where a command's keys start, and how to find them from there.
+pub struct KeySpec {
+    /// The index of the first key, or of the number of keys.
+    pub index: usize,
+
+    /// How to find the keys after `index`.
+    pub find: FindKeys,
+}
+
+/// How to find keys, starting from the index in a [`KeySpec`].
+pub enum FindKeys {
+    /// The keys run to `lastkey`, relative to the start or, if negative, the end of the arguments.
+    Range { lastkey: i64, keystep: usize },
+
+    /// The number of keys is at the index, and the keys follow it.
+    Keynum,
+}
+
+/// A condition that a blocked client is waiting for.
+pub type Condition = fn(&Client, &Store) -> bool;
+
+/// What a blocked client is waiting for before its command is run again.
+pub enum Wake {
+    /// Any of the keys at these request indices becoming ready.
+    Keys(StepBy<Range<usize>>),
+
+    /// A condition on the client and store becoming true, checked after every command.
+    When(Condition),
 }
 
 /// The result of a blocking command.
 pub struct BlockResult {
-    /// They keys a command is blocking on.
-    pub keys: StepBy<Range<usize>>,
+    /// What the command is waiting for.
+    pub wake: Wake,
 
     /// The timeout for a blocking operation.
     pub timeout: Duration,
+
+    /// The reply sent when the timeout expires, or when the command can't block at all.
+    pub timeout_reply: Reply,
 }
 
 impl BlockResult {
-    /// Create a new [`BlockResult`].
+    /// Create a new [`BlockResult`] that waits for keys.
     fn new(timeout: Duration, keys: StepBy<Range<usize>>) -> Self {
-        Self { keys, timeout }
+        Self {
+            wake: Wake::Keys(keys),
+            timeout,
+            timeout_reply: Reply::Nil,
+        }
+    }
+
+    /// Create a new [`BlockResult`] that waits for `condition`, replying with `timeout_reply` if
+    /// it doesn't hold in time.
+    fn when(timeout: Duration, condition: Condition, timeout_reply: impl Into<Reply>) -> Self {
+        Self {
+            wake: Wake::When(condition),
+            timeout,
+            timeout_reply: timeout_reply.into(),
+        }
     }
 }
 
@@ -119,21 +194,54 @@ pub struct Command {
 
     /// Does this command write data?
     pub write: bool,
+
+    /// Can this command block the client?
+    pub blocking: bool,
+
+    /// Does this command run in constant or logarithmic time?
+    pub fast: bool,
+
+    /// The ACL categories this command belongs to, like `@string`.
+    pub categories: &'static [&'static str],
+
+    /// The subcommands of this command, like `CLIENT KILL`.
+    pub subcommands: &'static [&'static Subcommand],
 }
 
 impl From<&[u8]> for &'static Command {
     fn from(value: &[u8]) -> &'static Command {
-        lex::<CommandKind>(value).map_or(&UNKNOWN, |kind| kind.command())
+        lookup(value).unwrap_or(&UNKNOWN)
     }
 }
 
 impl Command {
+    /// The flags listed for this command by `COMMAND INFO`.
+    pub fn flags(&self) -> impl Iterator<Item = &'static str> {
+        [
+            (self.write, "write"),
+            (self.readonly, "readonly"),
+            (self.admin, "admin"),
+            (self.pubsub, "pubsub"),
+            (self.noscript, "noscript"),
+            (self.blocking, "blocking"),
+            (self.fast, "fast"),
+        ]
+        .into_iter()
+        .filter_map(|(value, name)| value.then_some(name))
+    }
+
     /// Can this command be executed while monitoring?
     pub fn monitor_allowed(&self) -> bool {
         // TODO: Also disallow may_replicate commands.
         !self.readonly && !self.write
     }
 
+    /// Can this command be executed while a script is busy?
+    pub fn busy_allowed(&self) -> bool {
+        use CommandKind::*;
+        matches!(self.kind, Script | Shutdown)
+    }
+
     /// Is this command allowed in pubsub mode?
     pub fn pubsub_allowed(&self) -> bool {
         use CommandKind::*;
@@ -146,10 +254,46 @@ impl Command {
     /// Is this command queueable during a transaction?
     pub fn queueable(&self) -> bool {
         use CommandKind::*;
-        !matches!(self.kind, Exec | Discard | Multi | Quit | Reset | Watch)
+        !matches!(
+            self.kind,
+            Exec | Discard | Hello | Multi | Quit | Reset | Watch
+        )
     }
 }
 
+/// Information about a subcommand, like `CLIENT KILL`, that can be run.
+pub struct Subcommand {
+    /// The full name of the subcommand, like `client|kill`.
+    pub name: &'static str,
+
+    /// What are the arguments to this subcommand, including the command?
+    pub arity: Arity,
+
+    /// What function runs this subcommand?
+    pub run: fn(&mut Client, &mut Store) -> CommandResult,
+
+    /// Where are the keys in this subcommand, counting from the command?
+    pub keys: Keys,
+}
+
+/// Find a command by name, ignoring case.
+pub fn lookup(name: &[u8]) -> Option<&'static Command> {
+    let mut key = ArrayVec::<u8, MAX_NAME_LEN>::new();
+    key.try_extend_from_slice(name).ok()?;
+    key.make_ascii_lowercase();
+    COMMANDS.get(&key[..]).copied()
+}
+
+/// Find a subcommand of `command` by name, ignoring case.
+pub fn lookup_subcommand(command: &Command, name: &[u8]) -> Option<&'static Subcommand> {
+    let mut key = ArrayVec::<u8, MAX_NAME_LEN>::new();
+    key.try_extend_from_slice(command.name.as_bytes()).ok()?;
+    key.try_push(b'|').ok()?;
+    key.try_extend_from_slice(name).ok()?;
+    key.make_ascii_lowercase();
+    SUBCOMMANDS.get(&key[..]).copied()
+}
+
 impl std::fmt::Debug for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Command")
@@ -166,681 +310,40 @@ impl std::fmt::Debug for Command {
     }
 }
 
-pub static ALL: [&Command; 125] = [
-    &APPEND,
-    &BITCOUNT,
-    &BITFIELD,
-    &BITOP,
-    &BITPOS,
-    &BLMOVE,
-    &BLPOP,
-    &BRPOP,
-    &BRPOPLPUSH,
-    &BZMPOP,
-    &BZPOPMAX,
-    &BZPOPMIN,
-    &CLIENT,
-    &COMMAND,
-    &CONFIG,
-    &COPY,
-    &DBSIZE,
-    &DECR,
-    &DECRBY,
-    &DEL,
-    &DISCARD,
-    &ECHO,
-    &EVAL,
-    &EXEC,
-    &EXISTS,
-    &EXPIRE,
-    &EXPIREAT,
-    &EXPIRETIME,
-    &FLUSHALL,
-    &FLUSHDB,
-    &GET,
-    &GETDEL,
-    &GETEX,
-    &GETBIT,
-    &GETRANGE,
-    &GETSET,
-    &HDEL,
-    &HELLO,
-    &HEXISTS,
-    &HGET,
-    &HGETALL,
-    &HINCRBY,
-    &HINCRBYFLOAT,
-    &HKEYS,
-    &HLEN,
-    &HMGET,
-    &HSET,
-    &HSETNX,
-    &HMSET,
-    &HSTRLEN,
-    &HVALS,
-    &INCR,
-    &INCRBY,
-    &INCRBYFLOAT,
-    &KEYS,
-    &LINDEX,
-    &LINSERT,
-    &LLEN,
-    &LMOVE,
-    &LPOP,
-    &LPOS,
-    &LPUSH,
-    &LPUSHX,
-    &LRANGE,
-    &LREM,
-    &LSET,
-    &LTRIM,
-    &MGET,
-    &MOVE,
-    &MSET,
-    &MSETNX,
-    &MULTI,
-    &PERSIST,
-    &PEXPIRE,
-    &PEXPIREAT,
-    &PEXPIRETIME,
-    &PING,
-    &PSETEX,
-    &PSUBSCRIBE,
-    &PTTL,
-    &PUBLISH,
-    &PUBSUB,
-    &PUNSUBSCRIBE,
-    &QUIT,
-    &RENAME,
-    &RENAMENX,
-    &RESET,
-    &RPOP,
-    &RPOPLPUSH,
-    &RPUSH,
-    &RPUSHX,
-    &SADD,
-    &SCARD,
-    &SELECT,
-    &SET,
-    &SETBIT,
-    &SETEX,
-    &SETNX,
-    &SETRANGE,
-    &SISMEMBER,
-    &SMEMBERS,
-    &SMISMEMBER,
-    &SPOP,
-    &SREM,
-    &STRLEN,
-    &SUBSCRIBE,
-    &SWAPDB,
-    &TTL,
-    &TYPE,
-    &UNLINK,
-    &UNSUBSCRIBE,
-    &UNWATCH,
-    &WATCH,
-    &ZADD,
-    &ZCARD,
-    &ZCOUNT,
-    &ZMPOP,
-    &ZPOPMIN,
-    &ZRANGEBYSCORE,
-    &ZRANK,
-    &ZREM,
-    &ZREMRANGEBYSCORE,
-    &ZREVRANGE,
-    &ZREVRANGEBYSCORE,
-    &ZSCORE,
-];
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
-pub enum CommandKind {
-    #[regex(b"(?i:append)")]
-    Append,
-
-    #[regex(b"(?i:bitcount)")]
-    Bitcount,
-
-    #[regex(b"(?i:bitfield)")]
-    Bitfield,
-
-    #[regex(b"(?i:bitfield_ro)")]
-    Bitfieldro,
-
-    #[regex(b"(?i:bitop)")]
-    Bitop,
-
-    #[regex(b"(?i:bitpos)")]
-    Bitpos,
-
-    #[regex(b"(?i:blmove)")]
-    Blmove,
-
-    #[regex(b"(?i:blmpop)")]
-    Blmpop,
-
-    #[regex(b"(?i:blpop)")]
-    Blpop,
-
-    #[regex(b"(?i:brpop)")]
-    Brpop,
-
-    #[regex(b"(?i:brpoplpush)")]
-    Brpoplpush,
-
-    #[regex(b"(?i:bzmpop)")]
-    Bzmpop,
-
-    #[regex(b"(?i:bzpopmax)")]
-    Bzpopmax,
-
-    #[regex(b"(?i:bzpopmin)")]
-    Bzpopmin,
-
-    #[regex(b"(?i:client)")]
-    Client,
-
-    #[regex(b"(?i:command)")]
-    Command,
-
-    #[regex(b"(?i:config)")]
-    Config,
-
-    #[regex(b"(?i:copy)")]
-    Copy,
-
-    #[regex(b"(?i:dbsize)")]
-    Dbsize,
-
-    #[regex(b"(?i:decr)")]
-    Decr,
-
-    #[regex(b"(?i:debug)")]
-    Debug,
-
-    #[regex(b"(?i:decrby)")]
-    Decrby,
-
-    #[regex(b"(?i:del)")]
-    Del,
-
-    #[regex(b"(?i:discard)")]
-    Discard,
-
-    #[regex(b"(?i:echo)")]
-    Echo,
-
-    #[regex(b"(?i:eval)")]
-    Eval,
-
-    #[regex(b"(?i:exists)")]
-    Exists,
-
-    #[regex(b"(?i:expire)")]
-    Expire,
-
-    #[regex(b"(?i:expireat)")]
-    Expireat,
-
-    #[regex(b"(?i:expiretime)")]
-    Expiretime,
-
-    #[regex(b"(?i:exec)")]
-    Exec,
-
-    #[regex(b"(?i:flushall)")]
-    Flushall,
-
-    #[regex(b"(?i:flushdb)")]
-    Flushdb,
-
-    #[regex(b"(?i:get)")]
-    Get,
-
-    #[regex(b"(?i:getdel)")]
-    Getdel,
-
-    #[regex(b"(?i:getex)")]
-    Getex,
-
-    #[regex(b"(?i:getbit)")]
-    Getbit,
-
-    #[regex(b"(?i:getrange)")]
-    Getrange,
-
-    #[regex(b"(?i:getset)")]
-    Getset,
-
-    #[regex(b"(?i:hdel)")]
-    Hdel,
-
-    #[regex(b"(?i:hello)")]
-    Hello,
-
-    #[regex(b"(?i:hexists)")]
-    Hexists,
-
-    #[regex(b"(?i:hget)")]
-    Hget,
-
-    #[regex(b"(?i:hgetall)")]
-    Hgetall,
-
-    #[regex(b"(?i:hincrby)")]
-    Hincrby,
-
-    #[regex(b"(?i:hincrbyfloat)")]
-    Hincrbyfloat,
-
-    #[regex(b"(?i:hkeys)")]
-    Hkeys,
-
-    #[regex(b"(?i:hlen)")]
-    Hlen,
-
-    #[regex(b"(?i:hmget)")]
-    Hmget,
-
-    #[regex(b"(?i:hset)")]
-    Hset,
-
-    #[regex(b"(?i:hsetnx)")]
-    Hsetnx,
-
-    #[regex(b"(?i:hmset)")]
-    Hmset,
-
-    #[regex(b"(?i:hstrlen)")]
-    Hstrlen,
-
-    #[regex(b"(?i:hvals)")]
-    Hvals,
+include!(concat!(env!("OUT_DIR"), "/commands.rs"));
 
-    #[regex(b"(?i:incr)")]
-    Incr,
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    #[regex(b"(?i:incrby)")]
-    Incrby,
-
-    #[regex(b"(?i:incrbyfloat)")]
-    Incrbyfloat,
-
-    #[regex(b"(?i:info)")]
-    Info,
-
-    #[regex(b"(?i:linsert)")]
-    Linsert,
-
-    #[regex(b"(?i:rpop)")]
-    Rpop,
-
-    #[regex(b"(?i:rpoplpush)")]
-    Rpoplpush,
-
-    #[regex(b"(?i:keys)")]
-    Keys,
-
-    #[regex(b"(?i:lindex)")]
-    Lindex,
-
-    #[regex(b"(?i:llen)")]
-    Llen,
-
-    #[regex(b"(?i:lmove)")]
-    Lmove,
-
-    #[regex(b"(?i:lmpop)")]
-    Lmpop,
-
-    #[regex(b"(?i:lpop)")]
-    Lpop,
-
-    #[regex(b"(?i:lpos)")]
-    Lpos,
-
-    #[regex(b"(?i:lpush)")]
-    Lpush,
-
-    #[regex(b"(?i:lpushx)")]
-    Lpushx,
-
-    #[regex(b"(?i:lrange)")]
-    Lrange,
-
-    #[regex(b"(?i:lrem)")]
-    Lrem,
-
-    #[regex(b"(?i:lset)")]
-    Lset,
-
-    #[regex(b"(?i:ltrim)")]
-    Ltrim,
-
-    #[regex(b"(?i:mget)")]
-    Mget,
-
-    #[regex(b"(?i:monitor)")]
-    Monitor,
-
-    #[regex(b"(?i:move)")]
-    Move,
-
-    #[regex(b"(?i:mset)")]
-    Mset,
-
-    #[regex(b"(?i:msetnx)")]
-    Msetnx,
-
-    #[regex(b"(?i:multi)")]
-    Multi,
-
-    #[regex(b"(?i:object)")]
-    Object,
-
-    #[regex(b"(?i:persist)")]
-    Persist,
-
-    #[regex(b"(?i:pexpire)")]
-    Pexpire,
-
-    #[regex(b"(?i:pexpireat)")]
-    Pexpireat,
-
-    #[regex(b"(?i:pexpiretime)")]
-    Pexpiretime,
-
-    #[regex(b"(?i:ping)")]
-    Ping,
-
-    #[regex(b"(?i:psetex)")]
-    Psetex,
-
-    #[regex(b"(?i:pttl)")]
-    Pttl,
-
-    #[regex(b"(?i:publish)")]
-    Publish,
-
-    #[regex(b"(?i:pubsub)")]
-    Pubsub,
-
-    #[regex(b"(?i:psubscribe)")]
-    Psubscribe,
-
-    #[regex(b"(?i:punsubscribe)")]
-    Punsubscribe,
-
-    #[regex(b"(?i:quit)")]
-    Quit,
-
-    #[regex(b"(?i:rename)")]
-    Rename,
-
-    #[regex(b"(?i:renamenx)")]
-    Renamenx,
-
-    #[regex(b"(?i:reset)")]
-    Reset,
-
-    #[regex(b"(?i:rpush)")]
-    Rpush,
-
-    #[regex(b"(?i:rpushx)")]
-    Rpushx,
-
-    #[regex(b"(?i:sadd)")]
-    Sadd,
-
-    #[regex(b"(?i:scard)")]
-    Scard,
-
-    #[regex(b"(?i:select)")]
-    Select,
-
-    #[regex(b"(?i:set)")]
-    Set,
-
-    #[regex(b"(?i:setbit)")]
-    Setbit,
-
-    #[regex(b"(?i:setex)")]
-    Setex,
-
-    #[regex(b"(?i:setnx)")]
-    Setnx,
-
-    #[regex(b"(?i:setrange)")]
-    Setrange,
-
-    #[regex(b"(?i:sismember)")]
-    Sismember,
-
-    #[regex(b"(?i:smembers)")]
-    Smembers,
-
-    #[regex(b"(?i:smismember)")]
-    Smismember,
-
-    #[regex(b"(?i:spop)")]
-    Spop,
-
-    #[regex(b"(?i:srem)")]
-    Srem,
-
-    #[regex(b"(?i:strlen)")]
-    Strlen,
-
-    #[regex(b"(?i:subscribe)")]
-    Subscribe,
-
-    #[regex(b"(?i:swapdb)")]
-    Swapdb,
-
-    #[regex(b"(?i:ttl)")]
-    Ttl,
-
-    #[regex(b"(?i:type)")]
-    Type,
-
-    #[regex(b"(?i:watch)")]
-    Watch,
-
-    #[regex(b"(?i:unlink)")]
-    Unlink,
-
-    #[regex(b"(?i:unsubscribe)")]
-    Unsubscribe,
-
-    #[regex(b"(?i:unwatch)")]
-    Unwatch,
-
-    #[regex(b"(?i:zadd)")]
-    Zadd,
-
-    #[regex(b"(?i:zcard)")]
-    Zcard,
-
-    #[regex(b"(?i:zcount)")]
-    Zcount,
-
-    #[regex(b"(?i:zmpop)")]
-    Zmpop,
-
-    #[regex(b"(?i:zpopmax)")]
-    Zpopmax,
-
-    #[regex(b"(?i:zpopmin)")]
-    Zpopmin,
-
-    #[regex(b"(?i:zrange)")]
-    Zrange,
-
-    #[regex(b"(?i:zrank)")]
-    Zrank,
-
-    #[regex(b"(?i:zrangebyscore)")]
-    Zrangebyscore,
-
-    #[regex(b"(?i:zrem)")]
-    Zrem,
-
-    #[regex(b"(?i:zremrangebyscore)")]
-    Zremrangebyscore,
-
-    #[regex(b"(?i:zrevrange)")]
-    Zrevrange,
-
-    #[regex(b"(?i:zrevrangebyscore)")]
-    Zrevrangebyscore,
-
-    #[regex(b"(?i:zscore)")]
-    Zscore,
-
-    Unknown,
-}
-
-impl CommandKind {
-    pub fn command(self) -> &'static Command {
-        use CommandKind::*;
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(lookup(b"GET").unwrap().kind, CommandKind::Get);
+        let command = lookup(b"zRemRangeByScore").unwrap();
+        assert_eq!(command.kind, CommandKind::Zremrangebyscore);
+        assert!(lookup(b"nosuch").is_none());
+        assert!(lookup(b"client|kill").is_none());
+        assert!(lookup(&[b'a'; 64]).is_none());
+    }
 
-        match self {
-            Append => &APPEND,
-            Bitcount => &BITCOUNT,
-            Bitfield => &BITFIELD,
-            Bitfieldro => &BITFIELD_RO,
-            Bitop => &BITOP,
-            Bitpos => &BITPOS,
-            Blmove => &BLMOVE,
-            Blmpop => &BLMPOP,
-            Blpop => &BLPOP,
-            Brpop => &BRPOP,
-            Brpoplpush => &BRPOPLPUSH,
-            Bzmpop => &BZMPOP,
-            Bzpopmax => &BZPOPMAX,
-            Bzpopmin => &BZPOPMIN,
-            Client => &CLIENT,
-            Command => &COMMAND,
-            Config => &CONFIG,
-            Copy => &COPY,
-            Dbsize => &DBSIZE,
-            Debug => &DEBUG,
-            Decr => &DECR,
-            Decrby => &DECRBY,
-            Del => &DEL,
-            Discard => &DISCARD,
-            Echo => &ECHO,
-            Eval => &EVAL,
-            Exec => &EXEC,
-            Exists => &EXISTS,
-            Expire => &EXPIRE,
-            Expireat => &EXPIREAT,
-            Expiretime => &EXPIRETIME,
-            Flushall => &FLUSHALL,
-            Flushdb => &FLUSHDB,
-            Get => &GET,
-            Getdel => &GETDEL,
-            Getex => &GETEX,
-            Getbit => &GETBIT,
-            Getrange => &GETRANGE,
-            Getset => &GETSET,
-            Hdel => &HDEL,
-            Hello => &HELLO,
-            Hexists => &HEXISTS,
-            Hget => &HGET,
-            Hgetall => &HGETALL,
-            Hincrby => &HINCRBY,
-            Hincrbyfloat => &HINCRBYFLOAT,
-            Hkeys => &HKEYS,
-            Hlen => &HLEN,
-            Hmget => &HMGET,
-            Hset => &HSET,
-            Hsetnx => &HSETNX,
-            Hmset => &HMSET,
-            Hstrlen => &HSTRLEN,
-            Hvals => &HVALS,
-            Incr => &INCR,
-            Incrby => &INCRBY,
-            Incrbyfloat => &INCRBYFLOAT,
-            Info => &INFO,
-            Keys => &KEYS,
-            Lindex => &LINDEX,
-            Linsert => &LINSERT,
-            Llen => &LLEN,
-            Lmove => &LMOVE,
-            Lmpop => &LMPOP,
-            Lpop => &LPOP,
-            Lpos => &LPOS,
-            Lpush => &LPUSH,
-            Lpushx => &LPUSHX,
-            Lrange => &LRANGE,
-            Lrem => &LREM,
-            Lset => &LSET,
-            Ltrim => &LTRIM,
-            Mget => &MGET,
-            Monitor => &MONITOR,
-            Move => &MOVE,
-            Mset => &MSET,
-            Msetnx => &MSETNX,
-            Multi => &MULTI,
-            Object => &OBJECT,
-            Persist => &PERSIST,
-            Pexpire => &PEXPIRE,
-            Pexpireat => &PEXPIREAT,
-            Pexpiretime => &PEXPIRETIME,
-            Ping => &PING,
-            Psetex => &PSETEX,
-            Psubscribe => &PSUBSCRIBE,
-            Pttl => &PTTL,
-            Publish => &PUBLISH,
-            Pubsub => &PUBSUB,
-            Punsubscribe => &PUNSUBSCRIBE,
-            Quit => &QUIT,
-            Rename => &RENAME,
-            Renamenx => &RENAMENX,
-            Reset => &RESET,
-            Rpop => &RPOP,
-            Rpoplpush => &RPOPLPUSH,
-            Rpush => &RPUSH,
-            Rpushx => &RPUSHX,
-            Sadd => &SADD,
-            Scard => &SCARD,
-            Select => &SELECT,
-            Set => &SET,
-            Setbit => &SETBIT,
-            Setex => &SETEX,
-            Setnx => &SETNX,
-            Setrange => &SETRANGE,
-            Sismember => &SISMEMBER,
-            Smembers => &SMEMBERS,
-            Smismember => &SMISMEMBER,
-            Spop => &SPOP,
-            Srem => &SREM,
-            Strlen => &STRLEN,
-            Subscribe => &SUBSCRIBE,
-            Swapdb => &SWAPDB,
-            Ttl => &TTL,
-            Type => &TYPE,
-            Unlink => &UNLINK,
-            Unsubscribe => &UNSUBSCRIBE,
-            Unwatch => &UNWATCH,
-            Unknown => &UNKNOWN,
-            Watch => &WATCH,
-            Zadd => &ZADD,
-            Zcard => &ZCARD,
-            Zcount => &ZCOUNT,
-            Zmpop => &ZMPOP,
-            Zpopmax => &ZPOPMAX,
-            Zpopmin => &ZPOPMIN,
-            Zrange => &ZRANGE,
-            Zrank => &ZRANK,
-            Zrangebyscore => &ZRANGEBYSCORE,
-            Zrem => &ZREM,
-            Zremrangebyscore => &ZREMRANGEBYSCORE,
-            Zrevrange => &ZREVRANGE,
-            Zrevrangebyscore => &ZREVRANGEBYSCORE,
-            Zscore => &ZSCORE,
+    #[test]
+    fn lookup_every_command() {
+        for command in ALL {
+            let found = lookup(command.name.as_bytes()).unwrap();
+            assert_eq!(found.kind, command.kind);
         }
     }
+
+    #[test]
+    fn lookup_subcommands() {
+        let kill = lookup_subcommand(&CLIENT, b"KILL").unwrap();
+        assert!(kill.arity.accepts(2));
+        let unblock = lookup_subcommand(&CLIENT, b"unblock").unwrap();
+        assert!(!unblock.arity.accepts(2));
+        assert!(unblock.arity.accepts(3));
+        assert!(unblock.arity.accepts(4));
+        assert!(!unblock.arity.accepts(5));
+        assert!(lookup_subcommand(&CLIENT, b"count").is_none());
+        assert!(lookup_subcommand(&GET, b"kill").is_none());
+    }
 }
@@ -1,10 +1,12 @@
 use crate::{
     Client, CommandResult, Reply, ReplyError, Store,
     buffer::ArrayBuffer,
-    bytes::{lex, parse},
-    command::{Arity, Command, CommandKind, Keys},
+    bytes::{lex, parse, parse_i64_exact},
+    db::{Raw, StringValue, Value},
     slice::slice,
+    store::WriteEffect,
 };
+use bytes::Bytes;
 use logos::Logos;
 use std::{
     cmp::{max, min},
@@ -21,31 +23,33 @@ pub enum Unit {
     Byte,
 }
 
-fn increment_field(field: Field, value: i64, by: i64, overflow: Overflow) -> Option<i64> {
-    let Field { signed, bits, .. } = field;
-
-    // First, check if the i64 add overflows.
-    let (result, mut wrapped) = value.overflowing_add(by);
+/// Convert `start` and `end` indexes in `unit` to bit indexes. Byte indexes are saturated so
+/// that very large or very small values are clamped to the value by `slice` later on.
+fn bit_range(start: i64, end: i64, unit: Unit) -> (i64, i64) {
+    match unit {
+        Unit::Bit => (start, end),
+        Unit::Byte => (
+            start.saturating_mul(8),
+            end.saturating_mul(8).saturating_add(7),
+        ),
+    }
+}
 
-    // Now check for overflow in smaller values.
-    wrapped |= if signed {
-        let mask = !0 << (bits - 1);
+/// Add `by` to a field `value`, handling results outside the range of the field according to
+/// `overflow`. Values are i128 so that both i64 and u64 fields fit, along with their sums.
+fn increment_field(field: Field, value: i128, by: i128, overflow: Overflow) -> Option<i128> {
+    let Field { signed, bits, .. } = field;
 
-        // Using two's complement, positive values should be all zeros on the left and negative
-        // values should be all ones.
-        if result >= 0 {
-            result & mask != 0
-        } else {
-            !result & mask != 0
-        }
+    let (min, max) = if signed {
+        (-1 << (bits - 1), (1 << (bits - 1)) - 1)
     } else {
-        let mask = !0 << bits;
-
-        // A negative value is an underflow, and any ones past the highest bit is an overflow.
-        result < 0 || mask & result != 0
+        (0, (1 << bits) - 1)
     };
 
-    if !wrapped {
+    // Both operands fit in 65 bits, so the sum can't overflow an i128.
+    let result = value + by;
+
+    if (min..=max).contains(&result) {
         return Some(result);
     }
 
@@ -53,19 +57,20 @@ fn increment_field(field: Field, value: i64, by: i64, overflow: Overflow) -> Opt
 
     match overflow {
         Fail => None,
-        // Prevent panic from shift left with overflow.
-        Wrap if bits >= 64 => Some(result),
-        Wrap => Some(result & !(!0 << bits)),
-        Sat => Some(match (signed, result < 0) {
-            (true, true) => !0 << (bits - 1),
-            (true, false) => !(!0 << (bits - 1)),
-            (false, true) => 0,
-            (false, false) => !(!0 << bits),
-        }),
+        Wrap => {
+            // Keep the low bits, then sign extend them for signed fields.
+            let shift = 128 - bits;
+            if signed {
+                Some((result << shift) >> shift)
+            } else {
+                Some(result & max)
+            }
+        }
+        Sat => Some(if result < min { min } else { max }),
     }
 }
 
-fn get_field(mut value: &[u8], field: Field) -> i64 {
+fn get_field(mut value: &[u8], field: Field) -> i128 {
     let Field {
         signed,
         bits,
@@ -81,18 +86,16 @@ fn get_field(mut value: &[u8], field: Field) -> i64 {
     let len = min(value.len(), buffer.len());
     buffer[..len].copy_from_slice(&value[..len]);
 
-    let result = if signed {
+    if signed {
         let result = i128::from_be_bytes(buffer) << (offset % 8);
-        i64::try_from(result >> (128 - bits))
+        result >> (128 - bits)
     } else {
         let result = u128::from_be_bytes(buffer) << (offset % 8);
-        i64::try_from(result >> (128 - bits))
-    };
-
-    result.expect("get_field i64 overflow")
+        i128::try_from(result >> (128 - bits)).expect("get_field i128 overflow")
+    }
 }
 
-fn set_field(value: &mut [u8], field: Field, n: i64) {
+fn set_field(value: &mut [u8], field: Field, n: i128) {
     let Field { bits, offset, .. } = field;
 
     // Slice just the required bytes, including leading and trailing bits.
@@ -135,19 +138,6 @@ fn set_field(value: &mut [u8], field: Field, n: i64) {
     value.copy_from_slice(&result[0..value.len()]);
 }
 
-pub static BITCOUNT: Command = Command {
-    kind: CommandKind::Bitcount,
-    name: "bitcount",
-    arity: Arity::Minimum(2),
-    run: bitcount,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
 trait CountBits {
     fn count_bits(&self) -> i64;
 }
@@ -165,7 +155,7 @@ macro_rules! impl_count_bits {
 impl_count_bits!(u8);
 impl_count_bits!(u128);
 
-fn bitcount(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn bitcount(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let mut buffer = ArrayBuffer::default();
@@ -176,16 +166,15 @@ fn bitcount(client: &mut Client, store: &mut Store) -> CommandResult {
         2 => {
             let start = client.request.i64()?;
             let end = client.request.i64()?;
-            (8 * start, 7 + 8 * end)
+            bit_range(start, end, Unit::Byte)
         }
         3 => {
             let start = client.request.i64()?;
             let end = client.request.i64()?;
-            match lex(&client.request.pop()?) {
-                Some(Unit::Bit) => (start, end),
-                Some(Unit::Byte) => (8 * start, 7 + 8 * end),
-                None => return Err(ReplyError::Syntax.into()),
-            }
+            let Some(unit) = lex(&client.request.pop()?) else {
+                return Err(ReplyError::Syntax.into());
+            };
+            bit_range(start, end, unit)
         }
         _ => return Err(ReplyError::Syntax.into()),
     };
@@ -241,32 +230,6 @@ fn bitcount(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static BITFIELD: Command = Command {
-    kind: CommandKind::Bitfield,
-    name: "bitfield",
-    arity: Arity::Minimum(2),
-    run: bitfield,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static BITFIELD_RO: Command = Command {
-    kind: CommandKind::Bitfieldro,
-    name: "bitfieldro",
-    arity: Arity::Minimum(2),
-    run: bitfield,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Logos, PartialEq)]
 pub enum BitfieldOpType {
     #[regex(b"(?i:get)")]
@@ -292,8 +255,8 @@ pub struct Field {
 #[derive(Debug, Eq, PartialEq)]
 pub enum BitfieldOp {
     Get(Field),
-    Set(Field, i64),
-    Incrby(Field, i64),
+    Set(Field, i128),
+    Incrby(Field, i128),
     Overflow(Overflow),
 }
 
@@ -305,16 +268,18 @@ fn field(client: &mut Client) -> Result<Field, ReplyError> {
         _ => return Err(ReplyError::InvalidBitfield),
     };
 
-    let bits = match parse(&arg[1..]) {
-        Some(bits) if signed && bits <= 64 && bits > 0 => bits,
-        Some(bits) if !signed && bits <= 63 && bits > 0 => bits,
+    let bits = match parse_i64_exact(&arg[1..]).and_then(|bits| usize::try_from(bits).ok()) {
+        Some(bits) if bits <= 64 && bits > 0 => bits,
         _ => return Err(ReplyError::InvalidBitfield),
     };
 
+    // A leading `#` multiplies the offset by the width of the field.
     let offset = client.request.pop()?;
     let offset = match offset.first() {
-        Some(b'#') => parse::<usize>(&offset[1..]).map(|n| n * bits),
-        _ => parse(&offset[..]),
+        Some(b'#') => parse_i64_exact(&offset[1..])
+            .and_then(|n| usize::try_from(n).ok())
+            .and_then(|n| n.checked_mul(bits)),
+        _ => parse_i64_exact(&offset[..]).and_then(|n| usize::try_from(n).ok()),
     }
     .ok_or(ReplyError::BitOffset)?;
 
@@ -354,6 +319,21 @@ fn overflow(client: &mut Client) -> Result<Overflow, ReplyError> {
     }
 }
 
+/// Parse a value for SET or INCRBY, which may be an i64 or, for u64 fields, a larger u64.
+fn field_value(client: &mut Client) -> Result<i128, ReplyError> {
+    let arg = client.request.pop()?;
+    parse_i64_exact(&arg)
+        .map(i128::from)
+        .or_else(|| {
+            // Only digits with no leading zero, so the value is exactly reproduced.
+            matches!(arg.first(), Some(b'1'..=b'9'))
+                .then(|| parse::<u64>(&arg))
+                .flatten()
+                .map(i128::from)
+        })
+        .ok_or(ReplyError::Integer)
+}
+
 fn bitfield_op(client: &mut Client, readonly: bool) -> Result<BitfieldOp, ReplyError> {
     let argument = client.request.pop()?;
     let Some(op) = lex(&argument[..]) else {
@@ -369,12 +349,12 @@ fn bitfield_op(client: &mut Client, readonly: bool) -> Result<BitfieldOp, ReplyE
         }
         Set => {
             let field = field(client)?;
-            let value = client.request.i64()?;
+            let value = field_value(client)?;
             Ok(BitfieldOp::Set(field, value))
         }
         Incrby => {
             let field = field(client)?;
-            let value = client.request.i64()?;
+            let value = field_value(client)?;
             Ok(BitfieldOp::Incrby(field, value))
         }
         Overflow => {
@@ -384,20 +364,25 @@ fn bitfield_op(client: &mut Client, readonly: bool) -> Result<BitfieldOp, ReplyE
     }
 }
 
-fn bitfield(client: &mut Client, store: &mut Store) -> CommandResult {
-    client.request.reset(2);
+pub(super) fn bitfield(client: &mut Client, store: &mut Store) -> CommandResult {
+    let key = client.request.pop()?;
+    let readonly = client.request.command.readonly;
+    let limit = store.string_limit();
+    let mut ops = Vec::new();
     let mut count = 0;
     let mut last_write = None;
-    let readonly = client.request.command.readonly;
 
-    // Count the operations, check for writes
+    // Parse every operation, checking offsets and for writes, before touching the value.
     while !client.request.is_empty() {
         use BitfieldOp::*;
-        match bitfield_op(client, readonly)? {
-            Get(_) => {
+        let op = bitfield_op(client, readonly)?;
+        match op {
+            Get(field) => {
+                limit.bit_offset(field.offset)?;
                 count += 1;
             }
             Incrby(field, _) | Set(field, _) => {
+                limit.bit_offset(field.offset)?;
                 count += 1;
                 let byte = (field.offset + field.bits - 1) / 8 + 1;
                 let max = max(byte, last_write.unwrap_or(0));
@@ -405,30 +390,32 @@ fn bitfield(client: &mut Client, store: &mut Store) -> CommandResult {
             }
             Overflow(_) => {}
         }
+        ops.push(op);
     }
 
-    client.request.reset(1);
-    client.reply(Reply::Array(count));
-
     if let Some(byte) = last_write {
-        bitfield_write(client, store, byte)
+        bitfield_write(client, store, &key, &ops, count, byte)
     } else {
-        bitfield_read(client, store)
+        bitfield_read(client, store, &key, &ops, count)
     }
 }
 
-fn bitfield_read(client: &mut Client, store: &mut Store) -> CommandResult {
-    let readonly = client.request.command.readonly;
-    let key = client.request.pop()?;
+fn bitfield_read(
+    client: &mut Client,
+    store: &mut Store,
+    key: &Bytes,
+    ops: &[BitfieldOp],
+    count: usize,
+) -> CommandResult {
     let db = store.get_db(client.db())?;
     let mut buffer = ArrayBuffer::default();
     let value = db
-        .get_string(&key)?
+        .get_string(key)?
         .map_or(&[][..], |value| value.as_bytes(&mut buffer));
 
-    while !client.request.is_empty() {
-        use BitfieldOp::*;
-        if let Get(field) = bitfield_op(client, readonly)? {
+    client.reply(Reply::Array(count));
+    for op in ops {
+        if let BitfieldOp::Get(field) = *op {
             client.reply(get_field(value, field));
         }
     }
@@ -436,15 +423,20 @@ fn bitfield_read(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-fn bitfield_write(client: &mut Client, store: &mut Store, last_write: usize) -> CommandResult {
+fn bitfield_write(
+    client: &mut Client,
+    store: &mut Store,
+    key: &Bytes,
+    ops: &[BitfieldOp],
+    count: usize,
+    last_write: usize,
+) -> CommandResult {
     let mut created = false;
-    let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let value = db
-        .entry_ref(&key)
-        .or_insert_with(|| {
+        .get_or_insert_with(key, || {
             created = true;
-            Vec::with_capacity(last_write).into()
+            Value::String(StringValue::Raw(Vec::with_capacity(last_write).into()))
         })
         .mut_string()?
         .raw()
@@ -454,12 +446,15 @@ fn bitfield_write(client: &mut Client, store: &mut Store, last_write: usize) ->
         value.resize(last_write, 0);
     }
 
+    // Only reply once the value is known to be a string, so an error can't follow the array.
+    client.reply(Reply::Array(count));
+
     let mut changes = 0;
     let mut overflow = Overflow::Wrap;
-    while !client.request.is_empty() {
+    for op in ops {
         use BitfieldOp::*;
 
-        match bitfield_op(client, false)? {
+        match *op {
             Get(field) => {
                 client.reply(get_field(value, field));
             }
@@ -494,29 +489,23 @@ fn bitfield_write(client: &mut Client, store: &mut Store, last_write: usize) ->
     }
 
     if changes > 0 {
-        store.dirty += changes;
-        store.touch(client.db(), &key);
+        store.write(
+            client.db(),
+            key,
+            WriteEffect::new("setbit").changes(changes),
+        );
     }
 
     Ok(None)
 }
 
-pub static BITOP: Command = Command {
-    kind: CommandKind::Bitop,
-    name: "bitop",
-    arity: Arity::Minimum(4),
-    run: bitop,
-    keys: Keys::SkipOne,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Debug, Eq, PartialEq)]
 pub enum Bitop {
     And,
+    Andor,
+    Diff,
+    Diff1,
+    One,
     Or,
     Xor,
 }
@@ -526,18 +515,68 @@ pub enum BitopType {
     #[regex(b"(?i:and)")]
     And,
 
+    #[regex(b"(?i:andor)")]
+    Andor,
+
+    #[regex(b"(?i:diff)")]
+    Diff,
+
+    #[regex(b"(?i:diff1)")]
+    Diff1,
+
+    #[regex(b"(?i:not)")]
+    Not,
+
+    #[regex(b"(?i:one)")]
+    One,
+
     #[regex(b"(?i:or)")]
     Or,
 
     #[regex(b"(?i:xor)")]
     Xor,
+}
 
-    #[regex(b"(?i:not)")]
-    Not,
+/// Read up to 16 bytes into a lane, padding with zeros.
+fn lane(bytes: &[u8]) -> u128 {
+    let mut buffer = [0u8; 16];
+    let len = min(bytes.len(), buffer.len());
+    buffer[..len].copy_from_slice(&bytes[..len]);
+    u128::from_ne_bytes(buffer)
+}
+
+/// Combine `bytes` into `result` with `f`, one u128 lane at a time. Bytes past the end of `bytes`
+/// are treated as zeros.
+fn combine<F>(result: &mut [u8], bytes: &[u8], mut f: F)
+where
+    F: FnMut(u128, u128) -> u128,
+{
+    // Whole lanes on both sides are read directly, so that only the last few need padding.
+    let whole = min(result.len(), bytes.len()) / 16 * 16;
+    let (head, tail) = result.split_at_mut(whole);
+    for (chunk, other) in head.chunks_exact_mut(16).zip(bytes.chunks_exact(16)) {
+        let a = u128::from_ne_bytes(chunk.try_into().unwrap());
+        let b = u128::from_ne_bytes(other.try_into().unwrap());
+        chunk.copy_from_slice(&f(a, b).to_ne_bytes());
+    }
+
+    // Past the end of `bytes`, there's nothing to pad.
+    let rest = &bytes[whole..];
+    for (index, chunk) in tail.chunks_mut(16).enumerate() {
+        let a = match <[u8; 16]>::try_from(&*chunk) {
+            Ok(a) => u128::from_ne_bytes(a),
+            Err(_) => lane(chunk),
+        };
+        let b = match rest.get(16 * index..) {
+            Some(other) if !other.is_empty() => lane(other),
+            _ => 0,
+        };
+        let value = f(a, b).to_ne_bytes();
+        chunk.copy_from_slice(&value[..chunk.len()]);
+    }
 }
 
-// TODO: Try out packed_simd crate here
-fn bitop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn bitop(client: &mut Client, store: &mut Store) -> CommandResult {
     let op = {
         let op = client.request.pop()?;
         let Some(op) = lex(&op) else {
@@ -547,18 +586,36 @@ fn bitop(client: &mut Client, store: &mut Store) -> CommandResult {
         use BitopType::*;
         match op {
             And => Bitop::And,
+            Andor => Bitop::Andor,
+            Diff => Bitop::Diff,
+            Diff1 => Bitop::Diff1,
+            Not => return bitop_not(client, store),
+            One => Bitop::One,
             Or => Bitop::Or,
             Xor => Bitop::Xor,
-            Not => return bitop_not(client, store),
         }
     };
 
     let destination = client.request.pop()?;
+
+    // DIFF, DIFF1, and ANDOR compare the first key to all the others.
+    if matches!(op, Bitop::Andor | Bitop::Diff | Bitop::Diff1) && client.request.remaining() < 2 {
+        return Err(ReplyError::BitopSources.into());
+    }
+
     let db = store.mut_db(client.db())?;
     let mut max_len = 0;
     let mut buffer = ArrayBuffer::default();
 
-    for key in client.request.iter() {
+    // How many times the destination is also a source, and where it was last.
+    let mut occurrences = 0;
+    let mut position = 0;
+
+    for (index, key) in client.request.iter().enumerate() {
+        if key == destination {
+            occurrences += 1;
+            position = index;
+        }
         if let Some(value) = db.get(&key) {
             let len = value.as_string()?.as_bytes(&mut buffer).len();
             max_len = max(len, max_len);
@@ -568,43 +625,95 @@ fn bitop(client: &mut Client, store: &mut Store) -> CommandResult {
     // Are all the keys empty?
     if max_len == 0 {
         if db.remove(&destination).is_some() {
-            store.dirty += 1;
-            store.touch(client.db(), &destination);
+            store.write(client.db(), &destination, WriteEffect::new("del"));
         }
         client.reply(0);
         return Ok(None);
     }
 
+    client.request.reset(3);
+
     use Bitop::*;
 
-    let init = match op {
-        And => 0xff,
-        Or => 0,
-        Xor => 0,
+    // When the destination is a source once, and not the first key that DIFF, DIFF1, and ANDOR
+    // treat differently, combine the other keys into its bytes rather than a new vector. Each
+    // operation gives the same result when it starts from the destination's bytes.
+    let first_differs = matches!(op, Andor | Diff | Diff1) && position == 0;
+    let mut taken = if occurrences == 1 && !first_differs {
+        db.mut_string(&destination)?.map(std::mem::take)
+    } else {
+        None
     };
 
-    let op = match op {
-        And => |a: u8, b: u8| a & b,
-        Or => |a: u8, b: u8| a | b,
-        Xor => |a: u8, b: u8| a ^ b,
+    let skip = taken.is_some().then_some(position);
+
+    let init = if op == And { 0xff } else { 0 };
+    let mut scratch = Vec::new();
+    let result = if let Some(value) = &mut taken {
+        let bytes = value.raw().make_mut_with_capacity(max_len);
+        bytes.resize(max_len, 0);
+        bytes
+    } else {
+        scratch.resize(max_len, init);
+        &mut scratch
     };
 
-    let mut result = vec![init; max_len];
-    client.request.reset(3);
+    // The bits seen in more than one key for ONE, by lane.
+    let mut multiple: Vec<u128> = if op == One {
+        vec![0; max_len.div_ceil(16)]
+    } else {
+        Vec::new()
+    };
+
+    // The first key, for DIFF, DIFF1, and ANDOR.
+    let mut first = Vec::new();
 
-    for key in client.request.iter() {
-        let bytes = match db.get(&key) {
+    let snapshot = db.snapshot();
+    for (index, key) in client.request.iter().enumerate() {
+        if skip == Some(index) {
+            continue;
+        }
+
+        let bytes = match snapshot.get(&key) {
             Some(value) => value.as_string()?.as_bytes(&mut buffer),
             None => &[],
         };
-        for (index, value) in result.iter_mut().enumerate() {
-            *value = op(*bytes.get(index).unwrap_or(&0), *value);
+
+        match op {
+            And => combine(result, bytes, |a, b| a & b),
+            Or => combine(result, bytes, |a, b| a | b),
+            Xor => combine(result, bytes, |a, b| a ^ b),
+            One => {
+                let mut lanes = multiple.iter_mut();
+                combine(result, bytes, |a, b| {
+                    if let Some(lane) = lanes.next() {
+                        *lane |= a & b;
+                    }
+                    a | b
+                });
+            }
+            Andor | Diff | Diff1 if index == 0 => first.extend_from_slice(bytes),
+            Andor | Diff | Diff1 => combine(result, bytes, |a, b| a | b),
         }
     }
+    drop(snapshot);
 
-    db.set(&destination, result);
-    store.dirty += 1;
-    store.touch(client.db(), &destination);
+    match op {
+        One => {
+            let mut lanes = multiple.iter();
+            combine(result, &[], |a, _| a & !lanes.next().unwrap_or(&0));
+        }
+        Andor => combine(result, &first, |a, b| b & a),
+        Diff => combine(result, &first, |a, b| b & !a),
+        Diff1 => combine(result, &first, |a, b| !b & a),
+        And | Or | Xor => {}
+    }
+
+    match taken {
+        Some(mut value) => db.set(&destination, std::mem::take(value.raw())),
+        None => db.set(&destination, scratch),
+    };
+    store.write(client.db(), &destination, WriteEffect::new("set"));
     client.reply(max_len);
     Ok(None)
 }
@@ -618,55 +727,56 @@ fn bitop_not(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     let db = store.mut_db(client.db())?;
-    let mut buffer = ArrayBuffer::default();
-    let value = db
-        .get_string(&source)?
-        .map_or(&[][..], |value| value.as_bytes(&mut buffer));
-    let len = value.len();
+    let len = db.get_string(&source)?.map_or(0, |value| value.len());
 
-    if value.is_empty() {
+    if len == 0 {
         if db.remove(&destination).is_some() {
-            store.dirty += 1;
-            store.touch(client.db(), &destination);
+            store.write(client.db(), &destination, WriteEffect::new("del"));
         }
         client.reply(0);
+        return Ok(None);
+    }
+
+    // Negate the source in place when it's also the destination, rather than copying it.
+    let result = if source == destination {
+        let mut raw = db
+            .mut_string(&source)?
+            .map(|value| std::mem::take(value.raw()))
+            .unwrap_or_default();
+        negate(raw.make_mut());
+        raw
     } else {
-        let mut result: Vec<u8> = Vec::from(value);
+        let mut buffer = ArrayBuffer::default();
+        let value = db
+            .get_string(&source)?
+            .map_or(&[][..], |value| value.as_bytes(&mut buffer));
+        let mut result = Vec::from(value);
+        negate(&mut result);
+        Raw::from(result)
+    };
 
-        // SAFETY: There are no invalid bit patterns for u128 and we only use them to negate bits.
-        let (prefix, middle, suffix) = unsafe { result.align_to_mut::<u128>() };
+    db.set(&destination, result);
+    store.write(client.db(), &destination, WriteEffect::new("set"));
+    client.reply(len);
+    Ok(None)
+}
 
-        for x in prefix {
-            *x = !*x;
-        }
-        for x in middle {
-            *x = !*x;
-        }
-        for x in suffix {
-            *x = !*x;
-        }
+/// Negate every bit in `bytes`.
+fn negate(bytes: &mut [u8]) {
+    // SAFETY: There are no invalid bit patterns for u128 and we only use them to negate bits.
+    let (prefix, middle, suffix) = unsafe { bytes.align_to_mut::<u128>() };
 
-        db.set(&destination, result);
-        store.dirty += 1;
-        store.touch(client.db(), &destination);
-        client.reply(len);
+    for x in prefix {
+        *x = !*x;
+    }
+    for x in middle {
+        *x = !*x;
+    }
+    for x in suffix {
+        *x = !*x;
     }
-    Ok(None)
 }
 
-pub static BITPOS: Command = Command {
-    kind: CommandKind::Bitpos,
-    name: "bitpos",
-    arity: Arity::Minimum(3),
-    run: bitpos,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
 trait BitIndex: std::fmt::Debug {
     const SIZE: usize;
     fn bit_index(&self, bit: bool) -> Option<usize>;
@@ -697,7 +807,7 @@ macro_rules! impl_bit_index {
 impl_bit_index!(u8);
 impl_bit_index!(u128);
 
-fn bitpos(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn bitpos(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let bit = client.request.bit()?;
     let end_given = client.request.len() > 4;
@@ -705,12 +815,12 @@ fn bitpos(client: &mut Client, store: &mut Store) -> CommandResult {
         0 => (0, -1),
         1 => {
             let start = client.request.i64()?;
-            (8 * start, -1)
+            bit_range(start, -1, Unit::Byte)
         }
         2 => {
             let start = client.request.i64()?;
             let end = client.request.i64()?;
-            (8 * start, 7 + 8 * end)
+            bit_range(start, end, Unit::Byte)
         }
         3 => {
             let start = client.request.i64()?;
@@ -719,11 +829,7 @@ fn bitpos(client: &mut Client, store: &mut Store) -> CommandResult {
             let Some(unit) = lex(&unit) else {
                 return Err(ReplyError::Syntax.into());
             };
-
-            match unit {
-                Unit::Bit => (start, end),
-                Unit::Byte => (8 * start, 7 + 8 * end),
-            }
+            bit_range(start, end, unit)
         }
         _ => return Err(ReplyError::Syntax.into()),
     };
@@ -787,22 +893,11 @@ fn bitpos(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static GETBIT: Command = Command {
-    kind: CommandKind::Getbit,
-    name: "getbit",
-    arity: Arity::Exact(3),
-    run: getbit,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn getbit(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn getbit(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let offset = client.request.bit_offset()?;
+    let offset = store
+        .string_limit()
+        .bit_offset(client.request.bit_offset()?)?;
     let db = store.get_db(client.db())?;
     let mut buffer = ArrayBuffer::default();
     let value = db.get_string(&key[..])?.ok_or(0)?.as_bytes(&mut buffer);
@@ -817,22 +912,11 @@ fn getbit(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SETBIT: Command = Command {
-    kind: CommandKind::Setbit,
-    name: "setbit",
-    arity: Arity::Exact(4),
-    run: setbit,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn setbit(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn setbit(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let offset = client.request.bit_offset()?;
+    let offset = store
+        .string_limit()
+        .bit_offset(client.request.bit_offset()?)?;
     let bit = client.request.bit()?;
 
     let bytes = offset / 8;
@@ -842,10 +926,9 @@ fn setbit(client: &mut Client, store: &mut Store) -> CommandResult {
     let mut created = false;
     let db = store.mut_db(client.db())?;
     let value = db
-        .entry_ref(&key)
-        .or_insert_with(|| {
+        .get_or_insert_with(&key, || {
             created = true;
-            Vec::with_capacity(bytes).into()
+            Value::String(StringValue::Raw(Vec::with_capacity(bytes + 1).into()))
         })
         .mut_string()?
         .raw()
@@ -864,8 +947,7 @@ fn setbit(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     if created || bit != original {
-        store.dirty += 1;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("setbit"));
     }
 
     client.reply(i64::from(original));
@@ -1,94 +1,36 @@
 use crate::{
-    CommandResult, VERSION,
-    bytes::lex,
-    client::{Argument, Client, ClientId, ReplyMode, Tx},
-    command::{ALL, Arity, Command, CommandKind, Keys},
+    BlockResult, CommandResult, VERSION,
+    bytes::{lex, parse_i64_exact},
+    client::{Addr, Argument, Client, ClientId, ClientInfo, Endpoint, ReplyMode, Tx},
+    command::{ALL, Arity, Command, CommandKind, FindKeys, Keys, lookup},
     config::YesNoOption,
-    db::DBIndex,
-    epoch, glob,
+    db::{DBIndex, KeyTypes},
+    epoch,
+    glob::Pattern,
     reply::{Reply, ReplyError},
     store::{Monitor, Store},
 };
 use bytes::Bytes;
 use logos::Logos;
 use respite::RespVersion;
-use std::io::Write;
+use std::{io::Write, time::Duration};
 
-pub static CLIENT: Command = Command {
-    kind: CommandKind::Client,
-    name: "client",
-    arity: Arity::Minimum(2),
-    run: client,
-    keys: Keys::None,
-    readonly: false,
-    admin: true,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
-pub enum ClientSubcommand {
-    #[regex(b"(?i:getname)")]
-    Getname,
-
-    #[regex(b"(?i:help)")]
-    Help,
-
-    #[regex(b"(?i:id)")]
-    Id,
-
-    #[regex(b"(?i:info)")]
-    Info,
-
-    #[regex(b"(?i:kill)")]
-    Kill,
-
-    #[regex(b"(?i:list)")]
-    List,
-
-    #[regex(b"(?i:reply)")]
-    Reply,
-
-    #[regex(b"(?i:setname)")]
-    Setname,
-
-    #[regex(b"(?i:unblock)")]
-    Unblock,
-}
-
-fn client(client: &mut Client, store: &mut Store) -> CommandResult {
-    let len = client.request.len();
-    let subcommand = client.request.pop()?;
-
-    use ClientSubcommand::*;
-    let subcommand = match (lex(&subcommand[..]), len) {
-        (Some(Getname), 2) => getname,
-        (Some(Help), 2) => client_help,
-        (Some(Id), 2) => client_id,
-        (Some(Info), 2) => client_info,
-        (Some(Kill), _) => kill,
-        (Some(List), _) => list,
-        (Some(Reply), 3) => client_reply,
-        (Some(Setname), 3) => setname,
-        (Some(Unblock), 3..=4) => unblock,
-        _ => return Err(client.request.unknown_subcommand().into()),
-    };
-
-    subcommand(client, store)
+pub(super) fn client(client: &mut Client, store: &mut Store) -> CommandResult {
+    let subcommand = client.request.subcommand()?;
+    (subcommand.run)(client, store)
 }
 
-fn client_help(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn client_help(client: &mut Client, _: &mut Store) -> CommandResult {
     client.verbatim("txt", include_str!("../help/client.txt"));
     Ok(None)
 }
 
-fn client_id(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn client_id(client: &mut Client, _: &mut Store) -> CommandResult {
     client.reply(client.id);
     Ok(None)
 }
 
-fn client_info(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn client_info(client: &mut Client, store: &mut Store) -> CommandResult {
     let mut buffer = Vec::new();
     let info = store.clients.get(&client.id).expect("client not found");
     info.write_info(store, &mut buffer);
@@ -96,7 +38,7 @@ fn client_info(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-fn getname(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn getname(client: &mut Client, _: &mut Store) -> CommandResult {
     client.reply(client.name.clone());
     Ok(None)
 }
@@ -113,23 +55,30 @@ pub enum ReplyModeOption {
     Skip,
 }
 
-fn client_reply(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn client_reply(client: &mut Client, _: &mut Store) -> CommandResult {
     let Some(option) = lex(&client.request.pop()?[..]) else {
         return Err(ReplyError::Syntax.into());
     };
 
     use ReplyModeOption::*;
-    let reply_mode = match option {
-        On => ReplyMode::On,
-        Off => ReplyMode::Off,
-        Skip => ReplyMode::Skip,
-    };
-    client.set_reply_mode(reply_mode);
-    client.reply("OK");
+    match option {
+        On => {
+            client.set_reply_mode(ReplyMode::On);
+            client.reply("OK");
+        }
+        Off => client.set_reply_mode(ReplyMode::Off),
+        Skip => {
+            client.skip_next_reply();
+            // EXEC already promised a reply for every queued command.
+            if client.in_exec {
+                client.reply("OK");
+            }
+        }
+    }
     Ok(None)
 }
 
-fn setname(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn setname(client: &mut Client, store: &mut Store) -> CommandResult {
     let name = client_name(client)?;
     store.set_name(client, name);
     client.reply("OK");
@@ -150,46 +99,60 @@ fn client_name(client: &mut Client) -> Result<Option<Bytes>, ReplyError> {
     }
 }
 
-pub static HELLO: Command = Command {
-    kind: CommandKind::Hello,
-    name: "hello",
-    arity: Arity::Minimum(1),
-    run: hello,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum HelloOption {
+    #[regex(b"(?i:auth)")]
+    Auth,
+
     #[regex(b"(?i:setname)")]
     Setname,
 }
 
-fn hello(client: &mut Client, store: &mut Store) -> CommandResult {
-    let version = client.request.usize().map_err(|_| ReplyError::Noproto)?;
-    let version = match version {
-        2 => RespVersion::V2,
-        3 => RespVersion::V3,
-        _ => return Err(ReplyError::Noproto.into()),
+pub(super) fn hello(client: &mut Client, store: &mut Store) -> CommandResult {
+    // Switching protocols inside a transaction would change the format of the EXEC reply
+    // partway through, so HELLO isn't queued and can't run until the transaction ends.
+    if !matches!(client.tx(), Tx::None) {
+        client.reply(ReplyError::HelloInMulti);
+        return Ok(None);
+    }
+
+    let version = if client.request.is_empty() {
+        client.protocol()
+    } else {
+        let version = client
+            .request
+            .i64()
+            .map_err(|_| ReplyError::ProtocolVersion)?;
+        match version {
+            2 => RespVersion::V2,
+            3 => RespVersion::V3,
+            _ => return Err(ReplyError::Noproto.into()),
+        }
     };
 
+    // Parse every option before applying any, so an error leaves the client unchanged.
+    let mut name = None;
     while !client.request.is_empty() {
         use HelloOption::*;
         let argument = client.request.pop()?;
 
         match lex(&argument[..]) {
-            Some(Setname) => {
-                let name = client_name(client)?;
-                store.set_name(client, name);
+            Some(Auth) if client.request.remaining() >= 2 => {
+                let username = client.request.pop()?;
+                let password = client.request.pop()?;
+                authenticate(&username, &password)?;
+            }
+            Some(Setname) if !client.request.is_empty() => {
+                name = Some(client_name(client)?);
             }
-            None => return Err(ReplyError::Hello(argument).into()),
+            _ => return Err(ReplyError::Hello(argument).into()),
         }
     }
 
+    if let Some(name) = name {
+        store.set_name(client, name);
+    }
+
     client.set_protocol(version);
 
     client.reply(Reply::Map(4));
@@ -209,20 +172,17 @@ fn hello(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static QUIT: Command = Command {
-    kind: CommandKind::Quit,
-    name: "quit",
-    arity: Arity::Minimum(1),
-    run: quit,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
+/// Check a username and password. There are no users or passwords to configure yet, so the
+/// default user is always enabled and accepts any password.
+fn authenticate(username: &[u8], _password: &[u8]) -> Result<(), ReplyError> {
+    if username == b"default" {
+        Ok(())
+    } else {
+        Err(ReplyError::WrongPass)
+    }
+}
 
-fn quit(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn quit(client: &mut Client, _: &mut Store) -> CommandResult {
     client.reply("OK");
     client.quit();
     Ok(None)
@@ -243,20 +203,18 @@ pub enum KillOption {
     Skipme,
 }
 
-fn kill(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn kill(client: &mut Client, store: &mut Store) -> CommandResult {
+    if client.request.remaining() == 1 {
+        return kill_addr(client, store);
+    }
+
+    // Filters are `None` when they aren't given, and `Some(None)` for an address that can't
+    // match any client.
     let mut addr = None;
     let mut id = None;
     let mut laddr = None;
     let mut skipme = true;
 
-    if client.request.remaining() == 1 {
-        if let Some(x) = client.request.addr()? {
-            addr = Some(x);
-        } else {
-            return Err(ReplyError::Syntax.into());
-        }
-    }
-
     while !client.request.is_empty() {
         let Some(option) = lex(&client.request.pop()?[..]) else {
             return Err(ReplyError::Syntax.into());
@@ -267,13 +225,13 @@ fn kill(client: &mut Client, store: &mut Store) -> CommandResult {
         match option {
             // TODO: TYPE/USER
             Addr => {
-                addr = client.request.addr()?;
+                addr = Some(client.request.addr()?);
             }
             Id => {
                 id = Some(ClientId(client.request.i64()?));
             }
             Laddr => {
-                laddr = client.request.addr()?;
+                laddr = Some(client.request.addr()?);
             }
             Skipme => match lex(&client.request.pop()?[..]) {
                 Some(Yes) => {
@@ -287,46 +245,77 @@ fn kill(client: &mut Client, store: &mut Store) -> CommandResult {
         }
     }
 
-    // Should the current client quit after replying?
-    let mut quit = false;
+    let (count, quit) = kill_clients(client, store, |other| {
+        if skipme && other.id == client.id {
+            return false;
+        }
 
-    let count = store
-        .clients
-        .values_mut()
-        .filter(|other| {
-            if skipme && other.id == client.id {
-                return false;
-            }
+        // Every filter that's given must match.
+        let endpoint = |filter: &Option<Option<Endpoint>>, get: fn(&Addr) -> &Endpoint| {
+            filter.as_ref().is_none_or(|filter| {
+                filter.is_some() && filter.as_ref() == other.addr.as_ref().map(get)
+            })
+        };
 
-            if id == Some(other.id) {
-                return true;
-            }
+        id.is_none_or(|id| id == other.id)
+            && endpoint(&laddr, |addr| &addr.local)
+            && endpoint(&addr, |addr| &addr.peer)
+    });
 
-            if laddr == other.addr.map(|a| a.local) {
-                return true;
-            }
+    client.reply(count);
+    if quit {
+        client.quit();
+    }
+    Ok(None)
+}
 
-            if addr == other.addr.map(|a| a.peer) {
-                return true;
-            }
+/// The old form, `CLIENT KILL addr:port`, replies with OK instead of a count, and can kill the
+/// current client.
+fn kill_addr(client: &mut Client, store: &mut Store) -> CommandResult {
+    let Some(addr) = client.request.addr()? else {
+        return Err(ReplyError::NoSuchClient.into());
+    };
 
-            false
-        })
+    let (count, quit) = kill_clients(client, store, |other| {
+        other.addr.as_ref().is_some_and(|other| other.peer == addr)
+    });
+
+    if count == 0 {
+        return Err(ReplyError::NoSuchClient.into());
+    }
+
+    client.reply("OK");
+    if quit {
+        client.quit();
+    }
+    Ok(None)
+}
+
+/// Ask every client that `matches` to quit, and return how many matched and whether the current
+/// client should quit after replying.
+fn kill_clients(
+    client: &Client,
+    store: &mut Store,
+    matches: impl Fn(&ClientInfo) -> bool,
+) -> (usize, bool) {
+    let mut quit = false;
+
+    let count = store
+        .clients
+        .values_mut()
+        .filter(|other| matches(other))
         .map(|other| {
             if other.id == client.id {
                 quit = true;
             } else {
                 other.quit();
                 store.blocking.remove(other.id);
+                store.pubsub.disconnect(other.id);
             }
         })
         .count();
 
-    client.reply(count);
-    if quit {
-        client.quit();
-    }
-    Ok(None)
+    (count, quit)
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
@@ -335,7 +324,7 @@ pub enum ListOption {
     Id,
 }
 
-fn list(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn list(client: &mut Client, store: &mut Store) -> CommandResult {
     if client.request.is_empty() {
         let mut buffer = Vec::new();
         for info in store.clients.values() {
@@ -370,19 +359,19 @@ pub enum UnblockOption {
     Timeout,
 }
 
-fn unblock(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn unblock(client: &mut Client, store: &mut Store) -> CommandResult {
     let id = ClientId(client.request.i64()?);
-    let mut reply = Reply::Nil;
+    let mut error = None;
 
     if !client.request.is_empty() {
-        reply = match lex(&client.request.pop()?[..]) {
-            Some(UnblockOption::Error) => ReplyError::Unblocked.into(),
-            Some(UnblockOption::Timeout) => Reply::Nil,
+        error = match lex(&client.request.pop()?[..]) {
+            Some(UnblockOption::Error) => Some(ReplyError::Unblocked),
+            Some(UnblockOption::Timeout) => None,
             None => return Err(Reply::from(ReplyError::Syntax)),
         };
     }
 
-    if store.blocking.unblock_with(id, reply) {
+    if store.blocking.unblock(id, error) {
         client.reply(1);
     } else {
         client.reply(0);
@@ -390,57 +379,31 @@ fn unblock(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static DISCARD: Command = Command {
-    kind: CommandKind::Discard,
-    name: "discard",
-    arity: Arity::Exact(1),
-    run: discard,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-fn discard(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn discard(client: &mut Client, store: &mut Store) -> CommandResult {
     client.discard(store);
     client.reply("OK");
     Ok(None)
 }
 
-pub static EXEC: Command = Command {
-    kind: CommandKind::Exec,
-    name: "exec",
-    arity: Arity::Exact(1),
-    run: exec,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-fn exec(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn exec(client: &mut Client, store: &mut Store) -> CommandResult {
     let count = match client.set_tx(Tx::None) {
         Tx::None => return Err(ReplyError::ExecWithoutMulti.into()),
         Tx::Error(_) => {
-            client.queue.clear();
+            client.clear_queue();
             return Err(ReplyError::ExecAbort.into());
         }
         Tx::Some(count) => count,
     };
 
     if store.is_dirty(client.id) {
-        client.queue.clear();
+        client.clear_queue();
         store.unwatch(client.id);
         return Err(Reply::Nil);
     }
 
     client.reply(Reply::Array(count));
     client.in_exec = true;
-    client.request.clear();
+    client.clear_request();
 
     for _ in 0..count {
         while let Some(Argument::Push(argument)) = client.queue.pop_front() {
@@ -449,27 +412,14 @@ fn exec(client: &mut Client, store: &mut Store) -> CommandResult {
         client.run(store);
     }
 
-    client.queue.clear();
+    client.clear_queue();
     client.in_exec = false;
 
     store.unwatch(client.id);
     Ok(None)
 }
 
-pub static MULTI: Command = Command {
-    kind: CommandKind::Multi,
-    name: "multi",
-    arity: Arity::Exact(1),
-    run: multi,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-fn multi(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn multi(client: &mut Client, _: &mut Store) -> CommandResult {
     if !matches!(client.tx(), Tx::None) {
         return Err(ReplyError::MultiNested.into());
     }
@@ -479,20 +429,7 @@ fn multi(client: &mut Client, _: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static WATCH: Command = Command {
-    kind: CommandKind::Watch,
-    name: "watch",
-    arity: Arity::Minimum(2),
-    run: watch,
-    keys: Keys::All,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-fn watch(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn watch(client: &mut Client, store: &mut Store) -> CommandResult {
     if !matches!(client.tx(), Tx::None) {
         client.reply(ReplyError::WatchInMulti);
         return Ok(None);
@@ -511,59 +448,98 @@ fn watch(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static UNWATCH: Command = Command {
-    kind: CommandKind::Unwatch,
-    name: "unwatch",
-    arity: Arity::Exact(1),
-    run: unwatch,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
+/// Replication isn't supported, so no replica ever acknowledges a write.
+const ACKNOWLEDGED: i64 = 0;
 
-fn unwatch(client: &mut Client, store: &mut Store) -> CommandResult {
-    store.unwatch(client.id);
-    client.reply("OK");
-    Ok(None)
+pub(super) fn wait(client: &mut Client, _: &mut Store) -> CommandResult {
+    let replicas = client.request.i64()?;
+    let timeout = client
+        .request
+        .i64()
+        .map_err(|_| ReplyError::TimeoutInteger)?;
+    let timeout = u64::try_from(timeout).map_err(|_| ReplyError::NegativeTimeout)?;
+
+    if replicas <= ACKNOWLEDGED {
+        client.reply(ACKNOWLEDGED);
+        return Ok(None);
+    }
+
+    let timeout = Duration::from_millis(timeout);
+    let block = BlockResult::when(timeout, acknowledged, ACKNOWLEDGED);
+    Ok(Some(block))
 }
 
-pub static COMMAND: Command = Command {
-    kind: CommandKind::Command,
-    name: "command",
-    arity: Arity::Minimum(1),
-    run: command,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
+/// Have enough replicas acknowledged the writes before a WAIT?
+fn acknowledged(client: &Client, _: &Store) -> bool {
+    let replicas = client.request.get(1).as_deref().and_then(parse_i64_exact);
+    replicas.is_some_and(|replicas| replicas <= ACKNOWLEDGED)
+}
+
+/// Without replication, this server is always a master with no replicas and nothing replicated.
+pub(super) fn role(client: &mut Client, _: &mut Store) -> CommandResult {
+    client.reply(Reply::Array(3));
+    client.reply("master");
+    client.reply(0);
+    client.reply(Reply::Array(0));
+    Ok(None)
+}
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
-pub enum CommandSubcommand {
-    #[regex(b"(?i:count)")]
-    Count,
+pub enum FailoverOption {
+    #[regex(b"(?i:abort)")]
+    Abort,
+
+    #[regex(b"(?i:force)")]
+    Force,
+
+    #[regex(b"(?i:timeout)")]
+    Timeout,
+
+    #[regex(b"(?i:to)")]
+    To,
+}
 
-    #[regex(b"(?i:getkeys)")]
-    Getkeys,
+/// Check the arguments to FAILOVER like Redis does. A master with no replicas has nothing to
+/// fail over to, so a valid request is refused, and there's never a failover to abort.
+pub(super) fn failover(client: &mut Client, _: &mut Store) -> CommandResult {
+    use FailoverOption::*;
 
-    #[regex(b"(?i:help)")]
-    Help,
+    if client.request.remaining() == 1
+        && client.request.get(1).as_deref().and_then(lex) == Some(Abort)
+    {
+        return Err(ReplyError::NoFailover.into());
+    }
 
-    #[regex(b"(?i:info)")]
-    Info,
+    let (mut force, mut timeout, mut to) = (false, false, false);
+    while !client.request.is_empty() {
+        match lex(&client.request.pop()?[..]) {
+            Some(Timeout) if !timeout && !client.request.is_empty() => {
+                if client.request.i64()? <= 0 {
+                    return Err(ReplyError::FailoverTimeout.into());
+                }
+                timeout = true;
+            }
+            Some(To) if !to && client.request.remaining() >= 2 => {
+                client.request.pop()?;
+                client.request.i64()?;
+                to = true;
+            }
+            Some(Force) if !force => force = true,
+            _ => return Err(ReplyError::Syntax.into()),
+        }
+    }
+
+    Err(ReplyError::FailoverReplicas.into())
+}
 
-    #[regex(b"(?i:list)")]
-    List,
+pub(super) fn unwatch(client: &mut Client, store: &mut Store) -> CommandResult {
+    store.unwatch(client.id);
+    client.reply("OK");
+    Ok(None)
 }
 
-fn command(client: &mut Client, store: &mut Store) -> CommandResult {
-    let len = client.request.len();
-    if len == 1 {
+pub(super) fn command(client: &mut Client, store: &mut Store) -> CommandResult {
+    if client.request.len() == 1 {
         client.reply(Reply::Array(ALL.len()));
         for command in ALL {
             command_reply(client, command);
@@ -571,26 +547,64 @@ fn command(client: &mut Client, store: &mut Store) -> CommandResult {
         return Ok(None);
     }
 
-    let subcommand = client.request.pop()?;
-    use CommandSubcommand::*;
-    let subcommand = match (lex(&subcommand[..]), len) {
-        (Some(Count), 2) => command_count,
-        (Some(Getkeys), 3..) => command_getkeys,
-        (Some(Help), 2) => command_help,
-        (Some(Info), _) => command_info,
-        (Some(List), _) => command_list,
-        _ => return Err(client.request.unknown_subcommand().into()),
-    };
+    let subcommand = client.request.subcommand()?;
+    (subcommand.run)(client, store)
+}
 
-    subcommand(client, store)
+pub(super) fn command_count(client: &mut Client, _: &mut Store) -> CommandResult {
+    client.reply(command_names().count());
+    Ok(None)
+}
+
+/// Every command followed by its subcommands, which are named like `config|get`.
+fn command_names() -> impl Iterator<Item = (&'static Command, &'static str)> {
+    ALL.iter().flat_map(|&command| {
+        let subcommands = command.subcommands.iter();
+        std::iter::once((command, command.name))
+            .chain(subcommands.map(move |subcommand| (command, subcommand.name)))
+    })
 }
 
-fn command_count(client: &mut Client, _: &mut Store) -> CommandResult {
-    client.reply(ALL.len());
+pub(super) fn command_docs(client: &mut Client, _: &mut Store) -> CommandResult {
+    if client.request.is_empty() {
+        client.reply(Reply::Map(ALL.len()));
+        for command in ALL {
+            command_docs_reply(client, command);
+        }
+        return Ok(None);
+    }
+
+    // Unknown commands are left out, as in Redis.
+    let commands: Vec<&Command> = client
+        .request
+        .iter()
+        .filter_map(|name| lookup(&name))
+        .collect();
+    client.reply(Reply::Map(commands.len()));
+    for command in commands {
+        command_docs_reply(client, command);
+    }
     Ok(None)
 }
 
-fn command_getkeys(client: &mut Client, _: &mut Store) -> CommandResult {
+/// Reply with the name and docs of a command. There are no summaries or arguments yet, so the
+/// docs only describe subcommands.
+fn command_docs_reply(client: &mut Client, command: &Command) {
+    client.reply(command.name);
+    if command.subcommands.is_empty() {
+        client.reply(Reply::Map(0));
+        return;
+    }
+    client.reply(Reply::Map(1));
+    client.reply("subcommands");
+    client.reply(Reply::Map(command.subcommands.len()));
+    for subcommand in command.subcommands {
+        client.reply(subcommand.name);
+        client.reply(Reply::Map(0));
+    }
+}
+
+pub(super) fn command_getkeys(client: &mut Client, _: &mut Store) -> CommandResult {
     let Some(command) = client.request.pop_front() else {
         return Err(ReplyError::InvalidCommand.into());
     };
@@ -620,18 +634,18 @@ fn command_getkeys(client: &mut Client, _: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-fn command_help(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn command_help(client: &mut Client, _: &mut Store) -> CommandResult {
     client.verbatim("txt", include_str!("../help/command.txt"));
     Ok(None)
 }
 
-fn command_info(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn command_info(client: &mut Client, _: &mut Store) -> CommandResult {
     client.reply(Reply::Array(client.request.remaining()));
     for _ in 0..client.request.remaining() {
         let arg = client.request.pop()?;
-        match lex::<CommandKind>(&arg[..]) {
-            Some(name) => {
-                command_reply(client, name.command());
+        match lookup(&arg[..]) {
+            Some(command) => {
+                command_reply(client, command);
             }
             None => {
                 client.reply(Reply::Nil);
@@ -642,40 +656,112 @@ fn command_info(client: &mut Client, _: &mut Store) -> CommandResult {
 }
 
 fn command_reply(client: &mut Client, command: &Command) {
-    client.reply(Reply::Array(6));
+    client.reply(Reply::Array(10));
     client.reply(command.name);
     client.reply(&command.arity);
-
-    let flags = [
-        (command.readonly, "readonly"),
-        (command.admin, "admin"),
-        (command.pubsub, "pubsub"),
-        (command.noscript, "noscript"),
-    ];
-
-    let filtered = flags.iter().filter(|(value, _)| *value);
-
-    client.reply(Reply::Array(filtered.clone().count()));
-    for (_, name) in filtered {
-        client.reply(*name);
-    }
+    client.deferred_array(command.flags());
 
     let (first, last, step) = command.keys.first_last_step();
     client.reply(first);
     client.reply(last);
     client.reply(step);
+
+    client.array(command.categories.iter().copied());
+
+    // Tips
+    client.reply(Reply::Array(0));
+
+    key_specs(client, command, &command.keys);
+
+    // Subcommands share the flags and categories of their command.
+    client.reply(Reply::Array(command.subcommands.len()));
+    for subcommand in command.subcommands {
+        client.reply(Reply::Array(10));
+        client.reply(subcommand.name);
+        client.reply(&subcommand.arity);
+        client.deferred_array(command.flags());
+
+        let (first, last, step) = subcommand.keys.first_last_step();
+        client.reply(first);
+        client.reply(last);
+        client.reply(step);
+
+        client.array(command.categories.iter().copied());
+        client.reply(Reply::Array(0));
+        key_specs(client, command, &subcommand.keys);
+        client.reply(Reply::Array(0));
+    }
+}
+
+/// Reply with the Redis 7 key specifications of `keys`, flagged by the access of `command`.
+fn key_specs(client: &mut Client, command: &Command, keys: &Keys) {
+    let flags: &[&str] = if command.readonly {
+        &["RO", "access"]
+    } else if command.write {
+        &["RW", "update"]
+    } else {
+        &["RO"]
+    };
+    let specs = keys.specs();
+    client.reply(Reply::Array(specs.len()));
+    for spec in specs {
+        client.reply(Reply::Map(3));
+        client.reply("flags");
+        client.array(flags.iter().copied());
+        client.reply("begin_search");
+        client.reply(Reply::Map(2));
+        client.reply("type");
+        client.reply("index");
+        client.reply("spec");
+        client.reply(Reply::Map(1));
+        client.reply("index");
+        client.reply(spec.index);
+        client.reply("find_keys");
+        client.reply(Reply::Map(2));
+        client.reply("type");
+        match spec.find {
+            FindKeys::Range { lastkey, keystep } => {
+                client.reply("range");
+                client.reply("spec");
+                client.reply(Reply::Map(3));
+                client.reply("lastkey");
+                client.reply(lastkey);
+                client.reply("keystep");
+                client.reply(keystep);
+                client.reply("limit");
+                client.reply(0);
+            }
+            FindKeys::Keynum => {
+                client.reply("keynum");
+                client.reply("spec");
+                client.reply(Reply::Map(3));
+                client.reply("keynumidx");
+                client.reply(0);
+                client.reply("firstkey");
+                client.reply(1);
+                client.reply("keystep");
+                client.reply(1);
+            }
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum FilterBy {
+    #[regex(b"(?i:aclcat)")]
+    Aclcat,
+
+    #[regex(b"(?i:module)")]
+    Module,
+
     #[regex(b"(?i:pattern)")]
     Pattern,
 }
 
-fn command_list(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn command_list(client: &mut Client, _: &mut Store) -> CommandResult {
     match client.request.len() {
         2 => {
-            client.array(ALL.iter().map(|command| command.name));
+            client.deferred_array(command_names().map(|(_, name)| name));
             return Ok(None);
         }
         5 => {}
@@ -686,52 +772,41 @@ fn command_list(client: &mut Client, _: &mut Store) -> CommandResult {
     if !filterby.eq_ignore_ascii_case(b"filterby") {
         return Err(ReplyError::Syntax.into());
     }
-    match lex(&client.request.pop()?[..]) {
-        Some(FilterBy::Pattern) => {
-            let pattern = client.request.pop()?;
-            client.deferred_array(ALL.iter().filter_map(|command| {
-                let name = command.name.as_bytes();
-                glob::matches_nocase(name, &pattern).then_some(command.name)
+    let filter = client.request.pop()?;
+    let value = client.request.pop()?;
+    match lex(&filter[..]) {
+        Some(FilterBy::Aclcat) => {
+            // Subcommands share the categories of their command.
+            client.deferred_array(command_names().filter_map(|(command, name)| {
+                let mut categories = command.categories.iter();
+                categories
+                    .any(|category| category.as_bytes()[1..].eq_ignore_ascii_case(&value))
+                    .then_some(name)
             }));
         }
-        _ => return Err(ReplyError::Syntax.into()),
+        // Every command is built in, so none belong to a module.
+        Some(FilterBy::Module) => {
+            client.reply(Reply::Array(0));
+        }
+        Some(FilterBy::Pattern) => {
+            let pattern = Pattern::compile_nocase(&value);
+            client.deferred_array(
+                command_names()
+                    .filter_map(|(_, name)| pattern.matches(name.as_bytes()).then_some(name)),
+            );
+        }
+        None => return Err(ReplyError::Syntax.into()),
     }
     Ok(None)
 }
 
-pub static ECHO: Command = Command {
-    kind: CommandKind::Echo,
-    name: "echo",
-    arity: Arity::Exact(2),
-    run: echo,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn echo(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn echo(client: &mut Client, _: &mut Store) -> CommandResult {
     let value = client.request.pop()?;
     client.reply(value);
     Ok(None)
 }
 
-pub static PING: Command = Command {
-    kind: CommandKind::Ping,
-    name: "ping",
-    arity: Arity::Minimum(1),
-    run: ping,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn ping(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn ping(client: &mut Client, _: &mut Store) -> CommandResult {
     if client.request.len() > 2 {
         return Err(client.request.wrong_arguments().into());
     }
@@ -758,19 +833,6 @@ fn ping(client: &mut Client, _: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static INFO: Command = Command {
-    kind: CommandKind::Info,
-    name: "info",
-    arity: Arity::Minimum(1),
-    run: info,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum InfoSection {
     #[regex(b"(?i:all)")]
@@ -800,6 +862,9 @@ pub enum InfoSection {
     #[regex(b"(?i:keyspace)")]
     Keyspace,
 
+    #[regex(b"(?i:keyspace-types)")]
+    KeyspaceTypes,
+
     #[regex(b"(?i:latencystats)")]
     Latencystats,
 
@@ -843,7 +908,7 @@ impl InfoSection {
 }
 
 // TODO: Finish implementing this.
-fn info(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn info(client: &mut Client, store: &mut Store) -> CommandResult {
     let mut buffer = Vec::new();
 
     macro_rules! info {
@@ -889,6 +954,18 @@ fn info(client: &mut Client, store: &mut Store) -> CommandResult {
         info!("server_time_usec:{}", epoch().as_micros());
     }
 
+    if include(InfoSection::Clients) {
+        info!("#Clients");
+        info!("connected_clients:{}", store.clients.len());
+        info!("maxclients:{}", store.connections.max());
+        let pubsub_clients = store
+            .clients
+            .keys()
+            .filter(|id| store.pubsub.subscribed(**id))
+            .count();
+        info!("pubsub_clients:{pubsub_clients}");
+    }
+
     if include(InfoSection::Persistence) {
         info!("#Persistence");
         info!("rdb_changes_since_last_save:{}", store.dirty);
@@ -898,6 +975,38 @@ fn info(client: &mut Client, store: &mut Store) -> CommandResult {
         info!("#Stats");
         info!("total_connections_received:{}", store.numconnections);
         info!("total_commands_processed:{}", store.numcommands);
+        info!("rejected_connections:{}", store.connections.rejected());
+        store.collect_expired();
+        info!("expired_keys:{}", store.expired_keys);
+        info!("expired_stale_perc:{:.2}", store.expired_stale_perc);
+        info!("evicted_keys:{}", store.evicted_keys);
+        info!("evicted_clients:{}", store.evicted_clients);
+        info!("pubsub_channels:{}", store.pubsub.channels_len());
+        info!("pubsub_patterns:{}", store.pubsub.patterns_len());
+        info!(
+            "total_error_replies:{}",
+            store.errorstats.values().sum::<usize>()
+        );
+    }
+
+    if include(InfoSection::Errorstats) {
+        info!("#Errorstats");
+        for (prefix, count) in &store.errorstats {
+            info!("errorstat_{prefix}:count={count}");
+        }
+    }
+
+    if include(InfoSection::KeyspaceTypes) {
+        info!("#Keyspace-types");
+        for (index, db) in store.dbs.iter().enumerate() {
+            let types = db.types();
+            if types != KeyTypes::default() {
+                info!(
+                    "db{index}:strings={},lists={},sets={},hashes={},zsets={}",
+                    types.strings, types.lists, types.sets, types.hashes, types.sorted_sets
+                );
+            }
+        }
     }
 
     client.verbatim("txt", buffer);
@@ -905,20 +1014,7 @@ fn info(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static MONITOR: Command = Command {
-    kind: CommandKind::Monitor,
-    name: "monitor",
-    arity: Arity::Exact(1),
-    run: monitor,
-    keys: Keys::None,
-    readonly: false,
-    admin: true,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-fn monitor(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn monitor(client: &mut Client, store: &mut Store) -> CommandResult {
     let reply_sender = client.reply_sender.clone();
     let monitor = Monitor::new(client.id, reply_sender);
     store.monitors.insert_back(monitor);
@@ -927,20 +1023,7 @@ fn monitor(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static RESET: Command = Command {
-    kind: CommandKind::Reset,
-    name: "reset",
-    arity: Arity::Exact(1),
-    run: reset,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-fn reset(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn reset(client: &mut Client, store: &mut Store) -> CommandResult {
     client.discard(store);
     store.set_name(client, None);
     client.set_reply_mode(ReplyMode::On);
@@ -956,6 +1039,50 @@ fn reset(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
+enum ShutdownOption {
+    #[regex(b"(?i:force)")]
+    Force,
+
+    #[regex(b"(?i:nosave)")]
+    Nosave,
+
+    #[regex(b"(?i:now)")]
+    Now,
+
+    #[regex(b"(?i:save)")]
+    Save,
+}
+
+pub(super) fn shutdown(client: &mut Client, store: &mut Store) -> CommandResult {
+    let mut nosave = false;
+    let mut save = false;
+
+    while let Some(argument) = client.request.try_pop() {
+        use ShutdownOption::*;
+        match lex(&argument[..]) {
+            Some(Nosave) => nosave = true,
+            Some(Save) => save = true,
+            // There's no persistence, so there's never anything to wait for.
+            Some(Force | Now) => {}
+            None => return Err(ReplyError::Syntax.into()),
+        }
+    }
+
+    if nosave && save {
+        return Err(ReplyError::Syntax.into());
+    }
+
+    // Saving isn't safe while a script is partway through.
+    if store.busy && !nosave {
+        return Err(ReplyError::Busy.into());
+    }
+
+    // Every client is disconnected, so there's no reply.
+    store.shutdown();
+    Ok(None)
+}
+
 pub static UNKNOWN: Command = Command {
     kind: CommandKind::Unknown,
     name: "unknown",
@@ -967,9 +1094,15 @@ pub static UNKNOWN: Command = Command {
     noscript: false,
     pubsub: false,
     write: false,
+    blocking: false,
+    fast: false,
+    categories: &[],
+    subcommands: &[],
 };
 
 fn unknown(client: &mut Client, _: &mut Store) -> CommandResult {
-    client.reply(ReplyError::UnknownCommand);
+    let name = client.request.get(0).unwrap_or_default();
+    let arguments = client.request.iter().collect();
+    client.reply(ReplyError::UnknownCommand(name, arguments));
     Ok(None)
 }
@@ -1,27 +1,14 @@
 use crate::{
-    CommandResult,
+    CommandResult, DBIndex,
     bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Keys},
+    command::CommandKind,
     reply::ReplyError,
-    store::Store,
+    store::{Store, WriteEffect},
 };
 use logos::Logos;
 use std::mem;
 
-pub static COPY: Command = Command {
-    kind: CommandKind::Copy,
-    name: "copy",
-    arity: Arity::Minimum(3),
-    run: copy,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum CopyOption {
     #[regex(b"(?i:db)")]
@@ -31,7 +18,7 @@ pub enum CopyOption {
     Replace,
 }
 
-fn copy(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn copy(client: &mut Client, store: &mut Store) -> CommandResult {
     // TODO: Not allowed in cluster mode.
 
     let source = client.request.pop()?;
@@ -77,44 +64,18 @@ fn copy(client: &mut Client, store: &mut Store) -> CommandResult {
     } else {
         to.set(&destination, value);
     }
-    store.touch(db, &destination);
+    store.write(db, &destination, WriteEffect::new("copy_to").ready());
     client.reply(1);
     Ok(None)
 }
 
-pub static DBSIZE: Command = Command {
-    kind: CommandKind::Dbsize,
-    name: "dbsize",
-    arity: Arity::Exact(1),
-    run: dbsize,
-    keys: Keys::None,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn dbsize(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn dbsize(client: &mut Client, store: &mut Store) -> CommandResult {
     let db = store.get_db(client.db())?;
     let size = db.size();
     client.reply(size);
     Ok(None)
 }
 
-pub static FLUSHALL: Command = Command {
-    kind: CommandKind::Flushall,
-    name: "flushall",
-    arity: Arity::Minimum(1),
-    run: flushall,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum FlushOption {
     #[regex(b"(?i:async)")]
@@ -124,7 +85,7 @@ pub enum FlushOption {
     Sync,
 }
 
-fn flushall(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn flushall(client: &mut Client, store: &mut Store) -> CommandResult {
     let mut lazy = store.lazy_user_flush;
 
     if !client.request.is_empty() {
@@ -140,6 +101,10 @@ fn flushall(client: &mut Client, store: &mut Store) -> CommandResult {
         }
     }
 
+    store.collect_expired();
+    for index in 0..store.dbs.len() {
+        store.touch_db(DBIndex(index));
+    }
     for db in &mut store.dbs {
         let db = mem::take(db);
         if lazy {
@@ -152,20 +117,7 @@ fn flushall(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static FLUSHDB: Command = Command {
-    kind: CommandKind::Flushdb,
-    name: "flushdb",
-    arity: Arity::Minimum(1),
-    run: flushdb,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn flushdb(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn flushdb(client: &mut Client, store: &mut Store) -> CommandResult {
     let mut lazy = store.lazy_user_flush;
 
     if !client.request.is_empty() {
@@ -181,6 +133,8 @@ fn flushdb(client: &mut Client, store: &mut Store) -> CommandResult {
         }
     }
 
+    store.collect_expired();
+    store.touch_db(client.db());
     let db = store.mut_db(client.db())?;
     let db = mem::take(db);
     if lazy {
@@ -192,20 +146,7 @@ fn flushdb(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static MOVE: Command = Command {
-    kind: CommandKind::Move,
-    name: "move",
-    arity: Arity::Exact(3),
-    run: move_,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn move_(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn move_(client: &mut Client, store: &mut Store) -> CommandResult {
     // TODO: Not allowed in cluster mode.
 
     let key = client.request.pop()?;
@@ -233,39 +174,13 @@ fn move_(client: &mut Client, store: &mut Store) -> CommandResult {
     } else {
         to.set(&key, value);
     }
-    store.touch(client.db(), &key);
-    store.touch(db, &key);
+    store.write(client.db(), &key, WriteEffect::new("move_from"));
+    store.write(db, &key, WriteEffect::new("move_to").ready());
     client.reply(1);
     Ok(None)
 }
 
-pub static RENAME: Command = Command {
-    kind: CommandKind::Rename,
-    name: "rename",
-    arity: Arity::Exact(3),
-    run: rename,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static RENAMENX: Command = Command {
-    kind: CommandKind::Renamenx,
-    name: "renamenx",
-    arity: Arity::Exact(3),
-    run: rename,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn rename(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn rename(client: &mut Client, store: &mut Store) -> CommandResult {
     let nx = client.request.kind() == CommandKind::Renamenx;
     let from = client.request.pop()?;
     let to = client.request.pop()?;
@@ -297,8 +212,8 @@ fn rename(client: &mut Client, store: &mut Store) -> CommandResult {
         }
     }
 
-    store.touch(client.db(), &from);
-    store.touch(client.db(), &to);
+    store.write(client.db(), &from, WriteEffect::new("rename_from"));
+    store.write(client.db(), &to, WriteEffect::new("rename_to").ready());
 
     if nx {
         client.reply(1);
@@ -308,20 +223,7 @@ fn rename(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SELECT: Command = Command {
-    kind: CommandKind::Select,
-    name: "select",
-    arity: Arity::Exact(2),
-    run: select,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn select(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn select(client: &mut Client, store: &mut Store) -> CommandResult {
     let index = client.request.db_index()?;
     store.dbs.get(index.0).ok_or(ReplyError::DBIndex)?;
 
@@ -330,31 +232,19 @@ fn select(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SWAPDB: Command = Command {
-    kind: CommandKind::Swapdb,
-    name: "swapdb",
-    arity: Arity::Exact(3),
-    run: swapdb,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn swapdb(client: &mut Client, store: &mut Store) -> CommandResult {
-    let a = client.request.db_index()?;
-    let b = client.request.db_index()?;
+pub(super) fn swapdb(client: &mut Client, store: &mut Store) -> CommandResult {
+    let a = client
+        .request
+        .db_index_or(ReplyError::InvalidFirstDBIndex)?;
+    let b = client
+        .request
+        .db_index_or(ReplyError::InvalidSecondDBIndex)?;
 
     if a.0 >= store.dbs.len() || b.0 >= store.dbs.len() {
         return Err(ReplyError::DBIndex.into());
     }
 
-    store.dbs.swap(a.0, b.0);
-
-    // TODO: Check blocked clients.
-
+    store.swap_dbs(a, b);
     client.reply("OK");
     Ok(None)
 }
@@ -1,45 +1,49 @@
 use crate::{
     CommandResult,
-    bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Keys},
+    config::EnableDebugCommand,
+    db::{List, RawWrites, Value},
+    glob::Pattern,
+    reply::{Reply, ReplyError},
     store::Store,
 };
-use logos::Logos;
-
-pub static DEBUG: Command = Command {
-    kind: CommandKind::Debug,
-    name: "debug",
-    arity: Arity::Minimum(2),
-    run: debug,
-    keys: Keys::None,
-    readonly: true,
-    admin: true,
-    noscript: true,
-    pubsub: false,
-    write: false,
-};
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
-pub enum DebugSubcommand {
-    #[regex(b"(?i:log)")]
-    Log,
-}
+use rand::Rng;
+use std::fmt::Write;
 
-fn debug(client: &mut Client, store: &mut Store) -> CommandResult {
-    let len = client.request.len();
-    let subcommand = client.request.pop()?;
-
-    use DebugSubcommand::*;
-    let subcommand = match (lex(&subcommand[..]), len) {
-        (Some(Log), _) => debug_log,
-        _ => return Err(client.request.unknown_subcommand().into()),
+pub(super) fn debug(client: &mut Client, store: &mut Store) -> CommandResult {
+    let allowed = match store.enable_debug_command {
+        EnableDebugCommand::No => false,
+        EnableDebugCommand::Yes => true,
+        // Connections made in process don't have an address, and are local too.
+        EnableDebugCommand::Local => client.addr.as_ref().is_none_or(|addr| addr.peer.is_local()),
     };
-    subcommand(client, store)
+    if !allowed {
+        return Err(ReplyError::DebugCommand.into());
+    }
+
+    let subcommand = client.request.subcommand()?;
+    (subcommand.run)(client, store)
 }
 
 // TODO: Test this…?
-fn debug_log(client: &mut Client, _: &mut Store) -> CommandResult {
+/// The number of keys of each type in the current database.
+pub(super) fn debug_keyspace_types(client: &mut Client, store: &mut Store) -> CommandResult {
+    let types = store.get_db(client.db())?.types();
+    client.reply(Reply::Map(5));
+    for (name, count) in [
+        ("strings", types.strings),
+        ("lists", types.lists),
+        ("sets", types.sets),
+        ("hashes", types.hashes),
+        ("zsets", types.sorted_sets),
+    ] {
+        client.reply(name);
+        client.reply(count);
+    }
+    Ok(None)
+}
+
+pub(super) fn debug_log(client: &mut Client, _: &mut Store) -> CommandResult {
     let message = client.request.pop()?;
     let message = std::str::from_utf8(&message).unwrap_or("[invalid utf8]");
     // TODO: Log level
@@ -47,3 +51,72 @@ fn debug_log(client: &mut Client, _: &mut Store) -> CommandResult {
     client.reply("OK");
     Ok(None)
 }
+
+pub(super) fn debug_object(client: &mut Client, store: &mut Store) -> CommandResult {
+    let max = store.list_max_listpack_size;
+    let key = client.request.pop()?;
+    let db = store.get_db(client.db())?;
+    let value = db.peek(&key).ok_or(ReplyError::NoSuchKey)?;
+    let idle = db.idletime(&key).unwrap_or_default();
+    let mut result = format!(
+        "refcount:1 encoding:{} lru_seconds_idle:{idle}",
+        value.encoding()
+    );
+    if let Value::List(list) = value
+        && let List::Quick(quick) = &**list
+    {
+        // Nodes are never compressed, so `ql_uncompressed_size` is the size in memory.
+        let nodes = quick.packs();
+        #[allow(clippy::cast_precision_loss)]
+        let average = quick.len() as f64 / nodes as f64;
+        let size = quick.size();
+        write!(
+            result,
+            " ql_nodes:{nodes} ql_avg_node:{average:.2} ql_listpack_max:{max} ql_compressed:0 \
+             ql_uncompressed_size:{size}"
+        )
+        .unwrap();
+    }
+    client.reply(Reply::Status(result.into_bytes().into()));
+    Ok(None)
+}
+
+pub(super) fn debug_panic(_: &mut Client, _: &mut Store) -> CommandResult {
+    panic!("DEBUG PANIC called")
+}
+
+/// How many writes to shared byte values copied them first, and how many were made in place.
+pub(super) fn debug_raw_writes(client: &mut Client, _: &mut Store) -> CommandResult {
+    let writes = RawWrites::get();
+    client.reply(Reply::Map(2));
+    client.reply("copied");
+    client.reply(writes.copied);
+    client.reply("in-place");
+    client.reply(writes.in_place);
+    Ok(None)
+}
+
+pub(super) fn debug_set_active_expire(client: &mut Client, store: &mut Store) -> CommandResult {
+    store.active_expire = client.request.i64()? != 0;
+    client.reply("OK");
+    Ok(None)
+}
+
+/// Match random patterns against random strings, checking that the glob matcher neither panics
+/// nor hangs. Redis runs ten million cycles, which is too slow for a debug build.
+pub(super) fn debug_stringmatch_len(client: &mut Client, _: &mut Store) -> CommandResult {
+    let mut rng = rand::thread_rng();
+    for _ in 0..100_000 {
+        let pattern: Vec<u8> = (0..rng.gen_range(0..32))
+            .map(|_| rng.gen_range(0..128))
+            .collect();
+        let string: Vec<u8> = (0..rng.gen_range(0..32))
+            .map(|_| rng.gen_range(0..128))
+            .collect();
+        Pattern::compile(&pattern).matches(&string);
+    }
+    client.reply(Reply::Status(
+        "Apparently Redis did not crash: test passed".into(),
+    ));
+    Ok(None)
+}
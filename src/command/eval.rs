@@ -1,32 +1,32 @@
-use crate::{
-    Client, CommandResult, Reply, Store,
-    command::{Arity, Command, CommandKind, Keys},
-};
-use piccolo::{Closure, Executor, Lua};
+use crate::{Client, CommandResult, ReplyError, Store, script};
 
-pub static EVAL: Command = Command {
-    kind: CommandKind::Eval,
-    name: "eval",
-    arity: Arity::Minimum(3),
-    run: eval,
-    keys: Keys::Argument(2),
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: true,
-};
-
-fn eval(client: &mut Client, _store: &mut Store) -> CommandResult {
+pub(super) fn eval(client: &mut Client, store: &mut Store) -> CommandResult {
     let code = client.request.pop()?;
-    let mut lua = Lua::core();
-    let executor = lua
-        .try_enter(|context| {
-            let closure = Closure::load(context, None, &code[..])?;
-            Ok(context.stash(Executor::start(context, closure.into(), ())))
-        })
-        .unwrap();
-    let result = lua.execute::<Reply>(&executor).unwrap();
-    client.reply(result);
+    let numkeys = client.request.numkeys()?;
+    let keys = (0..numkeys)
+        .map(|_| client.request.pop())
+        .collect::<Result<_, _>>()?;
+    let argv = client.request.iter().collect();
+    script::eval(client, store, &code, keys, argv)
+}
+
+pub(super) fn script(client: &mut Client, store: &mut Store) -> CommandResult {
+    match client.request.subcommand() {
+        Ok(subcommand) => (subcommand.run)(client, store),
+        Err(_) if store.busy => Err(ReplyError::Busy.into()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+pub(super) fn kill(client: &mut Client, store: &mut Store) -> CommandResult {
+    if !store.busy {
+        return Err(ReplyError::Notbusy.into());
+    }
+    // Stopping a script after it has written would leave its writes half applied.
+    if store.dirty != store.script_dirty {
+        return Err(ReplyError::Unkillable.into());
+    }
+    store.kill_script = true;
+    client.reply("OK");
     Ok(None)
 }
@@ -1,27 +1,17 @@
+use super::string::{GetexOption, GetexTtl};
 use crate::{
     CommandResult,
     buffer::ArrayBuffer,
+    bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Keys},
+    command::CommandKind,
     db::Hash,
-    reply::Reply,
-    store::Store,
+    epoch,
+    reply::{Reply, ReplyError},
+    store::{Store, WriteEffect},
 };
 
-pub static HDEL: Command = Command {
-    kind: CommandKind::Hdel,
-    name: "hdel",
-    arity: Arity::Minimum(3),
-    run: hdel,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn hdel(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hdel(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let hash = db.mut_hash(&key)?.ok_or(0)?;
@@ -39,52 +29,25 @@ fn hdel(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     if count > 0 {
-        store.dirty += count;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("hdel").changes(count));
     }
 
     client.reply(count);
     Ok(None)
 }
 
-pub static HEXISTS: Command = Command {
-    kind: CommandKind::Hexists,
-    name: "hexists",
-    arity: Arity::Exact(3),
-    run: hexists,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hexists(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hexists(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let field = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?.ok_or(0)?;
     let result = i64::from(hash.contains_key(&field[..]));
 
     client.reply(result);
     Ok(None)
 }
 
-pub static HGET: Command = Command {
-    kind: CommandKind::Hget,
-    name: "hget",
-    arity: Arity::Exact(3),
-    run: hget,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hget(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hget(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let field = client.request.pop()?;
     let db = store.get_db(client.db())?;
@@ -94,23 +57,10 @@ fn hget(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static HGETALL: Command = Command {
-    kind: CommandKind::Hgetall,
-    name: "hgetall",
-    arity: Arity::Exact(2),
-    run: hgetall,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hgetall(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hgetall(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?.ok_or(Reply::Map(0))?;
 
     client.reply(Reply::Map(hash.len()));
 
@@ -122,20 +72,97 @@ fn hgetall(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static HINCRBY: Command = Command {
-    kind: CommandKind::Hincrby,
-    name: "hincrby",
-    arity: Arity::Exact(4),
-    run: hincrby,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
+pub(super) fn hgetdel(client: &mut Client, store: &mut Store) -> CommandResult {
+    let key = client.request.pop()?;
+    let fields = client.request.pop()?;
+    numfields(client, &fields)?;
+    get_fields(client, store, &key, true)
+}
 
-fn hincrby(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hgetex(client: &mut Client, store: &mut Store) -> CommandResult {
+    let key = client.request.pop()?;
+    let mut ttl = None;
+
+    loop {
+        let argument = client.request.pop()?;
+        if argument.eq_ignore_ascii_case(b"fields") {
+            numfields(client, &argument)?;
+            break;
+        }
+
+        let Some(option) = lex(&argument[..]) else {
+            return Err(ReplyError::FieldsArgument.into());
+        };
+
+        use GetexTtl::*;
+        match (option, ttl) {
+            (GetexOption::Ex, None) => ttl = Some(Ex(client.request.positive_ttl()?)),
+            (GetexOption::Exat, None) => ttl = Some(Exat(client.request.positive_expiretime()?)),
+            (GetexOption::Persist, None) => ttl = Some(Persist),
+            (GetexOption::Px, None) => ttl = Some(Px(client.request.positive_pttl()?)),
+            (GetexOption::Pxat, None) => ttl = Some(Pxat(client.request.positive_pexpiretime()?)),
+            _ => return Err(ReplyError::Syntax.into()),
+        }
+    }
+
+    // Fields don't have their own expirations yet, so there are none to persist, and the only
+    // expiration that can be applied is one that has already passed, which deletes the fields.
+    let delete = match ttl {
+        None | Some(GetexTtl::Persist) => false,
+        Some(GetexTtl::Exat(at) | GetexTtl::Pxat(at)) if at <= epoch().as_millis() => true,
+        Some(_) => return Err(ReplyError::FieldExpiration.into()),
+    };
+    get_fields(client, store, &key, delete)
+}
+
+/// Check that `fields` is the `FIELDS` keyword, followed by the number of fields remaining in
+/// the request.
+fn numfields(client: &mut Client, fields: &[u8]) -> Result<(), ReplyError> {
+    if !fields.eq_ignore_ascii_case(b"fields") {
+        return Err(ReplyError::FieldsArgument);
+    }
+
+    let count = client.request.i64().map_err(|_| ReplyError::NumFields)?;
+    if count <= 0 {
+        return Err(ReplyError::NumFields);
+    }
+    if usize::try_from(count).ok() != Some(client.request.remaining()) {
+        return Err(ReplyError::NumFieldsMismatch);
+    }
+    Ok(())
+}
+
+/// Reply with the value of each remaining field in the request, deleting the fields if `delete`
+/// is set, along with the hash once it's empty.
+fn get_fields(client: &mut Client, store: &mut Store, key: &[u8], delete: bool) -> CommandResult {
+    let db = store.mut_db(client.db())?;
+    let mut hash = db.mut_hash(key)?;
+
+    let mut count = 0;
+    client.reply(Reply::Array(client.request.remaining()));
+    while !client.request.is_empty() {
+        let field = client.request.pop()?;
+        let Some(hash) = hash.as_mut() else {
+            client.reply(Reply::Nil);
+            continue;
+        };
+        client.reply(hash.get(&field[..]));
+        if delete && hash.remove(&field[..]) {
+            count += 1;
+        }
+    }
+
+    if hash.is_some_and(|hash| hash.is_empty()) {
+        db.remove(key);
+    }
+
+    if count > 0 {
+        store.write(client.db(), key, WriteEffect::new("hdel").changes(count));
+    }
+    Ok(None)
+}
+
+pub(super) fn hincrby(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let field = client.request.pop()?;
     let by = client.request.i64()?;
@@ -145,25 +172,11 @@ fn hincrby(client: &mut Client, store: &mut Store) -> CommandResult {
     let hash = db.hash_or_default(&key)?;
     let result = hash.incrby(&field[..], by, max_len, max_size)?;
     client.reply(result);
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("hincrby"));
     Ok(None)
 }
 
-pub static HINCRBYFLOAT: Command = Command {
-    kind: CommandKind::Hincrbyfloat,
-    name: "hincrbyfloat",
-    arity: Arity::Exact(4),
-    run: hincrbyfloat,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn hincrbyfloat(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hincrbyfloat(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let field = client.request.pop()?;
     let by = client.request.f64()?;
@@ -172,29 +185,15 @@ fn hincrbyfloat(client: &mut Client, store: &mut Store) -> CommandResult {
     let db = store.mut_db(client.db())?;
     let hash = db.hash_or_default(&key)?;
     let result = hash.incrbyfloat(&field[..], by, max_len, max_size)?;
-    client.reply(result);
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    client.bulk(result);
+    store.write(client.db(), &key, WriteEffect::new("hincrbyfloat"));
     Ok(None)
 }
 
-pub static HKEYS: Command = Command {
-    kind: CommandKind::Hkeys,
-    name: "hkeys",
-    arity: Arity::Exact(2),
-    run: hkeys,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hkeys(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hkeys(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?.ok_or(Reply::Array(0))?;
     client.reply(Reply::Array(hash.len()));
     for key in hash.keys() {
         client.reply(key);
@@ -202,80 +201,28 @@ fn hkeys(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static HLEN: Command = Command {
-    kind: CommandKind::Hlen,
-    name: "hlen",
-    arity: Arity::Exact(2),
-    run: hlen,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hlen(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hlen(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?.ok_or(0)?;
     client.reply(hash.len());
     Ok(None)
 }
 
-pub static HMGET: Command = Command {
-    kind: CommandKind::Hmget,
-    name: "hmget",
-    arity: Arity::Minimum(3),
-    run: hmget,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hmget(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hmget(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?;
     client.reply(Reply::Array(client.request.remaining()));
     while !client.request.is_empty() {
         let field = client.request.pop()?;
-        let value = hash.get(&field[..]);
+        let value = hash.and_then(|hash| hash.get(&field[..]));
         client.reply(value);
     }
     Ok(None)
 }
 
-pub static HSET: Command = Command {
-    kind: CommandKind::Hset,
-    name: "hset",
-    arity: Arity::Minimum(4),
-    run: hset,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static HMSET: Command = Command {
-    kind: CommandKind::Hmset,
-    name: "hmset",
-    arity: Arity::Minimum(4),
-    run: hset,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn hset(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hset(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let max_len = store.hash_max_listpack_entries;
     let max_size = store.hash_max_listpack_value;
@@ -293,8 +240,7 @@ fn hset(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     if count > 0 {
-        store.dirty += count;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("hset").changes(count));
     }
 
     if client.request.kind() == CommandKind::Hmset {
@@ -305,20 +251,7 @@ fn hset(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static HSETNX: Command = Command {
-    kind: CommandKind::Hsetnx,
-    name: "hsetnx",
-    arity: Arity::Exact(4),
-    run: hsetnx,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn hsetnx(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hsetnx(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let field = client.request.pop()?;
     let value = client.request.pop()?;
@@ -337,30 +270,16 @@ fn hsetnx(client: &mut Client, store: &mut Store) -> CommandResult {
         db.set(&key, hash);
     }
 
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("hset"));
     client.reply(1);
     Ok(None)
 }
 
-pub static HSTRLEN: Command = Command {
-    kind: CommandKind::Hstrlen,
-    name: "hstrlen",
-    arity: Arity::Exact(3),
-    run: hstrlen,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hstrlen(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hstrlen(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let field = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?.ok_or(0)?;
     let mut buffer = ArrayBuffer::default();
     let len = hash
         .get(&field[..])
@@ -369,23 +288,10 @@ fn hstrlen(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static HVALS: Command = Command {
-    kind: CommandKind::Hvals,
-    name: "hvals",
-    arity: Arity::Exact(2),
-    run: hvals,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn hvals(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn hvals(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
-    let hash = db.get_hash(&key)?.ok_or(Reply::Nil)?;
+    let hash = db.get_hash(&key)?.ok_or(Reply::Array(0))?;
     client.reply(Reply::Array(hash.len()));
     for value in hash.values() {
         client.reply(value);
@@ -1,16 +1,16 @@
 use crate::{
     BlockResult, CommandResult,
-    bytes::{lex, parse},
+    bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Edge, Keys},
+    command::{CommandKind, Edge},
     db::Value,
     pack::Packable,
     reply::{Reply, ReplyError},
-    slice::slice,
-    store::Store,
+    slice::list_slice,
+    store::{Store, WriteEffect},
 };
 use logos::Logos;
-use std::{cmp::min, time::Duration};
+use std::time::Duration;
 use tokio::sync::oneshot;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
@@ -31,46 +31,15 @@ pub fn edge(client: &mut Client) -> Result<Edge, ReplyError> {
     }
 }
 
+/// Parse a count where a negative value counts from the right edge instead of the left.
 pub fn integer_with_edge(client: &mut Client) -> Result<(Edge, usize), ReplyError> {
-    Ok(match &client.request.pop()?[..] {
-        [b'-', rest @ ..] => {
-            let count = parse(rest).ok_or(ReplyError::Integer)?;
-            (Edge::Right, count)
-        }
-        rest => {
-            let count = parse(rest).ok_or(ReplyError::Integer)?;
-            (Edge::Left, count)
-        }
-    })
+    let count = client.request.i64()?;
+    let edge = if count < 0 { Edge::Right } else { Edge::Left };
+    let count = usize::try_from(count.unsigned_abs()).unwrap_or(usize::MAX);
+    Ok((edge, count))
 }
 
-pub static BLMOVE: Command = Command {
-    kind: CommandKind::Blmove,
-    name: "blmove",
-    arity: Arity::Exact(6),
-    run: blmove,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: true,
-};
-
-pub static BRPOPLPUSH: Command = Command {
-    kind: CommandKind::Brpoplpush,
-    name: "brpoplpush",
-    arity: Arity::Exact(4),
-    run: blmove,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: true,
-};
-
-fn blmove(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn blmove(client: &mut Client, store: &mut Store) -> CommandResult {
     let max = store.list_max_listpack_size;
     let source_key = client.request.pop()?;
     let destination_key = client.request.pop()?;
@@ -85,9 +54,6 @@ fn blmove(client: &mut Client, store: &mut Store) -> CommandResult {
     let exists = db.get_list(&source_key)?.is_some();
 
     if !exists {
-        if client.in_exec {
-            return Err(Reply::Nil);
-        }
         let block = BlockResult::new(timeout, (1..2).step_by(1));
         return Ok(Some(block));
     }
@@ -96,7 +62,7 @@ fn blmove(client: &mut Client, store: &mut Store) -> CommandResult {
     db.list_or_default(&destination_key)?;
 
     let [source, destination] = db
-        .get_many_mut([&source_key[..], &destination_key[..]])
+        .get_pair_mut([&source_key[..], &destination_key[..]])
         .map(|value| value.unwrap().mut_list().unwrap());
     let element = source.peek(from).unwrap();
     client.reply(&element);
@@ -105,40 +71,17 @@ fn blmove(client: &mut Client, store: &mut Store) -> CommandResult {
     if source.is_empty() {
         db.remove(&source_key);
     }
-    store.touch(client.db(), &source_key);
-    store.touch(client.db(), &destination_key);
-    store.mark_ready(client.db(), &destination_key);
+    store.write(client.db(), &source_key, WriteEffect::new(pop_event(from)));
+    store.write(
+        client.db(),
+        &destination_key,
+        WriteEffect::new(push_event(to)).ready(),
+    );
 
     Ok(None)
 }
 
-pub static BLPOP: Command = Command {
-    kind: CommandKind::Blpop,
-    name: "blpop",
-    arity: Arity::Minimum(3),
-    run: bpop,
-    keys: Keys::Trailing,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: true,
-};
-
-pub static BRPOP: Command = Command {
-    kind: CommandKind::Brpop,
-    name: "brpop",
-    arity: Arity::Minimum(3),
-    run: bpop,
-    keys: Keys::Trailing,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: false,
-    write: true,
-};
-
-fn bpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn bpop(client: &mut Client, store: &mut Store) -> CommandResult {
     let max = store.list_max_listpack_size;
     let edge = match client.request.command.kind {
         CommandKind::Blpop => Edge::Left,
@@ -171,12 +114,7 @@ fn bpop(client: &mut Client, store: &mut Store) -> CommandResult {
             db.remove(&key);
         }
 
-        store.touch(client.db(), &key);
-        return Ok(None);
-    }
-
-    if client.in_exec {
-        client.reply(Reply::Nil);
+        store.write(client.db(), &key, WriteEffect::new(pop_event(edge)));
         return Ok(None);
     }
 
@@ -185,20 +123,7 @@ fn bpop(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(Some(block))
 }
 
-pub static LINDEX: Command = Command {
-    kind: CommandKind::Lindex,
-    name: "lindex",
-    arity: Arity::Exact(3),
-    run: lindex,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn lindex(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lindex(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let mut index = client.request.i64()?;
     let list = store
@@ -226,19 +151,6 @@ fn lindex(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static LINSERT: Command = Command {
-    kind: CommandKind::Linsert,
-    name: "linsert",
-    arity: Arity::Exact(5),
-    run: linsert,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum LinsertOption {
     #[regex(b"(?i:after)")]
@@ -248,7 +160,7 @@ pub enum LinsertOption {
     Before,
 }
 
-fn linsert(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn linsert(client: &mut Client, store: &mut Store) -> CommandResult {
     let max = store.list_max_listpack_size;
     let key = client.request.pop()?;
     let position = client.request.pop()?;
@@ -266,7 +178,7 @@ fn linsert(client: &mut Client, store: &mut Store) -> CommandResult {
     if list.insert(&element[..], &pivot[..], before, max) {
         let len = list.len();
         client.reply(len);
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("linsert"));
     } else {
         client.reply(-1);
     }
@@ -274,20 +186,7 @@ fn linsert(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static LLEN: Command = Command {
-    kind: CommandKind::Llen,
-    name: "llen",
-    arity: Arity::Exact(2),
-    run: llen,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn llen(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn llen(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let len = store
         .get_db(client.db())?
@@ -299,33 +198,7 @@ fn llen(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static LMOVE: Command = Command {
-    kind: CommandKind::Lmove,
-    name: "lmove",
-    arity: Arity::Exact(5),
-    run: lmove,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static RPOPLPUSH: Command = Command {
-    kind: CommandKind::Rpoplpush,
-    name: "rpoplpush",
-    arity: Arity::Exact(3),
-    run: lmove,
-    keys: Keys::Double,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn lmove(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lmove(client: &mut Client, store: &mut Store) -> CommandResult {
     let max = store.list_max_listpack_size;
     let source_key = client.request.pop()?;
     let destination_key = client.request.pop()?;
@@ -343,14 +216,14 @@ fn lmove(client: &mut Client, store: &mut Store) -> CommandResult {
         } else {
             list.mv(from, to, max);
             client.reply(list.peek(to));
-            store.touch(client.db(), &source_key);
+            store.write(client.db(), &source_key, WriteEffect::new(push_event(to)));
         }
     } else {
         db.get_list(&source_key)?.ok_or(Reply::Nil)?;
         db.list_or_default(&destination_key)?;
 
         let [source, dest] = db
-            .get_many_mut([&source_key[..], &destination_key[..]])
+            .get_pair_mut([&source_key[..], &destination_key[..]])
             .map(|value| value.unwrap().mut_list().unwrap());
         let element = source.peek(from).unwrap();
         client.reply(&element);
@@ -359,46 +232,24 @@ fn lmove(client: &mut Client, store: &mut Store) -> CommandResult {
         if source.is_empty() {
             db.remove(&source_key);
         }
-        store.touch(client.db(), &source_key);
-        store.touch(client.db(), &destination_key);
+        store.write(client.db(), &source_key, WriteEffect::new(pop_event(from)));
+        store.write(
+            client.db(),
+            &destination_key,
+            WriteEffect::new(push_event(to)).ready(),
+        );
     }
 
     Ok(None)
 }
 
-pub static LMPOP: Command = Command {
-    kind: CommandKind::Lmpop,
-    name: "lmpop",
-    arity: Arity::Minimum(4),
-    run: lmpop,
-    keys: Keys::Argument(1),
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static BLMPOP: Command = Command {
-    kind: CommandKind::Blmpop,
-    name: "blmpop",
-    arity: Arity::Minimum(5),
-    run: lmpop,
-    keys: Keys::Argument(2),
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum MpopOption {
     #[regex(b"(?i:count)")]
     Count,
 }
 
-fn lmpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lmpop(client: &mut Client, store: &mut Store) -> CommandResult {
     let max = store.list_max_listpack_size;
     let blocking = client.request.kind() == CommandKind::Blmpop;
     let timeout = if blocking {
@@ -406,13 +257,9 @@ fn lmpop(client: &mut Client, store: &mut Store) -> CommandResult {
     } else {
         Duration::ZERO
     };
-    let numkeys = client.request.usize()?;
+    let numkeys = client.request.at_least(1, ReplyError::NumkeysZero)?;
     let start = client.request.next();
 
-    if numkeys == 0 {
-        return Err(ReplyError::NumkeysZero.into());
-    }
-
     if client.request.len() < start + numkeys + 1 {
         return Err(ReplyError::Syntax.into());
     }
@@ -423,14 +270,11 @@ fn lmpop(client: &mut Client, store: &mut Store) -> CommandResult {
     while let Some(argument) = client.request.try_pop() {
         match lex(&argument[..]) {
             Some(MpopOption::Count) if count.is_none() => {
-                count = Some(client.request.usize()?);
+                count = Some(client.request.at_least(1, ReplyError::CountZero)?);
             }
             _ => return Err(ReplyError::Syntax.into()),
         }
     }
-    if count == Some(0) {
-        return Err(ReplyError::CountZero.into());
-    }
     let count = count.unwrap_or(1);
     let db = store.mut_db(client.db())?;
 
@@ -445,20 +289,20 @@ fn lmpop(client: &mut Client, store: &mut Store) -> CommandResult {
         }
         client.reply(Reply::Array(2));
         client.reply(key.clone());
-        let count = min(count, list.len());
-        client.reply(Reply::Array(count));
-        for element in list.iter_from(edge).take(count) {
-            client.reply(element);
-        }
-        list.trim(edge, count, max);
+        client.reply(Reply::Array(count.min(list.len())));
+        let popped = list.pop_many(edge, count, max, |value| client.reply(value));
         if list.is_empty() {
             db.remove(&key);
         }
-        store.touch(client.db(), &key);
+        store.write(
+            client.db(),
+            &key,
+            WriteEffect::new(pop_event(edge)).changes(popped),
+        );
         return Ok(None);
     }
 
-    if !blocking || client.in_exec {
+    if !blocking {
         client.reply(Reply::Nil);
         return Ok(None);
     }
@@ -479,58 +323,30 @@ fn pop(client: &mut Client, store: &mut Store, edge: Edge) -> CommandResult {
         client.reply(list.peek(edge));
         list.trim(edge, 1, max);
     } else {
-        let count = client.request.usize().map_err(|_| ReplyError::Integer)?;
-        let count = min(count, list.len());
-        client.reply(Reply::Array(count));
-        for element in list.iter_from(edge).take(count) {
-            client.reply(element);
-        }
-        list.trim(edge, count, max);
+        let count = client.request.positive()?;
+        client.reply(Reply::Array(count.min(len)));
+        list.pop_many(edge, count, max, |value| client.reply(value));
     }
 
-    let modified = list.len() != len;
+    let popped = len - list.len();
 
     if list.is_empty() {
         db.remove(&key);
     }
 
-    if modified {
-        store.touch(client.db(), &key);
-    }
+    store.write(
+        client.db(),
+        &key,
+        WriteEffect::new(pop_event(edge)).changes(popped),
+    );
 
     Ok(None)
 }
 
-pub static LPOP: Command = Command {
-    kind: CommandKind::Lpop,
-    name: "lpop",
-    arity: Arity::Minimum(2),
-    run: lpop,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn lpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lpop(client: &mut Client, store: &mut Store) -> CommandResult {
     pop(client, store, Edge::Left)
 }
 
-pub static LPOS: Command = Command {
-    kind: CommandKind::Lpos,
-    name: "lpos",
-    arity: Arity::Minimum(3),
-    run: lpos,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum LposOption {
     #[regex(b"(?i:count)")]
@@ -543,7 +359,7 @@ pub enum LposOption {
     Rank,
 }
 
-fn lpos(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lpos(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let element = client.request.pop()?;
     let mut count = None;
@@ -558,13 +374,19 @@ fn lpos(client: &mut Client, store: &mut Store) -> CommandResult {
         use LposOption::*;
         match lex(&client.request.pop()?[..]) {
             Some(Count) => {
-                count = Some(client.request.integer()?);
+                count = Some(client.request.at_least(0, ReplyError::NegativeCount)?);
             }
             Some(Maxlen) => {
-                maxlen = client.request.integer()?;
+                maxlen = client.request.at_least(0, ReplyError::NegativeMaxlen)?;
             }
             Some(Rank) => {
-                (edge, rank) = integer_with_edge(client)?;
+                // A negative rank searches from the tail, so its magnitude is the rank.
+                (edge, rank) = match client.request.i64()? {
+                    0 => return Err(ReplyError::RankZero.into()),
+                    i64::MIN => return Err(ReplyError::RankRange.into()),
+                    value if value < 0 => (Edge::Right, value.unsigned_abs()),
+                    value => (Edge::Left, value.unsigned_abs()),
+                };
             }
             _ => return Err(ReplyError::Syntax.into()),
         }
@@ -616,7 +438,7 @@ fn lpos(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     if let Some(sender) = sender {
-        _ = sender.send(sent);
+        _ = sender.send(sent.into());
     } else {
         client.reply(Reply::Nil);
     }
@@ -627,51 +449,28 @@ fn push(client: &mut Client, store: &mut Store, edge: Edge) -> CommandResult {
     let max = store.list_max_listpack_size;
     let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
-    let list = db.entry_ref(&key).or_insert_with(Value::list).mut_list()?;
+    let list = db.get_or_insert_with(&key, Value::list).mut_list()?;
 
-    for value in client.request.iter() {
-        list.push(&&value[..], edge, max);
-    }
+    let values: Vec<_> = client.request.iter().collect();
+    list.push_many(&values, edge, max);
 
+    let pushed = values.len();
     let len = list.len();
-    store.touch(client.db(), &key);
-    store.mark_ready(client.db(), &key);
+    store.write(
+        client.db(),
+        &key,
+        WriteEffect::new(push_event(edge)).changes(pushed).ready(),
+    );
 
     client.reply(len);
     Ok(None)
 }
 
-pub static LPUSH: Command = Command {
-    kind: CommandKind::Lpush,
-    name: "lpush",
-    arity: Arity::Minimum(3),
-    run: lpush,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn lpush(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lpush(client: &mut Client, store: &mut Store) -> CommandResult {
     push(client, store, Edge::Left)
 }
 
-pub static LPUSHX: Command = Command {
-    kind: CommandKind::Lpushx,
-    name: "lpushx",
-    arity: Arity::Minimum(3),
-    run: lpushx,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn lpushx(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lpushx(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     client.request.reset(1);
     if !store.get_db(client.db())?.exists(&key) {
@@ -681,26 +480,13 @@ fn lpushx(client: &mut Client, store: &mut Store) -> CommandResult {
     push(client, store, Edge::Left)
 }
 
-pub static LRANGE: Command = Command {
-    kind: CommandKind::Lrange,
-    name: "lrange",
-    arity: Arity::Exact(4),
-    run: lrange,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn lrange(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lrange(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let start = client.request.i64()?;
     let end = client.request.i64()?;
     let db = store.get_db(client.db())?;
     let list = db.get_list(&key[..])?.ok_or(Reply::Array(0))?;
-    let range = slice(list.len(), start, end).ok_or(Reply::Array(0))?;
+    let range = list_slice(list.len(), start, end).ok_or(Reply::Array(0))?;
     let len = range.end - range.start;
 
     client.reply(Reply::Array(len));
@@ -712,60 +498,43 @@ fn lrange(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static LREM: Command = Command {
-    kind: CommandKind::Lrem,
-    name: "lrem",
-    arity: Arity::Exact(4),
-    run: lrem,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn lrem(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lrem(client: &mut Client, store: &mut Store) -> CommandResult {
+    let max = store.list_max_listpack_size;
     let key = client.request.pop()?;
     let (edge, count) = integer_with_edge(client)?;
     let element = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let list = db.mut_list(&key)?.ok_or(0)?;
 
-    let result = list.remove(element, count, edge);
-
-    if result > 0 {
-        store.touch(client.db(), &key);
-    }
+    let result = list.remove(element, count, edge, max);
+    store.write(client.db(), &key, WriteEffect::new("lrem").changes(result));
 
     client.reply(result);
     Ok(None)
 }
 
-pub static LSET: Command = Command {
-    kind: CommandKind::Lset,
-    name: "lset",
-    arity: Arity::Exact(4),
-    run: lset,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn lset(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn lset(client: &mut Client, store: &mut Store) -> CommandResult {
+    let max = store.list_max_listpack_size;
     let key = client.request.pop()?;
-    let index = client.request.usize()?;
+    let index = client.request.i64();
     let value = client.request.pop()?;
     let list = store
         .mut_db(client.db())?
         .mut_list(&key)?
         .ok_or(ReplyError::NoSuchKey)?;
 
-    if list.set(&value[..], index) {
-        store.touch(client.db(), &key);
+    // The key is checked before the index, and a negative index counts from the tail.
+    let mut index = index?;
+    if index < 0 {
+        index = i64::try_from(list.len())
+            .ok()
+            .and_then(|len| index.checked_add(len))
+            .ok_or(ReplyError::IndexOutOfRange)?;
+    }
+    let index = usize::try_from(index).or(Err(ReplyError::IndexOutOfRange))?;
+
+    if list.set(&value[..], index, max) {
+        store.write(client.db(), &key, WriteEffect::new("lset"));
         client.reply("OK");
     } else {
         client.reply(ReplyError::IndexOutOfRange);
@@ -774,20 +543,7 @@ fn lset(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static LTRIM: Command = Command {
-    kind: CommandKind::Ltrim,
-    name: "ltrim",
-    arity: Arity::Exact(4),
-    run: ltrim,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn ltrim(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn ltrim(client: &mut Client, store: &mut Store) -> CommandResult {
     let max = store.list_max_listpack_size;
     let key = client.request.pop()?;
     let start = client.request.i64()?;
@@ -795,7 +551,14 @@ fn ltrim(client: &mut Client, store: &mut Store) -> CommandResult {
     let db = store.mut_db(client.db())?;
     let list = db.mut_list(&key)?.ok_or("OK")?;
     let len = list.len();
-    let range = slice(len, start, end).ok_or("OK")?;
+
+    // An empty range removes every element.
+    let Some(range) = list_slice(len, start, end) else {
+        db.remove(&key);
+        store.write(client.db(), &key, WriteEffect::new("ltrim"));
+        client.reply("OK");
+        return Ok(None);
+    };
 
     // Is the list changed?
     if range.contains(&0) && range.contains(&(len - 1)) {
@@ -809,59 +572,20 @@ fn ltrim(client: &mut Client, store: &mut Store) -> CommandResult {
         db.remove(&key);
     }
 
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("ltrim"));
     client.reply("OK");
     Ok(None)
 }
 
-pub static RPOP: Command = Command {
-    kind: CommandKind::Rpop,
-    name: "rpop",
-    arity: Arity::Minimum(2),
-    run: rpop,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn rpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn rpop(client: &mut Client, store: &mut Store) -> CommandResult {
     pop(client, store, Edge::Right)
 }
 
-pub static RPUSH: Command = Command {
-    kind: CommandKind::Rpush,
-    name: "rpush",
-    arity: Arity::Minimum(3),
-    run: rpush,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn rpush(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn rpush(client: &mut Client, store: &mut Store) -> CommandResult {
     push(client, store, Edge::Right)
 }
 
-pub static RPUSHX: Command = Command {
-    kind: CommandKind::Rpushx,
-    name: "rpushx",
-    arity: Arity::Minimum(3),
-    run: rpushx,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn rpushx(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn rpushx(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     client.request.reset(1);
     if !store.get_db(client.db())?.exists(&key) {
@@ -870,3 +594,19 @@ fn rpushx(client: &mut Client, store: &mut Store) -> CommandResult {
 
     push(client, store, Edge::Right)
 }
+
+/// The event name for popping from one edge of a list.
+fn pop_event(edge: Edge) -> &'static str {
+    match edge {
+        Edge::Left => "lpop",
+        Edge::Right => "rpop",
+    }
+}
+
+/// The event name for pushing onto one edge of a list.
+fn push_event(edge: Edge) -> &'static str {
+    match edge {
+        Edge::Left => "lpush",
+        Edge::Right => "rpush",
+    }
+}
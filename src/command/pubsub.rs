@@ -1,107 +1,49 @@
 use crate::{
-    CommandResult,
-    buffer::ArrayBuffer,
-    bytes::lex,
-    client::Client,
-    command::{Arity, Command, CommandKind, Keys},
-    glob,
-    reply::Reply,
-    store::Store,
+    CommandResult, buffer::ArrayBuffer, client::Client, glob::Pattern, reply::Reply, store::Store,
 };
-use logos::Logos;
 
-pub static PUBSUB: Command = Command {
-    kind: CommandKind::Pubsub,
-    name: "pubsub",
-    arity: Arity::Minimum(2),
-    run: pubsub,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: true,
-    write: false,
-};
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
-pub enum PubsubSubcommand {
-    #[regex(b"(?i:channels)")]
-    Channels,
-
-    #[regex(b"(?i:help)")]
-    Help,
-
-    #[regex(b"(?i:numpat)")]
-    Numpat,
-
-    #[regex(b"(?i:numsub)")]
-    Numsub,
+pub(super) fn pubsub(client: &mut Client, store: &mut Store) -> CommandResult {
+    let subcommand = client.request.subcommand()?;
+    (subcommand.run)(client, store)
 }
 
-fn pubsub(client: &mut Client, store: &mut Store) -> CommandResult {
-    let len = client.request.len();
-    let subcommand = client.request.pop()?;
-
-    use PubsubSubcommand::*;
-    let subcommand = match (lex(&subcommand[..]), len) {
-        (Some(Channels), 2..=3) => channels,
-        (Some(Help), 2) => help,
-        (Some(Numpat), 2) => numpat,
-        (Some(Numsub), 2..) => numsub,
-        _ => return Err(client.request.unknown_subcommand().into()),
-    };
-
-    subcommand(client, store)
-}
-
-fn help(client: &mut Client, _: &mut Store) -> CommandResult {
+pub(super) fn help(client: &mut Client, _: &mut Store) -> CommandResult {
     client.verbatim("txt", include_str!("../help/pubsub.txt"));
     Ok(None)
 }
 
-fn numpat(client: &mut Client, store: &mut Store) -> CommandResult {
-    client.reply(store.pubsub.numpat());
+pub(super) fn numpat(client: &mut Client, store: &mut Store) -> CommandResult {
+    client.reply(store.pubsub.numpat(client.db()));
     Ok(None)
 }
 
-fn numsub(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn numsub(client: &mut Client, store: &mut Store) -> CommandResult {
+    // Redis replies with a flat array of pairs rather than a map, even with RESP3.
     client.reply(Reply::Array(client.request.remaining() * 2));
     while !client.request.is_empty() {
         let key = client.request.pop()?;
-        let count = store.pubsub.numsub(&key);
+        let count = store.pubsub.numsub(client.db(), &key);
         client.reply(key);
         client.reply(count);
     }
     Ok(None)
 }
 
-fn channels(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn channels(client: &mut Client, store: &mut Store) -> CommandResult {
     if let Some(pattern) = client.request.try_pop() {
+        let pattern = Pattern::compile(&pattern);
         let mut buffer = ArrayBuffer::default();
-        client.deferred_array(store.pubsub.channels().filter(|channel| {
+        client.deferred_array(store.pubsub.channels(client.db()).filter(|channel| {
             let bytes = channel.as_bytes(&mut buffer);
-            glob::matches(bytes, &pattern)
+            pattern.matches(bytes)
         }));
     } else {
-        client.deferred_array(store.pubsub.channels());
+        client.deferred_array(store.pubsub.channels(client.db()));
     }
     Ok(None)
 }
 
-pub static SUBSCRIBE: Command = Command {
-    kind: CommandKind::Subscribe,
-    name: "subscribe",
-    arity: Arity::Minimum(2),
-    run: subscribe,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: true,
-    write: false,
-};
-
-fn subscribe(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn subscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     while !client.request.is_empty() {
         let channel = client.request.pop()?;
         store.pubsub.subscribe(channel, client);
@@ -109,20 +51,7 @@ fn subscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static PSUBSCRIBE: Command = Command {
-    kind: CommandKind::Psubscribe,
-    name: "psubscribe",
-    arity: Arity::Minimum(2),
-    run: psubscribe,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: true,
-    write: false,
-};
-
-fn psubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn psubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     while !client.request.is_empty() {
         let pattern = client.request.pop()?;
         store.pubsub.psubscribe(pattern, client);
@@ -130,41 +59,15 @@ fn psubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static PUBLISH: Command = Command {
-    kind: CommandKind::Publish,
-    name: "publish",
-    arity: Arity::Exact(3),
-    run: publish,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: true,
-    write: false,
-};
-
-fn publish(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn publish(client: &mut Client, store: &mut Store) -> CommandResult {
     let channel = client.request.pop()?;
     let message = client.request.pop()?;
-    let count = store.pubsub.publish(&channel, &message);
+    let count = store.pubsub.publish(client.db(), &channel, &message);
     client.reply(count);
     Ok(None)
 }
 
-pub static UNSUBSCRIBE: Command = Command {
-    kind: CommandKind::Unsubscribe,
-    name: "unsubscribe",
-    arity: Arity::Minimum(1),
-    run: unsubscribe,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: true,
-    write: false,
-};
-
-fn unsubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn unsubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     if client.request.is_empty() {
         store.pubsub.unsubscribe_all(client);
     }
@@ -175,20 +78,7 @@ fn unsubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static PUNSUBSCRIBE: Command = Command {
-    kind: CommandKind::Punsubscribe,
-    name: "punsubscribe",
-    arity: Arity::Minimum(1),
-    run: punsubscribe,
-    keys: Keys::None,
-    readonly: false,
-    admin: false,
-    noscript: true,
-    pubsub: true,
-    write: false,
-};
-
-fn punsubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn punsubscribe(client: &mut Client, store: &mut Store) -> CommandResult {
     if client.request.is_empty() {
         store.pubsub.punsubscribe_all(client);
     }
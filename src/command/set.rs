@@ -1,26 +1,15 @@
 use crate::{
     CommandResult,
+    bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Keys},
+    db::{DB, Set},
     reply::{Reply, ReplyError},
-    store::Store,
+    store::{Store, WriteEffect},
 };
+use logos::Logos;
 use std::cmp::min;
 
-pub static SADD: Command = Command {
-    kind: CommandKind::Sadd,
-    name: "sadd",
-    arity: Arity::Minimum(3),
-    run: sadd,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn sadd(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn sadd(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let config = store.set_config;
     let db = store.mut_db(client.db())?;
@@ -34,28 +23,14 @@ fn sadd(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     if count > 0 {
-        store.dirty += count;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("sadd").changes(count));
     }
 
     client.reply(count);
     Ok(None)
 }
 
-pub static SCARD: Command = Command {
-    kind: CommandKind::Scard,
-    name: "scard",
-    arity: Arity::Exact(2),
-    run: scard,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn scard(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn scard(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
     let set = db.get_set(&key[..])?.ok_or(0)?;
@@ -64,20 +39,76 @@ fn scard(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SISMEMBER: Command = Command {
-    kind: CommandKind::Sismember,
-    name: "sismember",
-    arity: Arity::Exact(3),
-    run: sismember,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
+pub(super) fn sinter(client: &mut Client, store: &mut Store) -> CommandResult {
+    let count = client.request.remaining();
+    let db = store.get_db(client.db())?.snapshot();
+    let sets = smallest_first(client, &db, count)?.ok_or(Reply::Set(0))?;
+    let (first, others) = sets.split_first().ok_or(Reply::Set(0))?;
+    client.deferred_set(first.intersection(others));
+    Ok(None)
+}
 
-fn sismember(client: &mut Client, store: &mut Store) -> CommandResult {
+#[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
+pub enum SintercardOption {
+    #[regex(b"(?i:limit)")]
+    Limit,
+}
+
+pub(super) fn sintercard(client: &mut Client, store: &mut Store) -> CommandResult {
+    let numkeys = client.request.at_least(1, ReplyError::NumkeysZero)?;
+    if numkeys > client.request.remaining() {
+        return Err(ReplyError::NumberOfKeys.into());
+    }
+
+    let start = client.request.next();
+    client.request.reset(start + numkeys);
+    let mut limit = 0;
+    while let Some(argument) = client.request.try_pop() {
+        match lex(&argument[..]) {
+            Some(SintercardOption::Limit) if !client.request.is_empty() => {
+                limit = client.request.at_least(0, ReplyError::NegativeLimit)?;
+            }
+            _ => return Err(ReplyError::Syntax.into()),
+        }
+    }
+    client.request.reset(start);
+
+    // A limit of zero means no limit.
+    let limit = if limit == 0 { usize::MAX } else { limit };
+    let db = store.get_db(client.db())?.snapshot();
+    let sets = smallest_first(client, &db, numkeys)?.ok_or(0)?;
+    let (first, others) = sets.split_first().ok_or(0)?;
+    client.reply(first.intersection(others).take(limit).count());
+    Ok(None)
+}
+
+/// Get the sets at the next `count` keys, smallest first so that intersections look up as few
+/// values as possible, or `None` if any of them is missing. Every key is type checked, even after
+/// a missing one.
+fn smallest_first<'a>(
+    client: &mut Client,
+    db: &'a DB,
+    count: usize,
+) -> Result<Option<Vec<&'a Set>>, Reply> {
+    let mut sets = Vec::with_capacity(count);
+    let mut missing = false;
+    for _ in 0..count {
+        let key = client.request.pop()?;
+        match db.get_set(&key)? {
+            Some(set) => sets.push(set),
+            None => missing = true,
+        }
+    }
+
+    if missing {
+        return Ok(None);
+    }
+
+    sets.sort_by_key(|set| set.len());
+    Ok(Some(sets))
+}
+
+pub(super) fn sismember(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let value = client.request.pop()?;
     let db = store.get_db(client.db())?;
@@ -88,20 +119,7 @@ fn sismember(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SMEMBERS: Command = Command {
-    kind: CommandKind::Smembers,
-    name: "smembers",
-    arity: Arity::Exact(2),
-    run: smembers,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn smembers(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn smembers(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
     let set = db.get_set(&key)?.ok_or(Reply::Set(0))?;
@@ -114,20 +132,7 @@ fn smembers(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SMISMEMBER: Command = Command {
-    kind: CommandKind::Smismember,
-    name: "smismember",
-    arity: Arity::Minimum(3),
-    run: smismember,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn smismember(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn smismember(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
 
@@ -149,40 +154,32 @@ fn smismember(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static SPOP: Command = Command {
-    kind: CommandKind::Spop,
-    name: "spop",
-    arity: Arity::Minimum(2),
-    run: spop,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn spop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn spop(client: &mut Client, store: &mut Store) -> CommandResult {
     if client.request.len() > 3 {
         return Err(ReplyError::Syntax.into());
     }
 
     let key = client.request.pop()?;
+    let count = if client.request.is_empty() {
+        None
+    } else {
+        Some(client.request.positive()?)
+    };
     let db = store.mut_db(client.db())?;
-    let set = db.mut_set(&key)?.ok_or(Reply::Array(0))?;
 
-    if client.request.is_empty() {
+    let Some(count) = count else {
+        let set = db.mut_set(&key)?.ok_or(Reply::Nil)?;
         let member = set.pop().ok_or(Reply::Nil)?;
         client.reply(member);
         if set.is_empty() {
             db.remove(&key);
         }
-        store.dirty += 1;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("spop"));
         return Ok(None);
-    }
+    };
 
-    let count = min(client.request.usize()?, set.len());
+    let set = db.mut_set(&key)?.ok_or(Reply::Array(0))?;
+    let count = min(count, set.len());
     client.reply(Reply::Array(count));
     for _ in 0..count {
         let member = set.pop().ok_or(Reply::Nil)?;
@@ -192,27 +189,13 @@ fn spop(client: &mut Client, store: &mut Store) -> CommandResult {
         db.remove(&key);
     }
     if count > 0 {
-        store.dirty += count;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("spop").changes(count));
     }
 
     Ok(None)
 }
 
-pub static SREM: Command = Command {
-    kind: CommandKind::Srem,
-    name: "srem",
-    arity: Arity::Minimum(3),
-    run: srem,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn srem(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn srem(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let set = db.set_or_default(&key)?;
@@ -229,8 +212,7 @@ fn srem(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     if count > 0 {
-        store.dirty += count;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("srem").changes(count));
     }
 
     client.reply(count);
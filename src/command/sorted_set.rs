@@ -1,75 +1,17 @@
 use crate::{
     BlockResult, CommandResult,
-    bytes::{lex, parse},
+    bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Keys},
+    command::CommandKind,
     db::{Extreme, Insertion, SortedSetRef},
     reply::{Reply, ReplyError},
-    slice::slice,
-    store::Store,
+    slice::list_slice,
+    store::{Store, WriteEffect},
 };
 use logos::Logos;
-use std::{ops::Bound, time::Duration};
+use std::time::Duration;
 
-/// Parse a float, do not allow NaN.
-fn parse_float(value: &[u8]) -> Result<f64, Reply> {
-    let value: f64 = parse(value).ok_or(ReplyError::Float)?;
-    if value.is_nan() {
-        return Err(ReplyError::Float.into());
-    }
-    Ok(value)
-}
-
-/// Parse a score bound.
-fn score_bound(client: &mut Client) -> Result<Bound<f64>, Reply> {
-    let argument = client.request.pop()?;
-    use Bound::*;
-    Ok(match &argument[..] {
-        [b'(', rest @ ..] => Excluded(parse_float(rest)?),
-        rest => Included(parse_float(rest)?),
-    })
-}
-
-pub static BZMPOP: Command = Command {
-    kind: CommandKind::Bzmpop,
-    name: "bzmpop",
-    arity: Arity::Minimum(5),
-    run: zmpop,
-    keys: Keys::Argument(2),
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static BZPOPMAX: Command = Command {
-    kind: CommandKind::Bzpopmax,
-    name: "bzpopmax",
-    arity: Arity::Minimum(3),
-    run: bzpop,
-    keys: Keys::Trailing,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static BZPOPMIN: Command = Command {
-    kind: CommandKind::Bzpopmin,
-    name: "bzpopmin",
-    arity: Arity::Minimum(3),
-    run: bzpop,
-    keys: Keys::Trailing,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn bzpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn bzpop(client: &mut Client, store: &mut Store) -> CommandResult {
     let extreme = match client.request.command.kind {
         CommandKind::Bzpopmax => Extreme::Max,
         CommandKind::Bzpopmin => Extreme::Min,
@@ -101,12 +43,7 @@ fn bzpop(client: &mut Client, store: &mut Store) -> CommandResult {
             db.remove(&key);
         }
 
-        store.touch(client.db(), &key);
-        return Ok(None);
-    }
-
-    if client.in_exec {
-        client.reply(Reply::Nil);
+        store.write(client.db(), &key, WriteEffect::new(pop_event(extreme)));
         return Ok(None);
     }
 
@@ -115,19 +52,6 @@ fn bzpop(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(Some(block))
 }
 
-pub static ZADD: Command = Command {
-    kind: CommandKind::Zadd,
-    name: "zadd",
-    arity: Arity::Minimum(4),
-    run: zadd,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Logos)]
 pub enum ZaddOption {
     #[regex(b"(?i:ch)")]
@@ -146,7 +70,7 @@ pub enum ZaddOption {
     Xx,
 }
 
-fn zadd(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zadd(client: &mut Client, store: &mut Store) -> CommandResult {
     let max_len = store.zset_max_listpack_entries;
     let max_size = store.zset_max_listpack_value;
     let key = client.request.pop()?;
@@ -189,10 +113,20 @@ fn zadd(client: &mut Client, store: &mut Store) -> CommandResult {
         return Err(ReplyError::XxAndNx.into());
     }
 
-    if (gt || lt || nx) && !(gt ^ lt ^ nx) {
+    if (gt && lt) || (nx && (gt || lt)) {
         return Err(ReplyError::GtLtNx.into());
     }
 
+    client.request.assert_pairs()?;
+
+    // Ensure that scores are valid before starting.
+    let next = client.request.next();
+    while !client.request.is_empty() {
+        client.request.score()?;
+        client.request.pop()?;
+    }
+    client.request.reset(next);
+
     let db = store.mut_db(client.db())?;
 
     // If XX was passed and the key doesn't exist, there is nothing to be done.
@@ -203,20 +137,10 @@ fn zadd(client: &mut Client, store: &mut Store) -> CommandResult {
 
     let set = db.sorted_set_or_default(&key)?;
 
-    client.request.assert_pairs()?;
-
-    // Ensure that scores are valid before starting.
-    let next = client.request.next();
-    while !client.request.is_empty() {
-        client.request.not_nan()?;
-        client.request.pop()?;
-    }
-    client.request.reset(next);
-
     let mut added = 0;
     let mut changed = 0;
     while !client.request.is_empty() {
-        let score = client.request.not_nan()?;
+        let score = client.request.score()?;
         let member = client.request.pop()?;
 
         if gt || lt {
@@ -250,27 +174,16 @@ fn zadd(client: &mut Client, store: &mut Store) -> CommandResult {
         }
     }
 
-    store.dirty += added + changed;
-    store.touch(client.db(), &key);
-    store.mark_ready(client.db(), &key);
+    store.write(
+        client.db(),
+        &key,
+        WriteEffect::new("zadd").changes(added + changed).ready(),
+    );
     client.reply(if ch { added + changed } else { added });
     Ok(None)
 }
 
-pub static ZCARD: Command = Command {
-    kind: CommandKind::Zcard,
-    name: "zcard",
-    arity: Arity::Exact(2),
-    run: zcard,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn zcard(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zcard(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
     let set = db.get_sorted_set(&key)?.ok_or(0)?;
@@ -278,23 +191,10 @@ fn zcard(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static ZCOUNT: Command = Command {
-    kind: CommandKind::Zcount,
-    name: "zcount",
-    arity: Arity::Exact(4),
-    run: zcount,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn zcount(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zcount(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let min = score_bound(client)?;
-    let max = score_bound(client)?;
+    let min = client.request.score_bound()?;
+    let max = client.request.score_bound()?;
 
     let db = store.get_db(client.db())?;
     let set = db.get_sorted_set(&key)?.ok_or(0)?;
@@ -302,19 +202,6 @@ fn zcount(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static ZMPOP: Command = Command {
-    kind: CommandKind::Zmpop,
-    name: "zmpop",
-    arity: Arity::Minimum(4),
-    run: zmpop,
-    keys: Keys::Argument(1),
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum ZmpopOption {
     #[regex(b"(?i:count)")]
@@ -339,23 +226,16 @@ pub fn extreme(client: &mut Client) -> Result<Extreme, ReplyError> {
     }
 }
 
-fn zmpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zmpop(client: &mut Client, store: &mut Store) -> CommandResult {
     let blocking = client.request.kind() == CommandKind::Bzmpop;
     let timeout = if blocking {
         client.request.timeout()?
     } else {
         Duration::ZERO
     };
-    let numkeys = client
-        .request
-        .usize()
-        .map_err(|_| ReplyError::NumkeysZero)?;
+    let numkeys = client.request.at_least(1, ReplyError::NumkeysZero)?;
     let start = client.request.next();
 
-    if numkeys == 0 {
-        return Err(ReplyError::NumkeysZero.into());
-    }
-
     if client.request.len() < start + numkeys + 1 {
         return Err(ReplyError::Syntax.into());
     }
@@ -366,14 +246,11 @@ fn zmpop(client: &mut Client, store: &mut Store) -> CommandResult {
     while let Some(argument) = client.request.try_pop() {
         match lex(&argument[..]) {
             Some(ZmpopOption::Count) if count.is_none() => {
-                count = Some(client.request.usize().map_err(|_| ReplyError::CountZero)?);
+                count = Some(client.request.at_least(1, ReplyError::CountZero)?);
             }
             _ => return Err(ReplyError::Syntax.into()),
         }
     }
-    if count == Some(0) {
-        return Err(ReplyError::CountZero.into());
-    }
     let count = count.unwrap_or(1);
     let db = store.mut_db(client.db())?;
 
@@ -400,11 +277,15 @@ fn zmpop(client: &mut Client, store: &mut Store) -> CommandResult {
         if set.is_empty() {
             db.remove(&key);
         }
-        store.touch(client.db(), &key);
+        store.write(
+            client.db(),
+            &key,
+            WriteEffect::new(pop_event(extreme)).changes(count),
+        );
         return Ok(None);
     }
 
-    if !blocking || client.in_exec {
+    if !blocking {
         client.reply(Reply::Nil);
         return Ok(None);
     }
@@ -414,7 +295,7 @@ fn zmpop(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(Some(block))
 }
 
-fn zpop(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zpop(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let mut count = 1i64;
     let mut nested = false;
@@ -456,87 +337,14 @@ fn zpop(client: &mut Client, store: &mut Store) -> CommandResult {
         db.remove(&key);
     }
 
+    store.write(
+        client.db(),
+        &key,
+        WriteEffect::new(pop_event(extreme)).changes(count),
+    );
     Ok(None)
 }
 
-pub static ZPOPMAX: Command = Command {
-    kind: CommandKind::Zpopmax,
-    name: "zpopmax",
-    arity: Arity::Minimum(2),
-    run: zpop,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static ZPOPMIN: Command = Command {
-    kind: CommandKind::Zpopmin,
-    name: "zpopmin",
-    arity: Arity::Minimum(2),
-    run: zpop,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-pub static ZRANGE: Command = Command {
-    kind: CommandKind::Zrange,
-    name: "zrange",
-    arity: Arity::Minimum(4),
-    run: zrange,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-pub static ZRANGEBYSCORE: Command = Command {
-    kind: CommandKind::Zrangebyscore,
-    name: "zrangebyscore",
-    arity: Arity::Minimum(4),
-    run: zrange,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-pub static ZREVRANGE: Command = Command {
-    kind: CommandKind::Zrevrange,
-    name: "zrevrange",
-    arity: Arity::Minimum(4),
-    run: zrange,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-pub static ZREVRANGEBYSCORE: Command = Command {
-    kind: CommandKind::Zrevrangebyscore,
-    name: "zrevrangebyscore",
-    arity: Arity::Minimum(4),
-    run: zrange,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
 pub struct ZrangeOptions {
     pub by: Zrangeby,
     pub withscores: bool,
@@ -580,7 +388,7 @@ pub enum ZrangeOption {
     Withscores,
 }
 
-fn zrange(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zrange(client: &mut Client, store: &mut Store) -> CommandResult {
     // TODO: All the options
     client.request.reset(4);
     let mut options = ZrangeOptions::default();
@@ -590,7 +398,11 @@ fn zrange(client: &mut Client, store: &mut Store) -> CommandResult {
         Zrangebyscore => {
             options.by = Zrangeby::Score;
         }
-        Zrevrange | Zrevrangebyscore => {
+        Zrevrange => {
+            options.reverse = true;
+        }
+        Zrevrangebyscore => {
+            options.by = Zrangeby::Score;
             options.reverse = true;
         }
         _ => {}
@@ -616,8 +428,11 @@ fn zrange(client: &mut Client, store: &mut Store) -> CommandResult {
                 options.by = Zrangeby::Score;
             }
             Limit if limit_allowed => {
-                let offset = client.request.usize()?;
-                let count = client.request.usize()?;
+                // A negative offset skips everything and a negative count returns everything.
+                let offset = client.request.i64()?;
+                let count = client.request.i64()?;
+                let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+                let count = usize::try_from(count).unwrap_or(usize::MAX);
                 options.limit = Some((offset, count));
             }
             Rev if rev_allowed => {
@@ -642,11 +457,10 @@ fn zrange(client: &mut Client, store: &mut Store) -> CommandResult {
     f(client, store, &options)
 }
 
-fn zrangebylex(
-    _client: &mut Client,
-    _store: &mut Store,
-    _options: &ZrangeOptions,
-) -> CommandResult {
+fn zrangebylex(client: &mut Client, _store: &mut Store, _options: &ZrangeOptions) -> CommandResult {
+    let _key = client.request.pop()?;
+    let _min = client.request.lex_bound()?;
+    let _max = client.request.lex_bound()?;
     todo!()
 }
 
@@ -661,7 +475,7 @@ fn zrangebyrank(client: &mut Client, store: &mut Store, options: &ZrangeOptions)
     let db = store.get_db(client.db())?;
     let set = db.get_sorted_set(&key)?.ok_or(Reply::Array(0))?;
 
-    let range = slice(set.len(), min, max).ok_or(Reply::Array(0))?;
+    let range = list_slice(set.len(), min, max).ok_or(Reply::Array(0))?;
 
     if options.reverse {
         zrange_reply(client, set.rev_range(range), options);
@@ -674,16 +488,16 @@ fn zrangebyrank(client: &mut Client, store: &mut Store, options: &ZrangeOptions)
 
 fn zrangebyscore(client: &mut Client, store: &mut Store, options: &ZrangeOptions) -> CommandResult {
     let key = client.request.pop()?;
-    let min = score_bound(client)?;
-    let max = score_bound(client)?;
+    let min = client.request.score_bound()?;
+    let max = client.request.score_bound()?;
     let range = (min, max);
     let db = store.get_db(client.db())?;
     let set = db.get_sorted_set(&key)?.ok_or(Reply::Array(0))?;
 
     if options.reverse {
-        zrange_reply(client, set.rev_range_score(&range), options);
+        zrange_reply(client, set.rev_range_score(&range, options.limit), options);
     } else {
-        zrange_reply(client, set.range_score(&range), options);
+        zrange_reply(client, set.range_score(&range, options.limit), options);
     }
 
     Ok(None)
@@ -695,15 +509,12 @@ fn zrange_reply<'a, I: Iterator<Item = (f64, SortedSetRef<'a>)> + ExactSizeItera
     options: &ZrangeOptions,
 ) {
     let mut size = iterator.len();
-    let (offset, limit) = options.limit.unwrap_or((0, usize::MAX));
-    size -= offset;
-    size = std::cmp::min(size, limit);
     if options.withscores {
         size *= 2;
     }
     client.reply(Reply::Array(size));
 
-    for (score, value) in iterator.skip(offset).take(limit) {
+    for (score, value) in iterator {
         client.reply(value);
         if options.withscores {
             client.reply(score);
@@ -711,20 +522,7 @@ fn zrange_reply<'a, I: Iterator<Item = (f64, SortedSetRef<'a>)> + ExactSizeItera
     }
 }
 
-pub static ZRANK: Command = Command {
-    kind: CommandKind::Zrank,
-    name: "zrank",
-    arity: Arity::Exact(3),
-    run: zrank,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn zrank(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zrank(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let member = client.request.pop()?;
     let db = store.get_db(client.db())?;
@@ -736,20 +534,7 @@ fn zrank(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static ZREM: Command = Command {
-    kind: CommandKind::Zrem,
-    name: "zrem",
-    arity: Arity::Minimum(3),
-    run: zrem,
-    keys: Keys::All,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn zrem(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zrem(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let set = db.mut_sorted_set(&key)?.ok_or(0)?;
@@ -766,54 +551,57 @@ fn zrem(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     client.reply(count);
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("zrem").changes(count));
     Ok(None)
 }
 
-pub static ZREMRANGEBYSCORE: Command = Command {
-    kind: CommandKind::Zrem,
-    name: "zremrangebyscore",
-    arity: Arity::Exact(4),
-    run: zremrangebyscore,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
+pub(super) fn zremrangebyrank(client: &mut Client, store: &mut Store) -> CommandResult {
+    let key = client.request.pop()?;
+    let start = client.request.i64()?;
+    let end = client.request.i64()?;
+    let db = store.mut_db(client.db())?;
+    let set = db.mut_sorted_set(&key)?.ok_or(0)?;
+    let range = list_slice(set.len(), start, end).ok_or(0)?;
+
+    let count = set.remove_range_rank(range);
+
+    if set.is_empty() {
+        db.remove(&key);
+    }
 
-fn zremrangebyscore(client: &mut Client, store: &mut Store) -> CommandResult {
+    client.reply(count);
+    store.write(
+        client.db(),
+        &key,
+        WriteEffect::new("zremrangebyrank").changes(count),
+    );
+    Ok(None)
+}
+
+pub(super) fn zremrangebyscore(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let min = score_bound(client)?;
-    let max = score_bound(client)?;
+    let min = client.request.score_bound()?;
+    let max = client.request.score_bound()?;
     let range = (min, max);
     let db = store.mut_db(client.db())?;
     let set = db.mut_sorted_set(&key)?.ok_or(0)?;
 
-    client.reply(set.remove_range_score(&range));
+    let count = set.remove_range_score(&range);
 
     if set.is_empty() {
         db.remove(&key);
     }
 
+    client.reply(count);
+    store.write(
+        client.db(),
+        &key,
+        WriteEffect::new("zremrangebyscore").changes(count),
+    );
     Ok(None)
 }
 
-pub static ZSCORE: Command = Command {
-    kind: CommandKind::Zscore,
-    name: "zscore",
-    arity: Arity::Exact(3),
-    run: zscore,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn zscore(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn zscore(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let member = client.request.pop()?;
 
@@ -821,6 +609,14 @@ fn zscore(client: &mut Client, store: &mut Store) -> CommandResult {
     let set = db.get_sorted_set(&key)?.ok_or(Reply::Nil)?;
     let score = set.score(&member).ok_or(Reply::Nil)?;
 
-    client.bulk(score);
+    client.reply(score);
     Ok(None)
 }
+
+/// The event name for popping from one end of a sorted set.
+fn pop_event(extreme: Extreme) -> &'static str {
+    match extreme {
+        Extreme::Max => "zpopmax",
+        Extreme::Min => "zpopmin",
+    }
+}
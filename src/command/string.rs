@@ -1,13 +1,11 @@
 use crate::{
     CommandResult,
-    buffer::ArrayBuffer,
     bytes::lex,
     client::Client,
-    command::{Arity, Command, CommandKind, Keys},
-    epoch,
+    db::Expiry,
     reply::{Reply, ReplyError},
     slice::slice,
-    store::Store,
+    store::{Store, WriteEffect},
 };
 use bytes::Bytes;
 use logos::Logos;
@@ -23,71 +21,28 @@ pub enum Ttl {
     Pxat(u128),
 }
 
-pub static APPEND: Command = Command {
-    kind: CommandKind::Append,
-    name: "append",
-    arity: Arity::Exact(3),
-    run: append,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn append(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn append(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let bytes = client.request.pop()?;
-    let max = store.reader_config.blob_limit();
+    let limit = store.string_limit();
     let db = store.mut_db(client.db())?;
     let value = db.string_or_default(&key)?;
-
-    if max.saturating_sub(value.len()) < bytes.len() {
-        return Err(ReplyError::StringLength.into());
-    }
+    limit.check(value.len().checked_add(bytes.len()))?;
 
     value.append(&bytes[..]);
     let len = value.len();
     client.reply(len);
 
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("append"));
     Ok(None)
 }
 
-pub static DECR: Command = Command {
-    kind: CommandKind::Decr,
-    name: "decr",
-    arity: Arity::Exact(2),
-    run: decr,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn decr(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn decr(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     increment(client, store, &key, -1)
 }
 
-pub static DECRBY: Command = Command {
-    kind: CommandKind::Decrby,
-    name: "decrby",
-    arity: Arity::Exact(3),
-    run: decrby,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn decrby(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn decrby(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let by = client
         .request
@@ -97,20 +52,7 @@ fn decrby(client: &mut Client, store: &mut Store) -> CommandResult {
     increment(client, store, &key, by)
 }
 
-pub static GET: Command = Command {
-    kind: CommandKind::Get,
-    name: "get",
-    arity: Arity::Exact(2),
-    run: get,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn get(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn get(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
     let value = db.get_string(&key[..])?.ok_or(Reply::Nil)?;
@@ -119,43 +61,16 @@ fn get(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static GETDEL: Command = Command {
-    kind: CommandKind::Getdel,
-    name: "getdel",
-    arity: Arity::Exact(2),
-    run: getdel,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn getdel(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn getdel(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let value = db.get_string(&key)?.ok_or(Reply::Nil)?;
     client.reply(value);
     db.remove(&key);
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("del"));
     Ok(None)
 }
 
-pub static GETEX: Command = Command {
-    kind: CommandKind::Getex,
-    name: "getex",
-    arity: Arity::Minimum(2),
-    run: getex,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Debug, Eq, PartialEq)]
 pub enum GetexTtl {
     Ex(u128),
@@ -183,7 +98,7 @@ pub enum GetexOption {
     Pxat,
 }
 
-fn getex(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn getex(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let mut ttl = None;
 
@@ -195,22 +110,22 @@ fn getex(client: &mut Client, store: &mut Store) -> CommandResult {
         use GetexTtl::*;
         match (option, ttl) {
             (GetexOption::Ex, Some(Ex(_)) | None) => {
-                let at = client.request.ttl()?;
+                let at = client.request.positive_ttl()?;
                 ttl = Some(Ex(at));
             }
             (GetexOption::Exat, Some(Exat(_)) | None) => {
-                let at = client.request.expiretime()?;
+                let at = client.request.positive_expiretime()?;
                 ttl = Some(Exat(at));
             }
             (GetexOption::Persist, Some(Persist) | None) => {
                 ttl = Some(Persist);
             }
             (GetexOption::Px, Some(Px(_)) | None) => {
-                let at = client.request.pttl()?;
+                let at = client.request.positive_pttl()?;
                 ttl = Some(Px(at));
             }
             (GetexOption::Pxat, Some(Pxat(_)) | None) => {
-                let at = client.request.pexpiretime()?;
+                let at = client.request.positive_pexpiretime()?;
                 ttl = Some(Pxat(at));
             }
             _ => {
@@ -225,112 +140,47 @@ fn getex(client: &mut Client, store: &mut Store) -> CommandResult {
     if let Some(ttl) = ttl {
         use GetexTtl::*;
 
-        match ttl {
-            Ex(at) | Exat(at) | Px(at) | Pxat(at) => {
-                if epoch().as_millis() > at {
-                    db.remove(&key);
-                } else {
-                    db.expire(&key, at);
-                }
-            }
-            Persist => {
-                db.persist(&key);
-            }
-        }
-
-        store.dirty += 1;
-        store.touch(client.db(), &key);
+        let (expiry, event) = match ttl {
+            Ex(at) | Exat(at) | Px(at) | Pxat(at) => (Expiry::At(at), "expire"),
+            Persist => (Expiry::Persist, "persist"),
+        };
+        store.set_expiry(client.db(), &key, expiry, event)?;
     }
 
     client.reply(value);
     Ok(None)
 }
 
-pub static GETRANGE: Command = Command {
-    kind: CommandKind::Getrange,
-    name: "getrange",
-    arity: Arity::Exact(4),
-    run: getrange,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn getrange(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn getrange(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let start = client.request.i64()?;
     let end = client.request.i64()?;
     let db = store.get_db(client.db())?;
     let value = db.get_string(&key)?.ok_or("")?;
-    let mut buffer = ArrayBuffer::default();
-    let len = value.as_bytes(&mut buffer).len();
-    let range = slice(len, start, end).ok_or("")?;
+    let range = slice(value.len(), start, end).ok_or("")?;
 
     client.reply(value.slice(range));
     Ok(None)
 }
 
-pub static INCR: Command = Command {
-    kind: CommandKind::Incr,
-    name: "incr",
-    arity: Arity::Exact(2),
-    run: incr,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn incr(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn incr(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     increment(client, store, &key, 1)
 }
 
-pub static INCRBY: Command = Command {
-    kind: CommandKind::Incrby,
-    name: "incrby",
-    arity: Arity::Exact(3),
-    run: incrby,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn incrby(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn incrby(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let by = client.request.i64()?;
     increment(client, store, &key, by)
 }
 
-pub static INCRBYFLOAT: Command = Command {
-    kind: CommandKind::Incrbyfloat,
-    name: "incrbyfloat",
-    arity: Arity::Exact(3),
-    run: incrbyfloat,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn incrbyfloat(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn incrbyfloat(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let by = client.request.finite_f64()?;
     let db = store.mut_db(client.db())?;
 
     let value = db
-        .entry_ref(&key)
-        .or_insert_with(|| 0f64.into())
+        .get_or_insert_with(&key, || 0f64.into())
         .mut_string()?
         .float()
         .ok_or(ReplyError::Float)?;
@@ -342,18 +192,16 @@ fn incrbyfloat(client: &mut Client, store: &mut Store) -> CommandResult {
     }
 
     *value = sum;
-    client.reply(sum);
+    client.bulk(sum);
 
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("incrbyfloat"));
     Ok(None)
 }
 
 fn increment(client: &mut Client, store: &mut Store, key: &Bytes, by: i64) -> CommandResult {
     let db = store.mut_db(client.db())?;
     let value = db
-        .entry_ref(key)
-        .or_insert_with(|| 0i64.into())
+        .get_or_insert_with(key, || 0i64.into())
         .mut_string()?
         .integer()
         .ok_or(ReplyError::Integer)?;
@@ -361,55 +209,27 @@ fn increment(client: &mut Client, store: &mut Store, key: &Bytes, by: i64) -> Co
     *value = value.checked_add(by).ok_or(ReplyError::IncrOverflow)?;
     client.reply(*value);
 
-    store.dirty += 1;
-    store.touch(client.db(), key);
+    store.write(client.db(), key, WriteEffect::new("incrby"));
 
     Ok(None)
 }
 
-pub static GETSET: Command = Command {
-    kind: CommandKind::Getset,
-    name: "getset",
-    arity: Arity::Exact(3),
-    run: getset,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn getset(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn getset(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let value = client.request.pop()?;
     let db = store.mut_db(client.db())?;
     let original = db.get_string(&key)?.cloned();
 
     db.set(&key, &value);
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("set"));
     client.reply(original);
     Ok(None)
 }
 
-pub static MGET: Command = Command {
-    kind: CommandKind::Mget,
-    name: "mget",
-    arity: Arity::Minimum(2),
-    run: mget,
-    keys: Keys::All,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn mget(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn mget(client: &mut Client, store: &mut Store) -> CommandResult {
     client.reply(Reply::Array(client.request.remaining()));
 
-    let db = store.get_db(client.db())?;
+    let db = store.get_db(client.db())?.snapshot();
     while !client.request.is_empty() {
         let key = client.request.pop()?;
         let value = db.get_string(&key[..]).ok().flatten();
@@ -419,51 +239,16 @@ fn mget(client: &mut Client, store: &mut Store) -> CommandResult {
     Ok(None)
 }
 
-pub static MSET: Command = Command {
-    kind: CommandKind::Mset,
-    name: "mset",
-    arity: Arity::Minimum(3),
-    run: mset,
-    keys: Keys::Odd,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn mset(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn mset(client: &mut Client, store: &mut Store) -> CommandResult {
     client.request.assert_pairs()?;
-
-    while !client.request.is_empty() {
-        let key = client.request.pop()?;
-        let value = client.request.pop()?;
-        let db = store.mut_db(client.db())?;
-        db.set(&key, value);
-        store.dirty += 1;
-        store.touch(client.db(), &key);
-    }
-
+    set_pairs(client, store)?;
     client.reply("OK");
     Ok(None)
 }
 
-pub static MSETNX: Command = Command {
-    kind: CommandKind::Msetnx,
-    name: "msetnx",
-    arity: Arity::Minimum(3),
-    run: msetnx,
-    keys: Keys::Odd,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn msetnx(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn msetnx(client: &mut Client, store: &mut Store) -> CommandResult {
     client.request.assert_pairs()?;
-    let db = store.get_db(client.db())?;
+    let db = store.get_db(client.db())?.snapshot();
     while !client.request.is_empty() {
         let key = client.request.pop()?;
         _ = client.request.pop()?;
@@ -471,60 +256,38 @@ fn msetnx(client: &mut Client, store: &mut Store) -> CommandResult {
             return Err(0.into());
         }
     }
+    drop(db);
 
     client.request.reset(1);
-
-    while !client.request.is_empty() {
-        let key = client.request.pop()?;
-        let value = client.request.pop()?;
-        let db = store.mut_db(client.db())?;
-        db.set(&key, value);
-        store.dirty += 1;
-        store.touch(client.db(), &key);
-    }
-
+    set_pairs(client, store)?;
     client.reply(1);
     Ok(None)
 }
 
-pub static PSETEX: Command = Command {
-    kind: CommandKind::Psetex,
-    name: "psetex",
-    arity: Arity::Exact(4),
-    run: psetex,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
+/// Set each key/value pair left in the request, then apply the side effects for all the keys
+/// together.
+fn set_pairs(client: &mut Client, store: &mut Store) -> Result<(), Reply> {
+    let db = store.mut_db(client.db())?;
+    let mut arguments = client.request.iter();
+    while let (Some(key), Some(value)) = (arguments.next(), arguments.next()) {
+        db.set(&key, value);
+    }
+    let keys = client.request.iter().step_by(2);
+    store.write_all(client.db(), keys, WriteEffect::new("set"));
+    Ok(())
+}
 
-fn psetex(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn psetex(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let millis = client.request.u128()?;
+    let at = client.request.positive_pttl()?;
     let value = client.request.pop()?;
     let db = store.mut_db(client.db())?;
-    db.setex(&key, &value, epoch().as_millis() + millis);
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    db.setex(&key, &value, at);
+    store.write(client.db(), &key, WriteEffect::new("set"));
     client.reply("OK");
     Ok(None)
 }
 
-pub static SET: Command = Command {
-    kind: CommandKind::Set,
-    name: "set",
-    arity: Arity::Minimum(3),
-    run: set,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
 #[derive(Clone, Copy, Debug, Eq, Hash, Logos, PartialEq)]
 pub enum SetOption {
     #[regex(b"(?i:ex)")]
@@ -552,7 +315,15 @@ pub enum SetOption {
     Xx,
 }
 
-fn set(client: &mut Client, store: &mut Store) -> CommandResult {
+/// Make sure an expiration option for `SET` has a value.
+fn set_expire(client: &mut Client) -> Result<(), ReplyError> {
+    if client.request.is_empty() {
+        return Err(ReplyError::Syntax);
+    }
+    Ok(())
+}
+
+pub(super) fn set(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let value = client.request.pop()?;
     let mut ttl = Ttl::None;
@@ -567,10 +338,12 @@ fn set(client: &mut Client, store: &mut Store) -> CommandResult {
         use SetOption::*;
         match option {
             Ex if matches!(ttl, Ttl::Ex(_) | Ttl::None) => {
-                ttl = Ttl::Ex(client.request.u128()?);
+                set_expire(client)?;
+                ttl = Ttl::Ex(client.request.positive_ttl()?);
             }
             Exat if matches!(ttl, Ttl::Exat(_) | Ttl::None) => {
-                ttl = Ttl::Exat(client.request.u128()?);
+                set_expire(client)?;
+                ttl = Ttl::Exat(client.request.positive_expiretime()?);
             }
             Get => {
                 get = true;
@@ -582,10 +355,12 @@ fn set(client: &mut Client, store: &mut Store) -> CommandResult {
                 exists = Some(false);
             }
             Px if matches!(ttl, Ttl::Px(_) | Ttl::None) => {
-                ttl = Ttl::Px(client.request.u128()?);
+                set_expire(client)?;
+                ttl = Ttl::Px(client.request.positive_pttl()?);
             }
             Pxat if matches!(ttl, Ttl::Pxat(_) | Ttl::None) => {
-                ttl = Ttl::Pxat(client.request.u128()?);
+                set_expire(client)?;
+                ttl = Ttl::Pxat(client.request.positive_pexpiretime()?);
             }
             Xx if exists != Some(false) => {
                 exists = Some(true);
@@ -596,80 +371,52 @@ fn set(client: &mut Client, store: &mut Store) -> CommandResult {
 
     let db = store.mut_db(client.db())?;
 
-    match exists {
-        Some(false) if !db.exists(&key) => {}
-        Some(true) if db.exists(&key) => {}
-        None => {}
-        _ => return Err(Reply::Nil),
-    }
+    // Check the type of the previous value before deciding whether to write, so that GET
+    // returns an error for the wrong type regardless of NX or XX.
+    let previous = if get {
+        db.get_string(&key)?.cloned()
+    } else {
+        None
+    };
 
-    let previous = get
-        .then(|| db.get(&key))
-        .flatten()
-        .map(|value| value.as_string())
-        .transpose()?
-        .cloned();
-
-    match ttl {
-        Ttl::Ex(s) => db.setex(&key, value, epoch().as_millis() + (s * 1000)),
-        Ttl::Exat(at) => db.setex(&key, value, at * 1000),
-        Ttl::Keep => db.overwrite(&key, value),
-        Ttl::None => db.set(&key, value),
-        Ttl::Px(ms) => db.setex(&key, value, epoch().as_millis() + ms),
-        Ttl::Pxat(at) => db.setex(&key, value, at),
+    let write = match exists {
+        Some(exists) => exists == db.exists(&key),
+        None => true,
     };
 
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    if write {
+        match ttl {
+            Ttl::Ex(at) | Ttl::Exat(at) | Ttl::Px(at) | Ttl::Pxat(at) => db.setex(&key, value, at),
+            Ttl::Keep => db.overwrite(&key, value),
+            Ttl::None => db.set(&key, value),
+        };
+
+        store.write(client.db(), &key, WriteEffect::new("set"));
+    }
 
     if get {
         client.reply(previous);
-    } else {
+    } else if write {
         client.reply("OK");
+    } else {
+        client.reply(Reply::Nil);
     }
 
     Ok(None)
 }
 
-pub static SETEX: Command = Command {
-    kind: CommandKind::Setex,
-    name: "setex",
-    arity: Arity::Exact(4),
-    run: setex,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn setex(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn setex(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let seconds = client.request.u128()?;
+    let at = client.request.positive_ttl()?;
     let value = client.request.pop()?;
     let db = store.mut_db(client.db())?;
-    db.setex(&key, &value, epoch().as_millis() + seconds * 1_000);
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    db.setex(&key, &value, at);
+    store.write(client.db(), &key, WriteEffect::new("set"));
     client.reply("OK");
     Ok(None)
 }
 
-pub static SETNX: Command = Command {
-    kind: CommandKind::Setnx,
-    name: "setnx",
-    arity: Arity::Exact(3),
-    run: setnx,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn setnx(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn setnx(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let value = client.request.pop()?;
     let db = store.mut_db(client.db())?;
@@ -678,36 +425,30 @@ fn setnx(client: &mut Client, store: &mut Store) -> CommandResult {
         client.reply(0);
     } else {
         db.set(&key, &value);
-        store.dirty += 1;
-        store.touch(client.db(), &key);
+        store.write(client.db(), &key, WriteEffect::new("set"));
         client.reply(1);
     }
 
     Ok(None)
 }
 
-pub static SETRANGE: Command = Command {
-    kind: CommandKind::Setrange,
-    name: "setrange",
-    arity: Arity::Exact(4),
-    run: setrange,
-    keys: Keys::Single,
-    readonly: false,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: true,
-};
-
-fn setrange(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn setrange(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
-    let start = client.request.usize()?;
+    let start = client.request.offset()?;
     let bytes = client.request.pop()?;
 
-    if start + bytes.len() > store.reader_config.blob_limit() {
-        return Err(ReplyError::StringLength.into());
+    // Setting nothing doesn't create the key or check the limit, it just returns the length.
+    if bytes.is_empty() {
+        let len = store
+            .get_db(client.db())?
+            .get_string(&key)?
+            .map_or(0, |value| value.len());
+        client.reply(len);
+        return Ok(None);
     }
 
+    store.string_limit().check(start.checked_add(bytes.len()))?;
+
     let db = store.mut_db(client.db())?;
     let value = db.string_or_default(&key)?;
 
@@ -716,25 +457,11 @@ fn setrange(client: &mut Client, store: &mut Store) -> CommandResult {
     let len = value.len();
     client.reply(len);
 
-    store.dirty += 1;
-    store.touch(client.db(), &key);
+    store.write(client.db(), &key, WriteEffect::new("setrange"));
     Ok(None)
 }
 
-pub static STRLEN: Command = Command {
-    kind: CommandKind::Strlen,
-    name: "strlen",
-    arity: Arity::Exact(2),
-    run: strlen,
-    keys: Keys::Single,
-    readonly: true,
-    admin: false,
-    noscript: false,
-    pubsub: false,
-    write: false,
-};
-
-fn strlen(client: &mut Client, store: &mut Store) -> CommandResult {
+pub(super) fn strlen(client: &mut Client, store: &mut Store) -> CommandResult {
     let key = client.request.pop()?;
     let db = store.get_db(client.db())?;
     let len = db.get_string(&key)?.ok_or(0)?.len();
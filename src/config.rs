@@ -1,15 +1,12 @@
 mod error;
-mod key;
 
-pub use error::ConfigError;
-pub use key::ConfigKey;
+pub use error::{ConfigError, ConfigFileError};
 
 use crate::{
     bytes::{lex, parse},
     reply::{Reply, ReplyError},
     store::Store,
 };
-use bytes::Bytes;
 use logos::Logos;
 
 /// An option accepting "yes" or "no".
@@ -35,11 +32,62 @@ fn yes_no(value: &[u8]) -> Result<bool, ConfigError> {
 // Wrapper value for easy conversion to a `Reply`.
 pub struct YesNo(pub bool);
 
+/// How the value of an option is parsed and validated, and where it's kept in the store.
+pub enum ConfigType {
+    /// "yes" or "no".
+    Bool {
+        get: fn(&Store) -> bool,
+        set: fn(&mut Store, bool),
+    },
+
+    /// An integer between `min` and `max`, inclusive.
+    Integer {
+        min: i64,
+        max: i64,
+        get: fn(&Store) -> i64,
+        set: fn(&mut Store, i64),
+    },
+
+    /// A number of bytes between `min` and `max`, inclusive, with an optional unit like `100mb`.
+    Memory {
+        min: i64,
+        max: i64,
+        get: fn(&Store) -> usize,
+        set: fn(&mut Store, usize),
+    },
+
+    /// One of `names`, ignoring case, kept as an index into them.
+    Enum {
+        names: &'static [&'static str],
+        get: fn(&Store) -> usize,
+        set: fn(&mut Store, usize),
+    },
+}
+
+/// A parsed and validated value for an option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    Memory(usize),
+    Enum(usize),
+}
+
 pub struct Config {
-    pub key: ConfigKey,
+    /// The name of the option.
     pub name: &'static str,
-    pub getter: fn(&mut Store) -> Reply,
-    pub setter: fn(&Bytes, &mut Store) -> Result<(), ConfigError>,
+
+    /// Another name for the option, usually from an older version of Redis.
+    pub alias: Option<&'static str>,
+
+    /// The value of the option until it's set, as it would be written in a config file.
+    pub default: &'static str,
+
+    /// Can this option only be set from a config file, and not with CONFIG SET?
+    pub immutable: bool,
+
+    /// How the value of this option is parsed and stored.
+    pub kind: ConfigType,
 }
 
 impl std::fmt::Debug for Config {
@@ -48,304 +96,558 @@ impl std::fmt::Debug for Config {
     }
 }
 
-pub static PROTOMAXBULKLEN: Config = Config {
-    key: ConfigKey::ProtoMaxBulkLen,
-    name: "proto-max-bulk-len",
-    getter: get_proto_max_bulk_len,
-    setter: set_proto_max_bulk_len,
-};
+impl Config {
+    /// The names of this option, starting with the current one.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.name).chain(self.alias)
+    }
 
-fn get_proto_max_bulk_len(store: &mut Store) -> Reply {
-    match i64::try_from(store.reader_config.blob_limit()) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
+    /// Parse and validate a value for this option.
+    pub fn parse(&self, value: &[u8]) -> Result<ConfigValue, ConfigError> {
+        use ConfigType::*;
+        match self.kind {
+            Bool { .. } => yes_no(value).map(ConfigValue::Bool),
+            Integer { min, max, .. } => {
+                let value: i64 = parse(value).ok_or(ConfigError::Integer)?;
+                if !(min..=max).contains(&value) {
+                    return Err(ConfigError::Range(min, max));
+                }
+                Ok(ConfigValue::Integer(value))
+            }
+            Memory { min, max, .. } => {
+                let value = memory(value)?;
+                if !i64::try_from(value).is_ok_and(|value| (min..=max).contains(&value)) {
+                    return Err(ConfigError::Range(min, max));
+                }
+                Ok(ConfigValue::Memory(value))
+            }
+            Enum { names, .. } => names
+                .iter()
+                .position(|name| name.as_bytes().eq_ignore_ascii_case(value))
+                .map(ConfigValue::Enum)
+                .ok_or(ConfigError::Enum(names)),
+        }
     }
-}
 
-fn set_proto_max_bulk_len(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.reader_config.set_blob_limit(memory(value)?);
-    Ok(())
-}
+    /// Get the current value of this option, as CONFIG GET replies with it.
+    pub fn get(&self, store: &Store) -> Reply {
+        use ConfigType::*;
+        match self.kind {
+            Bool { get, .. } => YesNo(get(store)).into(),
+            Integer { get, .. } => Reply::Bulk(get(store).into()),
+            Memory { get, .. } => match i64::try_from(get(store)) {
+                Ok(value) => Reply::Bulk(value.into()),
+                Err(_) => ReplyError::InvalidUsize.into(),
+            },
+            Enum { names, get, .. } => Reply::Bulk(names[get(store)].into()),
+        }
+    }
 
-// TODO: This is new config…what should we do with it?
-pub static PROTO_INLINE_MAX_SIZE: Config = Config {
-    key: ConfigKey::ProtoInlineMaxSize,
-    name: "proto-inline-max-size",
-    getter: get_proto_inline_max_size,
-    setter: set_proto_inline_max_size,
-};
+    /// Store a value returned by [`Config::parse`].
+    ///
+    /// # Panics
+    /// Panics if `value` was parsed for an option of another type.
+    pub fn apply(&self, value: ConfigValue, store: &mut Store) {
+        match (&self.kind, value) {
+            (ConfigType::Bool { set, .. }, ConfigValue::Bool(value)) => set(store, value),
+            (ConfigType::Integer { set, .. }, ConfigValue::Integer(value)) => set(store, value),
+            (ConfigType::Memory { set, .. }, ConfigValue::Memory(value)) => set(store, value),
+            (ConfigType::Enum { set, .. }, ConfigValue::Enum(value)) => set(store, value),
+            (_, value) => panic!("{value:?} is not a value for {}", self.name),
+        }
+    }
 
-fn get_proto_inline_max_size(store: &mut Store) -> Reply {
-    match i64::try_from(store.reader_config.inline_limit()) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
+    /// Parse a value for this option and store it.
+    pub fn set(&self, value: &[u8], store: &mut Store) -> Result<(), ConfigError> {
+        let value = self.parse(value)?;
+        self.apply(value, store);
+        Ok(())
     }
 }
 
-fn set_proto_inline_max_size(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.reader_config.set_inline_limit(memory(value)?);
-    Ok(())
+/// Every option, for CONFIG GET and SET, config files, and defaults. Adding an option only
+/// requires a static below and an entry here.
+pub static CONFIGS: [&Config; 21] = [
+    &BUSY_REPLY_THRESHOLD,
+    &ENABLE_DEBUG_COMMAND,
+    &HASH_MAX_LISTPACK_ENTRIES,
+    &HASH_MAX_LISTPACK_VALUE,
+    &LAZY_EXPIRE,
+    &LAZY_USER_DEL,
+    &LAZY_USER_FLUSH,
+    &LIST_MAX_LISTPACK_SIZE,
+    &MAXCLIENTS,
+    &MAXMEMORY_CLIENTS,
+    &PROTO_INLINE_MAX_SIZE,
+    &PROTO_MAX_BULK_LEN,
+    &PUBSUB_PER_DB,
+    &SET_MAX_INTSET_ENTRIES,
+    &SET_MAX_LISTPACK_ENTRIES,
+    &SET_MAX_LISTPACK_VALUE,
+    &TCP_KEEPALIVE,
+    &TCP_NODELAY,
+    &TIMEOUT,
+    &ZSET_MAX_LISTPACK_ENTRIES,
+    &ZSET_MAX_LISTPACK_VALUE,
+];
+
+/// Find an option by name or alias, ignoring case.
+pub fn lookup(name: &[u8]) -> Option<&'static Config> {
+    CONFIGS.iter().copied().find(|config| {
+        config
+            .names()
+            .any(|option| option.as_bytes().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Set every option to its default.
+///
+/// # Panics
+/// Panics if a default isn't valid for its option.
+pub fn reset(store: &mut Store) {
+    for config in CONFIGS {
+        if let Err(error) = config.set(config.default.as_bytes(), store) {
+            panic!("invalid default for {}: {error}", config.name);
+        }
+    }
 }
 
-pub static HASH_MAX_ZIPLIST_ENTRIES: Config = Config {
-    key: ConfigKey::HashMaxZiplistEntries,
-    name: "hash-max-ziplist-entries",
-    getter: get_hash_max_listpack_entries,
-    setter: set_hash_max_listpack_entries,
-};
-
-pub static HASH_MAX_LISTPACK_ENTRIES: Config = Config {
-    key: ConfigKey::HashMaxListpackEntries,
-    name: "hash-max-listpack-entries",
-    getter: get_hash_max_listpack_entries,
-    setter: set_hash_max_listpack_entries,
-};
+/// Parse the contents of a config file, like `redis.conf`, without applying it. Each line is an
+/// option name and a value separated by whitespace, and the value may be wrapped in double quotes.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_file(contents: &str) -> Result<Vec<(&'static Config, ConfigValue)>, ConfigFileError> {
+    let mut options = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
 
-fn get_hash_max_listpack_entries(store: &mut Store) -> Reply {
-    match i64::try_from(store.hash_max_listpack_entries) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
+        let number = index + 1;
+        let mut words = line.split_whitespace();
+        let (Some(name), Some(value), None) = (words.next(), words.next(), words.next()) else {
+            return Err(ConfigFileError::Directive(number, line.into()));
+        };
+        let Some(config) = lookup(name.as_bytes()) else {
+            return Err(ConfigFileError::Directive(number, line.into()));
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        let value = config
+            .parse(value.as_bytes())
+            .map_err(|error| ConfigFileError::Argument(number, config, error))?;
+        options.push((config, value));
     }
+    Ok(options)
+}
+
+/// Who may run DEBUG, from `enable-debug-command`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EnableDebugCommand {
+    No,
+    Yes,
+    Local,
 }
 
-fn set_hash_max_listpack_entries(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.hash_max_listpack_entries = memory(value)?;
-    Ok(())
+impl EnableDebugCommand {
+    const ALL: [EnableDebugCommand; 3] = [Self::No, Self::Yes, Self::Local];
+    const NAMES: [&'static str; 3] = ["no", "yes", "local"];
 }
 
-pub static HASH_MAX_ZIPLIST_VALUE: Config = Config {
-    key: ConfigKey::HashMaxZiplistValue,
-    name: "hash-max-ziplist-value",
-    getter: get_hash_max_listpack_value,
-    setter: set_hash_max_listpack_value,
+pub static BUSY_REPLY_THRESHOLD: Config = Config {
+    name: "busy-reply-threshold",
+    alias: Some("lua-time-limit"),
+    default: "5000",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: i64::MAX,
+        get: |store| i64::try_from(store.busy_reply_threshold).unwrap_or(i64::MAX),
+        set: |store, value| store.busy_reply_threshold = value.unsigned_abs(),
+    },
+};
+
+/// Unlike Redis, DEBUG is allowed by default, since the server is usually embedded.
+pub static ENABLE_DEBUG_COMMAND: Config = Config {
+    name: "enable-debug-command",
+    alias: None,
+    default: "yes",
+    immutable: true,
+    kind: ConfigType::Enum {
+        names: &EnableDebugCommand::NAMES,
+        get: |store| store.enable_debug_command as usize,
+        set: |store, value| store.enable_debug_command = EnableDebugCommand::ALL[value],
+    },
+};
+
+pub static HASH_MAX_LISTPACK_ENTRIES: Config = Config {
+    name: "hash-max-listpack-entries",
+    alias: Some("hash-max-ziplist-entries"),
+    default: "512",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: i64::MAX,
+        get: |store| i64::try_from(store.hash_max_listpack_entries).unwrap_or(i64::MAX),
+        set: |store, value| store.hash_max_listpack_entries = size(value),
+    },
 };
 
 pub static HASH_MAX_LISTPACK_VALUE: Config = Config {
-    key: ConfigKey::HashMaxListpackValue,
     name: "hash-max-listpack-value",
-    getter: get_hash_max_listpack_value,
-    setter: set_hash_max_listpack_value,
+    alias: Some("hash-max-ziplist-value"),
+    default: "64",
+    immutable: false,
+    kind: ConfigType::Memory {
+        min: 0,
+        max: i64::MAX,
+        get: |store| store.hash_max_listpack_value,
+        set: |store, value| store.hash_max_listpack_value = value,
+    },
 };
 
-fn get_hash_max_listpack_value(store: &mut Store) -> Reply {
-    match i64::try_from(store.hash_max_listpack_value) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
-    }
-}
-
-fn set_hash_max_listpack_value(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.hash_max_listpack_value = memory(value)?;
-    Ok(())
-}
+pub static LAZY_EXPIRE: Config = Config {
+    name: "lazyfree-lazy-expire",
+    alias: None,
+    default: "no",
+    immutable: false,
+    kind: ConfigType::Bool {
+        get: |store| store.lazy_expire,
+        set: |store, value| store.lazy_expire = value,
+    },
+};
 
-pub static ZSET_MAX_ZIPLIST_ENTRIES: Config = Config {
-    key: ConfigKey::ZsetMaxZiplistEntries,
-    name: "zset-max-ziplist-entries",
-    getter: get_zset_max_listpack_entries,
-    setter: set_zset_max_listpack_entries,
+pub static LAZY_USER_DEL: Config = Config {
+    name: "lazyfree-lazy-user-del",
+    alias: None,
+    default: "no",
+    immutable: false,
+    kind: ConfigType::Bool {
+        get: |store| store.lazy_user_del,
+        set: |store, value| store.lazy_user_del = value,
+    },
 };
 
-pub static ZSET_MAX_LISTPACK_ENTRIES: Config = Config {
-    key: ConfigKey::ZsetMaxListpackEntries,
-    name: "zset-max-listpack-entries",
-    getter: get_zset_max_listpack_entries,
-    setter: set_zset_max_listpack_entries,
+pub static LAZY_USER_FLUSH: Config = Config {
+    name: "lazyfree-lazy-user-flush",
+    alias: None,
+    default: "no",
+    immutable: false,
+    kind: ConfigType::Bool {
+        get: |store| store.lazy_user_flush,
+        set: |store, value| store.lazy_user_flush = value,
+    },
 };
 
-fn get_zset_max_listpack_entries(store: &mut Store) -> Reply {
-    match i64::try_from(store.zset_max_listpack_entries) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
-    }
-}
+pub static LIST_MAX_LISTPACK_SIZE: Config = Config {
+    name: "list-max-listpack-size",
+    alias: Some("list-max-ziplist-size"),
+    default: "-2",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: -2_147_483_648,
+        max: 2_147_483_647,
+        get: |store| store.list_max_listpack_size,
+        set: |store, value| store.list_max_listpack_size = value,
+    },
+};
 
-fn set_zset_max_listpack_entries(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.zset_max_listpack_entries = memory(value)?;
-    Ok(())
-}
+pub static MAXCLIENTS: Config = Config {
+    name: "maxclients",
+    alias: None,
+    default: "10000",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 1,
+        max: 4_294_967_295,
+        get: |store| i64::try_from(store.connections.max()).unwrap_or(i64::MAX),
+        set: |store, value| store.connections.set_max(size(value)),
+    },
+};
 
-pub static ZSET_MAX_ZIPLIST_VALUE: Config = Config {
-    key: ConfigKey::ZsetMaxZiplistValue,
-    name: "zset-max-ziplist-value",
-    getter: get_zset_max_listpack_value,
-    setter: set_zset_max_listpack_value,
+/// The most memory that replies waiting in client queues can use before the clients with the
+/// most waiting are evicted, or zero for no limit.
+pub static MAXMEMORY_CLIENTS: Config = Config {
+    name: "maxmemory-clients",
+    alias: None,
+    default: "0",
+    immutable: false,
+    kind: ConfigType::Memory {
+        min: 0,
+        max: i64::MAX,
+        get: |store| store.maxmemory_clients,
+        set: |store, value| store.maxmemory_clients = value,
+    },
 };
 
-pub static ZSET_MAX_LISTPACK_VALUE: Config = Config {
-    key: ConfigKey::ZsetMaxListpackValue,
-    name: "zset-max-listpack-value",
-    getter: get_zset_max_listpack_value,
-    setter: set_zset_max_listpack_value,
+// TODO: This is new config…what should we do with it?
+pub static PROTO_INLINE_MAX_SIZE: Config = Config {
+    name: "proto-inline-max-size",
+    alias: None,
+    default: "64kb",
+    immutable: false,
+    kind: ConfigType::Memory {
+        min: 1,
+        max: i64::MAX,
+        get: |store| store.reader_config.inline_limit(),
+        set: |store, value| store.reader_config.set_inline_limit(value),
+    },
 };
 
-fn get_zset_max_listpack_value(store: &mut Store) -> Reply {
-    match i64::try_from(store.zset_max_listpack_value) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
-    }
-}
+pub static PROTO_MAX_BULK_LEN: Config = Config {
+    name: "proto-max-bulk-len",
+    alias: None,
+    default: "512mb",
+    immutable: false,
+    kind: ConfigType::Memory {
+        min: 1,
+        max: i64::MAX,
+        get: |store| store.reader_config.blob_limit(),
+        set: |store, value| store.reader_config.set_blob_limit(value),
+    },
+};
 
-fn set_zset_max_listpack_value(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.zset_max_listpack_value = memory(value)?;
-    Ok(())
-}
+/// Isolate pubsub channels by the selected database, for embedding with one database per tenant.
+/// This isn't a Redis option.
+pub static PUBSUB_PER_DB: Config = Config {
+    name: "pubsub-per-db",
+    alias: None,
+    default: "no",
+    immutable: false,
+    kind: ConfigType::Bool {
+        get: |store| store.pubsub.per_db,
+        set: |store, value| store.pubsub.per_db = value,
+    },
+};
 
 pub static SET_MAX_INTSET_ENTRIES: Config = Config {
-    key: ConfigKey::SetMaxIntsetEntries,
     name: "set-max-intset-entries",
-    getter: get_set_max_intset_entries,
-    setter: set_set_max_intset_entries,
+    alias: None,
+    default: "512",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: i64::MAX,
+        get: |store| i64::try_from(store.set_config.max_intset_entries).unwrap_or(i64::MAX),
+        set: |store, value| store.set_config.max_intset_entries = size(value),
+    },
 };
 
-fn get_set_max_intset_entries(store: &mut Store) -> Reply {
-    match i64::try_from(store.set_config.max_intset_entries) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
-    }
-}
-
-fn set_set_max_intset_entries(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.set_config.max_intset_entries = memory(value)?;
-    Ok(())
-}
-
 pub static SET_MAX_LISTPACK_ENTRIES: Config = Config {
-    key: ConfigKey::SetMaxListpackEntries,
     name: "set-max-listpack-entries",
-    getter: get_set_max_listpack_entries,
-    setter: set_set_max_listpack_entries,
+    alias: None,
+    default: "128",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: i64::MAX,
+        get: |store| i64::try_from(store.set_config.max_listpack_entries).unwrap_or(i64::MAX),
+        set: |store, value| store.set_config.max_listpack_entries = size(value),
+    },
 };
 
-fn get_set_max_listpack_entries(store: &mut Store) -> Reply {
-    match i64::try_from(store.set_config.max_listpack_entries) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
-    }
-}
-
-fn set_set_max_listpack_entries(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.set_config.max_listpack_entries = memory(value)?;
-    Ok(())
-}
-
 pub static SET_MAX_LISTPACK_VALUE: Config = Config {
-    key: ConfigKey::SetMaxListpackValue,
     name: "set-max-listpack-value",
-    getter: get_set_max_listpack_value,
-    setter: set_set_max_listpack_value,
+    alias: None,
+    default: "64",
+    immutable: false,
+    kind: ConfigType::Memory {
+        min: 0,
+        max: i64::MAX,
+        get: |store| store.set_config.max_listpack_value,
+        set: |store, value| store.set_config.max_listpack_value = value,
+    },
 };
 
-fn get_set_max_listpack_value(store: &mut Store) -> Reply {
-    match i64::try_from(store.set_config.max_listpack_value) {
-        Ok(value) => Reply::Bulk(value.into()),
-        Err(_) => ReplyError::InvalidUsize.into(),
-    }
-}
-
-fn set_set_max_listpack_value(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.set_config.max_listpack_value = memory(value)?;
-    Ok(())
-}
-
-pub static LAZY_EXPIRE: Config = Config {
-    key: ConfigKey::LazyExpire,
-    name: "lazyfree-lazy-expire",
-    getter: get_lazy_expire,
-    setter: set_lazy_expire,
+pub static TCP_KEEPALIVE: Config = Config {
+    name: "tcp-keepalive",
+    alias: None,
+    default: "300",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: 2_147_483_647,
+        get: |store| i64::try_from(store.connections.keepalive().as_secs()).unwrap_or(i64::MAX),
+        set: |store, value| store.connections.set_keepalive(value.unsigned_abs()),
+    },
 };
 
-fn get_lazy_expire(store: &mut Store) -> Reply {
-    YesNo(store.lazy_expire).into()
-}
-
-fn set_lazy_expire(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.lazy_expire = yes_no(&value[..])?;
-    Ok(())
-}
-
-pub static LAZY_USER_DEL: Config = Config {
-    key: ConfigKey::LazyUserDel,
-    name: "lazyfree-lazy-user-del",
-    getter: get_lazy_user_del,
-    setter: set_lazy_user_del,
+pub static TCP_NODELAY: Config = Config {
+    name: "tcp-nodelay",
+    alias: None,
+    default: "yes",
+    immutable: false,
+    kind: ConfigType::Bool {
+        get: |store| store.connections.nodelay(),
+        set: |store, value| store.connections.set_nodelay(value),
+    },
 };
 
-fn get_lazy_user_del(store: &mut Store) -> Reply {
-    YesNo(store.lazy_user_del).into()
-}
+pub static TIMEOUT: Config = Config {
+    name: "timeout",
+    alias: None,
+    default: "0",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: 2_147_483_647,
+        get: |store| i64::try_from(store.connections.timeout().as_secs()).unwrap_or(i64::MAX),
+        set: |store, value| store.connections.set_timeout(value.unsigned_abs()),
+    },
+};
 
-fn set_lazy_user_del(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.lazy_user_del = yes_no(&value[..])?;
-    Ok(())
-}
+pub static ZSET_MAX_LISTPACK_ENTRIES: Config = Config {
+    name: "zset-max-listpack-entries",
+    alias: Some("zset-max-ziplist-entries"),
+    default: "128",
+    immutable: false,
+    kind: ConfigType::Integer {
+        min: 0,
+        max: i64::MAX,
+        get: |store| i64::try_from(store.zset_max_listpack_entries).unwrap_or(i64::MAX),
+        set: |store, value| store.zset_max_listpack_entries = size(value),
+    },
+};
 
-pub static LAZY_USER_FLUSH: Config = Config {
-    key: ConfigKey::LazyUserFlush,
-    name: "lazyfree-lazy-user-flush",
-    getter: get_lazy_user_flush,
-    setter: set_lazy_user_flush,
+pub static ZSET_MAX_LISTPACK_VALUE: Config = Config {
+    name: "zset-max-listpack-value",
+    alias: Some("zset-max-ziplist-value"),
+    default: "64",
+    immutable: false,
+    kind: ConfigType::Memory {
+        min: 0,
+        max: i64::MAX,
+        get: |store| store.zset_max_listpack_value,
+        set: |store, value| store.zset_max_listpack_value = value,
+    },
 };
 
-fn get_lazy_user_flush(store: &mut Store) -> Reply {
-    YesNo(store.lazy_user_flush).into()
+// Convert an integer option that's been checked against its range into a size.
+fn size(value: i64) -> usize {
+    usize::try_from(value).unwrap_or(usize::MAX)
 }
 
-fn set_lazy_user_flush(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.lazy_user_flush = yes_no(&value[..])?;
-    Ok(())
-}
+fn memory(value: &[u8]) -> Result<usize, ConfigError> {
+    let (digits, unit) = match value {
+        [digits @ .., b'k' | b'K'] => (digits, 1000),
+        [digits @ .., b'k' | b'K', b'b' | b'B'] => (digits, 1024),
+        [digits @ .., b'm' | b'M'] => (digits, 1000 * 1000),
+        [digits @ .., b'm' | b'M', b'b' | b'B'] => (digits, 1024 * 1024),
+        [digits @ .., b'g' | b'G'] => (digits, 1000 * 1000 * 1000),
+        [digits @ .., b'g' | b'G', b'b' | b'B'] => (digits, 1024 * 1024 * 1024),
+        digits => (digits, 1),
+    };
 
-pub static LIST_MAX_LISTPACK_SIZE: Config = Config {
-    key: ConfigKey::ListMaxListpackSize,
-    name: "list-max-listpack-size",
-    getter: get_list_max_listpack_size,
-    setter: set_list_max_listpack_size,
-};
+    parse(digits)
+        .and_then(|value: usize| value.checked_mul(unit))
+        .ok_or(ConfigError::Memory)
+}
 
-pub static LIST_MAX_ZIPLIST_SIZE: Config = Config {
-    key: ConfigKey::ListMaxZiplistSize,
-    name: "list-max-ziplist-size",
-    getter: get_list_max_listpack_size,
-    setter: set_list_max_listpack_size,
-};
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn get_list_max_listpack_size(store: &mut Store) -> Reply {
-    store.list_max_listpack_size.into()
-}
+    #[test]
+    fn defaults() {
+        for config in CONFIGS {
+            assert!(
+                config.parse(config.default.as_bytes()).is_ok(),
+                "{config:?}"
+            );
+        }
+    }
 
-fn set_list_max_listpack_size(value: &Bytes, store: &mut Store) -> Result<(), ConfigError> {
-    store.list_max_listpack_size = parse(value).ok_or(ConfigError::Integer)?;
-    Ok(())
-}
+    #[test]
+    fn names() {
+        let mut names: Vec<_> = CONFIGS.iter().flat_map(|config| config.names()).collect();
+        let len = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), len);
+    }
 
-pub static UNKNOWN: Config = Config {
-    key: ConfigKey::Unknown,
-    name: "unknown",
-    getter: get_unknown,
-    setter: set_unknown,
-};
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(lookup(b"MaxClients").unwrap().name, "maxclients");
+        assert_eq!(
+            lookup(b"lua-time-limit").unwrap().name,
+            "busy-reply-threshold"
+        );
+        assert!(lookup(b"unknown").is_none());
+    }
 
-fn get_unknown(_: &mut Store) -> Reply {
-    Reply::Nil
-}
+    #[test]
+    fn memory_units() {
+        assert_eq!(memory(b"10").unwrap(), 10);
+        assert_eq!(memory(b"5k").unwrap(), 5_000);
+        assert_eq!(memory(b"5KB").unwrap(), 5_120);
+        assert_eq!(memory(b"5mb").unwrap(), 5_242_880);
+        assert!(matches!(memory(b"5x"), Err(ConfigError::Memory)));
+        assert!(matches!(memory(b"kb"), Err(ConfigError::Memory)));
+        assert!(matches!(
+            memory(b"18446744073709551615gb"),
+            Err(ConfigError::Memory)
+        ));
+    }
 
-fn set_unknown(_: &Bytes, _: &mut Store) -> Result<(), ConfigError> {
-    Ok(())
-}
+    #[test]
+    fn parse_values() {
+        assert_eq!(MAXCLIENTS.parse(b"10").unwrap(), ConfigValue::Integer(10));
+        assert!(matches!(
+            MAXCLIENTS.parse(b"0"),
+            Err(ConfigError::Range(1, _))
+        ));
+        assert!(matches!(MAXCLIENTS.parse(b"x"), Err(ConfigError::Integer)));
+        assert_eq!(TCP_NODELAY.parse(b"No").unwrap(), ConfigValue::Bool(false));
+        assert_eq!(
+            ENABLE_DEBUG_COMMAND.parse(b"LOCAL").unwrap(),
+            ConfigValue::Enum(2)
+        );
+        assert!(matches!(
+            ENABLE_DEBUG_COMMAND.parse(b"maybe"),
+            Err(ConfigError::Enum(_))
+        ));
+    }
 
-fn memory(value: &[u8]) -> Result<usize, ConfigError> {
-    let result = match value {
-        [digits @ .., b'k' | b'K'] => parse(digits).map(|v: usize| v * 1000),
-        [digits @ .., b'k' | b'K', b'b' | b'B'] => parse(digits).map(|v: usize| v * 1024),
-        [digits @ .., b'm' | b'M'] => parse(digits).map(|v: usize| v * 1000 * 1000),
-        [digits @ .., b'm' | b'M', b'b' | b'B'] => parse(digits).map(|v: usize| v * 1024 * 1024),
-        [digits @ .., b'g' | b'G'] => parse(digits).map(|v: usize| v * 1000 * 1000 * 1000),
-        [digits @ .., b'g' | b'G', b'b' | b'B'] => {
-            parse(digits).map(|v: usize| v * 1024 * 1024 * 1024)
-        }
-        digits => parse(digits),
-    };
+    #[test]
+    fn parse_config_file() {
+        let options = parse_file(
+            "# comment\n\nmaxclients 10\n  hash-max-ziplist-value \"1kb\"\ntcp-nodelay no\n",
+        )
+        .unwrap();
+        let options: Vec<_> = options
+            .into_iter()
+            .map(|(config, value)| (config.name, value))
+            .collect();
+        assert_eq!(
+            options,
+            [
+                ("maxclients", ConfigValue::Integer(10)),
+                ("hash-max-listpack-value", ConfigValue::Memory(1_024)),
+                ("tcp-nodelay", ConfigValue::Bool(false)),
+            ]
+        );
+    }
 
-    result.ok_or(ConfigError::Memory)
+    #[test]
+    fn parse_config_file_errors() {
+        assert!(matches!(
+            parse_file("maxclients 10\nunknown 1"),
+            Err(ConfigFileError::Directive(2, _))
+        ));
+        assert!(matches!(
+            parse_file("maxclients"),
+            Err(ConfigFileError::Directive(1, _))
+        ));
+        assert!(matches!(
+            parse_file("maxclients 1 2"),
+            Err(ConfigFileError::Directive(1, _))
+        ));
+        assert!(matches!(
+            parse_file("\nmaxclients 0"),
+            Err(ConfigFileError::Argument(2, _, ConfigError::Range(1, _)))
+        ));
+    }
 }
@@ -1,3 +1,4 @@
+use super::Config;
 use bytes::Bytes;
 use std::io;
 use thiserror::Error;
@@ -10,12 +11,28 @@ pub enum ConfigError {
     #[error("Can't chdir to {:?}: {}", .0, .1)]
     Dir(Bytes, io::Error),
 
+    #[error("argument(s) must be one of the following: {}", .0.join(", "))]
+    Enum(&'static [&'static str]),
+
     #[error("argument couldn't be parsed into an integer")]
     Integer,
 
     #[error("argument must be a memory value")]
     Memory,
 
+    #[error("argument must be between {} and {} inclusive", .0, .1)]
+    Range(i64, i64),
+
     #[error("argument must be 'yes' or 'no'")]
     YesNo,
 }
+
+/// An error in the contents of a config file, with the number of the line it's on.
+#[derive(Debug, Error)]
+pub enum ConfigFileError {
+    #[error("line {}: bad directive or wrong number of arguments: '{}'", .0, .1)]
+    Directive(usize, String),
+
+    #[error("line {}: invalid argument for '{}' - {}", .0, .1.name, .2)]
+    Argument(usize, &'static Config, ConfigError),
+}
@@ -5,36 +5,190 @@ mod value;
 
 pub use index::DBIndex;
 pub use key_ref::KeyRef;
-pub use raw::{Raw, RawSlice, RawSliceRef};
+pub use raw::{Raw, RawSlice, RawSliceRef, RawWrites};
 pub use value::{
     ArrayString, Edge, Extreme, Hash, HashKey, HashValue, Insertion, List, Set, SetRef, SetValue,
     SortedSet, SortedSetRef, SortedSetValue, StringSlice, StringValue, Value, ValueError,
     list_is_valid,
 };
 
-use crate::epoch;
-use hashbrown::{DefaultHashBuilder, HashMap, hash_map::EntryRef};
+use crate::{
+    epoch,
+    sharded_map::{self, ShardedMap},
+    time::clock,
+};
+use std::{
+    ops::Deref,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering::Relaxed},
+};
+
+/// A value along with the last time it was accessed, for OBJECT IDLETIME.
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+
+    /// The store clock, in seconds, when this value was last read or written.
+    accessed: AtomicU32,
+}
+
+impl Entry {
+    fn new(value: Value) -> Self {
+        Entry {
+            value,
+            accessed: AtomicU32::new(clock()),
+        }
+    }
+
+    /// Record an access. Reads only have a shared reference, hence the atomic.
+    fn touch(&self) {
+        self.accessed.store(clock(), Relaxed);
+    }
+}
+
+impl Clone for Entry {
+    fn clone(&self) -> Self {
+        Entry {
+            value: self.value.clone(),
+            accessed: AtomicU32::new(self.accessed.load(Relaxed)),
+        }
+    }
+}
+
+/// The time that expirations are checked against while a [`Snapshot`] is held, in milliseconds
+/// since the epoch, or zero to use the current time. Reads only have a shared reference, hence
+/// the atomic.
+#[derive(Debug, Default)]
+struct Frozen(AtomicU64);
+
+impl Clone for Frozen {
+    fn clone(&self) -> Self {
+        Frozen(AtomicU64::new(self.0.load(Relaxed)))
+    }
+}
+
+/// A database whose expirations are checked against the time the snapshot was taken, until it's
+/// dropped.
+pub struct Snapshot<'a> {
+    db: &'a DB,
+
+    /// Did this snapshot freeze the time, rather than an enclosing one?
+    frozen: bool,
+}
+
+impl Deref for Snapshot<'_> {
+    type Target = DB;
+
+    fn deref(&self) -> &Self::Target {
+        self.db
+    }
+}
+
+impl Drop for Snapshot<'_> {
+    fn drop(&mut self) {
+        if self.frozen {
+            self.db.frozen.0.store(0, Relaxed);
+        }
+    }
+}
+
+/// A change to the expiration of a key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Expiry {
+    /// Expire at a time in milliseconds since the epoch, deleting the key if it has passed.
+    At(u128),
+
+    /// Remove the expiration.
+    Persist,
+}
+
+/// What [`DB::set_expiry`] actually changed, so that side effects are only applied when
+/// something did.
+#[derive(Debug)]
+pub enum ExpiryChange {
+    /// Nothing changed, because the key doesn't exist or had no expiration to remove.
+    Unchanged,
+
+    /// The expiration was set or removed.
+    Updated,
+
+    /// The time had already passed, so the key was deleted.
+    Deleted(Value),
+}
+
+/// The number of keys of each type in a database, kept up to date as keys are added and removed.
+/// Like the key count in `INFO keyspace`, it includes keys that have expired but haven't been
+/// removed yet.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct KeyTypes {
+    pub hashes: usize,
+    pub lists: usize,
+    pub sets: usize,
+    pub sorted_sets: usize,
+    pub strings: usize,
+}
+
+impl KeyTypes {
+    /// The count for the type of `value`.
+    fn count(&mut self, value: &Value) -> &mut usize {
+        match value {
+            Value::Hash(_) => &mut self.hashes,
+            Value::List(_) => &mut self.lists,
+            Value::Set(_) => &mut self.sets,
+            Value::SortedSet(_) => &mut self.sorted_sets,
+            Value::String(_) => &mut self.strings,
+        }
+    }
+
+    fn add(&mut self, value: &Value) {
+        *self.count(value) += 1;
+    }
+
+    fn remove(&mut self, value: &Value) {
+        *self.count(value) -= 1;
+    }
+}
 
 /// A Redis database, storing all the values and their expiration times.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DB {
     /// A map containing all key value pairs in this database.
-    objects: HashMap<StringValue, Value>,
+    objects: ShardedMap<StringValue, Entry>,
 
     /// A map containing the expiration time of all volatile keys in this database.
-    expires: HashMap<StringValue, u128>,
+    expires: ShardedMap<StringValue, u128>,
+
+    /// The number of expired keys removed since the count was last taken.
+    expired: usize,
+
+    /// The number of keys of each type.
+    types: KeyTypes,
+
+    /// The time expirations are checked against during a [`Snapshot`].
+    frozen: Frozen,
 }
 
-impl Default for DB {
-    fn default() -> Self {
-        DB {
-            objects: HashMap::new(),
-            expires: HashMap::new(),
+impl DB {
+    /// Check every expiration against the current time until the snapshot is dropped, so that a
+    /// command reading several keys sees each of them either before or after it expires, rather
+    /// than some of each.
+    pub fn snapshot(&self) -> Snapshot<'_> {
+        let now = u64::try_from(epoch().as_millis()).unwrap_or(u64::MAX);
+        let frozen = self
+            .frozen
+            .0
+            .compare_exchange(0, now, Relaxed, Relaxed)
+            .is_ok();
+        Snapshot { db: self, frozen }
+    }
+
+    /// The time to check expirations against, in milliseconds since the epoch.
+    fn now(&self) -> u128 {
+        match self.frozen.0.load(Relaxed) {
+            0 => epoch().as_millis(),
+            now => now.into(),
         }
     }
-}
 
-impl DB {
     /// Get the value for `key`, unless it has expired.
     pub fn get<Q>(&self, key: &Q) -> Option<&Value>
     where
@@ -43,7 +197,34 @@ impl DB {
         if self.is_expired(key) {
             None
         } else {
-            self.objects.get(key)
+            let entry = self.objects.get(key)?;
+            entry.touch();
+            Some(&entry.value)
+        }
+    }
+
+    /// Get the value for `key` without counting it as an access, unless it has expired.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&Value>
+    where
+        Q: KeyRef<StringValue> + ?Sized,
+    {
+        if self.is_expired(key) {
+            None
+        } else {
+            self.objects.get(key).map(|entry| &entry.value)
+        }
+    }
+
+    /// How long it's been since `key` was last accessed, unless it has expired.
+    pub fn idletime<Q>(&self, key: &Q) -> Option<u32>
+    where
+        Q: KeyRef<StringValue> + ?Sized,
+    {
+        if self.is_expired(key) {
+            None
+        } else {
+            let accessed = self.objects.get(key)?.accessed.load(Relaxed);
+            Some(clock().saturating_sub(accessed))
         }
     }
 
@@ -64,31 +245,51 @@ impl DB {
             self.remove(key);
             None
         } else {
-            self.objects.get_mut(key)
+            let entry = self.objects.get_mut(key)?;
+            entry.touch();
+            Some(&mut entry.value)
         }
     }
 
-    /// Get the mutable value for many keys.
-    pub fn get_many_mut<const N: usize, Q>(&mut self, keys: [&Q; N]) -> [Option<&mut Value>; N]
+    /// Get the mutable values for two different keys.
+    pub fn get_pair_mut<Q>(&mut self, keys: [&Q; 2]) -> [Option<&mut Value>; 2]
     where
         Q: KeyRef<StringValue> + ?Sized,
     {
         // TODO: Check expirations…?
-        self.objects.get_many_mut(keys)
+        self.objects.get_pair_mut(keys).map(|entry| {
+            entry.map(|entry| {
+                entry.touch();
+                &mut entry.value
+            })
+        })
     }
 
-    /// Get an entry ref for a `key`.
-    pub fn entry_ref<'a, Q>(
+    /// Get the mutable value for `key`, inserting the result of `default` if it doesn't exist.
+    pub fn get_or_insert_with<'a, Q>(
         &'a mut self,
         key: &'a Q,
-    ) -> EntryRef<'a, 'a, StringValue, Q, Value, DefaultHashBuilder>
+        default: impl FnOnce() -> Value,
+    ) -> &'a mut Value
     where
         Q: KeyRef<StringValue> + ?Sized,
+        StringValue: From<&'a Q>,
     {
         if self.is_expired(key) {
             self.remove(key);
         }
-        self.objects.entry_ref(key)
+        match self.objects.entry(key) {
+            sharded_map::Entry::Occupied(entry) => {
+                let entry = entry.into_mut();
+                entry.touch();
+                &mut entry.value
+            }
+            sharded_map::Entry::Vacant(entry) => {
+                let value = default();
+                self.types.add(&value);
+                &mut entry.insert(key.into(), Entry::new(value)).value
+            }
+        }
     }
 
     /// Set the expiration time for `key`. Return `true` if the key exists, otherwise `false`.
@@ -97,10 +298,14 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized + 'a,
         StringValue: From<&'a Q>,
     {
-        if let EntryRef::Occupied(mut entry) = self.expires.entry_ref(key) {
-            if epoch().as_millis() >= *entry.get() {
+        let now = self.now();
+        if let sharded_map::Entry::Occupied(mut entry) = self.expires.entry(key) {
+            if now >= *entry.get() {
                 entry.remove();
-                self.objects.remove(key);
+                if let Some(entry) = self.objects.remove(key) {
+                    self.types.remove(&entry.value);
+                }
+                self.expired += 1;
                 false
             } else {
                 entry.insert(at);
@@ -114,8 +319,40 @@ impl DB {
         }
     }
 
+    /// Set or remove the expiration for `key`, returning what changed. An expired key is treated
+    /// as missing, so persisting it can't bring it back.
+    pub fn set_expiry<'a, Q>(&mut self, key: &'a Q, expiry: Expiry) -> ExpiryChange
+    where
+        Q: KeyRef<StringValue> + ?Sized + 'a,
+        StringValue: From<&'a Q>,
+    {
+        match expiry {
+            Expiry::At(at) if at < self.now() => match self.remove(key) {
+                Some(value) => ExpiryChange::Deleted(value),
+                None => ExpiryChange::Unchanged,
+            },
+            Expiry::At(at) => {
+                if self.expire(key, at) {
+                    ExpiryChange::Updated
+                } else {
+                    ExpiryChange::Unchanged
+                }
+            }
+            Expiry::Persist => {
+                if self.is_expired(key) {
+                    self.remove(key);
+                    ExpiryChange::Unchanged
+                } else if self.persist(key) {
+                    ExpiryChange::Updated
+                } else {
+                    ExpiryChange::Unchanged
+                }
+            }
+        }
+    }
+
     /// Remove the expiration for `key`. Return `true` if it exists.
-    pub fn persist<Q>(&mut self, key: &Q) -> bool
+    fn persist<Q>(&mut self, key: &Q) -> bool
     where
         Q: KeyRef<StringValue> + ?Sized,
     {
@@ -133,10 +370,19 @@ impl DB {
         if !keepttl || expired {
             self.persist(key);
         }
-        let value = match self.objects.entry_ref(key) {
-            EntryRef::Occupied(mut entry) => Some(entry.insert(value.into())),
-            EntryRef::Vacant(entry) => {
-                entry.insert(value.into());
+        if expired {
+            self.expired += 1;
+        }
+        let value = value.into();
+        self.types.add(&value);
+        let value = match self.objects.entry(key) {
+            sharded_map::Entry::Occupied(mut entry) => {
+                let replaced = entry.insert(Entry::new(value)).value;
+                self.types.remove(&replaced);
+                Some(replaced)
+            }
+            sharded_map::Entry::Vacant(entry) => {
+                entry.insert(key.into(), Entry::new(value));
                 None
             }
         };
@@ -170,18 +416,23 @@ impl DB {
         StringValue: From<&'a Q>,
         V: Into<Value>,
     {
-        if at <= epoch().as_millis() {
+        if at <= self.now() {
             // TODO: Should this also remove the previous value?
             return None;
         }
-        match self.objects.entry_ref(key) {
-            EntryRef::Occupied(mut entry) => {
+        let value = value.into();
+        self.types.add(&value);
+        match self.objects.entry(key) {
+            sharded_map::Entry::Occupied(mut entry) => {
                 self.expires.insert(entry.key().clone(), at);
-                Some(entry.insert(value.into()))
+                let replaced = entry.insert(Entry::new(value)).value;
+                self.types.remove(&replaced);
+                Some(replaced)
             }
-            EntryRef::Vacant(entry) => {
-                let occupied = entry.insert_entry(value.into());
-                self.expires.insert(occupied.key().clone(), at);
+            sharded_map::Entry::Vacant(entry) => {
+                let key = StringValue::from(key);
+                self.expires.insert(key.clone(), at);
+                entry.insert(key, Entry::new(value));
                 None
             }
         }
@@ -194,18 +445,54 @@ impl DB {
     {
         let expired = self.is_expired(key);
         self.persist(key);
-        let value = self.objects.remove(key);
-        if expired { None } else { value }
+        let value = self.objects.remove(key).map(|entry| entry.value);
+        if let Some(value) = &value {
+            self.types.remove(value);
+        }
+        if expired {
+            self.expired += 1;
+            None
+        } else {
+            value
+        }
+    }
+
+    /// Remove every expired key from this database, returning their values along with the number
+    /// of volatile keys that were checked.
+    pub fn remove_expired(&mut self) -> (usize, Vec<Value>) {
+        let now = self.now();
+        let checked = self.expires.len();
+        let mut values = Vec::new();
+        self.expires.retain(|key, &mut at| {
+            if now < at {
+                return true;
+            }
+            if let Some(entry) = self.objects.remove(key) {
+                self.types.remove(&entry.value);
+                values.push(entry.value);
+            }
+            false
+        });
+        self.expired += values.len();
+        (checked, values)
+    }
+
+    /// Return the number of expired keys removed since the last call, and reset it.
+    pub fn take_expired(&mut self) -> usize {
+        std::mem::take(&mut self.expired)
+    }
+
+    /// The number of keys with an expiration time, including any that have expired but haven't
+    /// been removed yet.
+    pub fn volatile(&self) -> usize {
+        self.expires.len()
     }
 
     /// Return the time until `key` expires in milliseconds.
     pub fn ttl(&self, key: impl AsRef<[u8]>) -> Option<u128> {
         let x = self.expires.get(key.as_ref())?;
-        if epoch().as_millis() >= *x {
-            None
-        } else {
-            Some(*x - epoch().as_millis())
-        }
+        let now = self.now();
+        if now >= *x { None } else { Some(*x - now) }
     }
 
     /// Return the expiration time for `key` in milliseconds.
@@ -220,7 +507,7 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized,
     {
         match self.expires.get(key) {
-            Some(x) => epoch().as_millis() >= *x,
+            Some(x) => self.now() >= *x,
             None => false,
         }
     }
@@ -236,7 +523,27 @@ impl DB {
         })
     }
 
-    /// The number of values in this database.
+    /// Collect the keys in the slots from `cursor` on, until at least `count` have been seen, and
+    /// return them with the cursor to continue from. A scan starts and ends at zero, and sees every
+    /// key that exists for the whole scan at least once, even as the keyspace grows. Expired keys
+    /// are skipped, but still count towards `count`.
+    pub fn scan_bucket(&self, cursor: usize, count: usize) -> (usize, Vec<StringValue>) {
+        let mut keys = Vec::with_capacity(count);
+        let cursor = self.objects.scan(cursor, count, |key, _| {
+            if !self.is_expired(key) {
+                keys.push(key.clone());
+            }
+        });
+        (cursor, keys)
+    }
+
+    /// The number of keys of each type in this database.
+    pub fn types(&self) -> KeyTypes {
+        self.types
+    }
+
+    /// The number of values in this database, including keys that have expired but haven't been
+    /// removed by the active expire cycle yet, like Redis.
     pub fn size(&self) -> usize {
         self.objects.len()
     }
@@ -264,7 +571,7 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized,
         StringValue: From<&'a Q>,
     {
-        self.entry_ref(key).or_insert_with(Value::hash).mut_hash()
+        self.get_or_insert_with(key, Value::hash).mut_hash()
     }
 
     /// Get a reference to a list value. Return an error if the type is wrong.
@@ -290,7 +597,7 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized,
         StringValue: From<&'a Q>,
     {
-        self.entry_ref(key).or_insert_with(Value::list).mut_list()
+        self.get_or_insert_with(key, Value::list).mut_list()
     }
 
     /// Get a reference to a set value. Return an error if the type is wrong.
@@ -316,7 +623,7 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized,
         StringValue: From<&'a Q>,
     {
-        self.entry_ref(key).or_insert_with(Value::set).mut_set()
+        self.get_or_insert_with(key, Value::set).mut_set()
     }
 
     /// Get a reference to a sorted set value. Return an error if the type is wrong.
@@ -345,8 +652,7 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized,
         StringValue: From<&'a Q>,
     {
-        self.entry_ref(key)
-            .or_insert_with(Value::sorted_set)
+        self.get_or_insert_with(key, Value::sorted_set)
             .mut_sorted_set()
     }
 
@@ -376,9 +682,7 @@ impl DB {
         Q: KeyRef<StringValue> + ?Sized,
         StringValue: From<&'a Q>,
     {
-        self.entry_ref(key)
-            .or_insert_with(Value::string)
-            .mut_string()
+        self.get_or_insert_with(key, Value::string).mut_string()
     }
 }
 
@@ -395,6 +699,29 @@ mod tests {
         assert_eq!(db.set(b"a", "y"), Some("x".into()));
     }
 
+    #[test]
+    fn scan_bucket() {
+        let mut db = DB::default();
+        for i in 0..5000 {
+            db.set(format!("key:{i}").as_bytes(), "x");
+        }
+        db.expire(b"key:0", epoch().as_millis() - 10_000);
+
+        let mut keys = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = db.scan_bucket(cursor, 100);
+            keys.extend(batch);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(keys.len(), 4999);
+        assert!(!keys.contains(&StringValue::from("key:0")));
+        assert!(keys.contains(&StringValue::from("key:4999")));
+    }
+
     #[test]
     fn set_expired() {
         let mut db = DB::default();
@@ -403,6 +730,72 @@ mod tests {
         assert_eq!(db.set(b"a", "y"), None);
     }
 
+    #[test]
+    fn set_expiry() {
+        let mut db = DB::default();
+        assert!(matches!(
+            db.set_expiry(b"a", Expiry::Persist),
+            ExpiryChange::Unchanged
+        ));
+        db.set(b"a", "x");
+        assert!(matches!(
+            db.set_expiry(b"a", Expiry::Persist),
+            ExpiryChange::Unchanged
+        ));
+
+        let at = epoch().as_millis() + 10_000;
+        assert!(matches!(
+            db.set_expiry(b"a", Expiry::At(at)),
+            ExpiryChange::Updated
+        ));
+        assert_eq!(db.expires_at(b"a"), Some(at));
+        assert!(matches!(
+            db.set_expiry(b"a", Expiry::Persist),
+            ExpiryChange::Updated
+        ));
+        assert_eq!(db.expires_at(b"a"), None);
+
+        let at = epoch().as_millis() - 10_000;
+        assert!(matches!(
+            db.set_expiry(b"b", Expiry::At(at)),
+            ExpiryChange::Unchanged
+        ));
+        assert!(matches!(
+            db.set_expiry(b"a", Expiry::At(at)),
+            ExpiryChange::Deleted(_)
+        ));
+        assert!(!db.exists(b"a"));
+
+        // Persisting an expired key doesn't bring it back.
+        db.set(b"c", "x");
+        db.expire(b"c", epoch().as_millis() + 10);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(matches!(
+            db.set_expiry(b"c", Expiry::Persist),
+            ExpiryChange::Unchanged
+        ));
+        assert!(!db.exists(b"c"));
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut db = DB::default();
+        db.setex(b"a", "x", epoch().as_millis() + 100);
+        db.setex(b"b", "x", epoch().as_millis() + 100);
+        {
+            let db = db.snapshot();
+            assert!(db.exists(b"a"));
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            let nested = db.snapshot();
+            assert!(nested.exists(b"b"));
+            drop(nested);
+            assert!(db.exists(b"b"));
+            assert!(db.ttl(b"b").is_some());
+        }
+        assert!(!db.exists(b"a"));
+        assert!(!db.exists(b"b"));
+    }
+
     #[test]
     fn setex() {
         let mut db = DB::default();
@@ -411,6 +804,18 @@ mod tests {
         assert!((9995..10_006).contains(&db.ttl("a").unwrap()));
     }
 
+    #[test]
+    fn size() {
+        let mut db = DB::default();
+        db.set(b"a", "x");
+        db.expire(b"a", epoch().as_millis() - 10_000);
+        db.setex(b"b", "x", epoch().as_millis() + 10_000);
+        db.set(b"c", "x");
+        assert_eq!(db.size(), 3);
+        db.remove_expired();
+        assert_eq!(db.size(), 2);
+    }
+
     #[test]
     fn keys() {
         let mut db = DB::default();
@@ -429,4 +834,71 @@ mod tests {
         db.expire(b"x", epoch().as_millis() - 10_000);
         assert_eq!(db.remove(b"x"), None);
     }
+
+    #[test]
+    fn count_expired() {
+        let mut db = DB::default();
+        for key in [b"a", b"b", b"c", b"d"] {
+            db.set(key, "x");
+            db.expire(key, epoch().as_millis() - 10_000);
+        }
+        db.get(b"a");
+        assert_eq!(db.take_expired(), 0);
+        db.get_mut(b"a");
+        db.set(b"b", "y");
+        db.remove(b"c");
+        db.expire(b"d", epoch().as_millis() + 10_000);
+        assert_eq!(db.take_expired(), 4);
+        assert_eq!(db.take_expired(), 0);
+    }
+
+    #[test]
+    fn types() {
+        let mut db = DB::default();
+        db.set(b"a", "x");
+        db.get_or_insert_with(&b"b"[..], Value::list);
+        db.setex(b"c", Value::hash(), epoch().as_millis() + 10_000);
+        db.set(b"d", Value::set());
+        db.set(b"e", "x");
+        db.expire(b"e", epoch().as_millis() - 10_000);
+        let expected = KeyTypes {
+            hashes: 1,
+            lists: 1,
+            sets: 1,
+            sorted_sets: 0,
+            strings: 2,
+        };
+        assert_eq!(db.types(), expected);
+
+        db.set(b"a", Value::sorted_set());
+        db.setex(b"b", "x", epoch().as_millis() + 10_000);
+        db.remove(b"c");
+        db.expire(b"d", epoch().as_millis() - 10_000);
+        db.remove_expired();
+        let expected = KeyTypes {
+            sorted_sets: 1,
+            strings: 1,
+            ..KeyTypes::default()
+        };
+        assert_eq!(db.types(), expected);
+
+        db.get_or_insert_with(&b"b"[..], Value::list);
+        db.set(b"b", Value::list());
+        db.remove(b"b");
+        assert_eq!(db.types().strings, 0);
+    }
+
+    #[test]
+    fn remove_expired() {
+        let mut db = DB::default();
+        db.set(b"a", "x");
+        db.set(b"b", "x");
+        db.expire(b"a", epoch().as_millis() - 10_000);
+        db.expire(b"b", epoch().as_millis() + 10_000);
+        db.set(b"c", "x");
+        assert_eq!(db.remove_expired(), (2, vec!["x".into()]));
+        assert_eq!(db.volatile(), 1);
+        assert_eq!(db.size(), 2);
+        assert_eq!(db.take_expired(), 1);
+    }
 }
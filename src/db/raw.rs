@@ -11,9 +11,41 @@ use std::{
     cmp::{Ord, PartialOrd},
     ops::{Deref, Range},
     str,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 use triomphe::Arc;
 
+/// The number of writes to a [`Raw`] that had to copy shared bytes first.
+static COPIED: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of writes to a [`Raw`] made in place.
+static IN_PLACE: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts of writes to [`Raw`] values since the process started, for `DEBUG RAW-WRITES`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RawWrites {
+    /// Writes that copied the bytes because they were shared.
+    pub copied: usize,
+
+    /// Writes made in place.
+    pub in_place: usize,
+}
+
+impl RawWrites {
+    pub fn get() -> Self {
+        RawWrites {
+            copied: COPIED.load(Ordering::Relaxed),
+            in_place: IN_PLACE.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Count a write to a [`Raw`], which is a copy unless the bytes are unique.
+fn count_write(unique: bool) {
+    let counter = if unique { &IN_PLACE } else { &COPIED };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
 /// A heap allocated, shared slice of bytes.
 #[derive(Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Raw(pub Arc<Vec<u8>>);
@@ -28,26 +60,46 @@ impl Raw {
     /// Return a mutable reference to the underlying bytes, or clone them first if this value is
     /// shared.
     pub fn make_mut(&mut self) -> &mut Vec<u8> {
+        count_write(self.0.is_unique());
         Arc::make_mut(&mut self.0)
     }
 
+    /// Return a mutable reference to the underlying bytes with room for at least `capacity` bytes.
+    /// If this value is shared, copy it into a single allocation of the right size rather than
+    /// cloning it and growing it again. Otherwise, grow it in place with amortized reallocation so
+    /// that repeated writes past the end don't copy the whole value each time.
+    pub fn make_mut_with_capacity(&mut self, capacity: usize) -> &mut Vec<u8> {
+        let unique = self.0.is_unique();
+        count_write(unique);
+        if !unique {
+            let mut value = Vec::with_capacity(std::cmp::max(capacity, self.len()));
+            value.extend_from_slice(&self.0);
+            self.0 = Arc::new(value);
+        }
+
+        let value = Arc::make_mut(&mut self.0);
+        value.reserve(capacity.saturating_sub(value.len()));
+        value
+    }
+
     /// Return a reference to a slice of this value.
     pub fn slice<'a>(&'a self, range: Range<usize>) -> RawSliceRef<'a> {
         RawSliceRef::new(self, range)
     }
 
-    /// Set the bytes for a particular range of this value.
+    /// Set the bytes for a particular range of this value, filling any gap with zeros.
     pub fn set_range(&mut self, bytes: &[u8], start: usize) {
         let end = start + bytes.len();
-        let value = self.make_mut();
-        if end > value.len() {
-            value.reserve_exact(end - value.len());
-            if start > value.len() {
-                value.resize(start, 0);
-            }
+        let value = self.make_mut_with_capacity(end);
+
+        // Zero fill the gap once, then copy over existing bytes and extend with the rest.
+        if start > value.len() {
+            value.resize(start, 0);
         }
-        let end = std::cmp::min(end, value.len());
-        value.splice(start..end, bytes.iter().copied());
+
+        let overlap = std::cmp::min(end, value.len()) - start;
+        value[start..start + overlap].copy_from_slice(&bytes[..overlap]);
+        value.extend_from_slice(&bytes[overlap..]);
     }
 }
 
@@ -112,3 +164,26 @@ impl<const N: usize> From<&'static [u8; N]> for Raw {
         Raw(Arc::new(value.to_vec()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Other tests write to raw values at the same time, so only check that the counts grow.
+    #[test]
+    fn writes() {
+        let mut raw = Raw::from("abc");
+        let before = RawWrites::get();
+        raw.make_mut().push(b'd');
+        let after = RawWrites::get();
+        assert!(after.in_place > before.in_place);
+
+        let shared = raw.clone();
+        let before = RawWrites::get();
+        raw.make_mut_with_capacity(8).push(b'e');
+        let after = RawWrites::get();
+        assert!(after.copied > before.copied);
+        assert_eq!(&shared[..], b"abcd");
+        assert_eq!(&raw[..], b"abcde");
+    }
+}
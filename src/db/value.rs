@@ -169,6 +169,33 @@ impl Value {
         }
     }
 
+    /// The name of the internal representation of this value, as reported by `OBJECT ENCODING`.
+    // TODO: Use encodings from redis…?
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Value::Hash(hash) => match **hash {
+                Hash::HashMap(_) => "hashtable",
+                Hash::PackMap(_) => "listpack",
+            },
+            Value::List(list) => list.encoding(),
+            Value::Set(set) => match **set {
+                Set::Int(_) => "intset",
+                Set::Pack(_) => "listpack",
+                Set::Hash(_) => "hashtable",
+            },
+            Value::SortedSet(set) => match **set {
+                SortedSet::Pack(_) => "listpack",
+                SortedSet::Skiplist(_, _) => "skiplist",
+            },
+            Value::String(value) => match value {
+                StringValue::Array(..) => "embstr",
+                StringValue::Float(_) => "float",
+                StringValue::Integer(_) => "int",
+                StringValue::Raw(_) => "raw",
+            },
+        }
+    }
+
     /// How much effort is required to drop this value?
     pub fn drop_effort(&self) -> usize {
         match self {
@@ -183,7 +210,7 @@ impl Value {
 
 impl From<Vec<u8>> for Value {
     fn from(value: Vec<u8>) -> Self {
-        Value::String(value[..].into())
+        Value::String(value.into())
     }
 }
 
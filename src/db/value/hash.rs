@@ -1,10 +1,10 @@
 use crate::{
     buffer::Buffer,
     db::{KeyRef, StringValue},
+    ordered_map::OrderedMap,
     pack::{PackMap, PackRef, Packable},
     reply::ReplyError,
 };
-use hashbrown::{HashMap, hash_map::EntryRef};
 
 /// A reference to a hash key.
 #[derive(Debug)]
@@ -25,6 +25,16 @@ impl<'a> From<&'a StringValue> for HashKey<'a> {
     }
 }
 
+impl HashKey<'_> {
+    pub fn as_bytes<'v>(&'v self, buffer: &'v mut impl Buffer) -> &'v [u8] {
+        use HashKey::*;
+        match self {
+            Pack(key) => key.as_bytes(buffer),
+            String(key) => key.as_bytes(buffer),
+        }
+    }
+}
+
 /// A reference to a hash value.
 #[derive(Debug, PartialEq)]
 pub enum HashValue<'a> {
@@ -54,10 +64,11 @@ impl<'a> From<&'a StringValue> for HashValue<'a> {
     }
 }
 
-/// A hash, stored as a [`HashMap`] or a [`PackMap`].
+/// A hash, stored as an [`OrderedMap`] or a [`PackMap`]. Either way, fields iterate in the
+/// order they were first inserted, so converting between encodings doesn't reorder them.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Hash {
-    HashMap(HashMap<StringValue, StringValue>),
+    HashMap(OrderedMap<StringValue, StringValue>),
     PackMap(PackMap),
 }
 
@@ -108,18 +119,17 @@ impl Hash {
         StringValue: From<&'a Q>,
     {
         match self {
-            Hash::HashMap(map) => match map.entry_ref(key) {
-                EntryRef::Occupied(mut entry) => {
-                    let i = entry.get_mut().integer().ok_or(ReplyError::Integer)?;
+            Hash::HashMap(map) => {
+                if let Some(value) = map.get_mut(key) {
+                    let i = value.integer().ok_or(ReplyError::Integer)?;
                     let sum = i.checked_add(by).ok_or(ReplyError::IncrOverflow)?;
                     *i = sum;
                     Ok(sum)
-                }
-                EntryRef::Vacant(entry) => {
-                    entry.insert(by.into());
+                } else {
+                    map.insert(key.into(), by.into());
                     Ok(by)
                 }
-            },
+            }
             Hash::PackMap(map) => {
                 if let Some(value) = map.get(&key) {
                     let value = value.integer().ok_or(ReplyError::Integer)?;
@@ -148,24 +158,23 @@ impl Hash {
         StringValue: From<&'a Q>,
     {
         match self {
-            Hash::HashMap(map) => match map.entry_ref(key) {
-                EntryRef::Occupied(mut entry) => {
-                    let f = entry.get_mut().float().ok_or(ReplyError::Float)?;
+            Hash::HashMap(map) => {
+                if let Some(value) = map.get_mut(key) {
+                    let f = value.float().ok_or(ReplyError::Float)?;
                     let sum = *f + by;
                     if !sum.is_finite() {
                         return Err(ReplyError::NanOrInfinity);
                     }
                     *f = sum;
                     Ok(sum)
-                }
-                EntryRef::Vacant(entry) => {
+                } else {
                     if !by.is_finite() {
                         return Err(ReplyError::NanOrInfinity);
                     }
-                    entry.insert(by.into());
+                    map.insert(key.into(), by.into());
                     Ok(by)
                 }
-            },
+            }
             Hash::PackMap(map) => {
                 if let Some(value) = map.get(&key) {
                     let f = value.float().ok_or(ReplyError::Float)?;
@@ -207,16 +216,15 @@ impl Hash {
         }
 
         match self {
-            Hash::HashMap(map) => match map.entry_ref(key) {
-                EntryRef::Occupied(mut entry) => {
-                    entry.insert(value.into());
+            Hash::HashMap(map) => {
+                if let Some(current) = map.get_mut(key) {
+                    *current = value.into();
                     false
-                }
-                EntryRef::Vacant(entry) => {
-                    entry.insert(value.into());
+                } else {
+                    map.insert(key.into(), value.into());
                     true
                 }
-            },
+            }
             Hash::PackMap(map) => {
                 let result = map.insert(&key, &value);
                 if map.len() > max_len {
@@ -285,7 +293,7 @@ impl Hash {
         match self {
             Hash::HashMap(_) => {}
             Hash::PackMap(packmap) => {
-                let mut hashmap = HashMap::with_capacity(packmap.len());
+                let mut hashmap = OrderedMap::with_capacity(packmap.len());
                 for (key, value) in packmap.iter() {
                     hashmap.insert(key.into(), value.into());
                 }
@@ -390,6 +398,6 @@ mod tests {
     #[test]
     #[cfg(target_pointer_width = "64")]
     fn size() {
-        assert_eq!(40, std::mem::size_of::<Hash>());
+        assert_eq!(56, std::mem::size_of::<Hash>());
     }
 }
@@ -36,12 +36,17 @@ impl List {
         }
     }
 
-    /// Trim `count` values from `edge`.
-    pub fn trim(&mut self, edge: Edge, count: usize, max: i64) {
+    /// Switch encodings if the list no longer fits in a single pack for `max`, or if it has
+    /// shrunk enough to fit in one comfortably. Every mutation that changes the size of the list
+    /// should end here.
+    fn encode(&mut self, max: i64) {
         match self {
-            List::Pack(list) => list.trim(edge, count),
+            List::Pack(pack) => {
+                if !list_is_valid(pack.len(), pack.size(), max) {
+                    *self = List::Quick(pack.split(max).into_iter().collect());
+                }
+            }
             List::Quick(quick) => {
-                quick.trim(edge, count);
                 if let Some(pack) = quick.convert(max) {
                     *self = List::Pack(pack);
                 }
@@ -49,6 +54,37 @@ impl List {
         }
     }
 
+    /// The name of the current encoding, as reported by `OBJECT ENCODING`.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            List::Pack(_) => "listpack",
+            List::Quick(_) => "quicklist",
+        }
+    }
+
+    /// Trim `count` values from `edge`.
+    pub fn trim(&mut self, edge: Edge, count: usize, max: i64) {
+        match self {
+            List::Pack(list) => list.trim(edge, count),
+            List::Quick(quick) => quick.trim(edge, count),
+        }
+        self.encode(max);
+    }
+
+    /// Remove at most `count` values from the `edge` end of the list, calling `f` with each one
+    /// in order from that edge before it's removed. Return the number removed.
+    pub fn pop_many<F>(&mut self, edge: Edge, count: usize, max: i64, f: F) -> usize
+    where
+        F: FnMut(PackRef<'_>),
+    {
+        let popped = match self {
+            List::Pack(list) => list.pop_many(edge, count, f),
+            List::Quick(quick) => quick.pop_many(edge, count, f),
+        };
+        self.encode(max);
+        popped
+    }
+
     /// Peek at the value on `edge` end of the list.
     pub fn peek<'a>(&'a self, edge: Edge) -> Option<PackRef<'a>> {
         match self {
@@ -76,25 +112,48 @@ impl List {
         }
     }
 
-    /// Set the value at `index`. Return true if the value exists, otherwise false.
-    pub fn set(&mut self, element: &[u8], index: usize) -> bool {
+    /// Push `values` onto the `edge` end of the list, in order, converting it at most once.
+    pub fn push_many<E>(&mut self, values: &[E], edge: Edge, max: i64)
+    where
+        E: Packable,
+    {
         match self {
-            // TODO: What if the element doesn't fit into the pack?
-            List::Pack(list) => list.set(&element, index),
-            List::Quick(list) => list.set(&element, index),
+            List::Pack(pack) => {
+                let count = pack.push_many(values, edge, max);
+                if count == values.len() {
+                    return;
+                }
+
+                let mut quick = QuickList::from(std::mem::take(pack));
+                quick.push_many(&values[count..], edge, max);
+                *self = List::Quick(quick);
+            }
+            List::Quick(quick) => quick.push_many(values, edge, max),
         }
     }
 
+    /// Set the value at `index`. Return true if the value exists, otherwise false.
+    pub fn set(&mut self, element: &[u8], index: usize, max: i64) -> bool {
+        let result = match self {
+            List::Pack(list) => list.set(&element, index),
+            List::Quick(list) => list.set(&element, index, max),
+        };
+        self.encode(max);
+        result
+    }
+
     /// Remove up to `count` values from the list on the `edge` side. Return
     /// the number of values that were removed.
-    pub fn remove<E>(&mut self, element: E, count: usize, edge: Edge) -> usize
+    pub fn remove<E>(&mut self, element: E, count: usize, edge: Edge, max: i64) -> usize
     where
         E: AsRef<[u8]>,
     {
-        match self {
+        let result = match self {
             List::Pack(list) => list.remove(&element, count, edge),
             List::Quick(list) => list.remove(&element, count, edge),
-        }
+        };
+        self.encode(max);
+        result
     }
 
     /// Move an element from one edge to the other.
@@ -140,7 +199,11 @@ impl List {
                     }
                 }
             }
-            List::Quick(list) => list.insert(&element, pivot, before, max),
+            List::Quick(list) => {
+                let result = list.insert(&element, pivot, before, max);
+                self.encode(max);
+                result
+            }
         }
     }
 
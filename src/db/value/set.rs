@@ -1,12 +1,13 @@
 use crate::{
     PackIter,
+    buffer::{ArrayBuffer, Buffer},
     bytes::parse_i64_exact,
     db::{KeyRef, StringValue},
-    int_set::{IntSet, Iter as IntSetIter},
-    pack::{PackRef, PackSet, PackValue, Packable},
+    int_set::{IntSet, Intersection as IntSetIntersection, Iter as IntSetIter},
+    pack::{PackRef, PackSet, PackSetIntersection, PackValue, Packable},
+    sharded_map::{Keys, ShardedMap},
     store::SetConfig,
 };
-use hashbrown::{HashSet, hash_set::Iter as HashSetIter};
 
 /// A reference to a [`Set`] value.
 pub enum SetRef<'a> {
@@ -33,6 +34,17 @@ impl<'a> From<PackRef<'a>> for SetRef<'a> {
     }
 }
 
+impl SetRef<'_> {
+    /// Return this value as a slice of bytes, optionally in the supplied [`Buffer`].
+    pub fn as_bytes<'v>(&'v self, buffer: &'v mut impl Buffer) -> &'v [u8] {
+        match self {
+            SetRef::Int(value) => buffer.write_i64(*value),
+            SetRef::Pack(value) => value.as_bytes(buffer),
+            SetRef::String(value) => value.as_bytes(buffer),
+        }
+    }
+}
+
 /// An owned value from a [`Set`].
 pub enum SetValue {
     Int(i64),
@@ -58,7 +70,7 @@ impl From<PackValue> for SetValue {
     }
 }
 
-/// A set of unique string values, stored as a [`HashSet`] or an [`IntSet`].
+/// A set of unique string values, stored as a [`ShardedMap`], a [`PackSet`], or an [`IntSet`].
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Set {
     /// Stored as an [`IntSet`].
@@ -67,8 +79,8 @@ pub enum Set {
     /// Stores as a [`PackSet`].
     Pack(PackSet),
 
-    /// Stored as a [`HashSet`].
-    Hash(HashSet<StringValue>),
+    /// Stored as a [`ShardedMap`] with no values, so that a large set grows one shard at a time.
+    Hash(ShardedMap<StringValue, ()>),
 }
 
 impl Default for Set {
@@ -117,7 +129,33 @@ impl Set {
                 None => false,
             },
             Set::Pack(set) => set.contains(&value),
-            Set::Hash(set) => set.contains(value),
+            Set::Hash(set) => set.contains_key(value),
+        }
+    }
+
+    /// Does this set contain `value`, from another set? Values from compact sets of the same
+    /// encoding are compared without formatting them.
+    pub fn contains_ref(&self, value: &SetRef) -> bool {
+        match (self, value) {
+            (Set::Int(set), SetRef::Int(value)) => set.contains(*value),
+            (Set::Pack(set), SetRef::Int(value)) => set.contains(value),
+            (Set::Pack(set), SetRef::Pack(value)) => set.contains(value),
+            _ => {
+                let mut buffer = ArrayBuffer::default();
+                self.contains(value.as_bytes(&mut buffer))
+            }
+        }
+    }
+
+    /// Return an iterator over the values in this set that are also in every one of `others`.
+    /// Pass the smallest set as `self`, since each of its values is looked up in the others.
+    /// Two int sets are walked together in order instead, and two pack sets are compared without
+    /// converting either one.
+    pub fn intersection<'a>(&'a self, others: &'a [&'a Set]) -> Intersection<'a> {
+        match (self, others) {
+            (Set::Int(set), [Set::Int(other)]) => Intersection::Int(set.intersection(other)),
+            (Set::Pack(set), [Set::Pack(other)]) => Intersection::Pack(set.intersection(other)),
+            _ => Intersection::Lookup(self.iter(), others),
         }
     }
 
@@ -162,7 +200,7 @@ impl Set {
                     set.insert(&value)
                 }
             }
-            Set::Hash(set) => set.insert(value.into()),
+            Set::Hash(set) => set.insert(value.into(), ()).is_none(),
         }
     }
 
@@ -173,7 +211,7 @@ impl Set {
             Set::Pack(set) => Some(set.pop()?.into()),
             Set::Hash(set) => {
                 // TODO: Make it random.
-                let member = set.iter().next()?.clone();
+                let member = set.keys().next()?.clone();
                 set.remove(&member);
                 Some(member.into())
             }
@@ -192,7 +230,7 @@ impl Set {
                 None => false,
             },
             Set::Pack(set) => set.remove(&value),
-            Set::Hash(set) => set.remove(value),
+            Set::Hash(set) => set.remove(value).is_some(),
         }
     }
 
@@ -201,11 +239,11 @@ impl Set {
         match self {
             Set::Int(set) => Iter::Int(set.iter()),
             Set::Pack(set) => Iter::Pack(set.iter()),
-            Set::Hash(set) => Iter::String(set.iter()),
+            Set::Hash(set) => Iter::String(set.keys()),
         }
     }
 
-    /// Convert from an [`IntSet`] or [`PackSet`] to a [`HashSet`] and insert a new value.
+    /// Convert from an [`IntSet`] or [`PackSet`] to a [`ShardedMap`] and insert a new value.
     fn convert<'a, Q>(&mut self, config: &SetConfig, value: &'a Q)
     where
         Q: AsRef<[u8]> + KeyRef<StringValue> + ?Sized,
@@ -217,24 +255,24 @@ impl Set {
                 let max_entries = config.max_listpack_entries;
                 let max_value = config.max_listpack_value;
 
-                if set.len() == max_entries || set.longest() > max_value {
-                    let mut hashset = HashSet::with_capacity(set.len() + 1);
+                if set.len() >= max_entries || set.longest() > max_value {
+                    let mut map = ShardedMap::default();
                     for x in set.iter() {
-                        hashset.insert(x.into());
+                        map.insert(x.into(), ());
                     }
-                    hashset.insert(value.into());
-                    *self = Set::Hash(hashset);
+                    map.insert(value.into(), ());
+                    *self = Set::Hash(map);
                 } else {
                     *self = Set::Pack((set.iter(), value).into());
                 }
             }
             Set::Pack(set) => {
-                let mut hashset = HashSet::with_capacity(set.len() + 1);
+                let mut map = ShardedMap::default();
                 for x in set.iter() {
-                    hashset.insert(x.into());
+                    map.insert(x.into(), ());
                 }
-                hashset.insert(value.into());
-                *self = Set::Hash(hashset);
+                map.insert(value.into(), ());
+                *self = Set::Hash(map);
             }
             Set::Hash(_) => {}
         }
@@ -245,7 +283,7 @@ impl Set {
 pub enum Iter<'a> {
     Int(IntSetIter<'a>),
     Pack(PackIter<'a>),
-    String(HashSetIter<'a, StringValue>),
+    String(Keys<'a, StringValue, ()>),
 }
 
 impl<'a> Iterator for Iter<'a> {
@@ -260,12 +298,36 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+/// An iterator over the values in several [`Set`]s.
+pub enum Intersection<'a> {
+    Int(IntSetIntersection<'a>),
+    Pack(PackSetIntersection<'a>),
+    Lookup(Iter<'a>, &'a [&'a Set]),
+}
+
+impl<'a> Iterator for Intersection<'a> {
+    type Item = SetRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Intersection::Int(iter) => iter.next().map(|value| value.into()),
+            Intersection::Pack(iter) => iter.next().map(|value| value.into()),
+            Intersection::Lookup(iter, others) => {
+                if others.iter().any(|other| other.is_empty()) {
+                    return None;
+                }
+                iter.find(|value| others.iter().all(|other| other.contains_ref(value)))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn size() {
-        assert_eq!(48, std::mem::size_of::<Set>());
+        assert_eq!(40, std::mem::size_of::<Set>());
     }
 }
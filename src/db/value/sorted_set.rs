@@ -1,9 +1,10 @@
 use crate::{
+    buffer::Buffer,
     db::{Extreme, KeyRef, StringValue},
     pack::{PackRef, PackSortedSet, PackValue, Packable},
+    sharded_map::{Entry, ShardedMap},
     skiplist::Skiplist,
 };
-use hashbrown::{HashMap, hash_map::EntryRef};
 use ordered_float::NotNan;
 use std::ops::{Range, RangeBounds};
 
@@ -19,6 +20,16 @@ pub enum SortedSetRef<'a> {
     String(&'a StringValue),
 }
 
+impl SortedSetRef<'_> {
+    /// Return this value as a slice of bytes, optionally in the supplied [`Buffer`].
+    pub fn as_bytes<'v>(&'v self, buffer: &'v mut impl Buffer) -> &'v [u8] {
+        match self {
+            SortedSetRef::Pack(value) => value.as_bytes(buffer),
+            SortedSetRef::String(value) => value.as_bytes(buffer),
+        }
+    }
+}
+
 impl<'a> From<PackRef<'a>> for SortedSetRef<'a> {
     fn from(value: PackRef<'a>) -> Self {
         SortedSetRef::Pack(value)
@@ -52,7 +63,7 @@ impl From<StringValue> for SortedSetValue {
 #[derive(Clone, Debug)]
 pub enum SortedSet {
     Pack(PackSortedSet),
-    Skiplist(Skiplist, HashMap<StringValue, NotNan<f64>>),
+    Skiplist(Skiplist, ShardedMap<StringValue, NotNan<f64>>),
 }
 
 impl Default for SortedSet {
@@ -129,10 +140,8 @@ impl SortedSet {
                 }
                 result
             }
-            SortedSet::Skiplist(list, map) => {
-                let entry = map.entry_ref(value);
-
-                if let EntryRef::Occupied(mut entry) = entry {
+            SortedSet::Skiplist(list, map) => match map.entry(value) {
+                Entry::Occupied(mut entry) => {
                     if *entry.get() == score {
                         None
                     } else {
@@ -141,12 +150,14 @@ impl SortedSet {
                         entry.insert(score);
                         Some(Insertion::Changed)
                     }
-                } else {
-                    let entry = entry.insert(score);
-                    list.insert(score, entry.key().clone());
+                }
+                Entry::Vacant(entry) => {
+                    let value = StringValue::from(value);
+                    list.insert(score, value.clone());
+                    entry.insert(value, score);
                     Some(Insertion::Added)
                 }
-            }
+            },
         }
     }
 
@@ -175,8 +186,8 @@ impl SortedSet {
         match self {
             SortedSet::Pack(set) => set.remove(&value.as_ref()),
             SortedSet::Skiplist(list, map) => {
-                if let EntryRef::Occupied(entry) = map.entry_ref(value.as_ref()) {
-                    let (value, score) = entry.remove_entry();
+                if let Entry::Occupied(entry) = map.entry(value.as_ref()) {
+                    let (value, score) = entry.remove();
                     list.remove(*score, &value);
                     true
                 } else {
@@ -198,6 +209,15 @@ impl SortedSet {
         }
     }
 
+    pub fn remove_range_rank(&mut self, range: Range<usize>) -> usize {
+        match self {
+            SortedSet::Pack(set) => set.remove_range_rank(range),
+            SortedSet::Skiplist(list, map) => list.remove_range_rank(range, |value| {
+                map.remove(value);
+            }),
+        }
+    }
+
     pub fn pop(&mut self, extreme: Extreme) -> Option<(f64, SortedSetValue)> {
         match self {
             SortedSet::Pack(set) => set.pop(extreme).map(|(score, value)| (score, value.into())),
@@ -231,26 +251,28 @@ impl SortedSet {
     pub fn range_score<'a, R>(
         &'a self,
         bounds: &'a R,
+        limit: Option<(usize, usize)>,
     ) -> impl ExactSizeIterator<Item = (f64, SortedSetRef<'a>)>
     where
         R: RangeBounds<f64>,
     {
         match self {
-            SortedSet::Pack(set) => Iter::Pack(set.range_score(bounds)),
-            SortedSet::Skiplist(list, _) => Iter::Skiplist(list.range_score(bounds)),
+            SortedSet::Pack(set) => Iter::Pack(set.range_score(bounds, limit)),
+            SortedSet::Skiplist(list, _) => Iter::Skiplist(list.range_score(bounds, limit)),
         }
     }
 
     pub fn rev_range_score<'a, R>(
         &'a self,
         bounds: &'a R,
+        limit: Option<(usize, usize)>,
     ) -> impl ExactSizeIterator<Item = (f64, SortedSetRef<'a>)>
     where
         R: RangeBounds<f64>,
     {
         match self {
-            SortedSet::Pack(set) => Iter::Pack(set.rev_range_score(bounds)),
-            SortedSet::Skiplist(list, _) => Iter::Skiplist(list.rev_range_score(bounds)),
+            SortedSet::Pack(set) => Iter::Pack(set.rev_range_score(bounds, limit)),
+            SortedSet::Skiplist(list, _) => Iter::Skiplist(list.rev_range_score(bounds, limit)),
         }
     }
 
@@ -259,7 +281,7 @@ impl SortedSet {
             SortedSet::Skiplist(_, _) => {}
             SortedSet::Pack(set) => {
                 let mut list = Skiplist::default();
-                let mut map = HashMap::default();
+                let mut map = ShardedMap::default();
                 for (score, value) in set.iter().rev() {
                     let score = NotNan::new(score).unwrap();
                     let value: StringValue = value.into();
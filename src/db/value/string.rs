@@ -7,12 +7,15 @@ use crate::{
 use bytes::Bytes;
 use hashbrown::Equivalent;
 use std::{
-    cmp::Ordering,
+    cmp::{Ordering, max},
     hash::{Hash, Hasher},
     io::Write,
     ops::Range,
 };
 
+/// The longest slice of a raw value that is copied out rather than shared.
+const SLICE_COPY_MAX: usize = 4096;
+
 /// A redis string value, represented in various ways to save memory or
 /// facilitate specific operations.
 #[derive(Clone, Debug, PartialEq)]
@@ -87,7 +90,8 @@ impl Hash for StringValue {
 }
 
 /// Convert a value into a [`StringValue`]. This ensures that values
-/// are put into an array or integer if possible.
+/// are put into an array or integer if possible, so that canonical
+/// integers are only parsed once rather than on every INCR.
 fn into_string<V>(value: V) -> StringValue
 where
     V: Into<Raw> + AsRef<[u8]>,
@@ -167,8 +171,10 @@ impl From<PackRef<'_>> for StringValue {
     fn from(value: PackRef) -> Self {
         use PackRef::*;
         match value {
+            Bool(b) => StringValue::Integer(b.into()),
             Float(f) => StringValue::Float(f),
             Integer(i) => StringValue::Integer(i),
+            Nil => into_string(b""),
             Slice(s) => into_string(&s[..]),
         }
     }
@@ -296,7 +302,8 @@ impl StringValue {
         }
     }
 
-    /// Append `bytes` to the string.
+    /// Append `bytes` to the string. Like [`into_string`], the result is stored as an integer or
+    /// an array if it can be, whatever the encoding was before.
     pub fn append(&mut self, bytes: &[u8]) {
         fn append(a: &[u8], b: &[u8]) -> StringValue {
             let mut vec = Vec::with_capacity(a.len() + b.len());
@@ -311,6 +318,8 @@ impl StringValue {
             Array(value) => {
                 if value.append(bytes).is_err() {
                     *self = append(value, bytes);
+                } else if let Some(i) = parse_i64_exact(value) {
+                    *self = Integer(i);
                 }
             }
             Float(value) => {
@@ -320,43 +329,70 @@ impl StringValue {
                 *self = append(buffer.write_i64(*value), bytes);
             }
             Raw(value) => {
-                value.make_mut().extend_from_slice(bytes);
-                *self = into_string(std::mem::take(value));
+                let len = value.len() + bytes.len();
+                value.make_mut_with_capacity(len).extend_from_slice(bytes);
+                self.shrink_raw();
             }
         }
     }
 
-    /// Set a range of bytes in the string.
+    /// Set a range of bytes in the string, keeping the encoding canonical like [`Self::append`].
     pub fn set_range(&mut self, bytes: &[u8], start: usize) {
+        fn set_range(a: &[u8], b: &[u8], start: usize) -> StringValue {
+            let capacity = max(a.len(), start + b.len());
+            let mut raw = crate::db::Raw::from(Vec::with_capacity(capacity));
+            raw.make_mut().extend_from_slice(a);
+            raw.set_range(b, start);
+            into_string(raw)
+        }
+
+        let mut buffer = ArrayBuffer::default();
+        use StringValue::*;
         match self {
-            StringValue::Array(value) => {
+            Array(value) => {
                 if value.set_range(bytes, start).is_err() {
-                    let mut raw = Raw::from(&value[..]);
-                    raw.set_range(bytes, start);
-                    *self = into_string(raw);
+                    *self = set_range(value, bytes, start);
+                } else if let Some(i) = parse_i64_exact(value) {
+                    *self = Integer(i);
                 }
             }
-            StringValue::Float(f) => {
-                let mut raw = Raw::default();
-                raw.make_mut().write_f64(*f);
-                raw.set_range(bytes, start);
-                *self = into_string(raw);
+            Float(value) => {
+                *self = set_range(buffer.write_f64(*value), bytes, start);
             }
-            StringValue::Integer(i) => {
-                let mut raw = Raw::default();
-                raw.make_mut().write_i64(*i);
-                raw.set_range(bytes, start);
-                *self = into_string(raw);
+            Integer(value) => {
+                *self = set_range(buffer.write_i64(*value), bytes, start);
             }
-            StringValue::Raw(raw) => {
+            Raw(raw) => {
                 raw.set_range(bytes, start);
-                *self = into_string(std::mem::take(raw));
+                self.shrink_raw();
+            }
+        }
+    }
+
+    /// Move a raw value into an integer or an array if it fits. A value that stays raw keeps its
+    /// allocation, so repeated writes to a long string don't allocate each time.
+    fn shrink_raw(&mut self) {
+        if let StringValue::Raw(raw) = self {
+            if let Some(i) = parse_i64_exact(raw) {
+                *self = StringValue::Integer(i);
+            } else if let Ok(array) = raw[..].try_into() {
+                *self = StringValue::Array(array);
             }
         }
     }
 
-    /// Return a slice of the string.
+    /// Return a slice of the string. A small slice of a large raw value is copied out rather
+    /// than shared, so that holding onto it (e.g. in a pending reply) doesn't force the next
+    /// write to the value to copy the whole thing. Anything longer than a few KB is shared, since
+    /// copying it would cost as much as that write.
     pub fn slice(&self, range: Range<usize>) -> StringSlice {
+        if let StringValue::Raw(raw) = self
+            && range.len() <= SLICE_COPY_MAX
+            && range.len() < raw.len() / 2
+        {
+            let len = range.len();
+            return StringSlice::new(raw[range].into(), 0..len);
+        }
         StringSlice::new(self.clone(), range)
     }
 }
@@ -420,6 +456,26 @@ mod tests {
         assert_eq!(value, StringValue::Integer(-5i64));
     }
 
+    #[test]
+    fn integer_preserved() {
+        let mut value = StringValue::from(&b"12"[..]);
+        assert_eq!(value, StringValue::Integer(12));
+
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(value.as_bytes(&mut buffer), b"12");
+        assert_eq!(value.len(), 2);
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(value.slice(0..1).as_bytes(&mut buffer), b"1");
+        assert_eq!(value, StringValue::Integer(12));
+
+        value.append(b"3");
+        assert_eq!(value, StringValue::Integer(123));
+
+        value.append(b"x");
+        assert_eq!(value, StringValue::from(&b"123x"[..]));
+        assert!(!matches!(value, StringValue::Integer(_)));
+    }
+
     #[test]
     fn raw() {
         let mut value = StringValue::Float(-5.6f64);
@@ -460,4 +516,111 @@ mod tests {
         assert_eq!(f, None);
         assert_eq!(value, StringValue::Raw("invalid".into()));
     }
+
+    #[test]
+    fn set_range() {
+        let mut value = StringValue::Integer(123);
+        value.set_range(b"x", 5);
+        assert_eq!(value, StringValue::from(&b"123\0\0x"[..]));
+
+        let mut value = StringValue::from(&b"abcdef"[..]);
+        value.set_range(b"xy", 4);
+        assert_eq!(value, StringValue::from(&b"abcdxy"[..]));
+
+        let mut value = StringValue::Raw("1234567890123456789012345678901234567890".into());
+        value.set_range(b"x", 45);
+        let mut buffer = ArrayBuffer::default();
+        let bytes = value.as_bytes(&mut buffer);
+        assert_eq!(bytes.len(), 46);
+        assert_eq!(&bytes[40..], b"\0\0\0\0\0x");
+    }
+
+    #[test]
+    fn canonical() {
+        let mut value = StringValue::from(&b"-"[..]);
+        value.append(b"12");
+        assert!(matches!(value, StringValue::Integer(-12)));
+
+        let mut value = StringValue::from(&b"abc"[..]);
+        value.set_range(b"123", 0);
+        assert!(matches!(value, StringValue::Integer(123)));
+
+        let mut value = StringValue::from(&b"0"[..]);
+        value.append(b"1");
+        assert!(matches!(value, StringValue::Array(_)));
+
+        let mut value = StringValue::Integer(12);
+        value.append(b"");
+        assert!(matches!(value, StringValue::Integer(12)));
+    }
+
+    #[test]
+    fn set_range_shared() {
+        let original = StringValue::Raw("1234567890123456789012345678901234567890".into());
+        let mut value = original.clone();
+        value.set_range(b"xyz", 1000);
+
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(original.as_bytes(&mut buffer).len(), 40);
+        let mut buffer = ArrayBuffer::default();
+        let bytes = value.as_bytes(&mut buffer);
+        assert_eq!(bytes.len(), 1003);
+        assert!(bytes[40..1000].iter().all(|&byte| byte == 0));
+        assert_eq!(&bytes[1000..], b"xyz");
+    }
+
+    #[test]
+    fn set_range_in_place() {
+        let mut value = StringValue::Raw(vec![b'a'; 1000].into());
+        let mut buffer = ArrayBuffer::default();
+        let before = value.as_bytes(&mut buffer).as_ptr();
+        value.set_range(b"xyz", 10);
+        let mut buffer = ArrayBuffer::default();
+        let bytes = value.as_bytes(&mut buffer);
+        assert_eq!(bytes.as_ptr(), before);
+        assert_eq!(&bytes[9..14], b"axyza");
+    }
+
+    #[test]
+    fn append_shared() {
+        let original = StringValue::Raw(vec![b'a'; 1000].into());
+        let mut value = original.clone();
+        value.append(b"xyz");
+
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(original.as_bytes(&mut buffer).len(), 1000);
+        let mut buffer = ArrayBuffer::default();
+        let bytes = value.as_bytes(&mut buffer);
+        assert_eq!(bytes.len(), 1003);
+        assert_eq!(&bytes[1000..], b"xyz");
+    }
+
+    #[test]
+    fn slice_does_not_share_small_ranges() {
+        let value = StringValue::Raw(vec![b'a'; 1000].into());
+        let slice = value.slice(10..20);
+        let StringValue::Raw(raw) = &value else {
+            unreachable!()
+        };
+        assert!(raw.0.is_unique());
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(slice.as_bytes(&mut buffer), &[b'a'; 10]);
+
+        let slice = value.slice(0..1000);
+        assert!(!raw.0.is_unique());
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(slice.as_bytes(&mut buffer).len(), 1000);
+    }
+
+    #[test]
+    fn slice_shares_large_ranges() {
+        let value = StringValue::Raw(vec![b'a'; 1 << 20].into());
+        let slice = value.slice(1000..(1 << 19));
+        let StringValue::Raw(raw) = &value else {
+            unreachable!()
+        };
+        assert!(!raw.0.is_unique());
+        let mut buffer = ArrayBuffer::default();
+        assert_eq!(slice.as_bytes(&mut buffer).len(), (1 << 19) - 1000);
+    }
 }
@@ -1,87 +1,221 @@
-fn trim_start(slice: &[u8], value: u8) -> &[u8] {
-    match slice.iter().position(|&byte| byte != value) {
-        Some(index) => &slice[index..],
-        None => &[],
-    }
+/// A single step in a compiled [`Pattern`].
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A run of literal bytes, already case folded for case insensitive patterns.
+    Literal(Vec<u8>),
+
+    /// Any single byte.
+    Any,
+
+    /// Any number of bytes, including none.
+    Star,
+
+    /// One byte from a set, stored as a 256 bit table.
+    Class([u64; 4]),
 }
 
-fn glob<'a, F>(string: &'a [u8], pattern: &'a [u8], case: F) -> bool
-where
-    F: Fn(u8) -> u8,
-{
-    let brackets = |pattern: &'a [u8], byte: u8| {
-        let mut matched = false;
+/// A glob pattern compiled once into a list of tokens so that it can be matched against many
+/// strings without parsing the pattern each time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+    nocase: bool,
+}
+
+impl Pattern {
+    /// Compile a case sensitive pattern.
+    pub fn compile(pattern: &[u8]) -> Self {
+        Self::build(pattern, false)
+    }
+
+    /// Compile a case insensitive pattern.
+    pub fn compile_nocase(pattern: &[u8]) -> Self {
+        Self::build(pattern, true)
+    }
 
-        let (mut pattern, not) = match pattern {
-            [b'^', rest @ ..] => (rest, true),
-            _ => (pattern, false),
+    fn build(mut pattern: &[u8], nocase: bool) -> Self {
+        let case = |byte: u8| {
+            if nocase {
+                byte.to_ascii_lowercase()
+            } else {
+                byte
+            }
+        };
+
+        let mut tokens = Vec::new();
+        let literal = |tokens: &mut Vec<Token>, byte: u8| match tokens.last_mut() {
+            Some(Token::Literal(bytes)) => bytes.push(case(byte)),
+            _ => tokens.push(Token::Literal(vec![case(byte)])),
         };
 
         loop {
             pattern = match pattern {
-                [b']', rest @ ..] => {
-                    pattern = rest;
-                    break;
+                [b'?', rest @ ..] => {
+                    tokens.push(Token::Any);
+                    rest
                 }
-                [b'\\', c, rest @ ..] => {
-                    matched |= case(*c) == case(byte);
+                [b'*', rest @ ..] => {
+                    if tokens.last() != Some(&Token::Star) {
+                        tokens.push(Token::Star);
+                    }
                     rest
                 }
-                [start, b'-', end, rest @ ..] => {
-                    matched |= (case(*start)..=case(*end)).contains(&case(byte));
+                [b'[', rest @ ..] => {
+                    let (class, rest) = class(rest, case);
+                    tokens.push(Token::Class(class));
                     rest
                 }
-                [c, rest @ ..] => {
-                    matched |= case(*c) == case(byte);
+                [b'\\', byte, rest @ ..] | [byte, rest @ ..] => {
+                    literal(&mut tokens, *byte);
                     rest
                 }
-                _ => break,
+                [] => break,
             }
         }
 
-        if not ^ matched { Some(pattern) } else { None }
+        Pattern { tokens, nocase }
+    }
+
+    /// The literal bytes every match must start with. Case insensitive patterns have no literal
+    /// prefix.
+    pub fn prefix(&self) -> &[u8] {
+        match self.tokens.first() {
+            Some(Token::Literal(bytes)) if !self.nocase => bytes,
+            _ => &[],
+        }
+    }
+
+    /// Does `string` match this pattern? Like Redis, an empty string only matches an empty
+    /// pattern, so not even `*` matches it.
+    pub fn matches(&self, string: &[u8]) -> bool {
+        if string.is_empty() {
+            return self.tokens.is_empty();
+        }
+
+        let tokens = &self.tokens[..];
+        let (mut token, mut index) = (0, 0);
+
+        // The token after the most recent star and the string index it is currently matched at.
+        let mut star = None;
+
+        loop {
+            match tokens.get(token) {
+                Some(Token::Star) => {
+                    token += 1;
+                    star = Some((token, index));
+                    continue;
+                }
+                Some(step) => {
+                    if let Some(len) = self.step(step, &string[index..]) {
+                        token += 1;
+                        index += len;
+                        continue;
+                    }
+                }
+                None if index == string.len() => return true,
+                None => {}
+            }
+
+            // Backtrack by letting the most recent star consume one more byte.
+            match star {
+                Some((next, start)) if start < string.len() => {
+                    star = Some((next, start + 1));
+                    token = next;
+                    index = start + 1;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Match a single token at the start of `string`, returning the number of bytes consumed.
+    fn step(&self, token: &Token, string: &[u8]) -> Option<usize> {
+        match token {
+            Token::Literal(bytes) => {
+                let prefix = string.get(..bytes.len())?;
+                let eq = if self.nocase {
+                    prefix.eq_ignore_ascii_case(bytes)
+                } else {
+                    prefix == &bytes[..]
+                };
+                eq.then_some(bytes.len())
+            }
+            Token::Any => string.first().map(|_| 1),
+            Token::Class(class) => {
+                let byte = usize::from(*string.first()?);
+                (class[byte / 64] & (1 << (byte % 64)) != 0).then_some(1)
+            }
+            Token::Star => None,
+        }
+    }
+}
+
+/// Parse a bracket expression, returning the set of bytes it matches and the rest of the pattern.
+/// An unterminated expression runs to the end of the pattern.
+fn class(pattern: &[u8], case: impl Fn(u8) -> u8) -> ([u64; 4], &[u8]) {
+    let (mut pattern, not) = match pattern {
+        [b'^', rest @ ..] => (rest, true),
+        _ => (pattern, false),
     };
 
-    let mut state = (pattern, string);
+    // Escaped bytes are matched exactly, even when ignoring case, as they are in Redis.
+    let mut exact = Vec::new();
+    let mut ranges = Vec::new();
     loop {
-        state = match state {
-            ([b'?', pattern @ ..], [_, string @ ..]) => (pattern, string),
-            ([b'*', pattern @ ..], _) => {
-                let pattern = trim_start(pattern, b'*');
-                return (0..=string.len()).any(|index| matches(&string[index..], pattern));
+        pattern = match pattern {
+            [b']', rest @ ..] => {
+                pattern = rest;
+                break;
             }
-            ([b'[', pattern @ ..], [c, string @ ..]) => {
-                if let Some(end) = brackets(pattern, *c) {
-                    (end, string)
+            [b'\\', c, rest @ ..] => {
+                exact.push(*c);
+                rest
+            }
+            [start, b'-', end, rest @ ..] => {
+                // Reversed ranges are swapped before case folding, so `[Z-a]` ignoring case is
+                // the empty range `z-a`.
+                let (start, end) = if start > end {
+                    (end, start)
                 } else {
-                    return false;
-                }
+                    (start, end)
+                };
+                ranges.push((case(*start), case(*end)));
+                rest
             }
-            ([b'\\', p, pattern @ ..] | [p, pattern @ ..], [c, string @ ..]) => {
-                if case(*p) != case(*c) {
-                    return false;
-                }
-                (pattern, string)
+            [c, rest @ ..] => {
+                ranges.push((case(*c), case(*c)));
+                rest
             }
-            ([], []) => return true,
-            (pattern, []) => return trim_start(pattern, b'*').is_empty(),
-            _ => return false,
-        };
+            [] => break,
+        }
     }
-}
 
-pub fn matches(string: &[u8], pattern: &[u8]) -> bool {
-    glob(string, pattern, |x| x)
-}
+    let mut class = [0; 4];
+    for byte in 0..=u8::MAX {
+        let matched = exact.contains(&byte)
+            || ranges
+                .iter()
+                .any(|&(start, end)| (start..=end).contains(&case(byte)));
+        if not ^ matched {
+            class[usize::from(byte) / 64] |= 1 << (byte % 64);
+        }
+    }
 
-pub fn matches_nocase(string: &[u8], pattern: &[u8]) -> bool {
-    glob(string, pattern, |x| x.to_ascii_lowercase())
+    (class, pattern)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn matches(string: &[u8], pattern: &[u8]) -> bool {
+        Pattern::compile(pattern).matches(string)
+    }
+
+    fn matches_nocase(string: &[u8], pattern: &[u8]) -> bool {
+        Pattern::compile_nocase(pattern).matches(string)
+    }
+
     #[test]
     fn eq() {
         assert!(matches(b"abc", b"abc"));
@@ -156,5 +290,224 @@ mod tests {
         assert!(matches_nocase(b"abc", b"ABC"));
         assert!(matches_nocase(b"abc", b"AB[C]"));
         assert!(matches_nocase(b"abc", b"AB[C-D]"));
+        assert!(matches_nocase(b"ABC", b"a*c"));
+        assert!(matches_nocase(b"aXbYc", b"A*B*C"));
+    }
+
+    #[test]
+    fn backtracking() {
+        assert!(matches(b"abcabd", b"*abd"));
+        assert!(matches(b"aaab", b"*a?b"));
+        assert!(matches(b"a-b-c", b"a*-*c"));
+        assert!(!matches(b"abcabc", b"*abd"));
+        assert!(!matches(b"ab", b"a*bc"));
+    }
+
+    #[test]
+    fn empty_brackets() {
+        assert!(!matches(b"a", b"[]"));
+        assert!(!matches(b"", b"[]"));
+        assert!(matches(b"a", b"[^]"));
+    }
+
+    #[test]
+    fn brackets_reversed() {
+        assert!(matches(b"c", b"[z-a]"));
+        assert!(!matches(b"C", b"[z-a]"));
+        assert!(matches_nocase(b"C", b"[z-a]"));
+        assert!(!matches_nocase(b"c", b"[Z-a]"));
+    }
+
+    #[test]
+    fn brackets_escapes_nocase() {
+        assert!(matches_nocase(b"a", b"[\\a]"));
+        assert!(!matches_nocase(b"A", b"[\\a]"));
+        assert!(matches_nocase(b"A", b"[a]"));
+    }
+
+    #[test]
+    fn brackets_special() {
+        assert!(!matches(b"]", b"[]]"));
+        assert!(matches(b"]", b"[\\]]"));
+        assert!(matches(b"^", b"[\\^]"));
+        // The closing bracket ends the reversed range `]-a` instead.
+        assert!(matches(b"^", b"[a-]"));
+        assert!(!matches(b"-", b"[a-]"));
+        assert!(matches(b"a", b"[^"));
+        assert!(!matches(b"a", b"a["));
+    }
+
+    #[test]
+    fn empty_string() {
+        assert!(matches(b"", b""));
+        assert!(!matches(b"", b"*"));
+        assert!(!matches(b"", b"?"));
+        assert!(matches(b"a", b"a*"));
+    }
+
+    #[test]
+    fn compiled() {
+        let pattern = Pattern::compile(b"user:*:name");
+        assert!(pattern.matches(b"user:1:name"));
+        assert!(pattern.matches(b"user::name"));
+        assert!(!pattern.matches(b"user:1:email"));
+        assert!(!pattern.matches(b"User:1:name"));
+
+        let pattern = Pattern::compile_nocase(b"User:*");
+        assert!(pattern.matches(b"user:1"));
+        assert!(pattern.matches(b"USER:1"));
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(Pattern::compile(b"news.*").prefix(), b"news.");
+        assert_eq!(Pattern::compile(b"n\\*ws?").prefix(), b"n*ws");
+        assert_eq!(Pattern::compile(b"*news").prefix(), b"");
+        assert_eq!(Pattern::compile(b"[ab]c").prefix(), b"");
+        assert_eq!(Pattern::compile_nocase(b"news.*").prefix(), b"");
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(miri))]
+mod proptests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    /// A direct port of `stringmatchlen` from Redis, to check the compiled matcher against.
+    fn stringmatchlen(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+        stringmatchlen_impl(pattern, string, nocase, &mut false)
+    }
+
+    fn stringmatchlen_impl(
+        mut pattern: &[u8],
+        mut string: &[u8],
+        nocase: bool,
+        skip_longer: &mut bool,
+    ) -> bool {
+        let lower = |byte: u8| {
+            if nocase {
+                byte.to_ascii_lowercase()
+            } else {
+                byte
+            }
+        };
+
+        while !pattern.is_empty() && !string.is_empty() {
+            match pattern[0] {
+                b'*' => {
+                    while pattern.get(1) == Some(&b'*') {
+                        pattern = &pattern[1..];
+                    }
+                    if pattern.len() == 1 {
+                        return true;
+                    }
+                    while !string.is_empty() {
+                        if stringmatchlen_impl(&pattern[1..], string, nocase, skip_longer) {
+                            return true;
+                        }
+                        if *skip_longer {
+                            return false;
+                        }
+                        string = &string[1..];
+                    }
+                    *skip_longer = true;
+                    return false;
+                }
+                b'?' => {
+                    pattern = &pattern[1..];
+                }
+                b'[' => {
+                    pattern = &pattern[1..];
+                    let not = pattern.first() == Some(&b'^');
+                    if not {
+                        pattern = &pattern[1..];
+                    }
+                    let mut matched = false;
+                    loop {
+                        pattern = match pattern {
+                            [b'\\', c, rest @ ..] => {
+                                matched |= *c == string[0];
+                                rest
+                            }
+                            [b']', rest @ ..] => {
+                                pattern = rest;
+                                break;
+                            }
+                            [] => break,
+                            [start, b'-', end, rest @ ..] => {
+                                let (start, end) = (start.min(end), start.max(end));
+                                matched |=
+                                    (lower(*start)..=lower(*end)).contains(&lower(string[0]));
+                                rest
+                            }
+                            [c, rest @ ..] => {
+                                matched |= lower(*c) == lower(string[0]);
+                                rest
+                            }
+                        }
+                    }
+                    if not == matched {
+                        return false;
+                    }
+                }
+                _ => {
+                    if let [b'\\', _, ..] = pattern {
+                        pattern = &pattern[1..];
+                    }
+                    if lower(pattern[0]) != lower(string[0]) {
+                        return false;
+                    }
+                    pattern = &pattern[1..];
+                }
+            }
+            string = &string[1..];
+
+            if string.is_empty() {
+                while pattern.first() == Some(&b'*') {
+                    pattern = &pattern[1..];
+                }
+                break;
+            }
+        }
+
+        pattern.is_empty() && string.is_empty()
+    }
+
+    /// Short patterns from a small alphabet, heavy on the bytes with special meaning.
+    fn pattern() -> impl Strategy<Value = Vec<u8>> {
+        vec(prop::sample::select(&b"aAbz-^[]\\*?"[..]), 0..10)
+    }
+
+    fn string() -> impl Strategy<Value = Vec<u8>> {
+        vec(prop::sample::select(&b"aAbz-^[]\\*?"[..]), 0..8)
+    }
+
+    proptest! {
+        #[test]
+        fn matches_reference(pattern in pattern(), string in string(), nocase: bool) {
+            let compiled = if nocase {
+                Pattern::compile_nocase(&pattern)
+            } else {
+                Pattern::compile(&pattern)
+            };
+            prop_assert_eq!(
+                compiled.matches(&string),
+                stringmatchlen(&pattern, &string, nocase),
+            );
+        }
+
+        #[test]
+        fn matches_reference_bytes(pattern: Vec<u8>, string: Vec<u8>, nocase: bool) {
+            let compiled = if nocase {
+                Pattern::compile_nocase(&pattern)
+            } else {
+                Pattern::compile(&pattern)
+            };
+            prop_assert_eq!(
+                compiled.matches(&string),
+                stringmatchlen(&pattern, &string, nocase),
+            );
+        }
     }
 }
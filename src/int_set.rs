@@ -1,6 +1,6 @@
 use crate::bytes::i64_len;
 use rand::Rng;
-use std::slice::Iter as SliceIter;
+use std::{cmp::Ordering, iter::Peekable, slice::Iter as SliceIter};
 
 /// A set of variable sized integers, stored in a `Vec`.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -52,8 +52,7 @@ impl IntSet {
         fn contains<T: Ord + TryFrom<i64>>(set: &[T], value: i64) -> bool {
             value
                 .try_into()
-                .map(|i| set.binary_search(&i).is_ok())
-                .unwrap_or(false)
+                .is_ok_and(|i| set.binary_search(&i).is_ok())
         }
 
         use IntSet::*;
@@ -67,12 +66,18 @@ impl IntSet {
 
     /// Insert `value`. Return `false` if it's already present.
     pub fn insert(&mut self, value: i64) -> bool {
-        fn convert<A: Copy, B: From<A>>(set: &Vec<A>, value: B) -> Vec<B> {
+        // A value that doesn't fit the current width is either smaller or larger than every
+        // value in the set, so it goes at the front or the back.
+        fn convert<A: Copy, B: From<A>>(set: &Vec<A>, value: B, front: bool) -> Vec<B> {
             let mut new: Vec<B> = Vec::with_capacity(set.len() + 1);
             for item in set {
                 new.push((*item).into());
             }
-            new.push(value);
+            if front {
+                new.insert(0, value);
+            } else {
+                new.push(value);
+            }
             new
         }
 
@@ -85,19 +90,21 @@ impl IntSet {
             }
         }
 
+        let front = value < 0;
+
         use IntSet::*;
         match self {
             I8(set) => {
                 if let Ok(value) = value.try_into() {
                     insert(set, value)
                 } else if let Ok(value) = value.try_into() {
-                    *self = I16(convert(set, value));
+                    *self = I16(convert(set, value, front));
                     true
                 } else if let Ok(value) = value.try_into() {
-                    *self = I32(convert(set, value));
+                    *self = I32(convert(set, value, front));
                     true
                 } else {
-                    *self = I64(convert(set, value));
+                    *self = I64(convert(set, value, front));
                     true
                 }
             }
@@ -105,10 +112,10 @@ impl IntSet {
                 if let Ok(value) = value.try_into() {
                     insert(set, value)
                 } else if let Ok(value) = value.try_into() {
-                    *self = I32(convert(set, value));
+                    *self = I32(convert(set, value, front));
                     true
                 } else {
-                    *self = I64(convert(set, value));
+                    *self = I64(convert(set, value, front));
                     true
                 }
             }
@@ -116,7 +123,7 @@ impl IntSet {
                 if let Ok(value) = value.try_into() {
                     insert(set, value)
                 } else {
-                    *self = I64(convert(set, value));
+                    *self = I64(convert(set, value, front));
                     true
                 }
             }
@@ -141,9 +148,9 @@ impl IntSet {
 
         use IntSet::*;
         let result = match self {
-            I8(set) => value.try_into().map(|i| remove(set, &i)).unwrap_or(false),
-            I16(set) => value.try_into().map(|i| remove(set, &i)).unwrap_or(false),
-            I32(set) => value.try_into().map(|i| remove(set, &i)).unwrap_or(false),
+            I8(set) => value.try_into().is_ok_and(|i| remove(set, &i)),
+            I16(set) => value.try_into().is_ok_and(|i| remove(set, &i)),
+            I32(set) => value.try_into().is_ok_and(|i| remove(set, &i)),
             I64(set) => remove(set, &value),
         };
         if result {
@@ -163,6 +170,15 @@ impl IntSet {
         }
     }
 
+    /// Return an iterator over the values in both sets, in order. Both sets are sorted, so they're
+    /// walked together without any lookups, and it ends as soon as either one runs out.
+    pub fn intersection<'a>(&'a self, other: &'a IntSet) -> Intersection<'a> {
+        Intersection {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+        }
+    }
+
     /// Pop a random value.
     pub fn pop(&mut self) -> Option<i64> {
         if self.is_empty() {
@@ -242,6 +258,35 @@ impl DoubleEndedIterator for Iter<'_> {
     }
 }
 
+/// An iterator over the values in two [`IntSet`]s.
+pub struct Intersection<'a> {
+    left: Peekable<Iter<'a>>,
+    right: Peekable<Iter<'a>>,
+}
+
+impl Iterator for Intersection<'_> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let left = *self.left.peek()?;
+            let right = *self.right.peek()?;
+            match left.cmp(&right) {
+                Ordering::Less => {
+                    self.left.next();
+                }
+                Ordering::Greater => {
+                    self.right.next();
+                }
+                Ordering::Equal => {
+                    self.right.next();
+                    return self.left.next();
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -291,6 +336,16 @@ mod tests {
         assert!(!set.is_empty());
     }
 
+    #[test]
+    fn upgrade_negative() {
+        let mut set = IntSet::default();
+        set.insert(0);
+        set.insert(-129);
+        assert!(set.contains(0));
+        assert!(set.contains(-129));
+        assert_eq!(set.iter().collect::<Vec<_>>(), [-129, 0]);
+    }
+
     #[test]
     fn remove() {
         let mut set = IntSet::default();
@@ -387,6 +442,21 @@ mod tests {
         set.insert(1_234_567_890);
         assert_eq!(10, set.longest());
     }
+
+    #[test]
+    fn intersection() {
+        let mut a = IntSet::default();
+        let mut b = IntSet::default();
+        for i in [1, 3, 5, 7] {
+            a.insert(i);
+        }
+        for i in [3, 4, 7, 100_000] {
+            b.insert(i);
+        }
+        assert_eq!(a.intersection(&b).collect::<Vec<_>>(), [3, 7]);
+        assert_eq!(b.intersection(&a).collect::<Vec<_>>(), [3, 7]);
+        assert_eq!(a.intersection(&IntSet::default()).count(), 0);
+    }
 }
 
 #[cfg(test)]
@@ -395,6 +465,33 @@ mod proptests {
     use super::*;
     use prop::sample::Index;
     use proptest::{collection::vec, prelude::*};
+    use std::collections::BTreeSet;
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert(i64),
+        Remove(i64),
+        Pop,
+    }
+
+    /// Values of every width, so that sets are upgraded along the way.
+    fn value() -> impl Strategy<Value = i64> {
+        prop_oneof![
+            4 => -8i64..8,
+            2 => any::<i8>().prop_map(i64::from),
+            1 => any::<i16>().prop_map(i64::from),
+            1 => any::<i32>().prop_map(i64::from),
+            1 => any::<i64>(),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            4 => value().prop_map(Op::Insert),
+            2 => value().prop_map(Op::Remove),
+            1 => Just(Op::Pop),
+        ]
+    }
 
     proptest! {
         #[test]
@@ -429,5 +526,42 @@ mod proptests {
                 prop_assert!(set.contains(items[n]));
             }
         }
+
+        #[test]
+        fn intersection(a in vec(value(), 0..50), b in vec(value(), 0..50)) {
+            let mut left = IntSet::default();
+            let mut right = IntSet::default();
+            for &value in &a {
+                left.insert(value);
+            }
+            for &value in &b {
+                right.insert(value);
+            }
+
+            let a: BTreeSet<i64> = a.into_iter().collect();
+            let b: BTreeSet<i64> = b.into_iter().collect();
+            prop_assert!(left.intersection(&right).eq(a.intersection(&b).copied()));
+        }
+
+        #[test]
+        fn model(ops in vec(op(), 1..200)) {
+            let mut set = IntSet::default();
+            let mut model = BTreeSet::new();
+
+            for op in ops {
+                match op {
+                    Op::Insert(value) => prop_assert_eq!(set.insert(value), model.insert(value)),
+                    Op::Remove(value) => prop_assert_eq!(set.remove(value), model.remove(&value)),
+                    Op::Pop => match set.pop() {
+                        Some(value) => prop_assert!(model.remove(&value)),
+                        None => prop_assert!(model.is_empty()),
+                    },
+                }
+
+                prop_assert_eq!(set.len(), model.len());
+                prop_assert!(set.iter().eq(model.iter().copied()));
+                prop_assert!(set.iter().rev().eq(model.iter().rev().copied()));
+            }
+        }
     }
 }
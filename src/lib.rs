@@ -9,13 +9,19 @@ mod glob;
 mod int_set;
 mod linked_hash_set;
 mod linked_list;
+mod ordered_map;
 mod pack;
 mod pubsub;
 mod quicklist;
+// Decoders for values encoded by Redis, used once RDB files can be loaded.
+#[allow(dead_code)]
+mod rdb;
 mod reply;
 mod request;
 mod reversible;
+mod script;
 mod server;
+mod sharded_map;
 mod skiplist;
 mod slice;
 mod spawn;
@@ -23,14 +29,18 @@ mod store;
 mod time;
 
 // Public interface
-pub use client::Addr;
+pub use client::{Addr, Endpoint};
+pub use config::{ConfigError, ConfigFileError};
 pub use server::Server;
+pub use store::{ExportEntry, ExportReceiver, ExportValue};
+#[cfg(feature = "key-events")]
+pub use store::{KeyEvent, KeyEventReceiver};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 use client::{Client, ClientId, ReplyMessage};
 use command::{BlockResult, Command, CommandResult};
-use db::{DBIndex, Set, StringValue};
+use db::{DBIndex, StringValue};
 use pack::{Iter as PackIter, Pack, PackRef, PackValue, Packable};
 use reply::{BulkReply, Reply, ReplyError};
 use reversible::Reversible;
@@ -338,6 +338,7 @@ impl<T> Cursor<'_, T> {
         }
     }
 
+    /// Insert `value` at the cursor and move past it, so that consecutive inserts keep their order.
     pub fn insert(&mut self, value: T) {
         self.list.len += 1;
         let new = Node {
@@ -362,6 +363,12 @@ impl<T> Cursor<'_, T> {
         } else {
             self.list.back = new;
         }
+
+        if self.reverse {
+            self.next = new;
+        } else {
+            self.prev = new;
+        }
     }
 }
 
@@ -540,18 +547,21 @@ mod tests {
     fn insert() {
         let mut list = LinkedList::default();
         list.push_back(0);
-        list.push_back(2);
+        list.push_back(3);
         assert_eq!(list.len(), 2);
 
         let mut cursor = list.cursor(Edge::Left);
         cursor.next();
         cursor.insert(1);
+        cursor.insert(2);
+        assert_eq!(cursor.next(), Some(&mut 3));
 
-        assert_eq!(list.len(), 3);
+        assert_eq!(list.len(), 4);
         let mut iter = list.iter();
         assert_eq!(iter.next(), Some(&0));
         assert_eq!(iter.next(), Some(&1));
         assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&3));
         assert_eq!(iter.next(), None);
     }
 
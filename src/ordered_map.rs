@@ -0,0 +1,302 @@
+use crate::db::KeyRef;
+use hashbrown::{DefaultHashBuilder, HashTable};
+use std::{
+    hash::{BuildHasher, Hash},
+    slice,
+    sync::LazyLock,
+};
+
+/// Every map hashes with the same seed, so the hasher doesn't take up room in each one.
+static HASHER: LazyLock<DefaultHashBuilder> = LazyLock::new(DefaultHashBuilder::default);
+
+fn hash_one<Q: Hash + ?Sized>(key: &Q) -> u64 {
+    HASHER.hash_one(key)
+}
+
+/// A hash map that iterates in insertion order. Entries are stored in a vector in the order they
+/// were inserted, and a hash table maps each key to its position. Replacing the value for an
+/// existing key keeps its position, which matches how a [`PackMap`][`crate::pack::PackMap`]
+/// behaves.
+///
+/// Removing an entry leaves an empty slot behind so later positions stay valid. Once more than
+/// half of the slots are empty, the entries are moved down and the table is rebuilt.
+#[derive(Clone)]
+pub struct OrderedMap<K, V> {
+    entries: Vec<Option<(K, V)>>,
+    index: HashTable<usize>,
+}
+
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        OrderedMap {
+            entries: Vec::new(),
+            index: HashTable::new(),
+        }
+    }
+}
+
+/// Like a [`HashMap`][`hashbrown::HashMap`], equality ignores order.
+impl<K: Eq + Hash, V: PartialEq> PartialEq for OrderedMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K, V> OrderedMap<K, V> {
+    /// Is this map empty?
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The number of entries in the map
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// An iterator over the entries of the map, in insertion order
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            entries: self.entries.iter(),
+            len: self.len(),
+        }
+    }
+
+    /// An iterator over the keys of the map, in insertion order
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// An iterator over the values of the map, in insertion order
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<K: Eq + Hash, V> OrderedMap<K, V> {
+    pub fn new() -> Self {
+        OrderedMap::default()
+    }
+
+    /// Create an empty map with room for at least `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        OrderedMap {
+            entries: Vec::with_capacity(capacity),
+            index: HashTable::with_capacity(capacity),
+        }
+    }
+
+    /// Find the position of `key` in `entries`.
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let entries = &self.entries;
+        self.index
+            .find(hash_one(key), |&i| {
+                entries[i].as_ref().is_some_and(|(k, _)| key.equivalent(k))
+            })
+            .copied()
+    }
+
+    /// Does the map contain `key`?
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        self.position(key).is_some()
+    }
+
+    /// Get the value for `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let i = self.position(key)?;
+        self.entries[i].as_ref().map(|(_, value)| value)
+    }
+
+    /// Get a mutable reference to the value for `key`.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let i = self.position(key)?;
+        self.entries[i].as_mut().map(|(_, value)| value)
+    }
+
+    /// Insert an entry at the back, or replace the value in place if `key` is already present.
+    /// Return the previous value, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(current) = self.get_mut(&key) {
+            return Some(std::mem::replace(current, value));
+        }
+
+        let entries = &self.entries;
+        self.index
+            .insert_unique(hash_one(&key), entries.len(), |&i| {
+                entries[i].as_ref().map_or(0, |(k, _)| hash_one(k))
+            });
+        self.entries.push(Some((key, value)));
+        None
+    }
+
+    /// Remove the entry for `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let entries = &self.entries;
+        let i = self
+            .index
+            .find_entry(hash_one(key), |&i| {
+                entries[i].as_ref().is_some_and(|(k, _)| key.equivalent(k))
+            })
+            .ok()?
+            .remove()
+            .0;
+        let (_, value) = self.entries[i].take()?;
+
+        // Empty slots at the back can go right away.
+        while let Some(None) = self.entries.last() {
+            self.entries.pop();
+        }
+
+        if self.entries.len() > self.len() * 2 {
+            self.compact();
+        }
+
+        Some(value)
+    }
+
+    /// Move every entry down over the empty slots and rebuild the index.
+    fn compact(&mut self) {
+        self.entries.retain(Option::is_some);
+        self.index.clear();
+        let entries = &self.entries;
+        for (i, entry) in entries.iter().enumerate() {
+            if let Some((key, _)) = entry {
+                self.index.insert_unique(hash_one(key), i, |&i| {
+                    entries[i].as_ref().map_or(0, |(k, _)| hash_one(k))
+                });
+            }
+        }
+    }
+}
+
+/// An iterator over the entries of an [`OrderedMap`].
+pub struct Iter<'a, K, V> {
+    entries: slice::Iter<'a, Option<(K, V)>>,
+    len: usize,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.entries.by_ref().flatten().next()?;
+        self.len -= 1;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K: 'a, V: 'a> ExactSizeIterator for Iter<'a, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_order() {
+        let mut map: OrderedMap<i64, &str> = OrderedMap::new();
+        map.insert(3, "c");
+        map.insert(1, "a");
+        map.insert(2, "b");
+        let keys: Vec<_> = map.keys().copied().collect();
+        assert_eq!(keys, [3, 1, 2]);
+
+        // Replacing a value keeps its position
+        assert_eq!(map.insert(1, "x"), Some("a"));
+        let entries: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(entries, [(3, "c"), (1, "x"), (2, "b")]);
+
+        // Removing and inserting again moves it to the back
+        assert_eq!(map.remove(&3), Some("c"));
+        map.insert(3, "c");
+        let keys: Vec<_> = map.keys().copied().collect();
+        assert_eq!(keys, [1, 2, 3]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.iter().len(), 3);
+    }
+
+    #[test]
+    fn remove_all() {
+        let mut map: OrderedMap<i64, i64> = OrderedMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+        assert_eq!(map.remove(&2), Some(20));
+        assert_eq!(map.remove(&1), Some(10));
+        assert_eq!(map.remove(&1), None);
+        assert!(map.is_empty());
+        assert!(map.entries.is_empty());
+        assert_eq!(map.iter().count(), 0);
+    }
+
+    #[test]
+    fn compact() {
+        let mut map: OrderedMap<i64, i64> = OrderedMap::new();
+        for i in 0..100 {
+            map.insert(i, i * 10);
+        }
+
+        // Remove from the front and the middle, leaving the back in place
+        for i in (0..90).filter(|i| i % 10 != 5) {
+            assert_eq!(map.remove(&i), Some(i * 10));
+            assert!(map.entries.len() <= map.len() * 2);
+        }
+
+        let keys: Vec<_> = map.keys().copied().collect();
+        let expected: Vec<_> = (0..100).filter(|&i| i >= 90 || i % 10 == 5).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(map.iter().len(), expected.len());
+        for i in 0..100 {
+            assert_eq!(map.get(&i), expected.contains(&i).then_some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut map: OrderedMap<Vec<u8>, i64> = OrderedMap::new();
+        map.insert(b"foo".to_vec(), 1);
+        *map.get_mut(&b"foo"[..]).unwrap() += 1;
+        assert_eq!(map.get(&b"foo"[..]), Some(&2));
+        assert!(map.contains_key(&b"foo"[..]));
+        assert!(!map.contains_key(&b"bar"[..]));
+    }
+
+    #[test]
+    fn eq_ignores_order() {
+        let mut a: OrderedMap<i64, i64> = OrderedMap::new();
+        a.insert(1, 1);
+        a.insert(2, 2);
+        let mut b = OrderedMap::new();
+        b.insert(2, 2);
+        b.insert(1, 1);
+        assert_eq!(a, b);
+        assert_eq!(a, a.clone());
+        b.insert(1, 3);
+        assert_ne!(a, b);
+    }
+}
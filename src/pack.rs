@@ -4,14 +4,16 @@ mod packable;
 mod r#ref;
 mod set;
 mod sorted_set;
+mod splice;
 mod value;
 
 pub use list::{PackList, PackListInsert};
 pub use map::PackMap;
 pub use packable::Packable;
 pub use r#ref::PackRef;
-pub use set::PackSet;
+pub use set::{Intersection as PackSetIntersection, PackSet};
 pub use sorted_set::PackSortedSet;
+pub use splice::RawSplice;
 pub use value::PackValue;
 
 use crate::db::{Edge, Raw};
@@ -23,6 +25,7 @@ use bytes::Buf;
 ///
 /// * The length and size is stored in the header instead of in the data.
 /// * Has a dedicated tag for f64 rather than storing as i64.
+/// * Has dedicated tags for nil, false, and true, so they aren't confused with strings.
 /// * Does not append an end byte for detecting the end of the data.
 #[derive(Clone, Eq, PartialEq)]
 pub struct Pack {
@@ -81,6 +84,35 @@ impl Pack {
         self.len += 1;
     }
 
+    /// Add `values` to the beginning of the pack, as if each were prepended in turn, moving the
+    /// existing data only once.
+    pub fn prepend_many<T>(&mut self, values: &[T])
+    where
+        T: Packable,
+    {
+        let size = values.iter().map(Packable::pack_size).sum();
+        self.len += values.len();
+        RawSplice::new(self.make_mut(), 0..0, size).write(|data| {
+            for value in values.iter().rev() {
+                value.pack_write(&mut *data);
+            }
+        });
+    }
+
+    /// Add `values` to the end of the pack, reserving space for all of them at once.
+    pub fn append_many<T>(&mut self, values: &[T])
+    where
+        T: Packable,
+    {
+        let size = values.iter().map(Packable::pack_size).sum();
+        let data = self.make_mut();
+        data.reserve(size);
+        for value in values {
+            value.pack_write(&mut *data);
+        }
+        self.len += values.len();
+    }
+
     /// Add two values to the end of the pack, done together to prevent reallocating twice.
     pub fn append2<A, B>(&mut self, a: &A, b: &B)
     where
@@ -164,6 +196,9 @@ impl Pack {
                 all.advance(1);
                 Float(all.get_f64_le())
             }
+            0xf6 => Nil,
+            0xf7 => Bool(false),
+            0xf8 => Bool(true),
             _ => panic!("unknown pack encoding"),
         };
 
@@ -220,42 +255,28 @@ impl Pack {
         }
     }
 
+    /// Replace the `old_size` byte value at `offset` and return the size of the new one.
+    fn replace_at<V: Packable>(&mut self, offset: usize, old_size: usize, value: &V) -> usize {
+        let new_size = value.pack_size();
+        let data = self.make_mut();
+
+        if old_size == new_size {
+            value.pack_write(&mut data[offset..]);
+        } else {
+            RawSplice::new(data, offset..offset + old_size, new_size)
+                .write(|data| value.pack_write(data));
+        }
+
+        new_size
+    }
+
     /// Move an element from one edge to the other.
     pub fn mv(&mut self, from: Edge) {
-        let mut cursor = self.cursor(from);
-        let element = cursor.peek().unwrap().to_owned().clone();
-        let size = element.pack_size();
+        let size = self.cursor(from).peek().unwrap().size();
         let data = self.make_mut();
-        let len = data.len();
-        data.reserve(size);
-
         match from {
-            Edge::Left => {
-                unsafe {
-                    // Move the first element to the end.
-                    let from = data.as_mut_ptr();
-                    let to = from.add(len);
-                    from.copy_to(to, size);
-
-                    // Move everything back
-                    let to = data.as_mut_ptr();
-                    let from = to.add(size);
-                    from.copy_to(to, len);
-                }
-            }
-            Edge::Right => {
-                unsafe {
-                    // Create space on the left
-                    let from = data.as_mut_ptr();
-                    let to = from.add(size);
-                    from.copy_to(to, len);
-
-                    // Copy the last element from the right to the left.
-                    let to = data.as_mut_ptr();
-                    let from = to.add(len);
-                    from.copy_to(to, size);
-                }
-            }
+            Edge::Left => data.rotate_left(size),
+            Edge::Right => data.rotate_right(size),
         }
     }
 }
@@ -470,20 +491,9 @@ impl Cursor<'_> {
         A: Packable,
     {
         self.pack.len += 1;
-        let size = a.pack_size();
-        let mut data = self.pack.make_mut();
-        data.reserve(size);
-        let tail_len = data.len() - self.offset;
-        unsafe {
-            let from = data.as_mut_ptr().add(self.offset);
-            let to = from.add(size);
-            from.copy_to(to, tail_len);
-            data.set_len(self.offset);
-        }
-        a.pack_write(&mut data);
-        unsafe {
-            data.set_len(self.offset + size + tail_len);
-        }
+        let offset = self.offset;
+        RawSplice::new(self.pack.make_mut(), offset..offset, a.pack_size())
+            .write(|data| a.pack_write(data));
     }
 
     /// Insert two values at the current index together to avoid reallocating twice.
@@ -493,21 +503,12 @@ impl Cursor<'_> {
         B: Packable,
     {
         self.pack.len += 2;
+        let offset = self.offset;
         let size = a.pack_size() + b.pack_size();
-        let mut data = self.pack.make_mut();
-        data.reserve(size);
-        let tail_len = data.len() - self.offset;
-        unsafe {
-            let from = data.as_mut_ptr().add(self.offset);
-            let to = from.add(size);
-            from.copy_to(to, tail_len);
-            data.set_len(self.offset);
-        }
-        a.pack_write(&mut data);
-        b.pack_write(&mut data);
-        unsafe {
-            data.set_len(self.offset + size + tail_len);
-        }
+        RawSplice::new(self.pack.make_mut(), offset..offset, size).write(|data| {
+            a.pack_write(&mut *data);
+            b.pack_write(data);
+        });
     }
 
     /// Replace the value at the current index.
@@ -520,28 +521,11 @@ impl Cursor<'_> {
         } else {
             self.offset
         };
-        let new_size = value.pack_size();
-        let mut data = self.pack.make_mut();
-
-        if old_size == new_size {
-            value.pack_write(&mut data[offset..]);
-            return;
-        }
+        let new_size = self.pack.replace_at(offset, old_size, value);
 
-        if let Some(delta) = new_size.checked_sub(old_size) {
-            data.reserve(delta);
-        }
-        let tail_len = data.len() - self.offset - old_size;
-        unsafe {
-            let start = data.as_mut_ptr().add(self.offset);
-            let from = start.add(old_size);
-            let to = start.add(new_size);
-            from.copy_to(to, tail_len);
-            data.set_len(offset);
-        }
-        value.pack_write(&mut data);
-        unsafe {
-            data.set_len(offset + new_size + tail_len);
+        // In reverse, the cursor sits just after the value it replaced.
+        if self.reverse {
+            self.offset = offset + new_size;
         }
     }
 }
@@ -853,6 +837,52 @@ mod tests {
         assert_eq!(iterator.next(), None);
     }
 
+    #[test]
+    fn write_fixed_values() {
+        let mut pack = Pack::default();
+        pack.append(&());
+        pack.append(&false);
+        pack.append(&true);
+        assert_eq!(&pack.data[..], &b"\xf6\x01\xf7\x01\xf8\x01"[..]);
+        assert_eq!(pack.len(), 3);
+    }
+
+    #[test]
+    fn read_fixed_values() {
+        let mut pack = Pack::default();
+        pack.append(&());
+        pack.append(&false);
+        pack.append(&true);
+        pack.append(&"");
+        pack.append(&"1");
+        let mut iterator = pack.iter().rev();
+        assert_eq!(iterator.next(), Some(1.into()));
+        assert_eq!(iterator.next().map(|value| value.size()), Some(2));
+        assert_eq!(iterator.next(), Some(true.into()));
+        assert_eq!(iterator.next(), Some(false.into()));
+        assert_eq!(iterator.next(), Some(PackRef::Nil));
+        assert_eq!(iterator.next(), None);
+    }
+
+    #[test]
+    fn fixed_values_are_not_strings() {
+        let mut pack = Pack::default();
+        pack.append(&());
+        pack.append(&true);
+        let mut iterator = pack.iter();
+        let nil = iterator.next().unwrap();
+        assert!(!"".pack_eq(&nil));
+        assert!(().pack_eq(&nil));
+        let t = iterator.next().unwrap();
+        assert!(!1i64.pack_eq(&t));
+        assert!(!"1".pack_eq(&t));
+        assert!(true.pack_eq(&t));
+        assert!(!false.pack_eq(&t));
+        assert_eq!(t.integer(), Some(1));
+        assert_eq!(&t.raw()[..], b"1");
+        assert_eq!(&nil.raw()[..], b"");
+    }
+
     #[test]
     fn test_iterator() {
         let mut pack = Pack::default();
@@ -1121,6 +1151,23 @@ mod tests {
         assert_eq!(iterator.next(), None);
     }
 
+    #[test]
+    fn replace_middle_reverse() {
+        let mut pack = Pack::default();
+        pack.append(&1);
+        pack.append(&2);
+        pack.append(&3);
+        let mut cursor = pack.cursor(Edge::Right);
+        cursor.skip(1);
+        cursor.replace(&"x".repeat(200).as_str());
+        let mut iterator = pack.iter();
+        assert_eq!(iterator.next(), Some(1.into()));
+        let x = "x".repeat(200);
+        assert!(x.as_str().pack_eq(&iterator.next().unwrap()));
+        assert_eq!(iterator.next(), Some(3.into()));
+        assert_eq!(iterator.next(), None);
+    }
+
     #[test]
     fn cursor_prev() {
         let mut pack = Pack::default();
@@ -1299,7 +1346,127 @@ mod proptests {
     use super::*;
     use proptest::{collection::vec, prelude::*};
 
+    #[derive(Clone, Debug)]
+    enum Op {
+        Next,
+        Prev,
+        Insert(Vec<u8>),
+        Remove(usize),
+        Replace(Vec<u8>),
+    }
+
+    /// Short strings, integers, and longer strings, so values use every encoding.
+    fn value() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            vec(b'a'..=b'z', 0..5),
+            any::<i64>().prop_map(|i| i.to_string().into_bytes()),
+            vec(any::<u8>(), 60..200),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            3 => Just(Op::Next),
+            2 => Just(Op::Prev),
+            2 => value().prop_map(Op::Insert),
+            1 => (0usize..3).prop_map(Op::Remove),
+            1 => value().prop_map(Op::Replace),
+        ]
+    }
+
+    fn assert_pack_eq(pack: &Pack, model: &[Vec<u8>]) -> Result<(), TestCaseError> {
+        prop_assert_eq!(pack.len(), model.len());
+        prop_assert_eq!(pack.iter().count(), model.len());
+        prop_assert!(
+            model
+                .iter()
+                .zip(pack.iter())
+                .all(|(a, b)| (&&a[..]).pack_eq(&b))
+        );
+        prop_assert!(
+            model
+                .iter()
+                .rev()
+                .zip(pack.iter().rev())
+                .all(|(a, b)| (&&a[..]).pack_eq(&b))
+        );
+        Ok(())
+    }
+
     proptest! {
+        #[test]
+        fn cursor(
+            mut model in vec(value(), 0..10),
+            ops in vec(op(), 1..50),
+            reverse in any::<bool>(),
+            split in any::<bool>(),
+        ) {
+            let mut pack = Pack::default();
+            for item in &model {
+                pack.append(&&item[..]);
+            }
+
+            // The model cursor is an index between values, counted from the left.
+            let mut index = if reverse { model.len() } else { 0 };
+            let mut cursor = pack.cursor(if reverse { Edge::Right } else { Edge::Left });
+
+            for op in ops {
+                // The value the cursor will consume next, if any.
+                let current = if reverse { index.checked_sub(1) } else { Some(index) }
+                    .filter(|&i| i < model.len());
+
+                match op {
+                    Op::Next | Op::Prev => {
+                        let forward = matches!(op, Op::Next) != reverse;
+                        let expected = if forward {
+                            model.get(index).inspect(|_| index += 1)
+                        } else {
+                            index.checked_sub(1).inspect(|i| index = *i).map(|i| &model[i])
+                        };
+                        let actual = if matches!(op, Op::Next) { cursor.next() } else { cursor.prev() };
+                        match (expected, actual) {
+                            (Some(a), Some(b)) => prop_assert!((&&a[..]).pack_eq(&b)),
+                            (None, None) => index = if forward { 0 } else { model.len() },
+                            (a, b) => prop_assert!(false, "expected {:?}, got {:?}", a, b),
+                        }
+                    }
+                    Op::Insert(value) => {
+                        // The new value goes to the right of the cursor in either direction.
+                        cursor.insert(&&value[..]);
+                        model.insert(index, value);
+                    }
+                    Op::Remove(count) => {
+                        cursor.remove(count);
+                        if reverse {
+                            let start = index.saturating_sub(count);
+                            model.drain(start..index);
+                            index = start;
+                        } else {
+                            let end = std::cmp::min(index + count, model.len());
+                            model.drain(index..end);
+                        }
+                    }
+                    Op::Replace(value) => {
+                        cursor.replace(&&value[..]);
+                        if let Some(i) = current {
+                            model[i] = value;
+                        }
+                    }
+                }
+
+                prop_assert_eq!(cursor.index(), index);
+                prop_assert_eq!(cursor.len(), model.len());
+            }
+
+            if split {
+                let right = cursor.split();
+                let expected = model.split_off(index);
+                assert_pack_eq(&right, &expected)?;
+            }
+
+            assert_pack_eq(&pack, &model)?;
+        }
+
         #[test]
         fn append_one(expected in vec(any::<u8>(), 1..250)) {
             let mut pack = Pack::default();
@@ -28,6 +28,12 @@ where
     }
 }
 
+impl From<Pack> for PackList {
+    fn from(pack: Pack) -> Self {
+        Self { pack }
+    }
+}
+
 /// The result of attempting to insert a value into a [`PackList`]. The value may actually be
 /// inserted, or the result may indicate the position at which the value should be inserted.
 #[must_use]
@@ -95,6 +101,32 @@ impl PackList {
         true
     }
 
+    /// Push as many of `values` as fit onto the appropriate `edge`, in order, and return how many
+    /// were pushed.
+    pub fn push_many<V>(&mut self, values: &[V], edge: Edge, max: i64) -> usize
+    where
+        V: Packable,
+    {
+        let mut len = self.len();
+        let mut size = self.size();
+        let count = values
+            .iter()
+            .take_while(|value| {
+                len += 1;
+                size += value.pack_size();
+                list_is_valid(len, size, max)
+            })
+            .count();
+
+        let values = &values[..count];
+        match edge {
+            Edge::Left => self.pack.prepend_many(values),
+            Edge::Right => self.pack.append_many(values),
+        }
+
+        count
+    }
+
     /// If `index` exists, set the value and return `true`. Otherwise return `false`.
     pub fn set<V>(&mut self, value: &V, index: usize) -> bool
     where
@@ -206,6 +238,29 @@ impl PackList {
         self.pack.cursor(edge).remove(count);
     }
 
+    /// Remove at most `count` values from the `edge` of the list, calling `f` with each one in
+    /// order from that edge before they're trimmed. Return the number removed.
+    pub fn pop_many<F>(&mut self, edge: Edge, count: usize, f: F) -> usize
+    where
+        F: FnMut(PackRef<'_>),
+    {
+        let count = count.min(self.len());
+        self.iter_from(edge).take(count).for_each(f);
+        self.trim(edge, count);
+        count
+    }
+
+    /// Split this list into consecutive lists that are each valid for `max`.
+    pub fn split(&self, max: i64) -> Vec<PackList> {
+        let mut packs = vec![PackList::default()];
+        for value in self.iter() {
+            if !packs.last_mut().unwrap().push(&value, Edge::Right, max) {
+                packs.push((&value).into());
+            }
+        }
+        packs
+    }
+
     /// Move an element from one edge to the other.
     pub fn mv(&mut self, from: Edge) {
         self.pack.mv(from);
@@ -235,6 +290,23 @@ mod tests {
         assert_eq!(expected, list.pack);
     }
 
+    #[test]
+    fn test_push_many() {
+        let mut list = PackList::default();
+        list.push(&3, Edge::Right, 4);
+
+        assert_eq!(list.push_many(&[2, 1], Edge::Left, 4), 2);
+        assert_eq!(list.push_many(&[4, 5, 6], Edge::Right, 4), 1);
+        assert_eq!(list.push_many(&[0], Edge::Left, 4), 0);
+
+        let mut expected = Pack::default();
+        for value in 1..=4 {
+            expected.append(&value);
+        }
+
+        assert_eq!(expected, list.pack);
+    }
+
     #[test]
     fn test_peek() {
         let mut list = PackList::default();
@@ -333,6 +405,17 @@ mod tests {
         assert_eq!(expected, list.pack);
     }
 
+    #[test]
+    fn test_split() {
+        let mut list = PackList::default();
+        for i in 0..5 {
+            list.push(&i, Edge::Right, -2);
+        }
+
+        let packs = list.split(2);
+        assert_eq!(format!("{packs:?}"), "[[0, 1], [2, 3], [4]]");
+    }
+
     #[test]
     fn debug() {
         let mut list = PackList::default();
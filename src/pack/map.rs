@@ -1,11 +1,29 @@
 use crate::{Pack, PackIter, PackRef, Packable, db::Edge};
 
+/// Maps with at least this many pairs keep an index of their keys.
+const INDEX_LEN: usize = 16;
+
 /// A Redis map, stored in a [`Pack`] to improve memory usage and locality. Keys and values are
 /// stored in an alternating pattern, key first.
-#[derive(Clone, Default, Eq, PartialEq)]
+#[derive(Clone, Default)]
 pub struct PackMap {
     /// The [`Pack`] where the values are stored.
     pack: Pack,
+
+    /// The offsets of the keys in `pack`, sorted by their packed bytes so that lookups can binary
+    /// search rather than compare every key in turn. Small maps are quick enough to scan, so
+    /// this is empty until a map reaches [`INDEX_LEN`] pairs.
+    ///
+    /// Keys are always strings, which pack to the same bytes exactly when they're equal.
+    index: Vec<usize>,
+}
+
+impl Eq for PackMap {}
+
+impl PartialEq for PackMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.pack == other.pack
+    }
 }
 
 impl std::fmt::Debug for PackMap {
@@ -31,7 +49,11 @@ impl PackMap {
     where
         K: Packable,
     {
-        self.iter().any(|(other, _)| key.pack_eq(&other))
+        if self.index.is_empty() {
+            self.iter().any(|(other, _)| key.pack_eq(&other))
+        } else {
+            self.search(key).is_ok()
+        }
     }
 
     /// Get the value for a `key`.
@@ -39,9 +61,16 @@ impl PackMap {
     where
         K: Packable,
     {
-        self.iter()
-            .find(|(other, _)| key.pack_eq(other))
-            .map(|(_, value)| value)
+        if self.index.is_empty() {
+            return self
+                .iter()
+                .find(|(other, _)| key.pack_eq(other))
+                .map(|(_, value)| value);
+        }
+
+        let offset = self.index[self.search(key).ok()?];
+        let (_, next) = self.pack.read(offset)?;
+        self.pack.read(next).map(|(value, _)| value)
     }
 
     /// Remove the value for a `key`. Return `true` if it was removed.
@@ -49,15 +78,31 @@ impl PackMap {
     where
         K: Packable,
     {
-        let mut cursor = self.pack.cursor(Edge::Left);
-        while let Some(element) = cursor.peek() {
-            if key.pack_eq(&element) {
-                cursor.remove(2);
-                return true;
+        if self.index.is_empty() {
+            let mut cursor = self.pack.cursor(Edge::Left);
+            while let Some(element) = cursor.peek() {
+                if key.pack_eq(&element) {
+                    cursor.remove(2);
+                    return true;
+                }
+                cursor.skip(2);
             }
-            cursor.skip(2);
+            return false;
         }
-        false
+
+        let Ok(position) = self.search(key) else {
+            return false;
+        };
+        let offset = self.index.remove(position);
+        let end = self.pair_end(offset);
+        self.pack.make_mut().drain(offset..end);
+        self.pack.len -= 2;
+        self.shift(offset, end - offset, 0);
+
+        if self.len() < INDEX_LEN {
+            self.index = Vec::new();
+        }
+        true
     }
 
     /// Insert a `key` `value` pair into the map. Return `true` if it didn't already exist.
@@ -66,19 +111,94 @@ impl PackMap {
         K: Packable,
         V: Packable,
     {
-        let mut cursor = self.pack.cursor(Edge::Left);
+        if self.index.is_empty() {
+            let mut cursor = self.pack.cursor(Edge::Left);
 
-        while let Some(other) = cursor.peek() {
-            if key.pack_eq(&other) {
-                cursor.next();
-                cursor.replace(value);
-                return false;
+            while let Some(other) = cursor.peek() {
+                if key.pack_eq(&other) {
+                    cursor.next();
+                    cursor.replace(value);
+                    return false;
+                }
+                cursor.skip(2);
             }
-            cursor.skip(2);
+
+            self.pack.append2(key, value);
+            if self.len() >= INDEX_LEN {
+                self.reindex();
+            }
+            return true;
         }
 
-        self.pack.append2(key, value);
-        true
+        match self.search(key) {
+            Ok(position) => {
+                let offset = self.index[position];
+                let offset = offset + self.packed(offset).len();
+                let old_size = self.packed(offset).len();
+                let new_size = self.pack.replace_at(offset, old_size, value);
+                self.shift(offset, old_size, new_size);
+                false
+            }
+            Err(position) => {
+                self.index.insert(position, self.pack.size());
+                self.pack.append2(key, value);
+                true
+            }
+        }
+    }
+
+    /// Find `key` in the index, returning its position or the position where it belongs.
+    fn search<K>(&self, key: &K) -> Result<usize, usize>
+    where
+        K: Packable,
+    {
+        // Most keys are short enough to pack on the stack.
+        let size = key.pack_size();
+        let mut stack = [0; 64];
+        let mut heap = Vec::new();
+        let packed = if size <= stack.len() {
+            &mut stack[..size]
+        } else {
+            heap.resize(size, 0);
+            &mut heap[..]
+        };
+        key.pack_write(&mut *packed);
+
+        self.index
+            .binary_search_by(|&offset| self.packed(offset).cmp(packed))
+    }
+
+    /// The packed bytes of the value at `offset`.
+    fn packed(&self, offset: usize) -> &[u8] {
+        let end = self.pack.read(offset).map_or(offset, |(_, next)| next);
+        &self.pack.data[offset..end]
+    }
+
+    /// The offset just past the pair whose key is at `offset`.
+    fn pair_end(&self, offset: usize) -> usize {
+        let value = offset + self.packed(offset).len();
+        value + self.packed(value).len()
+    }
+
+    /// Adjust the index after `old_size` bytes at `offset` were replaced with `new_size` bytes.
+    fn shift(&mut self, offset: usize, old_size: usize, new_size: usize) {
+        for key in &mut self.index {
+            if *key > offset {
+                *key = *key - old_size + new_size;
+            }
+        }
+    }
+
+    /// Build the index from scratch.
+    fn reindex(&mut self) {
+        let mut index = Vec::with_capacity(self.len());
+        let mut offset = 0;
+        while offset < self.pack.size() {
+            index.push(offset);
+            offset = self.pair_end(offset);
+        }
+        index.sort_unstable_by(|&a, &b| self.packed(a).cmp(self.packed(b)));
+        self.index = index;
     }
 
     /// Return an iterator over each key value pair in this map.
@@ -134,6 +254,49 @@ mod tests {
         assert_eq!(map.get(&"foo"), None);
     }
 
+    #[test]
+    fn index() {
+        let mut map = PackMap::default();
+        let keys: Vec<String> = (0..INDEX_LEN).map(|i| format!("key:{i}")).collect();
+        for key in &keys {
+            assert!(map.insert(&key.as_bytes(), &"x"));
+        }
+        map.insert(&"10", &"ten");
+        assert_eq!(map.index.len(), INDEX_LEN + 1);
+
+        // Values that change size move the keys after them.
+        assert!(!map.insert(&"key:0".as_bytes(), &"x".repeat(100).as_bytes()));
+        assert!(!map.insert(&"key:1".as_bytes(), &5));
+        for key in &keys[2..] {
+            assert!(map.get(&key.as_bytes()).is_some_and(|v| (&"x").pack_eq(&v)));
+        }
+        assert!(map.get(&10).is_some_and(|v| (&"ten").pack_eq(&v)));
+        assert_eq!(map.get(&"key:1".as_bytes()), Some(PackRef::Integer(5)));
+        assert!(!map.contains_key(&"key:x".as_bytes()));
+
+        // Fields keep their insertion order.
+        {
+            let mut keys = map.keys();
+            assert!(keys.next().is_some_and(|k| (&"key:0").pack_eq(&k)));
+            assert!(keys.next().is_some_and(|k| (&"key:1").pack_eq(&k)));
+        }
+
+        assert!(map.remove(&"key:0".as_bytes()));
+        assert!(!map.remove(&"key:0".as_bytes()));
+        assert!(
+            map.get(&"key:2".as_bytes())
+                .is_some_and(|v| (&"x").pack_eq(&v))
+        );
+        assert_eq!(map.index.len(), INDEX_LEN);
+
+        assert!(map.remove(&"key:1".as_bytes()));
+        assert!(map.index.is_empty());
+        assert!(
+            map.get(&"10".as_bytes())
+                .is_some_and(|v| (&"ten").pack_eq(&v))
+        );
+    }
+
     #[test]
     fn debug() {
         let mut map = PackMap::default();
@@ -143,3 +306,77 @@ mod tests {
         assert_eq!(s, "{\"foo\": \"bar\", 2: 5}");
     }
 }
+
+#[cfg(test)]
+#[cfg(not(miri))]
+mod proptests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert(Vec<u8>, Vec<u8>),
+        Remove(Vec<u8>),
+    }
+
+    /// Enough keys to cross the index threshold in both directions.
+    fn key() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            (0..40).prop_map(|i: i32| i.to_string().into_bytes()),
+            vec(b'a'..=b'c', 0..3),
+        ]
+    }
+
+    /// Values of every size, so that replacing one moves the keys after it.
+    fn value() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            vec(b'a'..=b'z', 0..5),
+            any::<i64>().prop_map(|i| i.to_string().into_bytes()),
+            vec(any::<u8>(), 60..100),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            3 => (key(), value()).prop_map(|(key, value)| Op::Insert(key, value)),
+            1 => key().prop_map(Op::Remove),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn matches_model(ops in vec(op(), 0..200)) {
+            let mut map = PackMap::default();
+            let mut model: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+
+            for op in ops {
+                match op {
+                    Op::Insert(key, value) => {
+                        let position = model.iter().position(|(k, _)| *k == key);
+                        prop_assert_eq!(map.insert(&&key[..], &&value[..]), position.is_none());
+                        match position {
+                            Some(i) => model[i].1 = value,
+                            None => model.push((key, value)),
+                        }
+                    }
+                    Op::Remove(key) => {
+                        let position = model.iter().position(|(k, _)| *k == key);
+                        prop_assert_eq!(map.remove(&&key[..]), position.is_some());
+                        if let Some(i) = position {
+                            model.remove(i);
+                        }
+                    }
+                }
+
+                prop_assert_eq!(map.len(), model.len());
+                prop_assert_eq!(map.index.is_empty(), model.len() < INDEX_LEN);
+                for ((key, value), (k, v)) in model.iter().zip(map.iter()) {
+                    prop_assert!((&&key[..]).pack_eq(&k));
+                    prop_assert!((&&value[..]).pack_eq(&v));
+                    let found = map.get(&&key[..]);
+                    prop_assert!(found.is_some_and(|found| (&&value[..]).pack_eq(&found)));
+                }
+            }
+        }
+    }
+}
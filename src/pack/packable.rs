@@ -17,6 +17,38 @@ pub trait Packable {
     fn pack_eq<'a>(&'a self, other: &PackRef<'a>) -> bool;
 }
 
+/// Nil is a single tag, for stored values that distinguish it from an empty string.
+impl Packable for () {
+    fn pack_size(&self) -> usize {
+        2
+    }
+
+    fn pack_write(&self, mut buffer: impl BufMut) {
+        buffer.put_u8(0xf6);
+        buffer.put_u8(1);
+    }
+
+    fn pack_eq<'a>(&'a self, other: &PackRef<'a>) -> bool {
+        matches!(other, PackRef::Nil)
+    }
+}
+
+/// Booleans are a single tag each, so they compare equal only to other booleans.
+impl Packable for bool {
+    fn pack_size(&self) -> usize {
+        2
+    }
+
+    fn pack_write(&self, mut buffer: impl BufMut) {
+        buffer.put_u8(if *self { 0xf8 } else { 0xf7 });
+        buffer.put_u8(1);
+    }
+
+    fn pack_eq<'a>(&'a self, other: &PackRef<'a>) -> bool {
+        matches!(other, PackRef::Bool(other) if self == other)
+    }
+}
+
 impl Packable for f64 {
     fn pack_size(&self) -> usize {
         10
@@ -44,6 +76,7 @@ impl Packable for f64 {
                 }
             }
             Slice(other) => buffer.write_f64(*self) == &other[..],
+            Bool(_) | Nil => false,
         }
     }
 }
@@ -124,6 +157,7 @@ impl Packable for i64 {
                 Some(i) => *self == i,
                 None => false,
             },
+            Bool(_) | Nil => false,
         }
     }
 }
@@ -180,6 +214,7 @@ impl Packable for &[u8] {
                 None => false,
             },
             Slice(s) => self[..] == s[..],
+            Bool(_) | Nil => false,
         }
     }
 }
@@ -216,8 +251,10 @@ impl Packable for PackRef<'_> {
     fn pack_size(&self) -> usize {
         use PackRef::*;
         match self {
+            Bool(b) => b.pack_size(),
             Float(f) => f.pack_size(),
             Integer(i) => i.pack_size(),
+            Nil => ().pack_size(),
             Slice(s) => (&s[..]).pack_size(),
         }
     }
@@ -225,8 +262,10 @@ impl Packable for PackRef<'_> {
     fn pack_write(&self, buffer: impl BufMut) {
         use PackRef::*;
         match self {
+            Bool(b) => b.pack_write(buffer),
             Float(f) => f.pack_write(buffer),
             Integer(i) => i.pack_write(buffer),
+            Nil => ().pack_write(buffer),
             Slice(s) => (&s[..]).pack_write(buffer),
         }
     }
@@ -234,8 +273,10 @@ impl Packable for PackRef<'_> {
     fn pack_eq<'a>(&'a self, other: &PackRef<'a>) -> bool {
         use PackRef::*;
         match self {
+            Bool(b) => b.pack_eq(other),
             Float(f) => f.pack_eq(other),
             Integer(i) => i.pack_eq(other),
+            Nil => ().pack_eq(other),
             Slice(s) => (&s[..]).pack_eq(other),
         }
     }
@@ -245,8 +286,10 @@ impl Packable for PackValue {
     fn pack_size(&self) -> usize {
         use PackValue::*;
         match self {
+            Bool(b) => b.pack_size(),
             Float(f) => f.pack_size(),
             Integer(i) => i.pack_size(),
+            Nil => ().pack_size(),
             Raw(s) => (&s[..]).pack_size(),
         }
     }
@@ -254,8 +297,10 @@ impl Packable for PackValue {
     fn pack_write(&self, buffer: impl BufMut) {
         use PackValue::*;
         match self {
+            Bool(b) => b.pack_write(buffer),
             Float(f) => f.pack_write(buffer),
             Integer(i) => i.pack_write(buffer),
+            Nil => ().pack_write(buffer),
             Raw(s) => (&s[..]).pack_write(buffer),
         }
     }
@@ -263,8 +308,10 @@ impl Packable for PackValue {
     fn pack_eq<'a>(&'a self, other: &PackRef<'a>) -> bool {
         use PackValue::*;
         match self {
+            Bool(b) => b.pack_eq(other),
             Float(f) => f.pack_eq(other),
             Integer(i) => i.pack_eq(other),
+            Nil => ().pack_eq(other),
             Raw(s) => (&s[..]).pack_eq(other),
         }
     }
@@ -8,12 +8,18 @@ use std::io::Write;
 
 /// A reference to a value inside an existing [`Pack`][`crate::Pack`].
 pub enum PackRef<'a> {
+    /// A `true` or `false` value.
+    Bool(bool),
+
     /// A reference to an `f64` value.
     Float(f64),
 
     /// A reference to an `i64` value.
     Integer(i64),
 
+    /// A nil value.
+    Nil,
+
     /// A reference to a raw slice in a [`Pack`][`crate::Pack`].
     Slice(RawSliceRef<'a>),
 }
@@ -23,8 +29,10 @@ impl std::fmt::Debug for PackRef<'_> {
         use PackRef::*;
 
         match self {
+            Bool(value) => write!(f, "{value:?}"),
             Float(value) => write!(f, "{value:?}"),
             Integer(value) => write!(f, "{value:?}"),
+            Nil => write!(f, "nil"),
             Slice(value) => write!(f, "\"{:?}\"", Output(&value[..])),
         }
     }
@@ -34,8 +42,10 @@ impl PartialEq for PackRef<'_> {
     fn eq(&self, other: &Self) -> bool {
         use PackRef::*;
         match self {
+            Bool(b) => b.pack_eq(other),
             Float(f) => f.pack_eq(other),
             Integer(i) => i.pack_eq(other),
+            Nil => ().pack_eq(other),
             Slice(s) => (&s[..]).pack_eq(other),
         }
     }
@@ -46,8 +56,10 @@ impl<'a> PackRef<'a> {
     pub fn size(&self) -> usize {
         use PackRef::*;
         match self {
+            Bool(b) => b.pack_size(),
             Float(f) => f.pack_size(),
             Integer(i) => i.pack_size(),
+            Nil => ().pack_size(),
             Slice(s) => (&s[..]).pack_size(),
         }
     }
@@ -56,6 +68,8 @@ impl<'a> PackRef<'a> {
     pub fn float(&self) -> Option<f64> {
         use PackRef::*;
         match self {
+            Bool(b) => Some(f64::from(u8::from(*b))),
+            Nil => None,
             Float(f) => Some(*f),
             #[allow(clippy::cast_precision_loss)]
             Integer(i) => Some(*i as f64),
@@ -67,6 +81,8 @@ impl<'a> PackRef<'a> {
     pub fn integer(&self) -> Option<i64> {
         use PackRef::*;
         match self {
+            Bool(b) => Some(i64::from(*b)),
+            Nil => None,
             Float(f) => {
                 if f.fract() == 0f64 {
                     #[allow(clippy::cast_possible_truncation)]
@@ -84,6 +100,8 @@ impl<'a> PackRef<'a> {
     pub fn raw(&self) -> Raw {
         use PackRef::*;
         match self {
+            Bool(b) => vec![if *b { b'1' } else { b'0' }].into(),
+            Nil => Vec::new().into(),
             Float(f) => {
                 let mut v = Vec::new();
                 _ = write!(v, "{f}");
@@ -102,8 +120,11 @@ impl<'a> PackRef<'a> {
     pub fn as_bytes(&'a self, buffer: &'a mut impl Buffer) -> &'a [u8] {
         use PackRef::*;
         match self {
+            Bool(true) => b"1",
+            Bool(false) => b"0",
             Float(f) => buffer.write_f64(*f),
             Integer(i) => buffer.write_i64(*i),
+            Nil => b"",
             Slice(s) => &s[..],
         }
     }
@@ -112,13 +133,21 @@ impl<'a> PackRef<'a> {
     pub fn to_owned(&self) -> PackValue {
         use PackRef::*;
         match self {
+            Bool(b) => PackValue::Bool(*b),
             Float(f) => PackValue::Float(*f),
             Integer(i) => PackValue::Integer(*i),
+            Nil => PackValue::Nil,
             Slice(s) => PackValue::Raw(s.to_owned()),
         }
     }
 }
 
+impl From<bool> for PackRef<'_> {
+    fn from(value: bool) -> Self {
+        PackRef::Bool(value)
+    }
+}
+
 impl From<f64> for PackRef<'_> {
     fn from(value: f64) -> Self {
         PackRef::Float(value)
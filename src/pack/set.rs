@@ -1,4 +1,4 @@
-use crate::{Pack, PackIter, PackValue, Packable, db::Edge};
+use crate::{Pack, PackIter, PackRef, PackValue, Packable, db::Edge};
 use rand::Rng;
 
 /// A Redis set, stored in a [`Pack`] to improve memory usage and locality.
@@ -82,6 +82,32 @@ impl PackSet {
     pub fn iter<'a>(&'a self) -> PackIter<'a> {
         self.pack.iter()
     }
+
+    /// Return an iterator over the values in this set that are also in `other`, without
+    /// converting either one. Each value is a scan of `other`, so pass the smaller set as `self`.
+    pub fn intersection<'a>(&'a self, other: &'a PackSet) -> Intersection<'a> {
+        Intersection {
+            iter: self.iter(),
+            other,
+        }
+    }
+}
+
+/// An iterator over the values in two [`PackSet`]s.
+pub struct Intersection<'a> {
+    iter: PackIter<'a>,
+    other: &'a PackSet,
+}
+
+impl<'a> Iterator for Intersection<'a> {
+    type Item = PackRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.other.is_empty() {
+            return None;
+        }
+        self.iter.by_ref().find(|value| self.other.contains(value))
+    }
 }
 
 impl<I, TI, TV> From<(I, TV)> for PackSet
@@ -121,6 +147,19 @@ mod tests {
         assert!(set.is_empty());
     }
 
+    #[test]
+    fn intersection() {
+        let mut buffer = ArrayBuffer::default();
+        let a = PackSet::from((["a", "b", "c"].into_iter(), "1"));
+        let b = PackSet::from((["1", "c", "d"].into_iter(), "a"));
+        let values: Vec<Vec<u8>> = a
+            .intersection(&b)
+            .map(|value| value.as_bytes(&mut buffer).to_vec())
+            .collect();
+        assert_eq!(values, [b"a".to_vec(), b"c".to_vec(), b"1".to_vec()]);
+        assert_eq!(a.intersection(&PackSet::default()).count(), 0);
+    }
+
     #[test]
     fn test_remove() {
         let mut set = PackSet::default();
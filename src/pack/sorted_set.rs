@@ -54,8 +54,56 @@ impl PackSortedSet {
         self.range(range).rev()
     }
 
+    /// Return an iterator over the values with scores within `bounds`, skipping `offset` values
+    /// and returning at most `count` if `limit` is provided.
+    pub fn range_score<'a, R>(&'a self, bounds: &R, limit: Option<(usize, usize)>) -> Iter<'a>
+    where
+        R: RangeBounds<f64>,
+    {
+        let mut iter = self.within(bounds);
+
+        if let Some((offset, count)) = limit {
+            for _ in 0..offset {
+                if iter.next().is_none() {
+                    break;
+                }
+            }
+            while iter.len() > count {
+                iter.next_back();
+            }
+        }
+
+        iter
+    }
+
+    /// Return a reverse iterator over the values with scores within `bounds`, skipping `offset`
+    /// values and returning at most `count` if `limit` is provided.
+    pub fn rev_range_score<'a, R>(
+        &'a self,
+        bounds: &R,
+        limit: Option<(usize, usize)>,
+    ) -> Rev<Iter<'a>>
+    where
+        R: RangeBounds<f64>,
+    {
+        let mut iter = self.within(bounds);
+
+        if let Some((offset, count)) = limit {
+            for _ in 0..offset {
+                if iter.next_back().is_none() {
+                    break;
+                }
+            }
+            while iter.len() > count {
+                iter.next();
+            }
+        }
+
+        iter.rev()
+    }
+
     /// Return an iterator over the values with scores within `bounds`.
-    pub fn range_score<'a, R>(&'a self, bounds: &R) -> Iter<'a>
+    fn within<'a, R>(&'a self, bounds: &R) -> Iter<'a>
     where
         R: RangeBounds<f64>,
     {
@@ -78,14 +126,6 @@ impl PackSortedSet {
         iter
     }
 
-    /// Return a reverse iterator over the values with scores within `bounds`.
-    pub fn rev_range_score<'a, R>(&'a self, bounds: &R) -> Rev<Iter<'a>>
-    where
-        R: RangeBounds<f64>,
-    {
-        self.range_score(bounds).rev()
-    }
-
     /// Return the rank of `value`.
     pub fn rank<V: Packable>(&self, value: &V) -> Option<usize> {
         self.iter()
@@ -99,7 +139,7 @@ impl PackSortedSet {
     where
         R: RangeBounds<f64>,
     {
-        self.range_score(bounds).len()
+        self.within(bounds).len()
     }
 
     /// Return the score for `value`.
@@ -186,6 +226,20 @@ impl PackSortedSet {
         count
     }
 
+    /// Remove all values with ranks in `range` from the set.
+    pub fn remove_range_rank(&mut self, range: Range<usize>) -> usize {
+        let end = std::cmp::min(range.end, self.len());
+        let count = end.saturating_sub(range.start);
+        if count == 0 {
+            return 0;
+        }
+
+        let mut cursor = self.pack.cursor(Edge::Left);
+        cursor.skip(range.start * 2);
+        cursor.remove(count * 2);
+        count
+    }
+
     /// Pop a score value pair from one `extreme`.
     pub fn pop(&mut self, extreme: Extreme) -> Option<(f64, PackValue)> {
         let (edge, entry) = match extreme {
@@ -442,6 +496,54 @@ mod tests {
         assert_eq!(set.rev_range(2..20).len(), 3);
     }
 
+    #[test]
+    fn range_score() {
+        let set = pack_sorted_set!(
+            (1f64, b"b"),
+            (2f64, b"c"),
+            (0f64, b"a"),
+            (4f64, b"e"),
+            (3f64, b"d"),
+        );
+
+        assert_pack_sorted_set_eq!(
+            set.range_score(&(1f64..), None),
+            (1f64, b"b"),
+            (2f64, b"c"),
+            (3f64, b"d"),
+            (4f64, b"e"),
+        );
+        assert_pack_sorted_set_eq!(
+            set.range_score(&(1f64..), Some((1, 2))),
+            (2f64, b"c"),
+            (3f64, b"d"),
+        );
+        assert_eq!(0, set.range_score(&(1f64..), Some((10, 2))).count());
+        assert_pack_sorted_set_eq!(
+            set.rev_range_score(&(1f64..), Some((1, 2))),
+            (3f64, b"d"),
+            (2f64, b"c"),
+        );
+        assert_eq!(0, set.rev_range_score(&(1f64..), Some((1, 0))).count());
+    }
+
+    #[test]
+    fn remove_range_rank() {
+        let mut set = pack_sorted_set!(
+            (1f64, b"b"),
+            (2f64, b"c"),
+            (0f64, b"a"),
+            (4f64, b"e"),
+            (3f64, b"d"),
+        );
+
+        assert_eq!(set.remove_range_rank(1..3), 2);
+        assert_pack_sorted_set_eq!(set.iter(), (0f64, b"a"), (3f64, b"d"), (4f64, b"e"));
+        assert_eq!(set.remove_range_rank(3..5), 0);
+        assert_eq!(set.remove_range_rank(1..5), 2);
+        assert_pack_sorted_set_eq!(set.iter(), (0f64, b"a"));
+    }
+
     #[test]
     fn remove_range_score() {
         let mut set = pack_sorted_set!(
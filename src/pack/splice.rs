@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+/// Replace a range of bytes in a `Vec` with new bytes written directly in place, without building
+/// them in a temporary buffer first. The bytes after the range are moved once to make room, the
+/// `Vec` is truncated to the start of the range, and the new bytes are appended to it.
+///
+/// If the writer panics, or the splice is dropped without writing, the `Vec` is simply left
+/// truncated. Writing the wrong number of bytes panics rather than exposing uninitialized memory.
+pub struct RawSplice<'a> {
+    /// The bytes being modified.
+    data: &'a mut Vec<u8>,
+
+    /// The offset where new bytes are written.
+    start: usize,
+
+    /// The number of new bytes.
+    size: usize,
+
+    /// The number of bytes after the replaced range.
+    tail: usize,
+}
+
+impl<'a> RawSplice<'a> {
+    /// Make room for `size` bytes in place of `range`.
+    pub fn new(data: &'a mut Vec<u8>, range: Range<usize>, size: usize) -> Self {
+        assert!(
+            range.start <= range.end && range.end <= data.len(),
+            "splice range {range:?} is out of bounds for length {}",
+            data.len(),
+        );
+
+        let tail = data.len() - range.end;
+        if let Some(additional) = size.checked_sub(range.len()) {
+            data.reserve(additional);
+        }
+
+        // SAFETY: The capacity is at least `range.start + size + tail`, so the tail fits at its
+        // new position, and `copy_to` allows the regions to overlap. Truncating to `range.start`
+        // leaves only initialized bytes in the `Vec`.
+        unsafe {
+            let start = data.as_mut_ptr().add(range.start);
+            start.add(range.len()).copy_to(start.add(size), tail);
+            data.set_len(range.start);
+        }
+
+        RawSplice {
+            data,
+            start: range.start,
+            size,
+            tail,
+        }
+    }
+
+    /// Append exactly `size` bytes to the truncated `Vec` with `write`, then restore the tail. The
+    /// room is already reserved, so `write` must not reallocate.
+    pub fn write(self, write: impl FnOnce(&mut Vec<u8>)) {
+        let (pointer, capacity) = (self.data.as_ptr(), self.data.capacity());
+        write(self.data);
+
+        assert_eq!(
+            self.data.len(),
+            self.start + self.size,
+            "splice expected {} bytes",
+            self.size,
+        );
+        assert!(
+            self.data.as_ptr() == pointer && self.data.capacity() == capacity,
+            "splice reallocated",
+        );
+
+        // SAFETY: The new bytes were written without reallocating, so the tail moved in `new` is
+        // still in place directly after them.
+        unsafe {
+            self.data.set_len(self.start + self.size + self.tail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn splice(data: &[u8], range: Range<usize>, value: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        RawSplice::new(&mut data, range, value.len()).write(|data| data.extend_from_slice(value));
+        data
+    }
+
+    #[test]
+    fn insert() {
+        assert_eq!(splice(b"", 0..0, b"abc"), b"abc");
+        assert_eq!(splice(b"def", 0..0, b"abc"), b"abcdef");
+        assert_eq!(splice(b"adef", 1..1, b"bc"), b"abcdef");
+        assert_eq!(splice(b"abc", 3..3, b"def"), b"abcdef");
+    }
+
+    #[test]
+    fn replace() {
+        assert_eq!(splice(b"axc", 1..2, b"b"), b"abc");
+        assert_eq!(splice(b"axd", 1..2, b"bc"), b"abcd");
+        assert_eq!(splice(b"axyzd", 1..4, b"bc"), b"abcd");
+        assert_eq!(splice(b"abc", 0..3, b""), b"");
+    }
+
+    #[test]
+    fn drop_without_writing() {
+        let mut data = b"abcdef".to_vec();
+        RawSplice::new(&mut data, 2..4, 5);
+        assert_eq!(data, b"ab");
+    }
+
+    #[test]
+    #[should_panic(expected = "splice expected 2 bytes")]
+    fn write_too_few() {
+        let mut data = b"abc".to_vec();
+        RawSplice::new(&mut data, 1..2, 2).write(|data| data.push(b'x'));
+    }
+
+    #[test]
+    #[should_panic(expected = "splice reallocated")]
+    fn reallocate() {
+        let mut data = b"abc".to_vec();
+        RawSplice::new(&mut data, 1..2, 1).write(|data| {
+            data.shrink_to_fit();
+            data.reserve(100);
+            data.push(b'x');
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn out_of_bounds() {
+        let mut data = b"abc".to_vec();
+        RawSplice::new(&mut data, 2..4, 1);
+    }
+}
@@ -3,8 +3,10 @@ use crate::{buffer::Buffer, db::RawSlice};
 /// A packed value, optionally with a shared reference to the pack it comes from.
 #[derive(Clone, Debug)]
 pub enum PackValue {
+    Bool(bool),
     Float(f64),
     Integer(i64),
+    Nil,
     Raw(RawSlice),
 }
 
@@ -13,8 +15,11 @@ impl PackValue {
     pub fn as_bytes<'a>(&'a self, buffer: &'a mut impl Buffer) -> &'a [u8] {
         use PackValue::*;
         match self {
+            Bool(true) => b"1",
+            Bool(false) => b"0",
             Float(f) => buffer.write_f64(*f),
             Integer(i) => buffer.write_i64(*i),
+            Nil => b"",
             Raw(s) => &s[..],
         }
     }
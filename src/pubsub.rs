@@ -1,75 +1,241 @@
+mod patterns;
 mod subscriber;
 mod subscribers;
 
+use patterns::Patterns;
 pub use subscriber::Subscriber;
-use subscribers::Subscribers;
+use subscribers::{Subscribers, shrink};
 
 use crate::{
     buffer::ArrayBuffer,
     client::{Client, ClientId},
-    db::StringValue,
-    glob,
+    db::{DBIndex, StringValue},
     linked_hash_set::LinkedHashSet,
     reply::Reply,
 };
 use bytes::Bytes;
-use std::sync::atomic::Ordering;
+use hashbrown::{HashMap, HashSet};
+use triomphe::Arc;
 
-/// Keep track of pubsub subscribers and what channels they are subscribed to.
-pub struct Pubsub {
+/// How many unsubscribes to allow between passes that clean up empty namespaces and shrink
+/// oversized maps.
+const GC_INTERVAL: usize = 1024;
+
+/// A message published to a channel. Each one is allocated once and shared by every subscriber
+/// that receives it, so a publish costs one send per subscriber.
+#[derive(Debug)]
+pub struct Message {
+    /// The pattern that matched the channel, for pattern subscribers.
+    pub pattern: Option<Bytes>,
+
+    /// The channel the message was published to.
+    pub channel: Bytes,
+
+    /// The message itself.
+    pub message: Bytes,
+}
+
+/// The channels and patterns subscribed to in one namespace.
+struct Namespace {
     /// Clients subscribed to specific channels.
     subscribers: Subscribers,
 
     /// Clients subscribed to channel patterns.
     psubscribers: Subscribers,
+
+    /// Compiled patterns for each pattern with at least one subscriber, indexed by prefix.
+    patterns: Patterns,
 }
 
-impl Default for Pubsub {
+impl Default for Namespace {
     fn default() -> Self {
-        Pubsub {
+        Namespace {
             subscribers: Subscribers::new(),
             psubscribers: Subscribers::new(),
+            patterns: Patterns::default(),
+        }
+    }
+}
+
+impl Namespace {
+    /// Drop compiled patterns that no longer have any subscribers.
+    fn prune(&mut self) {
+        let psubscribers = &self.psubscribers;
+        self.patterns
+            .retain(|pattern| psubscribers.get(pattern).is_some());
+    }
+
+    /// Unsubscribe a client from every pattern, dropping the compiled patterns that nobody else
+    /// subscribes to. Return the patterns it was subscribed to, if any.
+    fn punsubscribe_all(&mut self, id: ClientId) -> Option<HashSet<StringValue>> {
+        let patterns = self.psubscribers.remove_all(&id)?;
+        let mut buffer = ArrayBuffer::default();
+        for pattern in &patterns {
+            let pattern = pattern.as_bytes(&mut buffer);
+            if self.psubscribers.get(pattern).is_none() {
+                self.patterns.remove(pattern);
+            }
+        }
+        Some(patterns)
+    }
+
+    /// Are there no subscriptions in this namespace?
+    fn is_empty(&self) -> bool {
+        self.subscribers.is_empty() && self.psubscribers.is_empty()
+    }
+
+    /// Give back memory held by maps that have shrunk well below their capacity.
+    fn shrink(&mut self) {
+        self.subscribers.shrink();
+        self.psubscribers.shrink();
+        self.patterns.shrink();
+    }
+}
+
+/// Keep track of pubsub subscribers and what channels they are subscribed to.
+pub struct Pubsub {
+    /// Subscriptions by namespace. Every database shares the `None` namespace unless `per_db` is
+    /// set, in which case each database index has its own.
+    namespaces: HashMap<Option<DBIndex>, Namespace>,
+
+    /// Are channels isolated by the selected database? Changing this only affects later
+    /// subscriptions and messages, so existing subscriptions stay in the namespace they were
+    /// made in.
+    pub per_db: bool,
+
+    /// Unsubscribes since the last garbage collection pass.
+    removals: usize,
+}
+
+impl Default for Pubsub {
+    fn default() -> Self {
+        Pubsub {
+            namespaces: HashMap::new(),
+            per_db: false,
+            removals: 0,
         }
     }
 }
 
 impl Pubsub {
-    /// The number of patterns subscribed to.
-    pub fn numpat(&self) -> usize {
-        self.psubscribers.iter().count()
+    /// The namespace for a client that has selected `db`.
+    fn key(&self, db: DBIndex) -> Option<DBIndex> {
+        self.per_db.then_some(db)
+    }
+
+    /// The namespace for a client that has selected `db`, if anything is subscribed in it.
+    fn namespace(&self, db: DBIndex) -> Option<&Namespace> {
+        self.namespaces.get(&self.key(db))
+    }
+
+    /// The namespace for a client that has selected `db`, creating it if necessary.
+    fn namespace_mut(&mut self, db: DBIndex) -> &mut Namespace {
+        self.namespaces.entry(self.key(db)).or_default()
     }
 
-    /// The number of subscribers.
-    pub fn numsub(&self, key: impl AsRef<[u8]>) -> usize {
-        self.subscribers.get(key).map_or(0, LinkedHashSet::len)
+    /// Remove namespaces that nothing is subscribed in anymore, and run a garbage collection pass
+    /// every so often.
+    fn removed(&mut self) {
+        self.namespaces.retain(|_, namespace| !namespace.is_empty());
+        self.removals += 1;
+        if self.removals >= GC_INTERVAL {
+            self.gc();
+        }
+    }
+
+    /// Remove empty namespaces and compiled patterns without subscribers, and shrink maps that
+    /// have grown far beyond what they hold. Unsubscribing cleans up as it goes, so this only
+    /// catches what slips through and gives back memory after churn.
+    fn gc(&mut self) {
+        self.removals = 0;
+        self.namespaces.retain(|_, namespace| {
+            namespace.prune();
+            namespace.shrink();
+            !namespace.is_empty()
+        });
+        shrink(&mut self.namespaces);
+    }
+
+    /// The number of channels subscribed to, in every namespace.
+    pub fn channels_len(&self) -> usize {
+        self.namespaces
+            .values()
+            .map(|namespace| namespace.subscribers.len())
+            .sum()
     }
 
-    /// The number of channels subscribed to.
-    pub fn channels(&self) -> impl Iterator<Item = &StringValue> {
-        self.subscribers.channels()
+    /// The number of patterns subscribed to, in every namespace.
+    pub fn patterns_len(&self) -> usize {
+        self.namespaces
+            .values()
+            .map(|namespace| namespace.psubscribers.len())
+            .sum()
+    }
+
+    /// The number of patterns subscribed to in the namespace for `db`.
+    pub fn numpat(&self, db: DBIndex) -> usize {
+        self.namespace(db)
+            .map_or(0, |namespace| namespace.psubscribers.len())
+    }
+
+    /// The number of subscribers to a channel in the namespace for `db`.
+    pub fn numsub(&self, db: DBIndex, key: impl AsRef<[u8]>) -> usize {
+        self.namespace(db)
+            .and_then(|namespace| namespace.subscribers.get(key))
+            .map_or(0, LinkedHashSet::len)
+    }
+
+    /// The channels subscribed to in the namespace for `db`.
+    pub fn channels(&self, db: DBIndex) -> impl Iterator<Item = &StringValue> {
+        self.namespace(db)
+            .into_iter()
+            .flat_map(|namespace| namespace.subscribers.channels())
     }
 
     /// Disconnect a client, removing all bookkeeping.
     pub fn disconnect(&mut self, id: ClientId) {
-        self.subscribers.remove_all(&id);
-        self.psubscribers.remove_all(&id);
+        let mut removed = false;
+        for namespace in self.namespaces.values_mut() {
+            removed |= namespace.subscribers.remove_all(&id).is_some();
+            removed |= namespace.punsubscribe_all(id).is_some();
+        }
+        if removed {
+            self.removed();
+        }
     }
 
     /// Reset a client, removing all subscribers.
     pub fn reset(&mut self, client: &mut Client) {
-        self.subscribers.remove_all(&client.id);
-        self.psubscribers.remove_all(&client.id);
-        client.pubsub = false;
+        self.disconnect(client.id);
+        self.sync(client);
+    }
+
+    /// Bring a client's pubsub flag in line with the registry. Every change to a client's
+    /// subscriptions ends here, so they can't drift apart. Subscription counts aren't copied
+    /// anywhere, so `CLIENT LIST` and `INFO` read them from here too.
+    fn sync(&self, client: &mut Client) {
+        client.pubsub = self.subscribed(client.id);
     }
 
-    /// The number of subscribers to a specific channel.
+    /// Is a client subscribed to any channels or patterns, in any namespace?
+    pub fn subscribed(&self, id: ClientId) -> bool {
+        self.count(id) > 0
+    }
+
+    /// The number of channels a client is subscribed to, in every namespace.
     pub fn subscribers(&self, id: ClientId) -> usize {
-        self.subscribers.count(&id)
+        self.namespaces
+            .values()
+            .map(|namespace| namespace.subscribers.count(&id))
+            .sum()
     }
 
-    /// The number of subscribers to a pattern.
+    /// The number of patterns a client is subscribed to, in every namespace.
     pub fn psubscribers(&self, id: ClientId) -> usize {
-        self.psubscribers.count(&id)
+        self.namespaces
+            .values()
+            .map(|namespace| namespace.psubscribers.count(&id))
+            .sum()
     }
 
     /// Total subscriptions for a client.
@@ -79,35 +245,45 @@ impl Pubsub {
 
     /// Subscribe a client to a channel.
     pub fn subscribe(&mut self, channel: Bytes, client: &mut Client) {
-        let subscribers = self.subscribers.add(&channel, client);
+        self.namespace_mut(client.db())
+            .subscribers
+            .add(&channel, client);
         client.reply(Reply::Push(3));
         client.reply("subscribe");
         client.reply(channel);
         client.reply(self.count(client.id));
-        client.pubsub = true;
-        client.subscribers.store(subscribers, Ordering::Relaxed);
+        self.sync(client);
     }
 
     /// Subscribe a client to a pattern.
     pub fn psubscribe(&mut self, pattern: Bytes, client: &mut Client) {
-        let psubscribers = self.psubscribers.add(&pattern, client);
+        let namespace = self.namespace_mut(client.db());
+        namespace.psubscribers.add(&pattern, client);
+        namespace.patterns.insert(&pattern);
         client.reply(Reply::Push(3));
         client.reply("psubscribe");
         client.reply(pattern);
         client.reply(self.count(client.id));
-        client.pubsub = true;
-        client.psubscribers.store(psubscribers, Ordering::Relaxed);
+        self.sync(client);
     }
 
-    /// Unsubscribe a client from all channels.
+    /// Unsubscribe a client from all channels, in every namespace.
     pub fn unsubscribe_all(&mut self, client: &mut Client) {
-        let Some(channels) = self.subscribers.remove_all(&client.id) else {
+        let channels: Vec<StringValue> = self
+            .namespaces
+            .values_mut()
+            .filter_map(|namespace| namespace.subscribers.remove_all(&client.id))
+            .flatten()
+            .collect();
+        self.removed();
+
+        if channels.is_empty() {
             client.reply(Reply::Push(3));
             client.reply("unsubscribe");
             client.reply(Reply::Nil);
             client.reply(self.count(client.id));
             return;
-        };
+        }
 
         let count = self.count(client.id);
         let len = channels.len();
@@ -119,21 +295,26 @@ impl Pubsub {
             client.reply(count + len - index - 1);
         }
 
-        if count == 0 {
-            client.pubsub = false;
-        }
-        client.subscribers.store(0, Ordering::Relaxed);
+        self.sync(client);
     }
 
-    /// Unsubscribe a client from all patterns.
+    /// Unsubscribe a client from all patterns, in every namespace.
     pub fn punsubscribe_all(&mut self, client: &mut Client) {
-        let Some(patterns) = self.psubscribers.remove_all(&client.id) else {
+        let patterns: Vec<StringValue> = self
+            .namespaces
+            .values_mut()
+            .filter_map(|namespace| namespace.punsubscribe_all(client.id))
+            .flatten()
+            .collect();
+        self.removed();
+
+        if patterns.is_empty() {
             client.reply(Reply::Push(3));
             client.reply("punsubscribe");
             client.reply(Reply::Nil);
             client.reply(self.count(client.id));
             return;
-        };
+        }
 
         let count = self.count(client.id);
         let len = patterns.len();
@@ -145,68 +326,72 @@ impl Pubsub {
             client.reply(count + len - index - 1);
         }
 
-        if count == 0 {
-            client.pubsub = false;
-        }
-        client.psubscribers.store(0, Ordering::Relaxed);
+        self.sync(client);
     }
 
-    /// Unsubscribe a client from a channel.
+    /// Unsubscribe a client from a channel in the namespace for its selected database.
     pub fn unsubscribe(&mut self, channel: Bytes, client: &mut Client) {
-        let subscribers = self.subscribers.remove(&channel, &client.id);
-        let count = self.count(client.id);
+        let key = self.key(client.db());
+        if let Some(namespace) = self.namespaces.get_mut(&key) {
+            namespace.subscribers.remove(&channel, &client.id);
+            self.removed();
+        }
         client.reply(Reply::Push(3));
         client.reply("unsubscribe");
         client.reply(channel);
-        client.reply(count);
-
-        if count == 0 {
-            client.pubsub = false;
-        }
-        client.subscribers.store(subscribers, Ordering::Relaxed);
+        client.reply(self.count(client.id));
+        self.sync(client);
     }
 
-    /// Unsubscribe a client from a pattern.
+    /// Unsubscribe a client from a pattern in the namespace for its selected database.
     pub fn punsubscribe(&mut self, pattern: Bytes, client: &mut Client) {
-        let psubscribers = self.psubscribers.remove(&pattern, &client.id);
-        let count = self.count(client.id);
+        let key = self.key(client.db());
+        if let Some(namespace) = self.namespaces.get_mut(&key) {
+            namespace.psubscribers.remove(&pattern, &client.id);
+            if namespace.psubscribers.get(&pattern).is_none() {
+                namespace.patterns.remove(&pattern[..]);
+            }
+            self.removed();
+        }
         client.reply(Reply::Push(3));
         client.reply("punsubscribe");
         client.reply(pattern);
-        client.reply(count);
-
-        if count == 0 {
-            client.pubsub = false;
-        }
-
-        client.psubscribers.store(psubscribers, Ordering::Relaxed);
+        client.reply(self.count(client.id));
+        self.sync(client);
     }
 
-    /// Publish a message to a channel.
-    pub fn publish(&mut self, channel: &Bytes, message: &Bytes) -> usize {
+    /// Publish a message to a channel in the namespace for `db`.
+    pub fn publish(&mut self, db: DBIndex, channel: &Bytes, message: &Bytes) -> usize {
         let mut count = 0;
 
-        if let Some(subscribers) = self.subscribers.get(&channel[..]) {
+        let Some(namespace) = self.namespace(db) else {
+            return count;
+        };
+
+        if let Some(subscribers) = namespace.subscribers.get(&channel[..]) {
             count += subscribers.len();
 
+            let message = Arc::new(Message {
+                pattern: None,
+                channel: channel.clone(),
+                message: message.clone(),
+            });
             for subscriber in subscribers.iter() {
-                subscriber.reply(Reply::Push(3));
-                subscriber.reply("message");
-                subscriber.reply(channel);
-                subscriber.reply(message);
+                subscriber.publish(&message);
             }
         }
 
-        for (pattern, subscribers) in self.psubscribers.iter() {
-            let mut buffer = ArrayBuffer::default();
-            if glob::matches(&channel[..], pattern.as_bytes(&mut buffer)) {
+        for pattern in namespace.patterns.matching(channel) {
+            if let Some(subscribers) = namespace.psubscribers.get(pattern) {
                 count += subscribers.len();
+
+                let message = Arc::new(Message {
+                    pattern: Some(pattern.clone()),
+                    channel: channel.clone(),
+                    message: message.clone(),
+                });
                 for subscriber in subscribers.iter() {
-                    subscriber.reply(Reply::Push(4));
-                    subscriber.reply("pmessage");
-                    subscriber.reply(pattern);
-                    subscriber.reply(channel);
-                    subscriber.reply(message);
+                    subscriber.publish(&message);
                 }
             }
         }
@@ -0,0 +1,151 @@
+use crate::{glob::Pattern, pubsub::subscribers::shrink};
+use bytes::Bytes;
+use hashbrown::HashMap;
+
+/// The longest literal prefix used to bucket patterns.
+const PREFIX: usize = 8;
+
+/// Compiled subscription patterns, bucketed by their literal prefix so that publishing only tests
+/// patterns that could possibly match a channel.
+#[derive(Default)]
+pub struct Patterns {
+    buckets: HashMap<Vec<u8>, HashMap<Bytes, Pattern>>,
+
+    /// The bucket of each pattern, so that a pattern is only compiled when it's first inserted.
+    index: HashMap<Bytes, Vec<u8>>,
+}
+
+impl Patterns {
+    /// The bucket for a compiled pattern.
+    fn bucket(pattern: &Pattern) -> &[u8] {
+        let prefix = pattern.prefix();
+        &prefix[..prefix.len().min(PREFIX)]
+    }
+
+    /// Add a pattern if it isn't already present.
+    pub fn insert(&mut self, pattern: &Bytes) {
+        if self.index.contains_key(pattern) {
+            return;
+        }
+        let compiled = Pattern::compile(pattern);
+        let key = Self::bucket(&compiled).to_vec();
+        let bucket = self.buckets.entry_ref(&key[..]).or_default();
+        bucket.insert(pattern.clone(), compiled);
+        self.index.insert(pattern.clone(), key);
+    }
+
+    /// Remove a pattern.
+    pub fn remove(&mut self, pattern: &[u8]) {
+        let Some(key) = self.index.remove(pattern) else {
+            return;
+        };
+        if let Some(bucket) = self.buckets.get_mut(&key) {
+            bucket.remove(pattern);
+            if bucket.is_empty() {
+                self.buckets.remove(&key);
+            }
+        }
+    }
+
+    /// Keep only the patterns for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&Bytes) -> bool) {
+        let index = &mut self.index;
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(|pattern, _| {
+                let keep = f(pattern);
+                if !keep {
+                    index.remove(pattern);
+                }
+                keep
+            });
+            !bucket.is_empty()
+        });
+    }
+
+    /// Give back memory held by buckets that have shrunk well below their capacity.
+    pub fn shrink(&mut self) {
+        for bucket in self.buckets.values_mut() {
+            shrink(bucket);
+        }
+        shrink(&mut self.buckets);
+        shrink(&mut self.index);
+    }
+
+    /// Return an iterator over the patterns that match `channel`.
+    pub fn matching<'a>(&'a self, channel: &'a [u8]) -> impl Iterator<Item = &'a Bytes> {
+        (0..=channel.len().min(PREFIX))
+            .filter_map(|len| self.buckets.get(&channel[..len]))
+            .flat_map(|bucket| bucket.iter())
+            .filter_map(|(pattern, compiled)| compiled.matches(channel).then_some(pattern))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(patterns: &Patterns, channel: &[u8]) -> Vec<Bytes> {
+        let mut result: Vec<Bytes> = patterns.matching(channel).cloned().collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn buckets() {
+        let mut patterns = Patterns::default();
+        for pattern in [
+            "*",
+            "news.*",
+            "news.sport.*",
+            "news.sport.football",
+            "n?ws.*",
+            "x*",
+        ] {
+            patterns.insert(&Bytes::from(pattern));
+        }
+
+        assert_eq!(
+            matching(&patterns, b"news.sport.football"),
+            [
+                "*",
+                "n?ws.*",
+                "news.*",
+                "news.sport.*",
+                "news.sport.football"
+            ]
+        );
+        assert_eq!(matching(&patterns, b"news"), ["*"]);
+        // Like Redis, not even "*" matches an empty channel.
+        assert!(matching(&patterns, b"").is_empty());
+        assert_eq!(matching(&patterns, b"xyz"), ["*", "x*"]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut patterns = Patterns::default();
+        patterns.insert(&Bytes::from("news.*"));
+        patterns.insert(&Bytes::from("news.*"));
+        patterns.insert(&Bytes::from("news.sport.*"));
+        patterns.remove(b"news.*");
+        assert_eq!(matching(&patterns, b"news.sport.x"), ["news.sport.*"]);
+        patterns.remove(b"news.sport.*");
+        assert!(patterns.buckets.is_empty());
+        assert!(patterns.index.is_empty());
+
+        // Removing a pattern that was never inserted does nothing.
+        patterns.remove(b"news.*");
+        assert!(patterns.buckets.is_empty());
+    }
+
+    #[test]
+    fn retain() {
+        let mut patterns = Patterns::default();
+        patterns.insert(&Bytes::from("a*"));
+        patterns.insert(&Bytes::from("b*"));
+        patterns.retain(|pattern| pattern.starts_with(b"a"));
+        assert_eq!(matching(&patterns, b"abc"), ["a*"]);
+        assert!(matching(&patterns, b"bcd").is_empty());
+        assert_eq!(patterns.buckets.len(), 1);
+        assert_eq!(patterns.index.len(), 1);
+    }
+}
@@ -1,15 +1,15 @@
 use crate::{
-    client::{ClientId, ReplyMessage},
-    reply::Reply,
+    client::{ClientId, ReplyMessage, ReplySender},
+    pubsub::Message,
 };
 use hashbrown::Equivalent;
 use std::hash::{Hash, Hasher};
-use tokio::sync::mpsc;
+use triomphe::Arc;
 
 #[derive(Clone, Debug)]
 pub struct Subscriber {
     id: ClientId,
-    reply_sender: mpsc::UnboundedSender<ReplyMessage>,
+    reply_sender: ReplySender,
 }
 
 impl Eq for Subscriber {}
@@ -33,11 +33,13 @@ impl Equivalent<Subscriber> for ClientId {
 }
 
 impl Subscriber {
-    pub fn new(id: ClientId, reply_sender: mpsc::UnboundedSender<ReplyMessage>) -> Self {
+    pub fn new(id: ClientId, reply_sender: ReplySender) -> Self {
         Subscriber { id, reply_sender }
     }
 
-    pub fn reply(&self, reply: impl Into<Reply>) {
-        _ = self.reply_sender.send(reply.into().into());
+    /// Send a published message without copying it.
+    pub fn publish(&self, message: &Arc<Message>) {
+        self.reply_sender
+            .send(ReplyMessage::Message(message.clone()));
     }
 }
@@ -11,13 +11,9 @@ macro_rules! remove {
         if let EntryRef::Occupied(mut entry) = $map.entry_ref($key) {
             let set = entry.get_mut();
             set.remove($value);
-            let len = set.len();
             if set.is_empty() {
                 entry.remove();
             }
-            len
-        } else {
-            0
         }
     }};
 }
@@ -36,7 +32,7 @@ impl Subscribers {
     }
 
     /// Add a subscription to a channel for a client
-    pub fn add(&mut self, channel: impl AsRef<[u8]>, client: &mut Client) -> usize {
+    pub fn add(&mut self, channel: impl AsRef<[u8]>, client: &mut Client) {
         let subscriber = Subscriber::new(client.id, client.reply_sender.clone());
         let key = self
             .channels
@@ -46,18 +42,16 @@ impl Subscribers {
             .entry(key.clone())
             .or_default()
             .insert_back(subscriber.clone());
-        let subscribers = self.subscribers.entry(subscriber).or_default();
-        subscribers.insert(key);
-        subscribers.len()
+        self.subscribers.entry(subscriber).or_default().insert(key);
     }
 
     /// Remove one channel from a subscriber
-    pub fn remove<Q>(&mut self, channel: impl AsRef<[u8]>, subscriber: &Q) -> usize
+    pub fn remove<Q>(&mut self, channel: impl AsRef<[u8]>, subscriber: &Q)
     where
         Q: KeyRef<Subscriber>,
     {
         remove!(self.channels, channel.as_ref(), subscriber);
-        remove!(self.subscribers, subscriber, channel.as_ref())
+        remove!(self.subscribers, subscriber, channel.as_ref());
     }
 
     /// Remove all subscriptions for a particular subscriber
@@ -87,13 +81,31 @@ impl Subscribers {
         self.subscribers.get(value).map_or(0, HashSet::len)
     }
 
-    /// Return an iterator over channels and subscribers.
-    pub fn iter(&self) -> impl Iterator<Item = (&StringValue, &LinkedHashSet<Subscriber>)> {
-        self.channels.iter()
+    /// The number of channels with at least one subscriber.
+    pub fn len(&self) -> usize {
+        self.channels.len()
     }
 
     /// Return an iterator over all channels.
     pub fn channels(&self) -> impl Iterator<Item = &StringValue> {
         self.channels.keys()
     }
+
+    /// Are there no subscriptions at all?
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty() && self.subscribers.is_empty()
+    }
+
+    /// Give back memory held by maps that have shrunk well below their capacity.
+    pub fn shrink(&mut self) {
+        shrink(&mut self.channels);
+        shrink(&mut self.subscribers);
+    }
+}
+
+/// Shrink a map that's using less than a quarter of its capacity, leaving room to grow again.
+pub fn shrink<K: Eq + std::hash::Hash, V>(map: &mut HashMap<K, V>) {
+    if map.capacity() > 4 * map.len() {
+        map.shrink_to(2 * map.len());
+    }
 }
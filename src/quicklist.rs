@@ -65,6 +65,11 @@ impl QuickList {
         self.list.len()
     }
 
+    /// The number of bytes used by all the packs in this quicklist.
+    pub fn size(&self) -> usize {
+        self.list.iter().map(PackList::size).sum()
+    }
+
     /// Convert this [`QuickList`] into a [`PackList`] if valid.
     pub fn convert(&mut self, max: i64) -> Option<PackList> {
         if self.list.len() != 1 {
@@ -104,6 +109,34 @@ impl QuickList {
         }
     }
 
+    /// Remove at most `count` elements from the `edge` end of the list, calling `f` with each one
+    /// in order from that edge. Packs are visited once and then unlinked or trimmed, so nothing is
+    /// copied out of the list. Return the number removed.
+    pub fn pop_many<F>(&mut self, edge: Edge, count: usize, mut f: F) -> usize
+    where
+        F: FnMut(PackRef<'_>),
+    {
+        let count = count.min(self.len);
+        let mut remaining = count;
+        while remaining > 0 {
+            let Some(pack) = self.list.edge_mut(edge) else {
+                break;
+            };
+
+            if pack.len() > remaining {
+                pack.pop_many(edge, remaining, &mut f);
+                self.len -= remaining;
+                break;
+            }
+
+            pack.iter_from(edge).for_each(&mut f);
+            remaining -= pack.len();
+            self.len -= pack.len();
+            self.list.pop(edge);
+        }
+        count
+    }
+
     /// Push `value` into the `edge` end of the list.
     pub fn push<V>(&mut self, value: &V, edge: Edge, max: i64)
     where
@@ -123,6 +156,28 @@ impl QuickList {
         }
     }
 
+    /// Push `values` into the `edge` end of the list, in order, filling each pack before starting
+    /// another.
+    pub fn push_many<V>(&mut self, mut values: &[V], edge: Edge, max: i64)
+    where
+        V: Packable,
+    {
+        self.len += values.len();
+        loop {
+            if let Some(pack) = self.list.edge_mut(edge) {
+                let count = pack.push_many(values, edge, max);
+                values = &values[count..];
+            }
+
+            // A pack always has room for a single value.
+            let Some((first, rest)) = values.split_first() else {
+                return;
+            };
+            self.list.push(first.into(), edge);
+            values = rest;
+        }
+    }
+
     pub fn iter<'a>(&'a self) -> Iter<'a> {
         Iter {
             iter: self.list.iter(),
@@ -166,9 +221,9 @@ impl QuickList {
         result
     }
 
-    /// Set the element at `index` to `value`. Return `false` if the
-    /// element doesn't exist.
-    pub fn set<V>(&mut self, value: &V, mut index: usize) -> bool
+    /// Set the element at `index` to `value`, splitting its pack if it no longer fits. Return
+    /// `false` if the element doesn't exist.
+    pub fn set<V>(&mut self, value: &V, mut index: usize, max: i64) -> bool
     where
         V: Packable,
     {
@@ -191,6 +246,13 @@ impl QuickList {
 
         while let Some(pack) = cursor.next() {
             if pack.set(value, index) {
+                if !list_is_valid(pack.len(), pack.size(), max) {
+                    cursor.prev();
+                    let pack = cursor.remove().unwrap();
+                    for pack in pack.split(max) {
+                        cursor.insert(pack);
+                    }
+                }
                 break;
             }
             index -= pack.len();
@@ -441,6 +503,17 @@ mod tests {
         assert_eq!(quick.list, linked!([0], [1, 2, 3], [4]));
     }
 
+    #[test]
+    fn test_push_many() {
+        let mut quick = QuickList::default();
+        let max = 3;
+        quick.push_many(&[1, 2, 3, 4, 5], Edge::Left, max);
+        quick.push_many(&[6, 7], Edge::Right, max);
+        assert_eq!(quick.len(), 7);
+
+        assert_eq!(quick.list, linked!([5, 4], [3, 2, 1], [6, 7]));
+    }
+
     #[test]
     fn test_peek() {
         let quick = quick!([0], [1, 2, 3], [4]);
@@ -469,6 +542,34 @@ mod tests {
         assert_eq!(quick.list, LinkedList::default());
     }
 
+    #[test]
+    fn pop_many() {
+        fn pop(quick: &mut QuickList, edge: Edge, count: usize) -> Vec<i64> {
+            let mut values = Vec::new();
+            let popped = quick.pop_many(edge, count, |value| match value {
+                PackRef::Integer(i) => values.push(i),
+                _ => panic!("expected an integer"),
+            });
+            assert_eq!(popped, values.len());
+            values
+        }
+
+        let mut quick = quick!([0], [1, 2, 3], [4]);
+
+        assert_eq!(pop(&mut quick, Edge::Left, 3), [0, 1, 2]);
+        assert_eq!(quick.len(), 2);
+        assert_eq!(quick.list, linked!([3], [4]));
+
+        assert_eq!(pop(&mut quick, Edge::Right, 5), [4, 3]);
+        assert_eq!(quick.len(), 0);
+        assert_eq!(quick.list, LinkedList::default());
+
+        let mut quick = quick!([0], [1, 2, 3], [4]);
+        assert_eq!(pop(&mut quick, Edge::Right, 3), [4, 3, 2]);
+        assert_eq!(quick.len(), 2);
+        assert_eq!(quick.list, linked!([0], [1]));
+    }
+
     #[test]
     fn push_with_negative_limit() {
         let sizes: [(i64, usize); 6] = [
@@ -533,19 +634,34 @@ mod tests {
     fn test_set() {
         let mut quick = quick!([1, 2, 3], [4, 5, 6]);
 
-        assert!(!quick.set(&4, 8));
+        assert!(!quick.set(&4, 8, -2));
         assert_eq!(quick.list, linked!([1, 2, 3], [4, 5, 6]));
 
-        assert!(quick.set(&10, 0));
+        assert!(quick.set(&10, 0, -2));
         assert_eq!(quick.list, linked!([10, 2, 3], [4, 5, 6]));
 
-        assert!(quick.set(&60, 5));
+        assert!(quick.set(&60, 5, -2));
         assert_eq!(quick.list, linked!([10, 2, 3], [4, 5, 60]));
 
-        assert!(quick.set(&40, 3));
+        assert!(quick.set(&40, 3, -2));
         assert_eq!(quick.list, linked!([10, 2, 3], [40, 5, 60]));
     }
 
+    #[test]
+    fn set_split() {
+        let mut quick = quick!([1, 2, 3], [4, 5, 6]);
+        let x = "x".repeat(5000);
+        let x = x.as_str();
+
+        assert!(quick.set(&x, 1, -1));
+        assert_eq!(quick.len(), 6);
+        assert_eq!(quick.list, linked!([1], [x], [3], [4, 5, 6]));
+
+        assert!(quick.set(&x, 5, -1));
+        assert_eq!(quick.len(), 6);
+        assert_eq!(quick.list, linked!([1], [x], [3], [4, 5], [x]));
+    }
+
     #[test]
     fn test_insert() {
         let mut quick = quick!([0, 2]);
@@ -637,5 +753,33 @@ mod proptests {
                 prop_assert!((&&expected[..]).pack_eq(&actual));
             }
         }
+
+        #[test]
+        fn push_many(
+            first in vec(vec(any::<u8>(), 0..250), 0..20),
+            second in vec(vec(any::<u8>(), 0..250), 1..20),
+            left in any::<bool>(),
+            max in prop_oneof![Just(-2), Just(-1), 1..5i64],
+        ) {
+            let edge = if left { Edge::Left } else { Edge::Right };
+            let first: Vec<_> = first.iter().map(|item| &item[..]).collect();
+            let second: Vec<_> = second.iter().map(|item| &item[..]).collect();
+
+            // Pushing many at once builds the same packs as pushing one at a time.
+            let mut many = QuickList::default();
+            many.push_many(&first, Edge::Right, max);
+            many.push_many(&second, edge, max);
+
+            let mut single = QuickList::default();
+            for item in &first {
+                single.push(item, Edge::Right, max);
+            }
+            for item in &second {
+                single.push(item, edge, max);
+            }
+
+            prop_assert_eq!(many.len(), single.len());
+            prop_assert_eq!(&many.list, &single.list);
+        }
     }
 }
@@ -0,0 +1,361 @@
+mod error;
+
+pub use error::RdbError;
+
+use crate::{Pack, int_set::IntSet, pack::PackList, quicklist::QuickList};
+
+/// A quicklist node holding a single large element.
+const CONTAINER_PLAIN: u64 = 1;
+
+/// A quicklist node holding a listpack.
+const CONTAINER_PACKED: u64 = 2;
+
+/// One entry of a ziplist or listpack. Redis stores strings that look like integers as integers,
+/// so appending an entry to a [`Pack`] keeps the same encoding.
+enum Entry<'a> {
+    Integer(i64),
+    String(&'a [u8]),
+}
+
+impl Entry<'_> {
+    fn append_to(&self, pack: &mut Pack) {
+        match self {
+            Entry::Integer(value) => pack.append(value),
+            Entry::String(value) => pack.append(value),
+        }
+    }
+}
+
+/// Decode a listpack, as Redis stores small hashes, sets, sorted sets, and quicklist nodes.
+///
+/// The layout is a header with the total size and number of entries, followed by the entries and
+/// an end byte. Each entry is much like an entry in a [`Pack`], which is derived from it.
+pub fn listpack(data: &[u8]) -> Result<Pack, RdbError> {
+    let mut rest = data;
+    let size = u32::from_le_bytes(take(&mut rest)?);
+    let count = u16::from_le_bytes(take(&mut rest)?);
+    if usize::try_from(size) != Ok(data.len()) {
+        return Err(RdbError::Header);
+    }
+
+    let mut pack = Pack::default();
+    loop {
+        match rest {
+            [0xff] => break,
+            [] | [0xff, ..] => return Err(RdbError::Truncated),
+            _ => listpack_entry(&mut rest)?.append_to(&mut pack),
+        }
+    }
+
+    // A count of u16::MAX means there were too many entries to count in the header.
+    if count != u16::MAX && usize::from(count) != pack.len() {
+        return Err(RdbError::Header);
+    }
+    Ok(pack)
+}
+
+fn listpack_entry<'a>(data: &mut &'a [u8]) -> Result<Entry<'a>, RdbError> {
+    let start = data.len();
+    let [tag] = take(data)?;
+
+    let entry = match tag {
+        // u7
+        tag if tag & 0x80 == 0x00 => Entry::Integer(tag.into()),
+        // 6 bit string length
+        tag if tag & 0xc0 == 0x80 => Entry::String(bytes(data, usize::from(tag & 0x3f))?),
+        // i13
+        tag if tag & 0xe0 == 0xc0 => {
+            let [next] = take(data)?;
+            // Shift left and then right to get the correct leading bits
+            let value = i16::from_be_bytes([tag & 0x1f, next]) << 3 >> 3;
+            Entry::Integer(value.into())
+        }
+        // 12 bit string length
+        tag if tag & 0xf0 == 0xe0 => {
+            let [next] = take(data)?;
+            let len = u16::from_be_bytes([tag & 0x0f, next]);
+            Entry::String(bytes(data, len.into())?)
+        }
+        // 32 bit string length
+        0xf0 => {
+            let len = u32::from_le_bytes(take(data)?);
+            Entry::String(bytes(data, usize::try_from(len).unwrap_or(usize::MAX))?)
+        }
+        0xf1 => Entry::Integer(i16::from_le_bytes(take(data)?).into()),
+        0xf2 => {
+            let [a, b, c] = take(data)?;
+            Entry::Integer((i32::from_le_bytes([0, a, b, c]) >> 8).into())
+        }
+        0xf3 => Entry::Integer(i32::from_le_bytes(take(data)?).into()),
+        0xf4 => Entry::Integer(i64::from_le_bytes(take(data)?)),
+        tag => return Err(RdbError::Encoding(tag)),
+    };
+
+    // Skip the length stored after the entry for iterating backwards.
+    let mut len = start - data.len();
+    while len > 0 {
+        take::<1>(data)?;
+        len >>= 7;
+    }
+    Ok(entry)
+}
+
+/// Decode a ziplist, as Redis stored small values before listpacks replaced them.
+///
+/// The layout is a header with the total size, the offset of the last entry, and the number of
+/// entries, followed by the entries and an end byte. Each entry starts with the size of the entry
+/// before it.
+pub fn ziplist(data: &[u8]) -> Result<Pack, RdbError> {
+    let mut rest = data;
+    let size = u32::from_le_bytes(take(&mut rest)?);
+    let _tail = u32::from_le_bytes(take(&mut rest)?);
+    let count = u16::from_le_bytes(take(&mut rest)?);
+    if usize::try_from(size) != Ok(data.len()) {
+        return Err(RdbError::Header);
+    }
+
+    let mut pack = Pack::default();
+    loop {
+        match rest {
+            [0xff] => break,
+            [] | [0xff, ..] => return Err(RdbError::Truncated),
+            _ => ziplist_entry(&mut rest)?.append_to(&mut pack),
+        }
+    }
+
+    // A count of u16::MAX means there were too many entries to count in the header.
+    if count != u16::MAX && usize::from(count) != pack.len() {
+        return Err(RdbError::Header);
+    }
+    Ok(pack)
+}
+
+fn ziplist_entry<'a>(data: &mut &'a [u8]) -> Result<Entry<'a>, RdbError> {
+    match take(data)? {
+        [0xfe] => _ = take::<4>(data)?,
+        [0xff] => return Err(RdbError::Encoding(0xff)),
+        _ => {}
+    }
+
+    let [tag] = take(data)?;
+    let entry = match tag {
+        // 6 bit string length
+        tag if tag >> 6 == 0 => Entry::String(bytes(data, usize::from(tag & 0x3f))?),
+        // 14 bit string length
+        tag if tag >> 6 == 1 => {
+            let [next] = take(data)?;
+            let len = u16::from_be_bytes([tag & 0x3f, next]);
+            Entry::String(bytes(data, len.into())?)
+        }
+        // 32 bit string length
+        0x80 => {
+            let len = u32::from_be_bytes(take(data)?);
+            Entry::String(bytes(data, usize::try_from(len).unwrap_or(usize::MAX))?)
+        }
+        0xc0 => Entry::Integer(i16::from_le_bytes(take(data)?).into()),
+        0xd0 => Entry::Integer(i32::from_le_bytes(take(data)?).into()),
+        0xe0 => Entry::Integer(i64::from_le_bytes(take(data)?)),
+        0xf0 => {
+            let [a, b, c] = take(data)?;
+            Entry::Integer((i32::from_le_bytes([0, a, b, c]) >> 8).into())
+        }
+        0xfe => Entry::Integer(i8::from_le_bytes(take(data)?).into()),
+        // Integers from 0 to 12 are stored in the tag, plus one.
+        0xf1..=0xfd => Entry::Integer(i64::from(tag & 0x0f) - 1),
+        tag => return Err(RdbError::Encoding(tag)),
+    };
+    Ok(entry)
+}
+
+/// Decode an intset, as Redis stores small sets of integers.
+///
+/// The layout is the width of each integer in bytes, the number of integers, and then the sorted
+/// integers themselves.
+pub fn intset(data: &[u8]) -> Result<IntSet, RdbError> {
+    let mut rest = data;
+    let encoding = u32::from_le_bytes(take(&mut rest)?);
+    let len = u32::from_le_bytes(take(&mut rest)?);
+
+    let mut set = IntSet::default();
+    for _ in 0..len {
+        let value = match encoding {
+            2 => i16::from_le_bytes(take(&mut rest)?).into(),
+            4 => i32::from_le_bytes(take(&mut rest)?).into(),
+            8 => i64::from_le_bytes(take(&mut rest)?),
+            encoding => return Err(RdbError::IntSetEncoding(encoding)),
+        };
+        set.insert(value);
+    }
+
+    if !rest.is_empty() {
+        return Err(RdbError::Header);
+    }
+    Ok(set)
+}
+
+/// Decode the nodes of a list stored as `RDB_TYPE_LIST_QUICKLIST`, where every node is a ziplist.
+pub fn quicklist<'a>(nodes: impl IntoIterator<Item = &'a [u8]>) -> Result<QuickList, RdbError> {
+    nodes
+        .into_iter()
+        .map(|node| ziplist(node).map(PackList::from))
+        .filter(|pack| !matches!(pack, Ok(pack) if pack.is_empty()))
+        .collect()
+}
+
+/// Decode the nodes of a list stored as `RDB_TYPE_LIST_QUICKLIST_2`, where each node is a
+/// container type followed by either a single large element or a listpack.
+pub fn quicklist2<'a>(
+    nodes: impl IntoIterator<Item = (u64, &'a [u8])>,
+) -> Result<QuickList, RdbError> {
+    nodes
+        .into_iter()
+        .map(|(container, node)| match container {
+            CONTAINER_PLAIN => Ok(PackList::from(&node)),
+            CONTAINER_PACKED => listpack(node).map(PackList::from),
+            container => Err(RdbError::Container(container)),
+        })
+        .filter(|pack| !matches!(pack, Ok(pack) if pack.is_empty()))
+        .collect()
+}
+
+/// Read a fixed number of bytes from the front of `data`.
+fn take<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], RdbError> {
+    let (bytes, rest) = data.split_first_chunk::<N>().ok_or(RdbError::Truncated)?;
+    *data = rest;
+    Ok(*bytes)
+}
+
+/// Read `len` bytes from the front of `data`.
+fn bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], RdbError> {
+    let (bytes, rest) = data.split_at_checked(len).ok_or(RdbError::Truncated)?;
+    *data = rest;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Packable, db::Edge};
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/rdb/",
+                $name
+            ))
+        };
+    }
+
+    fn pack(values: &[&str]) -> Pack {
+        let mut pack = Pack::default();
+        for value in values {
+            pack.append(value);
+        }
+        pack
+    }
+
+    #[test]
+    fn decode_listpack() {
+        let long = "x".repeat(100);
+        let expected = pack(&[
+            "a",
+            "0",
+            "127",
+            "-1",
+            "4095",
+            "-4096",
+            "30000",
+            "-40000",
+            "8388607",
+            "2147483647",
+            "-2147483649",
+            &long,
+            "hello",
+        ]);
+        assert_eq!(listpack(fixture!("listpack.bin")), Ok(expected));
+    }
+
+    #[test]
+    fn decode_ziplist() {
+        let long = "y".repeat(300);
+        let expected = pack(&[
+            "a",
+            "0",
+            "12",
+            "13",
+            "-128",
+            "300",
+            "-40000",
+            "8388607",
+            "2147483647",
+            "-2147483649",
+            &long,
+            "hello",
+        ]);
+        assert_eq!(ziplist(fixture!("ziplist.bin")), Ok(expected));
+    }
+
+    #[test]
+    fn decode_intset() {
+        let set = intset(fixture!("intset16.bin")).unwrap();
+        assert_eq!(set.iter().collect::<Vec<_>>(), [-2, 1, 3, 32767]);
+        let set = intset(fixture!("intset64.bin")).unwrap();
+        let expected = [-5_000_000_000, 1, 5_000_000_000];
+        assert_eq!(set.iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn decode_quicklist() {
+        let list = quicklist([&fixture!("ziplist.bin")[..]]).unwrap();
+        assert_eq!(list.len(), 12);
+        assert_eq!(list.packs(), 1);
+    }
+
+    #[test]
+    fn decode_quicklist2() {
+        let large = [b'z'; 128];
+        let nodes = [
+            (CONTAINER_PACKED, &fixture!("listpack.bin")[..]),
+            (CONTAINER_PLAIN, &large[..]),
+        ];
+        let list = quicklist2(nodes).unwrap();
+        assert_eq!(list.len(), 14);
+        assert_eq!(list.packs(), 2);
+        let last = list.peek(Edge::Right).unwrap();
+        assert!((&large[..]).pack_eq(&last));
+
+        let nodes = [(3, &fixture!("listpack.bin")[..])];
+        assert_eq!(quicklist2(nodes), Err(RdbError::Container(3)));
+    }
+
+    #[test]
+    fn decode_errors() {
+        let data = fixture!("listpack.bin");
+        assert_eq!(listpack(&data[..4]), Err(RdbError::Truncated));
+        assert_eq!(listpack(&data[..data.len() - 1]), Err(RdbError::Header));
+
+        // Change the size in the header to drop the end byte.
+        let mut data = data[..data.len() - 1].to_vec();
+        data[0] -= 1;
+        assert_eq!(listpack(&data), Err(RdbError::Truncated));
+
+        // Change the count in the header.
+        let mut data = fixture!("ziplist.bin").to_vec();
+        data[8] += 1;
+        assert_eq!(ziplist(&data), Err(RdbError::Header));
+
+        // An unknown encoding.
+        assert_eq!(
+            listpack(b"\x08\0\0\0\x01\0\xf5\xff"),
+            Err(RdbError::Encoding(0xf5))
+        );
+
+        let data = fixture!("intset16.bin");
+        assert_eq!(intset(&data[..data.len() - 1]), Err(RdbError::Truncated));
+        assert_eq!(
+            intset(b"\x03\0\0\0\x01\0\0\0\0\0\0"),
+            Err(RdbError::IntSetEncoding(3))
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use thiserror::Error;
+
+/// Why a value encoded by Redis couldn't be decoded.
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum RdbError {
+    #[error("invalid quicklist node container {0}")]
+    Container(u64),
+
+    #[error("unknown entry encoding {0:#04x}")]
+    Encoding(u8),
+
+    #[error("header doesn't match the encoded value")]
+    Header,
+
+    #[error("invalid intset encoding {0}")]
+    IntSetEncoding(u32),
+
+    #[error("unexpected end of encoded value")]
+    Truncated,
+}
@@ -1,8 +1,10 @@
 mod bulk;
+mod double;
 mod error;
 mod status;
 
 pub use bulk::BulkReply;
+pub use double::DoubleOutput;
 pub use error::ReplyError;
 pub use status::StatusReply;
 
@@ -18,19 +20,21 @@ use crate::{
 };
 use bytes::Bytes;
 use ordered_float::NotNan;
-use piccolo::FromMultiValue;
 use respite::RespError;
 use tokio::sync::oneshot;
 
 #[derive(Debug)]
 pub enum Reply {
     Array(usize),
+    /// An attribute with `len` key/value pairs, which precedes the reply it describes. RESP2
+    /// clients don't understand attributes, so they're dropped along with their contents.
+    Attribute(usize),
     Bignum(Bytes),
     Boolean(bool),
     Bulk(BulkReply),
-    DeferredArray(oneshot::Receiver<usize>),
-    DeferredMap(oneshot::Receiver<usize>),
-    DeferredSet(oneshot::Receiver<usize>),
+    DeferredArray(oneshot::Receiver<Deferred>),
+    DeferredMap(oneshot::Receiver<Deferred>),
+    DeferredSet(oneshot::Receiver<Deferred>),
     Double(f64),
     Error(ReplyError),
     Integer(i64),
@@ -42,6 +46,38 @@ pub enum Reply {
     Verbatim(Bytes, BulkReply),
 }
 
+impl Reply {
+    /// Roughly how many bytes this reply holds while it waits to be written, for counting
+    /// toward `maxmemory-clients`.
+    pub fn size(&self) -> usize {
+        use Reply::*;
+        let payload = match self {
+            Bignum(value) => value.len(),
+            Bulk(value) => value.size(),
+            Status(value) => value.size(),
+            Verbatim(format, value) => format.len() + value.size(),
+            _ => 0,
+        };
+        std::mem::size_of::<Reply>() + payload
+    }
+}
+
+/// The outcome of a deferred reply, sent after its elements.
+#[derive(Debug)]
+pub enum Deferred {
+    /// The reply is complete, with this many elements (or pairs, for a map).
+    Len(usize),
+
+    /// The reply was abandoned after `sent` replies, and is replaced by `error`.
+    Abort { sent: usize, error: ReplyError },
+}
+
+impl From<usize> for Deferred {
+    fn from(value: usize) -> Self {
+        Deferred::Len(value)
+    }
+}
+
 impl From<Raw> for Reply {
     fn from(value: Raw) -> Self {
         Reply::Bulk(value.into())
@@ -66,6 +102,15 @@ impl From<i64> for Reply {
     }
 }
 
+impl From<i128> for Reply {
+    fn from(value: i128) -> Self {
+        match i64::try_from(value) {
+            Ok(value) => Reply::Integer(value),
+            Err(_) => Reply::Bignum(value.to_string().into()),
+        }
+    }
+}
+
 impl From<usize> for Reply {
     fn from(value: usize) -> Self {
         match i64::try_from(value) {
@@ -158,7 +203,7 @@ impl From<&Arity> for Reply {
         use Arity::*;
         Reply::Integer(match arity {
             Exact(arity) => (*arity).into(),
-            Minimum(arity) => {
+            Minimum(arity) | Range(arity, _) => {
                 let arity: i64 = (*arity).into();
                 -arity
             }
@@ -256,8 +301,10 @@ impl From<PackRef<'_>> for Reply {
     fn from(value: PackRef) -> Self {
         use PackRef::*;
         match value {
+            Bool(b) => Reply::Boolean(b),
             Float(f) => Reply::Bulk(f.into()),
             Integer(i) => Reply::Bulk(i.into()),
+            Nil => Reply::Nil,
             Slice(s) => Reply::Bulk(s.to_owned().into()),
         }
     }
@@ -267,8 +314,10 @@ impl From<&PackRef<'_>> for Reply {
     fn from(value: &PackRef) -> Self {
         use PackRef::*;
         match value {
+            Bool(b) => Reply::Boolean(*b),
             Float(f) => Reply::Bulk((*f).into()),
             Integer(i) => Reply::Bulk((*i).into()),
+            Nil => Reply::Nil,
             Slice(s) => Reply::Bulk(s.to_owned().into()),
         }
     }
@@ -278,8 +327,10 @@ impl From<PackValue> for Reply {
     fn from(value: PackValue) -> Self {
         use PackValue::*;
         match value {
+            Bool(b) => Reply::Boolean(b),
             Float(f) => Reply::Bulk(f.into()),
             Integer(i) => Reply::Bulk(i.into()),
+            Nil => Reply::Nil,
             Raw(s) => Reply::Bulk(s.into()),
         }
     }
@@ -290,17 +341,3 @@ impl From<YesNo> for Reply {
         Reply::Bulk(if value.0 { "yes" } else { "no" }.into())
     }
 }
-
-impl<'gc> FromMultiValue<'gc> for Reply {
-    fn from_multi_value(
-        _context: piccolo::Context<'gc>,
-        mut values: impl Iterator<Item = piccolo::Value<'gc>>,
-    ) -> Result<Self, piccolo::TypeError> {
-        let first = values.next();
-        match first {
-            Some(piccolo::Value::Nil) => Ok(Reply::Nil),
-            Some(piccolo::Value::Integer(i)) => Ok(Reply::Integer(i)),
-            _ => todo!(),
-        }
-    }
-}
@@ -14,6 +14,17 @@ pub enum BulkReply {
 }
 
 impl BulkReply {
+    /// The size of the reply in bytes, without serializing it.
+    pub fn size(&self) -> usize {
+        use BulkReply::*;
+        match self {
+            Bytes(value) => value.len(),
+            RawSlice(value) => value.len(),
+            StringSlice(value) => value.range.len(),
+            StringValue(value) => value.len(),
+        }
+    }
+
     pub fn as_bytes<'v>(&'v self, buffer: &'v mut impl Buffer) -> &'v [u8] {
         use BulkReply::*;
         match self {
@@ -0,0 +1,57 @@
+use arrayvec::ArrayString;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// An output wrapper to format a double reply like Redis, as the shortest representation that
+/// round trips. Like `%.17g`, exponent notation is used below `1e-4` and from `1e17` up.
+pub struct DoubleOutput(pub f64);
+
+impl Display for DoubleOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value = self.0;
+
+        if value.is_nan() {
+            return f.write_str("nan");
+        }
+
+        if value.is_infinite() {
+            return f.write_str(if value < 0.0 { "-inf" } else { "inf" });
+        }
+
+        // Rust writes the shortest digits that round trip, e.g. "-1.25e-7".
+        let mut buffer = ArrayString::<32>::new();
+        write!(buffer, "{value:e}")?;
+        let Some((mantissa, exponent)) = buffer.split_once('e') else {
+            return Err(fmt::Error);
+        };
+        let exponent: i32 = exponent.parse().map_err(|_| fmt::Error)?;
+
+        if (-4..17).contains(&exponent) {
+            write!(f, "{value}")
+        } else {
+            let sign = if exponent < 0 { '-' } else { '+' };
+            write!(f, "{mantissa}e{sign}{:02}", exponent.abs())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixture() {
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/double/replies.txt"
+        ));
+        let cases = fixture
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        for case in cases {
+            let (value, expected) = case.split_once(' ').unwrap();
+            let value: f64 = value.parse().unwrap();
+            assert_eq!(DoubleOutput(value).to_string(), expected, "{value:?}");
+        }
+    }
+}
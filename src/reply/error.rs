@@ -5,8 +5,14 @@ use crate::{
 };
 use bytes::Bytes;
 use respite::RespError;
+use std::borrow::Cow;
 use thiserror::Error;
 
+/// An error reply, with the Redis error class as the first word of each message.
+///
+/// Errors that need context, like a key or command name, hold on to [`Bytes`] from the request
+/// or a `&'static` [`Command`] and format it only when the error is written, so building the
+/// success path never allocates for them.
 #[derive(Debug, Error)]
 pub enum ReplyError {
     #[error("ERR The bit argument must be 1 or 0.")]
@@ -21,12 +27,29 @@ pub enum ReplyError {
     #[error("ERR BITOP NOT must be called with a single source key.")]
     BitopNot,
 
+    #[error("ERR BITOP DIFF, DIFF1 and ANDOR must be called with at least two source keys.")]
+    BitopSources,
+
+    #[error(
+        "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE."
+    )]
+    Busy,
+
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+
     #[error("BUSYKEY Target key name already exists.")]
     BusyKey,
 
     #[error("ERR Client names cannot contain spaces, newlines or special characters.")]
     ClientName,
 
+    #[error(
+        "ERR CONFIG SET failed (possibly related to argument '{}') - can't set immutable config",
+        .0.name
+    )]
+    ConfigImmutable(&'static Config),
+
     #[error("ERR Invalid argument '{}' for CONFIG SET '{}' - {}", Output(.0), .1.name, .2)]
     ConfigSet(Bytes, &'static Config, ConfigError),
 
@@ -36,6 +59,11 @@ pub enum ReplyError {
     #[error("{}", Output(&.0[..]))]
     Custom(Bytes),
 
+    #[error(
+        "ERR DEBUG command not allowed. If the enable-debug-command option is set to \"local\", you can run it from a local connection, otherwise you need to set this option in the configuration file, and then restart the server."
+    )]
+    DebugCommand,
+
     #[error("ERR DB index is out of range")]
     DBIndex,
 
@@ -48,33 +76,43 @@ pub enum ReplyError {
     #[error("ERR invalid expire time in {} command", .0.name)]
     ExpireTime(&'static Command),
 
+    #[error("ERR FAILOVER requires connected replicas.")]
+    FailoverReplicas,
+
+    #[error("ERR FAILOVER timeout must be greater than 0")]
+    FailoverTimeout,
+
+    #[error("ERR hash field expiration is not supported")]
+    FieldExpiration,
+
+    #[error("ERR Mandatory argument FIELDS is missing or not at the right position")]
+    FieldsArgument,
+
     #[error("ERR value is not a valid float")]
     Float,
 
     #[error("ERR GT, LT, and/or NX options at the same time are not compatible")]
     GtLtNx,
 
-    #[error("ERR Syntax error in HELLO option {:?}", .0)]
+    #[error("ERR Syntax error in HELLO option '{}'", Output(.0))]
     Hello(Bytes),
 
+    #[error("ERR HELLO inside MULTI is not allowed")]
+    HelloInMulti,
+
     #[error("ERR increment or decrement would overflow")]
     IncrOverflow,
 
     #[error("ERR index out of range")]
     IndexOutOfRange,
 
-    #[error("ERR timeout is not finite")]
-    InfiniteTimeout,
-
     #[error("ERR value is not an integer or out of range")]
     Integer,
 
     #[error("ERR Invalid argument(s)")]
     InvalidArgument,
 
-    #[error(
-        "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
-    )]
+    #[error("ERR Invalid bitfield type. Use something like i16 u8.")]
     InvalidBitfield,
 
     #[error("ERR Invalid client ID")]
@@ -86,12 +124,18 @@ pub enum ReplyError {
     #[error("ERR Invalid arguments specified for command")]
     InvalidCommandArguments,
 
+    #[error("ERR invalid first DB index")]
+    InvalidFirstDBIndex,
+
     #[error("ERR Invalid number of arguments specified for command")]
     InvalidNumberOfArguments,
 
     #[error("ERR Invalid OVERFLOW type specified")]
     InvalidOverflow,
 
+    #[error("ERR invalid second DB index")]
+    InvalidSecondDBIndex,
+
     #[error("ERR timeout is not a float or out of range")]
     InvalidTimeout,
 
@@ -101,66 +145,183 @@ pub enum ReplyError {
     #[error("ERR invalid usize reply")]
     InvalidUsize,
 
+    #[error("LOADING Redis is loading the dataset in memory")]
+    Loading,
+
+    #[error("MASTERDOWN Link with MASTER is down and replica-serve-stale-data is set to 'no'.")]
+    MasterDown,
+
+    #[error("ERR max number of clients reached")]
+    MaxClients,
+
+    #[error("ERR min or max not valid string range item")]
+    MinOrMaxLex,
+
+    #[error("ERR min or max is not a float")]
+    MinOrMaxScore,
+
     #[error("ERR MULTI calls can not be nested")]
     MultiNested,
 
     #[error("ERR increment would produce NaN or Infinity")]
     NanOrInfinity,
 
+    #[error("ERR COUNT can't be negative")]
+    NegativeCount,
+
     #[error("ERR Number of keys can't be negative")]
     NegativeKeys,
 
+    #[error("ERR LIMIT can't be negative")]
+    NegativeLimit,
+
+    #[error("ERR MAXLEN can't be negative")]
+    NegativeMaxlen,
+
     #[error("ERR timeout is negative")]
     NegativeTimeout,
 
-    #[error("The command has no key arguments")]
+    #[error("NOAUTH Authentication required.")]
+    Noauth,
+
+    #[error("ERR No failover in progress.")]
+    NoFailover,
+
+    #[error("ERR The command has no key arguments")]
     Nokeys,
 
     #[error("NOPROTO unsupported protocol version")]
     Noproto,
 
+    #[error("NOREPLICAS Not enough good replicas to write.")]
+    Noreplicas,
+
     #[error("NOSCRIPT No matching script. Please use EVAL.")]
     Noscript,
 
+    #[error("ERR No such client")]
+    NoSuchClient,
+
     #[error("ERR no such key")]
     NoSuchKey,
 
+    #[error("NOTBUSY No scripts in execution right now.")]
+    Notbusy,
+
     #[error("ERR Number of keys can't be greater than number of args")]
     NumberOfKeys,
 
     #[error("ERR numkeys should be greater than 0")]
     NumkeysZero,
 
+    #[error("ERR Number of fields must be a positive integer")]
+    NumFields,
+
+    #[error("ERR The `numfields` parameter must match the number of arguments")]
+    NumFieldsMismatch,
+
     #[error("ERR offset is out of range")]
     OffsetRange,
 
+    #[error("OOM command not allowed when used memory > 'maxmemory'.")]
+    Oom,
+
+    #[error("ERR '{}' command panicked, closing the connection", .0.name)]
+    Panic(&'static Command),
+
+    #[error("ERR value is out of range, must be positive")]
+    Positive,
+
+    #[error("ERR Protocol version is not an integer or out of range")]
+    ProtocolVersion,
+
     #[error("ERR Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context", .0.name)]
     Pubsub(&'static Command),
 
+    #[error(
+        "ERR value is out of range, value must between -9223372036854775807 and 9223372036854775807"
+    )]
+    RankRange,
+
+    #[error(
+        "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list"
+    )]
+    RankZero,
+
+    #[error("READONLY You can't write against a read only replica.")]
+    ReadOnly,
+
     #[error("ERR Replica can't interact with the keyspace")]
     Replica,
 
+    #[error("ERR reply exceeds maximum allowed size (proto-max-bulk-len)")]
+    ReplyLength,
+
     #[error("ERR Protocol Error: {}", .0)]
     Resp(#[from] RespError),
 
     #[error("ERR source and destination objects are the same")]
     SameObject,
 
+    #[error("ERR {}", Output(.0))]
+    Script(Bytes),
+
+    #[error("ERR Lua redis lib command arguments must be strings or integers")]
+    ScriptArguments,
+
+    #[error("ERR Error compiling script (new function): {0}")]
+    ScriptCompile(String),
+
+    #[error("ERR Script killed by user with SCRIPT KILL...")]
+    ScriptKilled,
+
+    #[error("ERR wrong number or type of arguments")]
+    ScriptLibraryArguments,
+
+    #[error("ERR reached lua stack limit")]
+    ScriptNesting,
+
+    #[error("ERR Please specify at least one argument for this redis lib call")]
+    ScriptNoArguments,
+
+    #[error("ERR This Redis command is not allowed from script")]
+    ScriptNoscript,
+
+    #[error("ERR Invalid replication flags. Use REPL_AOF, REPL_REPLICA, REPL_ALL or REPL_NONE.")]
+    ScriptReplicationFlags,
+
+    #[error("ERR RESP version must be 2 or 3.")]
+    ScriptResp,
+
+    #[error("ERR Unknown Redis command called from script")]
+    ScriptUnknownCommand,
+
     #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
     StringLength,
 
     #[error("ERR syntax error")]
     Syntax,
 
+    #[error("ERR timeout is not an integer or out of range")]
+    TimeoutInteger,
+
+    #[error("ERR timeout is out of range")]
+    TimeoutRange,
+
     #[error("UNBLOCKED client unblocked via CLIENT UNBLOCK")]
     Unblocked,
 
-    #[error("ERR unknown command")]
-    UnknownCommand,
+    #[error("ERR unknown command {}", UnknownCommandContext(.0, .1))]
+    UnknownCommand(Bytes, Vec<Bytes>),
 
     #[error("ERR Unknown subcommand or wrong number of arguments for '{}'. Try {} HELP.", Output(.1), AsciiUpper(.0.name))]
     UnknownSubcommand(&'static Command, Bytes),
 
+    #[error(
+        "UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command."
+    )]
+    Unkillable,
+
     #[error("ERR Unknown option or number of arguments for CONFIG SET - '{}'", Output(.0))]
     UnsupportedParameter(Bytes),
 
@@ -170,6 +331,9 @@ pub enum ReplyError {
     #[error("ERR wrong number of arguments for '{}' command", .0.name)]
     WrongArguments(&'static Command),
 
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+
     #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
     WrongType,
 
@@ -181,3 +345,307 @@ pub enum ReplyError {
     )]
     ZrangeLimit,
 }
+
+/// The class of an error reply, which is the first word of its message.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorCode {
+    Busy,
+    BusyGroup,
+    BusyKey,
+    Err,
+    ExecAbort,
+    Loading,
+    MasterDown,
+    Noauth,
+    Noproto,
+    Noreplicas,
+    Noscript,
+    Notbusy,
+    Oom,
+    ReadOnly,
+    Unblocked,
+    Unkillable,
+    WrongPass,
+    WrongType,
+
+    /// A custom error from a script, with a class that isn't one of the above.
+    Other,
+}
+
+impl ErrorCode {
+    const ALL: [ErrorCode; 18] = [
+        ErrorCode::Busy,
+        ErrorCode::BusyGroup,
+        ErrorCode::BusyKey,
+        ErrorCode::Err,
+        ErrorCode::ExecAbort,
+        ErrorCode::Loading,
+        ErrorCode::MasterDown,
+        ErrorCode::Noauth,
+        ErrorCode::Noproto,
+        ErrorCode::Noreplicas,
+        ErrorCode::Noscript,
+        ErrorCode::Notbusy,
+        ErrorCode::Oom,
+        ErrorCode::ReadOnly,
+        ErrorCode::Unblocked,
+        ErrorCode::Unkillable,
+        ErrorCode::WrongPass,
+        ErrorCode::WrongType,
+    ];
+
+    /// The prefix that starts messages of this class, or `None` for [`ErrorCode::Other`].
+    pub fn prefix(self) -> Option<&'static str> {
+        Some(match self {
+            ErrorCode::Busy => "BUSY",
+            ErrorCode::BusyGroup => "BUSYGROUP",
+            ErrorCode::BusyKey => "BUSYKEY",
+            ErrorCode::Err => "ERR",
+            ErrorCode::ExecAbort => "EXECABORT",
+            ErrorCode::Loading => "LOADING",
+            ErrorCode::MasterDown => "MASTERDOWN",
+            ErrorCode::Noauth => "NOAUTH",
+            ErrorCode::Noproto => "NOPROTO",
+            ErrorCode::Noreplicas => "NOREPLICAS",
+            ErrorCode::Noscript => "NOSCRIPT",
+            ErrorCode::Notbusy => "NOTBUSY",
+            ErrorCode::Oom => "OOM",
+            ErrorCode::ReadOnly => "READONLY",
+            ErrorCode::Unblocked => "UNBLOCKED",
+            ErrorCode::Unkillable => "UNKILLABLE",
+            ErrorCode::WrongPass => "WRONGPASS",
+            ErrorCode::WrongType => "WRONGTYPE",
+            ErrorCode::Other => return None,
+        })
+    }
+
+    /// The class with this prefix, or [`ErrorCode::Other`] if there isn't one.
+    fn from_prefix(prefix: &[u8]) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|code| code.prefix().is_some_and(|p| p.as_bytes() == prefix))
+            .unwrap_or(ErrorCode::Other)
+    }
+}
+
+impl ReplyError {
+    /// The class of this error, so callers can branch on it without matching the message.
+    pub fn code(&self) -> ErrorCode {
+        use ReplyError::*;
+        match self {
+            Busy => ErrorCode::Busy,
+            BusyGroup => ErrorCode::BusyGroup,
+            BusyKey => ErrorCode::BusyKey,
+            Custom(message) => ErrorCode::from_prefix(first_word(message)),
+            ExecAbort => ErrorCode::ExecAbort,
+            Loading => ErrorCode::Loading,
+            MasterDown => ErrorCode::MasterDown,
+            Noauth => ErrorCode::Noauth,
+            Noproto => ErrorCode::Noproto,
+            Noreplicas => ErrorCode::Noreplicas,
+            Noscript => ErrorCode::Noscript,
+            Notbusy => ErrorCode::Notbusy,
+            Oom => ErrorCode::Oom,
+            ReadOnly => ErrorCode::ReadOnly,
+            Unblocked => ErrorCode::Unblocked,
+            Unkillable => ErrorCode::Unkillable,
+            WrongPass => ErrorCode::WrongPass,
+            WrongType => ErrorCode::WrongType,
+            BitArgument
+            | Bitfieldro
+            | BitOffset
+            | BitopNot
+            | BitopSources
+            | ClientName
+            | ConfigImmutable(..)
+            | ConfigSet(..)
+            | CountZero
+            | DebugCommand
+            | DBIndex
+            | ExecWithoutMulti
+            | ExpireTime(..)
+            | FailoverReplicas
+            | FailoverTimeout
+            | FieldExpiration
+            | FieldsArgument
+            | Float
+            | GtLtNx
+            | Hello(..)
+            | HelloInMulti
+            | IncrOverflow
+            | IndexOutOfRange
+            | Integer
+            | InvalidArgument
+            | InvalidBitfield
+            | InvalidClientId
+            | InvalidCommand
+            | InvalidCommandArguments
+            | InvalidFirstDBIndex
+            | InvalidNumberOfArguments
+            | InvalidOverflow
+            | InvalidSecondDBIndex
+            | InvalidTimeout
+            | InvalidTtl
+            | InvalidUsize
+            | MaxClients
+            | MinOrMaxLex
+            | MinOrMaxScore
+            | MultiNested
+            | NanOrInfinity
+            | NegativeCount
+            | NegativeKeys
+            | NegativeLimit
+            | NegativeMaxlen
+            | NegativeTimeout
+            | NoFailover
+            | Nokeys
+            | NoSuchClient
+            | NoSuchKey
+            | NumberOfKeys
+            | NumkeysZero
+            | NumFields
+            | NumFieldsMismatch
+            | OffsetRange
+            | Panic(..)
+            | Positive
+            | ProtocolVersion
+            | Pubsub(..)
+            | RankRange
+            | RankZero
+            | Replica
+            | ReplyLength
+            | Resp(..)
+            | SameObject
+            | Script(..)
+            | ScriptArguments
+            | ScriptCompile(..)
+            | ScriptKilled
+            | ScriptLibraryArguments
+            | ScriptNesting
+            | ScriptNoArguments
+            | ScriptNoscript
+            | ScriptReplicationFlags
+            | ScriptResp
+            | ScriptUnknownCommand
+            | StringLength
+            | Syntax
+            | TimeoutInteger
+            | TimeoutRange
+            | UnknownCommand(..)
+            | UnknownSubcommand(..)
+            | UnsupportedParameter(..)
+            | WatchInMulti
+            | WrongArguments(..)
+            | XxAndNx
+            | ZrangeLimit => ErrorCode::Err,
+        }
+    }
+
+    /// The first word of the message, as counted by `INFO errorstats`. This is the prefix of
+    /// [`ReplyError::code`] except for custom errors with an unknown class.
+    pub fn prefix(&self) -> Cow<'_, str> {
+        match (self, self.code().prefix()) {
+            (_, Some(prefix)) => Cow::Borrowed(prefix),
+            (ReplyError::Custom(message), None) => String::from_utf8_lossy(first_word(message)),
+            (_, None) => unreachable!("only custom errors have other codes"),
+        }
+    }
+}
+
+/// The first word of an error message.
+fn first_word(message: &[u8]) -> &[u8] {
+    message.split(|&b| b == b' ').next().unwrap_or_default()
+}
+
+/// The name and leading arguments of an unknown command, truncated like Redis so that a huge
+/// request doesn't produce a huge error.
+struct UnknownCommandContext<'a>(&'a [u8], &'a [Bytes]);
+
+impl std::fmt::Display for UnknownCommandContext<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const LIMIT: usize = 128;
+
+        let name = &self.0[..self.0.len().min(LIMIT)];
+        write!(f, "'{}', with args beginning with: ", Rejected(name))?;
+
+        let mut len = 0;
+        for argument in self.1 {
+            if len >= LIMIT {
+                break;
+            }
+            let argument = &argument[..argument.len().min(LIMIT - len)];
+            write!(f, "'{}' ", Rejected(argument))?;
+            len += argument.len() + 3;
+        }
+
+        Ok(())
+    }
+}
+
+/// Output for part of a rejected command, with line breaks replaced so the error stays on one line.
+struct Rejected<'a>(&'a [u8]);
+
+impl std::fmt::Display for Rejected<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bytes: Vec<u8> = self
+            .0
+            .iter()
+            .map(|&b| if b == b'\r' || b == b'\n' { b' ' } else { b })
+            .collect();
+        write!(f, "{}", Output(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code() {
+        assert_eq!(ReplyError::Syntax.code(), ErrorCode::Err);
+        assert_eq!(ReplyError::WrongType.code(), ErrorCode::WrongType);
+        assert_eq!(ReplyError::Oom.code(), ErrorCode::Oom);
+        assert_eq!(ReplyError::Script("oops".into()).code(), ErrorCode::Err);
+        assert_eq!(ReplyError::Custom("OOM no".into()).code(), ErrorCode::Oom);
+        assert_eq!(ReplyError::Custom("MY err".into()).code(), ErrorCode::Other);
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(ReplyError::Syntax.prefix(), "ERR");
+        assert_eq!(ReplyError::BusyKey.prefix(), "BUSYKEY");
+        assert_eq!(ReplyError::Custom("MY err".into()).prefix(), "MY");
+        assert_eq!(ReplyError::Custom("".into()).prefix(), "");
+    }
+
+    #[test]
+    fn prefix_matches_message() {
+        for error in [
+            ReplyError::Busy,
+            ReplyError::BusyGroup,
+            ReplyError::BusyKey,
+            ReplyError::ExecAbort,
+            ReplyError::Loading,
+            ReplyError::MasterDown,
+            ReplyError::Noauth,
+            ReplyError::Noproto,
+            ReplyError::Noreplicas,
+            ReplyError::Noscript,
+            ReplyError::Notbusy,
+            ReplyError::Oom,
+            ReplyError::ReadOnly,
+            ReplyError::Unblocked,
+            ReplyError::Unkillable,
+            ReplyError::WrongPass,
+            ReplyError::WrongType,
+            ReplyError::Syntax,
+        ] {
+            let message = error.to_string();
+            assert_eq!(
+                message.split(' ').next(),
+                Some(&*error.prefix()),
+                "{message}"
+            );
+        }
+    }
+}
@@ -9,6 +9,16 @@ pub enum StatusReply {
 }
 
 impl StatusReply {
+    /// The size of the reply in bytes, without serializing it.
+    pub fn size(&self) -> usize {
+        use StatusReply::*;
+        match self {
+            Bytes(value) => value.len(),
+            Str(value) => value.len(),
+            StringValue(value) => value.len(),
+        }
+    }
+
     pub fn as_bytes<'v>(&'v self, buffer: &'v mut impl Buffer) -> &'v [u8] {
         use StatusReply::*;
         match self {
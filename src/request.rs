@@ -1,20 +1,41 @@
 use crate::{
-    bytes::parse,
-    client::ClientId,
-    command::{self, Arity, Command, CommandKind, Keys},
+    bytes::{parse, parse_f64, parse_f64_bound, parse_i64_exact},
+    client::{ClientId, Endpoint},
+    command::{self, Command, CommandKind, Keys, Subcommand},
     db::DBIndex,
     epoch,
     reply::ReplyError,
 };
 use bytes::Bytes;
 use ordered_float::NotNan;
-use std::{collections::VecDeque, iter::StepBy, net::SocketAddr, ops::Range, time::Duration};
+use std::{
+    collections::VecDeque,
+    iter::StepBy,
+    ops::{Bound, Range},
+    time::Duration,
+};
+
+/// One end of a lexicographic range, as for `ZRANGE BYLEX`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LexBound {
+    /// `-`, before every value
+    Min,
+
+    /// `+`, after every value
+    Max,
+
+    Included(Bytes),
+    Excluded(Bytes),
+}
 
 #[derive(Clone, Debug)]
 pub struct Request {
     arguments: VecDeque<Bytes>,
     pub command: &'static Command,
     next: usize,
+
+    /// The total length of the arguments, in bytes.
+    size: usize,
 }
 
 impl Default for Request {
@@ -23,6 +44,7 @@ impl Default for Request {
             arguments: VecDeque::new(),
             command: &command::UNKNOWN,
             next: 1,
+            size: 0,
         }
     }
 }
@@ -44,11 +66,15 @@ impl Request {
 
     pub fn pop_front(&mut self) -> Option<Bytes> {
         let argument = self.arguments.pop_front();
+        if let Some(argument) = &argument {
+            self.size -= argument.len();
+        }
         self.set_command();
         argument
     }
 
     pub fn push_front(&mut self, argument: Bytes) {
+        self.size += argument.len();
         self.arguments.push_front(argument);
         self.set_command();
     }
@@ -59,15 +85,18 @@ impl Request {
 
     pub fn clear(&mut self) {
         self.next = 0;
+        self.size = 0;
         self.arguments.clear();
         self.command = &command::UNKNOWN;
     }
 
     pub fn drain(&mut self) -> impl Iterator<Item = Bytes> + '_ {
+        self.size = 0;
         self.arguments.drain(..)
     }
 
     pub fn push_back(&mut self, argument: Bytes) {
+        self.size += argument.len();
         self.arguments.push_back(argument);
         if self.len() == 1 {
             self.set_command();
@@ -79,6 +108,11 @@ impl Request {
         self.arguments.len()
     }
 
+    /// The total length of the arguments, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
     pub fn remaining(&self) -> usize {
         self.arguments.len() - self.next
     }
@@ -105,11 +139,7 @@ impl Request {
     }
 
     pub fn is_valid(&self) -> bool {
-        use Arity::*;
-        match self.command.arity {
-            Exact(arity) => self.len() == arity.into(),
-            Minimum(arity) => self.len() >= arity.into(),
-        }
+        self.command.arity.accepts(self.len())
     }
 
     pub fn peek(&mut self) -> Option<Bytes> {
@@ -133,6 +163,15 @@ impl Request {
         ReplyError::WrongArguments(self.command)
     }
 
+    /// Pop the name of a subcommand and find it in the command table, if it accepts the number of
+    /// arguments in this request.
+    pub fn subcommand(&mut self) -> Result<&'static Subcommand, ReplyError> {
+        let name = self.pop()?;
+        command::lookup_subcommand(self.command, &name)
+            .filter(|subcommand| subcommand.arity.accepts(self.len()))
+            .ok_or_else(|| self.unknown_subcommand())
+    }
+
     pub fn unknown_subcommand(&self) -> ReplyError {
         let subcommand = self.get(1).unwrap_or_else(|| "".into());
         ReplyError::UnknownSubcommand(self.command, subcommand)
@@ -147,11 +186,32 @@ impl Request {
     }
 
     pub fn bit_offset(&mut self) -> Result<usize, ReplyError> {
-        self.usize().map_err(|_| ReplyError::BitOffset)
+        self.at_least(0, ReplyError::BitOffset)
     }
 
+    /// Parse an integer exactly the way Redis does: no surrounding space, no plus sign, and no
+    /// leading zeros.
     pub fn i64(&mut self) -> Result<i64, ReplyError> {
-        parse(&self.pop()?).ok_or(ReplyError::Integer)
+        parse_i64_exact(&self.pop()?).ok_or(ReplyError::Integer)
+    }
+
+    /// Parse an integer of at least `min`, replying with `error` if the argument isn't an integer
+    /// or is out of range.
+    pub fn at_least(&mut self, min: usize, error: ReplyError) -> Result<usize, ReplyError> {
+        parse_i64_exact(&self.pop()?)
+            .and_then(|value| usize::try_from(value).ok())
+            .filter(|&value| value >= min)
+            .ok_or(error)
+    }
+
+    /// Parse a count that can't be negative, as for `LPOP` or `SPOP`.
+    pub fn positive(&mut self) -> Result<usize, ReplyError> {
+        self.at_least(0, ReplyError::Positive)
+    }
+
+    /// Parse a string offset, as for `SETRANGE`.
+    pub fn offset(&mut self) -> Result<usize, ReplyError> {
+        usize::try_from(self.i64()?).map_err(|_| ReplyError::OffsetRange)
     }
 
     pub fn client_id(&mut self) -> Result<ClientId, ReplyError> {
@@ -161,11 +221,7 @@ impl Request {
     }
 
     pub fn f64(&mut self) -> Result<f64, ReplyError> {
-        parse(&self.pop()?).ok_or(ReplyError::Float)
-    }
-
-    pub fn u128(&mut self) -> Result<u128, ReplyError> {
-        parse(&self.pop()?).ok_or(ReplyError::Integer)
+        parse_f64(&self.pop()?).ok_or(ReplyError::Float)
     }
 
     pub fn finite_f64(&mut self) -> Result<f64, ReplyError> {
@@ -177,81 +233,134 @@ impl Request {
         }
     }
 
-    pub fn usize(&mut self) -> Result<usize, ReplyError> {
-        parse(&self.pop()?).ok_or(ReplyError::OffsetRange)
-    }
-
-    pub fn integer(&mut self) -> Result<usize, ReplyError> {
-        self.usize().map_err(|_| ReplyError::Integer)
+    pub fn db_index(&mut self) -> Result<DBIndex, ReplyError> {
+        self.db_index_or(ReplyError::Integer)
     }
 
-    pub fn db_index(&mut self) -> Result<DBIndex, ReplyError> {
-        let value = self.usize().map_err(|_| ReplyError::Integer)?;
-        Ok(DBIndex(value))
+    /// Parse a database index, replying with `error` if it isn't an integer. Negative indexes
+    /// are out of range.
+    pub fn db_index_or(&mut self, error: ReplyError) -> Result<DBIndex, ReplyError> {
+        let index = parse_i64_exact(&self.pop()?).ok_or(error)?;
+        usize::try_from(index)
+            .map(DBIndex)
+            .map_err(|_| ReplyError::DBIndex)
     }
 
-    pub fn addr(&mut self) -> Result<Option<SocketAddr>, ReplyError> {
-        Ok(parse(&self.pop()?))
+    pub fn addr(&mut self) -> Result<Option<Endpoint>, ReplyError> {
+        Ok(Endpoint::parse(&self.pop()?))
     }
 
-    pub fn not_nan(&mut self) -> Result<NotNan<f64>, ReplyError> {
+    /// Parse a score for a sorted set member, as for `ZADD`. Infinite scores are allowed.
+    pub fn score(&mut self) -> Result<NotNan<f64>, ReplyError> {
         let f = self.f64()?;
         NotNan::new(f).map_err(|_| ReplyError::Float)
     }
 
+    /// Parse a timeout in seconds for a blocking command, rounded up to the nearest millisecond.
     pub fn timeout(&mut self) -> Result<Duration, ReplyError> {
         if self.is_empty() {
             return Err(self.wrong_arguments());
         }
 
         let timeout = self.f64().map_err(|_| ReplyError::InvalidTimeout)?;
+        let millis = (timeout * 1000_f64).ceil();
 
-        if timeout < 0_f64 {
-            return Err(ReplyError::NegativeTimeout);
+        #[allow(clippy::cast_precision_loss)]
+        if millis > i64::MAX as f64 {
+            return Err(ReplyError::TimeoutRange);
         }
 
-        if !timeout.is_finite() {
-            return Err(ReplyError::InfiniteTimeout);
+        if millis < 0_f64 {
+            return Err(ReplyError::NegativeTimeout);
         }
 
-        Ok(Duration::from_secs_f64(timeout))
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(Duration::from_millis(millis as u64))
     }
 
-    fn ttl_with<const U: i128>(&mut self) -> Result<u128, ReplyError> {
-        parse::<i128>(&self.pop()?)
-            .and_then(|x| x.checked_mul(U))
-            .and_then(|x| {
-                let epoch = epoch().as_millis();
-                let abs = x.unsigned_abs();
-                if x < 0 {
-                    epoch.checked_sub(abs)
-                } else {
-                    epoch.checked_add(abs)
-                }
-            })
-            .ok_or(ReplyError::ExpireTime(self.command))
+    /// Parse one end of a score range, where a leading `(` excludes it.
+    pub fn score_bound(&mut self) -> Result<Bound<f64>, ReplyError> {
+        let argument = self.pop()?;
+        let (bound, value): (fn(f64) -> Bound<f64>, _) = match &argument[..] {
+            [b'(', rest @ ..] => (Bound::Excluded, rest),
+            rest => (Bound::Included, rest),
+        };
+        parse_f64_bound(value)
+            .map(bound)
+            .ok_or(ReplyError::MinOrMaxScore)
+    }
+
+    /// Parse one end of a lexicographic range. A leading `[` includes the value and a leading `(`
+    /// excludes it, while `-` and `+` are unbounded below and above.
+    pub fn lex_bound(&mut self) -> Result<LexBound, ReplyError> {
+        let argument = self.pop()?;
+        match &argument[..] {
+            b"-" => Ok(LexBound::Min),
+            b"+" => Ok(LexBound::Max),
+            [b'[', ..] => Ok(LexBound::Included(argument.slice(1..))),
+            [b'(', ..] => Ok(LexBound::Excluded(argument.slice(1..))),
+            _ => Err(ReplyError::MinOrMaxLex),
+        }
     }
 
-    pub fn ttl(&mut self) -> Result<u128, ReplyError> {
-        self.ttl_with::<1000>()
+    /// Parse an expire time in units of `unit` milliseconds and return it in milliseconds since
+    /// the epoch. A `relative` time is added to the current time, and a `positive` one must be
+    /// greater than zero. Times before the epoch are clamped to it.
+    fn expire_with(
+        &mut self,
+        unit: i64,
+        relative: bool,
+        positive: bool,
+    ) -> Result<u128, ReplyError> {
+        let value = self.i64()?;
+        let error = ReplyError::ExpireTime(self.command);
+
+        if positive && value <= 0 {
+            return Err(error);
+        }
+
+        let mut at = value.checked_mul(unit);
+        if relative {
+            let now = i64::try_from(epoch().as_millis()).ok();
+            at = at.zip(now).and_then(|(at, now)| at.checked_add(now));
+        }
+
+        let at = at.ok_or(error)?;
+        Ok(u128::try_from(at).unwrap_or(0))
     }
 
-    pub fn pttl(&mut self) -> Result<u128, ReplyError> {
-        self.ttl_with::<1>()
+    pub fn ttl(&mut self) -> Result<u128, ReplyError> {
+        self.expire_with(1000, true, false)
     }
 
-    fn expiretime_with<const U: u128>(&mut self) -> Result<u128, ReplyError> {
-        parse::<u128>(&self.pop()?)
-            .and_then(|x| x.checked_mul(U))
-            .ok_or(ReplyError::ExpireTime(self.command))
+    pub fn pttl(&mut self) -> Result<u128, ReplyError> {
+        self.expire_with(1, true, false)
     }
 
     pub fn expiretime(&mut self) -> Result<u128, ReplyError> {
-        self.expiretime_with::<1000>()
+        self.expire_with(1000, false, false)
     }
 
     pub fn pexpiretime(&mut self) -> Result<u128, ReplyError> {
-        self.expiretime_with::<1>()
+        self.expire_with(1, false, false)
+    }
+
+    /// Like [`Request::ttl`], but zero or a negative value is an error rather than an immediate
+    /// expiration, as for `SET EX`, `SETEX`, and `GETEX EX`.
+    pub fn positive_ttl(&mut self) -> Result<u128, ReplyError> {
+        self.expire_with(1000, true, true)
+    }
+
+    pub fn positive_pttl(&mut self) -> Result<u128, ReplyError> {
+        self.expire_with(1, true, true)
+    }
+
+    pub fn positive_expiretime(&mut self) -> Result<u128, ReplyError> {
+        self.expire_with(1000, false, true)
+    }
+
+    pub fn positive_pexpiretime(&mut self) -> Result<u128, ReplyError> {
+        self.expire_with(1, false, true)
     }
 
     pub fn numkeys(&mut self) -> Result<usize, ReplyError> {
@@ -262,12 +371,24 @@ impl Request {
         }
     }
 
-    /// Get an iterator with the index of all keys.
+    /// Get an iterator with the index of all keys, using the keys of the subcommand if there is
+    /// one.
     pub fn keys(&self) -> Result<StepBy<Range<usize>>, ReplyError> {
         use Keys::*;
         let len = self.len();
 
-        let keys = match self.command.keys {
+        let subcommand = self
+            .get(1)
+            .and_then(|name| command::lookup_subcommand(self.command, &name));
+        let keys = match subcommand {
+            Some(subcommand) if !subcommand.arity.accepts(len) => {
+                return Err(ReplyError::InvalidNumberOfArguments);
+            }
+            Some(subcommand) => &subcommand.keys,
+            Option::None => &self.command.keys,
+        };
+
+        let keys = match *keys {
             All => (1..len).step_by(1),
             Argument(index) => {
                 let count: usize = self
@@ -287,6 +408,7 @@ impl Request {
             Double => (1..3).step_by(1),
             Odd => (1..len).step_by(2),
             None => return Err(ReplyError::Nokeys),
+            Second => (2..3).step_by(1),
             Single => (1..2).step_by(1),
             SkipOne => (2..len).step_by(1),
             Trailing => (1..len - 1).step_by(1),
@@ -314,3 +436,20 @@ impl std::fmt::Display for Request {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_retains_capacity() {
+        let mut request = Request::default();
+        for _ in 0..100 {
+            request.push_back(Bytes::from_static(b"x"));
+        }
+        let capacity = request.arguments.capacity();
+        request.clear();
+        assert_eq!(request.arguments.capacity(), capacity);
+        assert_eq!(request.len(), 0);
+    }
+}
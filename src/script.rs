@@ -0,0 +1,358 @@
+mod cjson;
+mod cmsgpack;
+mod convert;
+mod random;
+
+use crate::{Client, CommandResult, Reply, ReplyError, Store, command::CommandKind};
+use bytes::Bytes;
+use piccolo::{
+    Callback, CallbackReturn, Closure, Context, Error, Executor, ExecutorMode, Fuel, Lua, Stack,
+    StashedExecutor, Table, Value, Variadic,
+};
+use std::{cell::Cell, collections::VecDeque, mem, rc::Rc};
+use web_time::{Duration, Instant};
+
+/// How much work a script does between checks of the busy reply threshold.
+const SCRIPT_FUEL: i32 = 4096;
+
+/// Flags for `redis.set_repl`, which choose where a script's effects are propagated.
+const REPL_NONE: i64 = 0;
+const REPL_AOF: i64 = 1;
+const REPL_REPLICA: i64 = 2;
+const REPL_ALL: i64 = REPL_AOF | REPL_REPLICA;
+
+/// Why a script stopped running.
+enum Outcome {
+    /// The script called `redis.call` or `redis.pcall` and is waiting for the replies.
+    Call {
+        protected: bool,
+        arguments: Vec<Bytes>,
+    },
+
+    /// The script is done, with these replies for the client.
+    Done(Vec<Reply>),
+}
+
+/// Run a script with `KEYS` and `ARGV` and reply with its result.
+///
+/// Commands called by the script run on the same client, with `scripting` set so their replies
+/// are collected for conversion instead of being sent. The client's own request is set aside in
+/// the meantime and restored afterward.
+pub fn eval(
+    client: &mut Client,
+    store: &mut Store,
+    code: &[u8],
+    keys: Vec<Bytes>,
+    argv: Vec<Bytes>,
+) -> CommandResult {
+    let request = mem::take(&mut client.request);
+    client.scripting = true;
+    store.script_dirty = store.dirty;
+
+    let result = run(client, store, code, keys, argv);
+
+    client.scripting = false;
+    client.scripting_reply.clear();
+    client.request = request;
+    store.busy = false;
+    store.kill_script = false;
+
+    for reply in result? {
+        client.reply(reply);
+    }
+    Ok(None)
+}
+
+fn run(
+    client: &mut Client,
+    store: &mut Store,
+    code: &[u8],
+    keys: Vec<Bytes>,
+    argv: Vec<Bytes>,
+) -> Result<Vec<Reply>, ReplyError> {
+    let start = Instant::now();
+    let resp3 = Rc::new(Cell::new(false));
+    let mut lua = Lua::core();
+
+    let executor = lua
+        .enter(|ctx| {
+            load(ctx, &resp3);
+            _ = ctx.set_global("KEYS", strings(ctx, keys));
+            _ = ctx.set_global("ARGV", strings(ctx, argv));
+            match Closure::load(ctx, Some("user_script"), code) {
+                Ok(closure) => Ok(ctx.stash(Executor::start(ctx, closure.into(), ()))),
+                Err(error) => Err(error.to_string()),
+            }
+        })
+        .map_err(ReplyError::ScriptCompile)?;
+
+    loop {
+        finish(&mut lua, &executor, store, start)?;
+
+        let outcome = lua.enter(|ctx| {
+            let executor = ctx.fetch(&executor);
+            let result = executor.take_result::<Variadic<Vec<Value>>>(ctx).unwrap();
+            let mut replies = Vec::new();
+            match result {
+                Ok(values) if executor.mode() == ExecutorMode::Suspended => {
+                    let mut values = values.0.into_iter();
+                    let protected = values.next().is_some_and(Value::to_bool);
+                    let arguments = values
+                        .map(|value| match value {
+                            Value::String(value) => Bytes::copy_from_slice(value.as_bytes()),
+                            _ => Bytes::new(),
+                        })
+                        .collect();
+                    return Outcome::Call {
+                        protected,
+                        arguments,
+                    };
+                }
+                Ok(values) => {
+                    let value = values.first().copied().unwrap_or(Value::Nil);
+                    convert::to_replies(ctx, value, resp3.get(), &mut replies);
+                }
+                Err(error) => replies.push(error_reply(ctx, error).into()),
+            }
+            Outcome::Done(replies)
+        });
+
+        let (protected, arguments) = match outcome {
+            Outcome::Call {
+                protected,
+                arguments,
+            } => (protected, arguments),
+            Outcome::Done(replies) => return Ok(replies),
+        };
+
+        // Errors are raised in the script by `redis.call` and returned by `redis.pcall`.
+        let replies = call(client, store, arguments);
+        let raise = !protected && matches!(replies.front(), Some(Reply::Error(_)));
+        lua.enter(|ctx| {
+            let executor = ctx.fetch(&executor);
+            let value = convert::to_lua(ctx, &mut replies.into_iter(), resp3.get());
+            if raise {
+                executor.resume_err(&ctx, Error::from_value(value))
+            } else {
+                executor.resume(ctx, value)
+            }
+        })
+        .unwrap();
+    }
+}
+
+/// Run a command for a script and collect its replies.
+fn call(client: &mut Client, store: &mut Store, arguments: Vec<Bytes>) -> VecDeque<Reply> {
+    client.request.clear();
+    for argument in arguments {
+        client.request.push_back(argument);
+    }
+
+    let command = client.request.command;
+    let error = if command.kind == CommandKind::Unknown {
+        ReplyError::ScriptUnknownCommand
+    } else if command.noscript {
+        ReplyError::ScriptNoscript
+    } else {
+        client.run(store);
+        return mem::take(&mut client.scripting_reply);
+    };
+
+    client.request.clear();
+    VecDeque::from([error.into()])
+}
+
+/// Run a script until it finishes or stops to call a command. Once it runs past the busy reply
+/// threshold, handle messages from other clients in between steps so they can stop it with
+/// SCRIPT KILL or SHUTDOWN NOSAVE.
+fn finish(
+    lua: &mut Lua,
+    executor: &StashedExecutor,
+    store: &mut Store,
+    start: Instant,
+) -> Result<(), ReplyError> {
+    let deadline = start + Duration::from_millis(store.busy_reply_threshold);
+    while Instant::now() < deadline {
+        if step(lua, executor) {
+            return Ok(());
+        }
+    }
+
+    // Other clients can't make progress on this worker until the script is done.
+    crate::block_in_place(|| {
+        loop {
+            store.busy_poll();
+            if store.kill_script || store.shutdown {
+                return Err(ReplyError::ScriptKilled);
+            }
+            if step(lua, executor) {
+                return Ok(());
+            }
+        }
+    })
+}
+
+/// Run a script for a little while. Return `true` if it's stopped.
+fn step(lua: &mut Lua, executor: &StashedExecutor) -> bool {
+    let mut fuel = Fuel::with(SCRIPT_FUEL);
+    lua.enter(|ctx| ctx.fetch(executor).step(ctx, &mut fuel))
+}
+
+/// Load the `redis`, `cjson`, and `cmsgpack` libraries, and a deterministic `math.random`. `redis.setresp` sets `resp3`, which
+/// chooses how command replies are converted.
+fn load<'gc>(ctx: Context<'gc>, resp3: &Rc<Cell<bool>>) {
+    let redis = Table::new(&ctx);
+
+    let status_reply = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        let (1, Value::String(status)) = (stack.len(), stack.get(0)) else {
+            return Err(raise(ctx, &ReplyError::ScriptLibraryArguments));
+        };
+        stack.replace(ctx, convert::field(ctx, "ok", status.into()));
+        Ok(CallbackReturn::Return)
+    });
+
+    let error_reply = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        let (1, Value::String(error)) = (stack.len(), stack.get(0)) else {
+            return Err(raise(ctx, &ReplyError::ScriptLibraryArguments));
+        };
+        stack.replace(ctx, convert::field(ctx, "err", error.into()));
+        Ok(CallbackReturn::Return)
+    });
+
+    let resp3 = Rc::clone(resp3);
+    let setresp = Callback::from_fn(&ctx, move |ctx, _, mut stack| {
+        match (stack.len(), stack.get(0).to_integer()) {
+            (1, Some(2)) => resp3.set(false),
+            (1, Some(3)) => resp3.set(true),
+            _ => return Err(raise(ctx, &ReplyError::ScriptResp)),
+        }
+        stack.clear();
+        Ok(CallbackReturn::Return)
+    });
+
+    let sha1hex = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        let (1, Value::String(value)) = (stack.len(), stack.get(0)) else {
+            return Err(raise(ctx, &ReplyError::ScriptLibraryArguments));
+        };
+        let digest = sha1_smol::Sha1::from(value.as_bytes()).digest().to_string();
+        stack.replace(ctx, ctx.intern(digest.as_bytes()));
+        Ok(CallbackReturn::Return)
+    });
+
+    // Scripts are always replicated by their effects, so these are only kept for compatibility.
+    let replicate_commands = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        stack.replace(ctx, true);
+        Ok(CallbackReturn::Return)
+    });
+
+    let set_repl = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        match (stack.len(), stack.get(0).to_integer()) {
+            (1, Some(REPL_NONE..=REPL_ALL)) => {}
+            (1, _) => return Err(raise(ctx, &ReplyError::ScriptReplicationFlags)),
+            _ => return Err(raise(ctx, &ReplyError::ScriptLibraryArguments)),
+        }
+        stack.clear();
+        Ok(CallbackReturn::Return)
+    });
+
+    _ = redis.set(ctx, "call", redis_call(ctx, false));
+    _ = redis.set(ctx, "pcall", redis_call(ctx, true));
+    _ = redis.set(ctx, "status_reply", status_reply);
+    _ = redis.set(ctx, "error_reply", error_reply);
+    _ = redis.set(ctx, "setresp", setresp);
+    _ = redis.set(ctx, "sha1hex", sha1hex);
+    _ = redis.set(ctx, "replicate_commands", replicate_commands);
+    _ = redis.set(ctx, "set_repl", set_repl);
+    _ = redis.set(ctx, "REPL_NONE", REPL_NONE);
+    _ = redis.set(ctx, "REPL_AOF", REPL_AOF);
+    _ = redis.set(ctx, "REPL_REPLICA", REPL_REPLICA);
+    _ = redis.set(ctx, "REPL_SLAVE", REPL_REPLICA);
+    _ = redis.set(ctx, "REPL_ALL", REPL_ALL);
+
+    _ = ctx.set_global("redis", redis);
+    _ = ctx.set_global("cjson", cjson::load(ctx));
+    _ = ctx.set_global("cmsgpack", cmsgpack::load(ctx));
+    random::load(ctx);
+}
+
+/// `redis.call` and `redis.pcall` check their arguments and then yield them to the host, which
+/// runs the command and resumes the script with the result.
+fn redis_call<'gc>(ctx: Context<'gc>, protected: bool) -> Callback<'gc> {
+    Callback::from_fn(&ctx, move |ctx, _, mut stack| {
+        if stack.is_empty() {
+            return fail(ctx, stack, protected, &ReplyError::ScriptNoArguments);
+        }
+
+        for index in 0..stack.len() {
+            stack[index] = match stack[index] {
+                Value::String(value) => Value::String(value),
+                Value::Integer(value) => ctx.intern(value.to_string().as_bytes()).into(),
+                Value::Number(value) => ctx.intern(value.to_string().as_bytes()).into(),
+                _ => return fail(ctx, stack, protected, &ReplyError::ScriptArguments),
+            };
+        }
+
+        stack.push_front(Value::Boolean(protected));
+        Ok(CallbackReturn::Yield {
+            to_thread: None,
+            then: None,
+        })
+    })
+}
+
+/// Raise an error from `redis.call`, or return it from `redis.pcall`.
+fn fail<'gc>(
+    ctx: Context<'gc>,
+    mut stack: Stack<'gc, '_>,
+    protected: bool,
+    error: &ReplyError,
+) -> Result<CallbackReturn<'gc>, Error<'gc>> {
+    if protected {
+        stack.replace(ctx, error_table(ctx, error));
+        Ok(CallbackReturn::Return)
+    } else {
+        Err(raise(ctx, error))
+    }
+}
+
+/// An error table, as returned by `redis.error_reply`.
+fn error_table<'gc>(ctx: Context<'gc>, error: &ReplyError) -> Value<'gc> {
+    convert::field(ctx, "err", ctx.intern(error.to_string().as_bytes()).into())
+}
+
+/// Raise an error table, which becomes the script's error reply if it isn't caught.
+fn raise<'gc>(ctx: Context<'gc>, error: &ReplyError) -> Error<'gc> {
+    Error::from_value(error_table(ctx, error))
+}
+
+/// Raise a plain Lua error, as the helper libraries do.
+fn lua_error<'gc>(ctx: Context<'gc>, message: &str) -> Error<'gc> {
+    Value::String(ctx.intern(message.as_bytes())).into()
+}
+
+/// The reply for an error that escapes the script. Error tables are sent as is, and anything
+/// else is reported as a generic error.
+fn error_reply<'gc>(ctx: Context<'gc>, error: Error<'gc>) -> ReplyError {
+    let message = match error {
+        Error::Lua(error) => match error.0 {
+            Value::Table(table) => match table.get(ctx, "err") {
+                Value::String(error) => {
+                    return ReplyError::Custom(convert::single_line(error.as_bytes()).into());
+                }
+                _ => "Error running script: unknown error".to_owned(),
+            },
+            value => value.to_string(),
+        },
+        Error::Runtime(error) => error.to_string(),
+    };
+    ReplyError::Script(convert::single_line(message.as_bytes()).into())
+}
+
+/// A Lua array of strings, for `KEYS` and `ARGV`.
+fn strings<'gc>(ctx: Context<'gc>, values: Vec<Bytes>) -> Table<'gc> {
+    let table = Table::new(&ctx);
+    for (index, value) in (1_i64..).zip(values) {
+        _ = table.set(ctx, index, ctx.intern(&value));
+    }
+    table
+}
@@ -0,0 +1,197 @@
+use super::lua_error;
+use piccolo::{Callback, CallbackReturn, Context, Error, Table, UserData, Value};
+use std::io::Write;
+
+/// Tables nested deeper than this can't be encoded, which also catches cycles.
+const MAX_DEPTH: usize = 1000;
+
+/// The `cjson.null` sentinel, which stands in for JSON nulls since tables can't hold nil.
+struct Null;
+
+/// Build the `cjson` table with `encode`, `decode`, and `null`.
+pub fn load<'gc>(ctx: Context<'gc>) -> Table<'gc> {
+    let null = UserData::new_static(&ctx, Null);
+    let cjson = Table::new(&ctx);
+
+    let encode = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        if stack.len() != 1 {
+            return Err(lua_error(
+                ctx,
+                "bad argument #1 to 'encode' (expected 1 argument)",
+            ));
+        }
+        let mut json = Vec::new();
+        encode_value(ctx, stack.get(0), &mut json, 0)?;
+        stack.replace(ctx, ctx.intern(&json));
+        Ok(CallbackReturn::Return)
+    });
+
+    let decode = Callback::from_fn_with(&ctx, null, |null, ctx, _, mut stack| {
+        let (1, Value::String(json)) = (stack.len(), stack.get(0)) else {
+            return Err(lua_error(
+                ctx,
+                "bad argument #1 to 'decode' (string expected)",
+            ));
+        };
+        let json: serde_json::Value = serde_json::from_slice(json.as_bytes())
+            .map_err(|error| lua_error(ctx, &error.to_string()))?;
+        stack.replace(ctx, decode_value(ctx, *null, json));
+        Ok(CallbackReturn::Return)
+    });
+
+    _ = cjson.set(ctx, "encode", encode);
+    _ = cjson.set(ctx, "decode", decode);
+    _ = cjson.set(ctx, "null", null);
+    cjson
+}
+
+fn encode_value<'gc>(
+    ctx: Context<'gc>,
+    value: Value<'gc>,
+    json: &mut Vec<u8>,
+    depth: usize,
+) -> Result<(), Error<'gc>> {
+    match value {
+        Value::Nil => json.extend_from_slice(b"null"),
+        Value::Boolean(value) => _ = write!(json, "{value}"),
+        Value::Integer(value) => _ = write!(json, "{value}"),
+        Value::Number(value) if value.is_finite() => _ = write!(json, "{value}"),
+        Value::Number(_) => {
+            return Err(lua_error(
+                ctx,
+                "Cannot serialise number: must not be NaN or Infinity",
+            ));
+        }
+        Value::String(value) => encode_string(value.as_bytes(), json),
+        Value::Table(table) => encode_table(ctx, table, json, depth + 1)?,
+        Value::UserData(value) if value.is_static::<Null>() => json.extend_from_slice(b"null"),
+        value => {
+            let message = format!("Cannot serialise {}: type not supported", value.type_name());
+            return Err(lua_error(ctx, &message));
+        }
+    }
+    Ok(())
+}
+
+fn encode_table<'gc>(
+    ctx: Context<'gc>,
+    table: Table<'gc>,
+    json: &mut Vec<u8>,
+    depth: usize,
+) -> Result<(), Error<'gc>> {
+    if depth > MAX_DEPTH {
+        let message = format!("Cannot serialise, excessive nesting ({depth})");
+        return Err(lua_error(ctx, &message));
+    }
+
+    // A table with only positive integer keys is an array, with null for any gaps.
+    let (mut len, mut count) = (0, 0);
+    let mut array = true;
+    for (key, _) in table {
+        match key {
+            Value::Integer(index) if index > 0 => (len, count) = (len.max(index), count + 1),
+            _ => {
+                array = false;
+                break;
+            }
+        }
+    }
+
+    if array && len > 10 && len > count * 2 {
+        return Err(lua_error(
+            ctx,
+            "Cannot serialise table: excessively sparse array",
+        ));
+    }
+
+    if array && len > 0 {
+        json.push(b'[');
+        for index in 1..=len {
+            if index > 1 {
+                json.push(b',');
+            }
+            encode_value(ctx, table.get(ctx, index), json, depth)?;
+        }
+        json.push(b']');
+        return Ok(());
+    }
+
+    json.push(b'{');
+    for (index, (key, value)) in table.iter().enumerate() {
+        if index > 0 {
+            json.push(b',');
+        }
+        match key {
+            Value::String(key) => encode_string(key.as_bytes(), json),
+            Value::Integer(_) | Value::Number(_) => {
+                json.push(b'"');
+                encode_value(ctx, key, json, depth)?;
+                json.push(b'"');
+            }
+            _ => {
+                return Err(lua_error(
+                    ctx,
+                    "Cannot serialise table: table key must be a number or string",
+                ));
+            }
+        }
+        json.push(b':');
+        encode_value(ctx, value, json, depth)?;
+    }
+    json.push(b'}');
+    Ok(())
+}
+
+fn encode_string(value: &[u8], json: &mut Vec<u8>) {
+    json.push(b'"');
+    for &byte in value {
+        match byte {
+            b'"' => json.extend_from_slice(b"\\\""),
+            b'\\' => json.extend_from_slice(b"\\\\"),
+            b'/' => json.extend_from_slice(b"\\/"),
+            b'\n' => json.extend_from_slice(b"\\n"),
+            b'\r' => json.extend_from_slice(b"\\r"),
+            b'\t' => json.extend_from_slice(b"\\t"),
+            0x08 => json.extend_from_slice(b"\\b"),
+            0x0c => json.extend_from_slice(b"\\f"),
+            0..0x20 | 0x7f => _ = write!(json, "\\u{byte:04x}"),
+            _ => json.push(byte),
+        }
+    }
+    json.push(b'"');
+}
+
+fn decode_value<'gc>(
+    ctx: Context<'gc>,
+    null: UserData<'gc>,
+    json: serde_json::Value,
+) -> Value<'gc> {
+    use serde_json::Value::*;
+    match json {
+        Null => null.into(),
+        Bool(value) => value.into(),
+        Number(value) => match value.as_i64() {
+            Some(value) => value.into(),
+            None => value.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        String(value) => ctx.intern(value.as_bytes()).into(),
+        Array(values) => {
+            let table = Table::new(&ctx);
+            for (index, value) in (1_i64..).zip(values) {
+                _ = table.set(ctx, index, decode_value(ctx, null, value));
+            }
+            table.into()
+        }
+        Object(values) => {
+            let table = Table::new(&ctx);
+            for (key, value) in values {
+                _ = table.set(
+                    ctx,
+                    ctx.intern(key.as_bytes()),
+                    decode_value(ctx, null, value),
+                );
+            }
+            table.into()
+        }
+    }
+}
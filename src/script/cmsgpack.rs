@@ -0,0 +1,176 @@
+use super::lua_error;
+use piccolo::{Callback, CallbackReturn, Context, Table, Value};
+use rmp::{Marker, decode::read_marker, encode};
+
+/// Tables nested deeper than this are packed as nil, which also catches cycles.
+const MAX_DEPTH: usize = 16;
+
+/// Input nested deeper than this can't be unpacked, so it can't overflow the stack.
+const MAX_UNPACK_DEPTH: usize = 1000;
+
+const MISSING: &str = "Missing bytes in input.";
+const BAD: &str = "Bad data format in input.";
+
+/// Build the `cmsgpack` table with `pack` and `unpack`.
+pub fn load<'gc>(ctx: Context<'gc>) -> Table<'gc> {
+    let cmsgpack = Table::new(&ctx);
+
+    let pack = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        if stack.is_empty() {
+            return Err(lua_error(ctx, "MessagePack pack needs input."));
+        }
+        let mut data = Vec::new();
+        for value in stack.drain(..) {
+            pack_value(ctx, value, &mut data, 0);
+        }
+        stack.replace(ctx, ctx.intern(&data));
+        Ok(CallbackReturn::Return)
+    });
+
+    let unpack = Callback::from_fn(&ctx, |ctx, _, mut stack| {
+        let (1, Value::String(data)) = (stack.len(), stack.get(0)) else {
+            return Err(lua_error(
+                ctx,
+                "bad argument #1 to 'unpack' (string expected)",
+            ));
+        };
+        let mut data = data.as_bytes();
+        stack.clear();
+        while !data.is_empty() {
+            let value = unpack_value(ctx, &mut data, 0).map_err(|error| lua_error(ctx, error))?;
+            stack.push_back(value);
+        }
+        Ok(CallbackReturn::Return)
+    });
+
+    _ = cmsgpack.set(ctx, "pack", pack);
+    _ = cmsgpack.set(ctx, "unpack", unpack);
+    cmsgpack
+}
+
+fn pack_value<'gc>(ctx: Context<'gc>, value: Value<'gc>, data: &mut Vec<u8>, depth: usize) {
+    match value {
+        Value::Boolean(value) => _ = encode::write_bool(data, value),
+        Value::Integer(value) => _ = encode::write_sint(data, value),
+        #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+        Value::Number(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+            _ = encode::write_sint(data, value as i64);
+        }
+        Value::Number(value) => _ = encode::write_f64(data, value),
+        Value::String(value) => {
+            let value = value.as_bytes();
+            _ = encode::write_str_len(data, u32::try_from(value.len()).unwrap_or(u32::MAX));
+            data.extend_from_slice(value);
+        }
+        Value::Table(table) if depth < MAX_DEPTH => pack_table(ctx, table, data, depth + 1),
+        _ => _ = encode::write_nil(data),
+    }
+}
+
+fn pack_table<'gc>(ctx: Context<'gc>, table: Table<'gc>, data: &mut Vec<u8>, depth: usize) {
+    // Tables with keys 1..n are arrays and anything else is a map.
+    let count = table.iter().count();
+    let array = table.iter().all(|(key, _)| {
+        matches!(key, Value::Integer(index) if usize::try_from(index).is_ok_and(|index| (1..=count).contains(&index)))
+    });
+    let len = u32::try_from(count).unwrap_or(u32::MAX);
+
+    if array {
+        _ = encode::write_array_len(data, len);
+        for index in (1_i64..).take(count) {
+            pack_value(ctx, table.get(ctx, index), data, depth);
+        }
+    } else {
+        _ = encode::write_map_len(data, len);
+        for (key, value) in table {
+            pack_value(ctx, key, data, depth);
+            pack_value(ctx, value, data, depth);
+        }
+    }
+}
+
+fn unpack_value<'gc>(
+    ctx: Context<'gc>,
+    data: &mut &[u8],
+    depth: usize,
+) -> Result<Value<'gc>, &'static str> {
+    if depth > MAX_UNPACK_DEPTH {
+        return Err(BAD);
+    }
+
+    let marker = read_marker(data).map_err(|_| MISSING)?;
+
+    use Marker::*;
+    let value = match marker {
+        FixPos(value) => Value::Integer(value.into()),
+        FixNeg(value) => Value::Integer(value.into()),
+        Null => Value::Nil,
+        True => Value::Boolean(true),
+        False => Value::Boolean(false),
+        U8 => Value::Integer(u8::from_be_bytes(take(data)?).into()),
+        U16 => Value::Integer(u16::from_be_bytes(take(data)?).into()),
+        U32 => Value::Integer(u32::from_be_bytes(take(data)?).into()),
+        #[allow(clippy::cast_possible_wrap)]
+        U64 => Value::Integer(u64::from_be_bytes(take(data)?) as i64),
+        I8 => Value::Integer(i8::from_be_bytes(take(data)?).into()),
+        I16 => Value::Integer(i16::from_be_bytes(take(data)?).into()),
+        I32 => Value::Integer(i32::from_be_bytes(take(data)?).into()),
+        I64 => Value::Integer(i64::from_be_bytes(take(data)?)),
+        F32 => Value::Number(f32::from_be_bytes(take(data)?).into()),
+        F64 => Value::Number(f64::from_be_bytes(take(data)?)),
+        FixStr(len) => string(ctx, len.into(), data)?,
+        Str8 | Bin8 => string(ctx, u8::from_be_bytes(take(data)?).into(), data)?,
+        Str16 | Bin16 => string(ctx, u16::from_be_bytes(take(data)?).into(), data)?,
+        Str32 | Bin32 => string(ctx, u32::from_be_bytes(take(data)?), data)?,
+        FixArray(len) => array(ctx, len.into(), data, depth + 1)?,
+        Array16 => array(ctx, u16::from_be_bytes(take(data)?).into(), data, depth + 1)?,
+        Array32 => array(ctx, u32::from_be_bytes(take(data)?), data, depth + 1)?,
+        FixMap(len) => map(ctx, len.into(), data, depth + 1)?,
+        Map16 => map(ctx, u16::from_be_bytes(take(data)?).into(), data, depth + 1)?,
+        Map32 => map(ctx, u32::from_be_bytes(take(data)?), data, depth + 1)?,
+        _ => return Err(BAD),
+    };
+    Ok(value)
+}
+
+/// Read a fixed number of bytes from the front of `data`.
+fn take<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], &'static str> {
+    let (bytes, rest) = data.split_first_chunk::<N>().ok_or(MISSING)?;
+    *data = rest;
+    Ok(*bytes)
+}
+
+fn string<'gc>(ctx: Context<'gc>, len: u32, data: &mut &[u8]) -> Result<Value<'gc>, &'static str> {
+    let len = usize::try_from(len).unwrap_or(usize::MAX);
+    let (value, rest) = data.split_at_checked(len).ok_or(MISSING)?;
+    *data = rest;
+    Ok(ctx.intern(value).into())
+}
+
+fn array<'gc>(
+    ctx: Context<'gc>,
+    len: u32,
+    data: &mut &[u8],
+    depth: usize,
+) -> Result<Value<'gc>, &'static str> {
+    let table = Table::new(&ctx);
+    for index in (1_i64..).take(usize::try_from(len).unwrap_or(usize::MAX)) {
+        _ = table.set(ctx, index, unpack_value(ctx, data, depth)?);
+    }
+    Ok(table.into())
+}
+
+fn map<'gc>(
+    ctx: Context<'gc>,
+    len: u32,
+    data: &mut &[u8],
+    depth: usize,
+) -> Result<Value<'gc>, &'static str> {
+    let table = Table::new(&ctx);
+    for _ in 0..len {
+        let key = unpack_value(ctx, data, depth)?;
+        let value = unpack_value(ctx, data, depth)?;
+        _ = table.set(ctx, key, value);
+    }
+    Ok(table.into())
+}
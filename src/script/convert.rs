@@ -0,0 +1,276 @@
+use crate::{
+    Reply, ReplyError,
+    reply::{Deferred, DoubleOutput, StatusReply},
+};
+use bytes::Bytes;
+use piccolo::{Context, Table, Value};
+use tokio::sync::oneshot;
+
+/// Tables nested deeper than this can't be converted to a reply, which also catches cycles.
+const MAX_DEPTH: usize = 1000;
+
+/// Convert the value returned by a script into replies.
+///
+/// Tables with an `err` or `ok` field become errors and statuses, and the RESP3 tables built by
+/// `redis.setresp(3)` conversions (`map`, `set`, `double`, `big_number`, and `verbatim_string`)
+/// become their RESP3 counterparts. Any other table is an array up to its first nil.
+pub fn to_replies<'gc>(
+    ctx: Context<'gc>,
+    value: Value<'gc>,
+    resp3: bool,
+    replies: &mut Vec<Reply>,
+) {
+    to_replies_with_depth(ctx, value, resp3, replies, 0);
+}
+
+fn to_replies_with_depth<'gc>(
+    ctx: Context<'gc>,
+    value: Value<'gc>,
+    resp3: bool,
+    replies: &mut Vec<Reply>,
+    depth: usize,
+) {
+    if depth > MAX_DEPTH {
+        replies.push(ReplyError::ScriptNesting.into());
+        return;
+    }
+
+    let reply = match value {
+        Value::Boolean(true) if resp3 => Reply::Boolean(true),
+        Value::Boolean(false) if resp3 => Reply::Boolean(false),
+        Value::Boolean(true) => Reply::Integer(1),
+        Value::Integer(value) => Reply::Integer(value),
+        #[allow(clippy::cast_possible_truncation)]
+        Value::Number(value) => Reply::Integer(value as i64),
+        Value::String(value) => Reply::Bulk(value.as_bytes().into()),
+        Value::Table(table) => return table_replies(ctx, table, resp3, replies, depth),
+        _ => Reply::Nil,
+    };
+    replies.push(reply);
+}
+
+fn table_replies<'gc>(
+    ctx: Context<'gc>,
+    table: Table<'gc>,
+    resp3: bool,
+    replies: &mut Vec<Reply>,
+    depth: usize,
+) {
+    if let Value::String(error) = table.get(ctx, "err") {
+        let error = Bytes::from(single_line(error.as_bytes()));
+        replies.push(ReplyError::Custom(error).into());
+        return;
+    }
+
+    if let Value::String(status) = table.get(ctx, "ok") {
+        let status = StatusReply::from(single_line(status.as_bytes()));
+        replies.push(Reply::Status(status));
+        return;
+    }
+
+    if let Some(value) = table.get(ctx, "double").to_number() {
+        replies.push(Reply::Double(value));
+        return;
+    }
+
+    if let Value::String(value) = table.get(ctx, "big_number") {
+        replies.push(Reply::Bignum(Bytes::copy_from_slice(value.as_bytes())));
+        return;
+    }
+
+    if let Value::Table(map) = table.get(ctx, "map") {
+        replies.push(Reply::Map(map.iter().count()));
+        for (key, value) in map {
+            to_replies_with_depth(ctx, key, resp3, replies, depth + 1);
+            to_replies_with_depth(ctx, value, resp3, replies, depth + 1);
+        }
+        return;
+    }
+
+    if let Value::Table(set) = table.get(ctx, "set") {
+        replies.push(Reply::Set(set.iter().count()));
+        for (key, _) in set {
+            to_replies_with_depth(ctx, key, resp3, replies, depth + 1);
+        }
+        return;
+    }
+
+    if let Value::Table(verbatim) = table.get(ctx, "verbatim_string") {
+        if let (Value::String(format), Value::String(string)) =
+            (verbatim.get(ctx, "format"), verbatim.get(ctx, "string"))
+        {
+            let format = Bytes::copy_from_slice(format.as_bytes());
+            replies.push(Reply::Verbatim(format, string.as_bytes().into()));
+            return;
+        }
+    }
+
+    let start = replies.len();
+    replies.push(Reply::Array(0));
+    let mut len = 0;
+    for index in 1_i64.. {
+        let value = table.get(ctx, index);
+        if value.is_nil() {
+            break;
+        }
+        to_replies_with_depth(ctx, value, resp3, replies, depth + 1);
+        len += 1;
+    }
+    replies[start] = Reply::Array(len);
+}
+
+/// Error and status replies can't span lines, so replace any line breaks with spaces.
+pub fn single_line(value: &[u8]) -> Vec<u8> {
+    value
+        .iter()
+        .map(|&byte| {
+            if byte == b'\r' || byte == b'\n' {
+                b' '
+            } else {
+                byte
+            }
+        })
+        .collect()
+}
+
+/// Convert the replies of a command run by a script into a Lua value. RESP2 conversions are used
+/// unless the script has called `redis.setresp(3)`.
+pub fn to_lua<'gc>(
+    ctx: Context<'gc>,
+    replies: &mut impl Iterator<Item = Reply>,
+    resp3: bool,
+) -> Value<'gc> {
+    let Some(reply) = replies.next() else {
+        return Value::Nil;
+    };
+
+    let mut buffer = Vec::new();
+
+    use Reply::*;
+    match reply {
+        Array(len) | Push(len) => array(ctx, replies, len, resp3),
+        DeferredArray(mut len) => match deferred(ctx, replies, &mut len, resp3) {
+            Ok(len) => array(ctx, replies, len, resp3),
+            Err(error) => error,
+        },
+        Attribute(len) => {
+            for _ in 0..len * 2 {
+                to_lua(ctx, replies, resp3);
+            }
+            to_lua(ctx, replies, resp3)
+        }
+        Bignum(value) if resp3 => field(ctx, "big_number", ctx.intern(&value).into()),
+        Bignum(value) => ctx.intern(&value).into(),
+        Boolean(value) if resp3 => value.into(),
+        Boolean(value) => Value::Integer(value.into()),
+        Bulk(value) => ctx.intern(value.as_bytes(&mut buffer)).into(),
+        Double(value) if resp3 => field(ctx, "double", value.into()),
+        Double(value) => ctx
+            .intern(DoubleOutput(value).to_string().as_bytes())
+            .into(),
+        Error(error) => field(ctx, "err", ctx.intern(error.to_string().as_bytes()).into()),
+        Integer(value) => value.into(),
+        Map(len) => map(ctx, replies, len, resp3),
+        DeferredMap(mut len) => match deferred(ctx, replies, &mut len, resp3) {
+            Ok(len) => map(ctx, replies, len, resp3),
+            Err(error) => error,
+        },
+        Nil if resp3 => Value::Nil,
+        Nil => false.into(),
+        Set(len) => set(ctx, replies, len, resp3),
+        DeferredSet(mut len) => match deferred(ctx, replies, &mut len, resp3) {
+            Ok(len) => set(ctx, replies, len, resp3),
+            Err(error) => error,
+        },
+        Status(value) => field(ctx, "ok", ctx.intern(value.as_bytes(&mut buffer)).into()),
+        Verbatim(format, value) if resp3 => {
+            let verbatim = Table::new(&ctx);
+            _ = verbatim.set(ctx, "format", ctx.intern(&format));
+            _ = verbatim.set(ctx, "string", ctx.intern(value.as_bytes(&mut buffer)));
+            field(ctx, "verbatim_string", verbatim.into())
+        }
+        Verbatim(_, value) => ctx.intern(value.as_bytes(&mut buffer)).into(),
+    }
+}
+
+/// A table with a single field, like `{err = "ERR…"}`.
+pub fn field<'gc>(ctx: Context<'gc>, key: &'static str, value: Value<'gc>) -> Value<'gc> {
+    let table = Table::new(&ctx);
+    _ = table.set(ctx, key, value);
+    table.into()
+}
+
+/// The length of a deferred reply, which is always sent before the command finishes. An abandoned
+/// reply becomes its error, after dropping the replies that were sent for it.
+fn deferred<'gc>(
+    ctx: Context<'gc>,
+    replies: &mut impl Iterator<Item = Reply>,
+    len: &mut oneshot::Receiver<Deferred>,
+    resp3: bool,
+) -> Result<usize, Value<'gc>> {
+    match len.try_recv() {
+        Ok(Deferred::Len(len)) => Ok(len),
+        Ok(Deferred::Abort { sent, error }) => {
+            for _ in 0..sent {
+                to_lua(ctx, replies, resp3);
+            }
+            Err(field(
+                ctx,
+                "err",
+                ctx.intern(error.to_string().as_bytes()).into(),
+            ))
+        }
+        Err(_) => Ok(0),
+    }
+}
+
+fn array<'gc>(
+    ctx: Context<'gc>,
+    replies: &mut impl Iterator<Item = Reply>,
+    len: usize,
+    resp3: bool,
+) -> Value<'gc> {
+    let table = Table::new(&ctx);
+    for index in (1_i64..).take(len) {
+        let value = to_lua(ctx, replies, resp3);
+        _ = table.set(ctx, index, value);
+    }
+    table.into()
+}
+
+fn map<'gc>(
+    ctx: Context<'gc>,
+    replies: &mut impl Iterator<Item = Reply>,
+    len: usize,
+    resp3: bool,
+) -> Value<'gc> {
+    if !resp3 {
+        return array(ctx, replies, len * 2, resp3);
+    }
+
+    let table = Table::new(&ctx);
+    for _ in 0..len {
+        let key = to_lua(ctx, replies, resp3);
+        let value = to_lua(ctx, replies, resp3);
+        _ = table.set(ctx, key, value);
+    }
+    field(ctx, "map", table.into())
+}
+
+fn set<'gc>(
+    ctx: Context<'gc>,
+    replies: &mut impl Iterator<Item = Reply>,
+    len: usize,
+    resp3: bool,
+) -> Value<'gc> {
+    if !resp3 {
+        return array(ctx, replies, len, resp3);
+    }
+
+    let table = Table::new(&ctx);
+    for _ in 0..len {
+        let key = to_lua(ctx, replies, resp3);
+        _ = table.set(ctx, key, true);
+    }
+    field(ctx, "set", table.into())
+}
@@ -0,0 +1,49 @@
+use super::lua_error;
+use piccolo::{Callback, CallbackReturn, Context, Value};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{cell::RefCell, rc::Rc};
+
+/// Replace `math.random` and `math.randomseed` with a generator that starts from the same seed
+/// in every script, so a script produces the same effects each time it runs with the same data.
+pub fn load<'gc>(ctx: Context<'gc>) {
+    let Value::Table(math) = ctx.get_global("math") else {
+        return;
+    };
+
+    let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(0)));
+
+    let random_rng = Rc::clone(&rng);
+    let random = Callback::from_fn(&ctx, move |ctx, _, mut stack| {
+        let mut rng = random_rng.borrow_mut();
+        let (low, high) = (stack.get(0).to_integer(), stack.get(1).to_integer());
+        let value: Value = match (stack.len(), low, high) {
+            (0, ..) => rng.r#gen::<f64>().into(),
+            (1, Some(high), _) if high >= 1 => rng.gen_range(1..=high).into(),
+            (2, Some(low), Some(high)) if low <= high => rng.gen_range(low..=high).into(),
+            (1 | 2, ..) => {
+                return Err(lua_error(
+                    ctx,
+                    "bad argument to 'random' (interval is empty)",
+                ));
+            }
+            _ => return Err(lua_error(ctx, "wrong number of arguments")),
+        };
+        stack.replace(ctx, value);
+        Ok(CallbackReturn::Return)
+    });
+
+    let randomseed = Callback::from_fn(&ctx, move |ctx, _, mut stack| {
+        let Some(seed) = stack.get(0).to_integer() else {
+            return Err(lua_error(
+                ctx,
+                "bad argument #1 to 'randomseed' (number expected)",
+            ));
+        };
+        *rng.borrow_mut() = StdRng::seed_from_u64(u64::from_ne_bytes(seed.to_ne_bytes()));
+        stack.clear();
+        Ok(CallbackReturn::Return)
+    });
+
+    _ = math.set(ctx, "random", random);
+    _ = math.set(ctx, "randomseed", randomseed);
+}
@@ -1,12 +1,20 @@
+#[cfg(feature = "key-events")]
+use crate::store::KeyEventReceiver;
 use crate::{
     client::{Addr, Client},
-    store::{Store, StoreMessage},
+    config::{self, ConfigFileError},
+    db::DBIndex,
+    glob::Pattern,
+    reply::ReplyError,
+    store::{
+        self, Connections, EXPORT_BUFFER, Export, ExportEntry, ExportReceiver, Store, StoreMessage,
+        StoreSender,
+    },
 };
 use respite::RespConfig;
-use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    sync::mpsc,
-};
+use std::pin::pin;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use triomphe::Arc;
 
 /// The main interface for starting a redis server. The `Default` implementation spawns a server to
 /// go with it.
@@ -14,29 +22,391 @@ pub struct Server {
     /// The reader config, shared with each client.
     config: RespConfig,
 
+    /// Connection counts and limits, shared with the store.
+    connections: Arc<Connections>,
+
     /// A channel for communicating with the store.
-    store_sender: mpsc::UnboundedSender<StoreMessage>,
+    store_sender: StoreSender,
 }
 
 impl Default for Server {
     fn default() -> Self {
-        let (store_sender, receiver) = mpsc::unbounded_channel();
-        let config = Store::spawn(receiver);
+        Server::start(Vec::new())
+    }
+}
+
+impl Server {
+    /// Spawn a server with the options in the contents of a config file, like `redis.conf`.
+    /// Options that aren't in the file keep their defaults.
+    pub fn with_config(contents: &str) -> Result<Self, ConfigFileError> {
+        Ok(Server::start(config::parse_file(contents)?))
+    }
+
+    fn start(options: Vec<(&'static config::Config, config::ConfigValue)>) -> Self {
+        let (store_sender, receiver) = store::channel();
+        let (config, connections) = Store::spawn(receiver, options);
         Server {
             config,
+            connections,
             store_sender,
         }
     }
-}
 
-impl Server {
-    /// Connect a client to the server with a stream and a source address.
+    /// Export every key in database `db` that matches `pattern` (or every key, without one)
+    /// along with its value and expiration. The channel closes after the last entry, or right
+    /// away if there's no such database.
+    ///
+    /// Keys are sent in batches between other clients' commands, and the export waits whenever
+    /// the channel is full. Like SCAN, every key that exists for the whole export is sent, but a
+    /// key written in the meantime may or may not be, and each value is read as it's sent.
+    #[must_use]
+    pub fn export(&self, db: usize, pattern: Option<&[u8]>) -> ExportReceiver {
+        let (sender, receiver) = tokio::sync::mpsc::channel(EXPORT_BUFFER);
+        let export = Export::new(
+            db,
+            pattern.map(Pattern::compile),
+            sender,
+            self.store_sender.clone(),
+        );
+        _ = self
+            .store_sender
+            .send(StoreMessage::Export(Box::new(export)));
+        receiver
+    }
+
+    /// Load `entries` into database `db`, replacing any keys that already exist, without going
+    /// through any commands. Entries from [`Server::export`] load back as they were. Clients
+    /// connected afterwards always see the loaded keys.
+    pub fn load(&self, db: usize, entries: Vec<ExportEntry>) {
+        _ = self
+            .store_sender
+            .send(StoreMessage::Load(DBIndex(db), entries));
+    }
+
+    /// Receive an event for every change to a key from now on, with the database, the key, and
+    /// the operation, regardless of `notify-keyspace-events`. Events stop when the receiver is
+    /// dropped.
+    #[cfg(feature = "key-events")]
+    #[must_use]
+    pub fn key_events(&self) -> KeyEventReceiver {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        _ = self.store_sender.send(StoreMessage::KeyEvents(sender));
+        receiver
+    }
+
+    /// Connect a client over TCP, after applying the `tcp-nodelay` and `tcp-keepalive` settings
+    /// to the socket.
+    #[cfg(feature = "tokio-runtime")]
+    pub fn connect_tcp(&self, stream: tokio::net::TcpStream) {
+        use socket2::{SockRef, TcpKeepalive};
+
+        _ = stream.set_nodelay(self.connections.nodelay());
+        let keepalive = self.connections.keepalive();
+        if !keepalive.is_zero() {
+            let keepalive = TcpKeepalive::new().with_time(keepalive);
+            _ = SockRef::from(&stream).set_tcp_keepalive(&keepalive);
+        }
+
+        let addr = match (stream.local_addr(), stream.peer_addr()) {
+            (Ok(local), Ok(peer)) => Some(Addr {
+                local: local.into(),
+                peer: peer.into(),
+            }),
+            _ => None,
+        };
+        self.connect(stream, addr);
+    }
+
+    /// Connect a client over a unix socket. Both ends of the connection are identified by the
+    /// path the listener is bound to.
+    #[cfg(all(unix, feature = "tokio-runtime"))]
+    pub fn connect_unix(&self, stream: tokio::net::UnixStream) {
+        let path = stream.local_addr().ok().and_then(|local| {
+            let path = local.as_pathname()?.to_str()?;
+            Some(crate::Endpoint::Unix(path.into()))
+        });
+        let addr = path.map(|path| Addr {
+            local: path.clone(),
+            peer: path,
+        });
+        self.connect(stream, addr);
+    }
+
+    /// Connect a client to the server with a stream and a source address. If `maxclients` clients
+    /// are already connected, the stream is sent an error and closed instead.
     pub fn connect<S: AsyncRead + AsyncWrite + Send + 'static>(
         &self,
         stream: S,
         addr: Option<Addr>,
     ) {
+        if !self.connections.accept() {
+            crate::spawn("reject", reject(stream));
+            return;
+        }
+
         let store_sender = self.store_sender.clone();
-        Client::spawn(stream, store_sender, self.config.clone(), addr);
+        let connections = self.connections.clone();
+        Client::spawn(stream, store_sender, self.config.clone(), connections, addr);
+    }
+}
+
+/// Tell a connection over the limit why it's being closed, then close it.
+async fn reject<S: AsyncWrite>(stream: S) {
+    let mut stream = pin!(stream);
+    let error = format!("-{}\r\n", ReplyError::MaxClients);
+    _ = stream.write_all(error.as_bytes()).await;
+    _ = stream.shutdown().await;
+}
+
+#[cfg(all(test, feature = "tokio-runtime"))]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::AsyncReadExt,
+        net::{TcpListener, TcpStream},
+    };
+
+    #[tokio::test]
+    async fn connect_tcp() -> std::io::Result<()> {
+        let server = Server::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let mut stream = TcpStream::connect(listener.local_addr()?).await?;
+        let (accepted, _) = listener.accept().await?;
+        server.connect_tcp(accepted);
+
+        stream.write_all(b"client info\r\n").await?;
+        let mut buffer = [0; 256];
+        let len = stream.read(&mut buffer).await?;
+        let info = String::from_utf8_lossy(&buffer[..len]);
+        let peer = format!("laddr={}", stream.peer_addr()?);
+        assert!(info.contains(&peer), "{info}");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn connect_unix() -> std::io::Result<()> {
+        let path = std::env::temp_dir().join(format!("bradis-{}.sock", std::process::id()));
+        _ = std::fs::remove_file(&path);
+        let server = Server::default();
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let mut stream = tokio::net::UnixStream::connect(&path).await?;
+        let (accepted, _) = listener.accept().await?;
+        server.connect_unix(accepted);
+
+        let laddr = format!("laddr={}:0", path.display());
+        stream.write_all(b"client info\r\n").await?;
+        let mut buffer = [0; 256];
+        let len = stream.read(&mut buffer).await?;
+        let info = String::from_utf8_lossy(&buffer[..len]);
+        assert!(info.contains(&laddr), "{info}");
+
+        let kill = format!("client kill laddr {}:0 skipme no\r\n", path.display());
+        stream.write_all(kill.as_bytes()).await?;
+        let len = stream.read(&mut buffer).await?;
+        assert_eq!(&buffer[..len], b":1\r\n");
+
+        _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_config() -> std::io::Result<()> {
+        let server = Server::with_config("# limits\nmaxclients 5\nenable-debug-command local\n")
+            .expect("valid config");
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let mut stream = TcpStream::connect(listener.local_addr()?).await?;
+        let (accepted, _) = listener.accept().await?;
+        server.connect_tcp(accepted);
+
+        let mut buffer = [0; 256];
+        stream.write_all(b"config get maxclients\r\n").await?;
+        let len = stream.read(&mut buffer).await?;
+        assert_eq!(&buffer[..len], b"*2\r\n+maxclients\r\n$1\r\n5\r\n");
+
+        stream.write_all(b"debug set-active-expire 1\r\n").await?;
+        let len = stream.read(&mut buffer).await?;
+        assert_eq!(&buffer[..len], b"+OK\r\n");
+        Ok(())
+    }
+
+    #[cfg(feature = "key-events")]
+    #[tokio::test]
+    async fn key_events() -> std::io::Result<()> {
+        use crate::KeyEvent;
+
+        let server = Server::default();
+        let mut events = server.key_events();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let mut stream = TcpStream::connect(listener.local_addr()?).await?;
+        let (accepted, _) = listener.accept().await?;
+        server.connect_tcp(accepted);
+
+        let mut buffer = [0; 64];
+        stream
+            .write_all(b"select 2\r\nrpush x a b\r\nsrem y a\r\ndel x\r\n")
+            .await?;
+        let mut len = 0;
+        while len < 17 {
+            len += stream.read(&mut buffer[len..]).await?;
+        }
+        assert_eq!(&buffer[..len], b"+OK\r\n:2\r\n:0\r\n:1\r\n");
+
+        let event = |key: &'static [u8], event| KeyEvent {
+            db: 2,
+            key: key.into(),
+            event,
+        };
+        assert_eq!(events.recv().await, Some(event(b"x", "rpush")));
+        assert_eq!(events.recv().await, Some(event(b"x", "del")));
+        assert!(events.try_recv().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn load_and_export() {
+        use crate::ExportValue;
+
+        let server = Server::default();
+        let entry = |key: &'static str, value, expires_at| ExportEntry {
+            key: key.into(),
+            value,
+            expires_at,
+        };
+        let hash = ExportValue::Hash(vec![("f".into(), "v".into())]);
+        let list = ExportValue::List(vec!["a".into(), "b".into()]);
+        let set = ExportValue::Set(vec!["1".into()]);
+        let sorted_set = ExportValue::SortedSet(vec![("m".into(), 1.5)]);
+        let string = ExportValue::String("x".into());
+        server.load(
+            0,
+            vec![
+                entry("h", hash.clone(), None),
+                entry("l", list.clone(), None),
+                entry("s", set.clone(), None),
+                entry("z", sorted_set.clone(), None),
+                entry("x", string.clone(), Some(u128::MAX)),
+                entry("empty", ExportValue::List(Vec::new()), None),
+                entry("expired", string.clone(), Some(1)),
+            ],
+        );
+
+        let mut exported = Vec::new();
+        let mut receiver = server.export(0, None);
+        while let Some(entry) = receiver.recv().await {
+            exported.push(entry);
+        }
+        exported.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(
+            exported,
+            vec![
+                entry("h", hash, None),
+                entry("l", list, None),
+                entry("s", set, None),
+                entry("x", string, Some(u128::MAX)),
+                entry("z", sorted_set, None),
+            ]
+        );
+        assert!(server.export(16, None).recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn export_waits_for_reader() {
+        use crate::ExportValue;
+
+        let server = Server::default();
+        let len = EXPORT_BUFFER * 5;
+        let entries = (0..len)
+            .map(|i| ExportEntry {
+                key: format!("{i}").into(),
+                value: ExportValue::String("x".into()),
+                expires_at: None,
+            })
+            .collect();
+        server.load(0, entries);
+
+        // Let the export fill the channel before reading anything.
+        let mut receiver = server.export(0, None);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert_eq!(receiver.len(), EXPORT_BUFFER);
+
+        let mut keys = std::collections::HashSet::new();
+        while let Some(entry) = receiver.recv().await {
+            assert!(keys.insert(entry.key));
+        }
+        assert_eq!(keys.len(), len);
+    }
+
+    #[tokio::test]
+    async fn with_config_disables_debug() -> std::io::Result<()> {
+        let server = Server::with_config("enable-debug-command no").expect("valid config");
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let mut stream = TcpStream::connect(listener.local_addr()?).await?;
+        let (accepted, _) = listener.accept().await?;
+        server.connect_tcp(accepted);
+
+        let mut buffer = [0; 512];
+        stream.write_all(b"debug set-active-expire 1\r\n").await?;
+        let len = stream.read(&mut buffer).await?;
+        let reply = String::from_utf8_lossy(&buffer[..len]);
+        assert!(
+            reply.starts_with("-ERR DEBUG command not allowed."),
+            "{reply}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pubsub_churn() -> Result<(), respite::RespError> {
+        use respite::{RespConfig, RespReader};
+        use std::fmt::Write as _;
+
+        let server = Server::default();
+        let (local, remote) = tokio::io::duplex(1 << 16);
+        server.connect(remote, None);
+        let (reader, mut writer) = tokio::io::split(local);
+        let mut reader = RespReader::new(reader, RespConfig::default());
+
+        // Churn through 100k subscribe and unsubscribe cycles, each on a new channel, with a new
+        // pattern every tenth cycle.
+        for batch in 0..100 {
+            let mut commands = String::new();
+            let mut replies = 0;
+            for index in batch * 1000..(batch + 1) * 1000 {
+                _ = write!(commands, "subscribe c{index}\r\nunsubscribe c{index}\r\n");
+                replies += 2;
+                if index % 10 == 0 {
+                    _ = write!(commands, "psubscribe {index}*\r\npunsubscribe {index}*\r\n");
+                    replies += 2;
+                }
+            }
+            writer.write_all(commands.as_bytes()).await?;
+            for _ in 0..replies {
+                reader.value().await?;
+            }
+        }
+
+        writer.write_all(b"reset\r\ninfo everything\r\n").await?;
+        reader.value().await?;
+        let info = reader.value().await?.unwrap();
+        let info = info.text().unwrap();
+        assert!(info.contains("pubsub_clients:0\r\n"), "{info}");
+        assert!(info.contains("pubsub_channels:0\r\n"), "{info}");
+        assert!(info.contains("pubsub_patterns:0\r\n"), "{info}");
+        Ok(())
+    }
+
+    #[test]
+    fn with_config_errors() {
+        let error = Server::with_config("maxclients 0").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "line 1: invalid argument for 'maxclients' - argument must be between 1 and 4294967295 inclusive"
+        );
+        let error = Server::with_config("\nnosuch 1").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "line 2: bad directive or wrong number of arguments: 'nosuch 1'"
+        );
     }
 }
@@ -0,0 +1,591 @@
+use crate::db::KeyRef;
+use hashbrown::{DefaultHashBuilder, HashTable, hash_table};
+use std::{
+    hash::{BuildHasher, Hash},
+    iter::Flatten,
+    slice,
+};
+
+/// The average number of entries in each shard before the next one is split.
+const SHARD_LOAD: usize = 1024;
+
+/// A hash map made of many small tables, which grows one table at a time using linear hashing.
+///
+/// A single table has to move every entry at once when it grows, which stalls the store for
+/// hundreds of milliseconds once it holds millions of keys. Instead, an insert that takes the
+/// map over its load splits one shard in two, so no insert moves more than a shard's worth of
+/// entries. Likewise, once deletes leave the map well under its load, the last shard is merged
+/// back into the one it was split from.
+#[derive(Clone)]
+pub struct ShardedMap<K, V> {
+    hasher: DefaultHashBuilder,
+    shards: Vec<HashTable<(K, V)>>,
+    len: usize,
+}
+
+impl<K, V> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        ShardedMap {
+            hasher: DefaultHashBuilder::default(),
+            shards: vec![HashTable::new()],
+            len: 0,
+        }
+    }
+}
+
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for ShardedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// The bits of `hash` that pick a shard. Tables use the low bits to pick a bucket and the high
+/// bits as a tag, so use the middle bits to keep them independent.
+fn shard_bits(hash: u64) -> usize {
+    #[allow(clippy::cast_possible_truncation)]
+    let bits = (hash >> 32) as u32;
+    bits as usize
+}
+
+impl<K, V> ShardedMap<K, V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.shards.iter().flatten())
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.iter())
+    }
+
+    /// Is the map small enough to lose a shard? Merging well before the split point keeps a map
+    /// that hovers around it from splitting and merging the same shard over and over.
+    fn underloaded(&self) -> bool {
+        self.shards.len() > 1 && self.len < self.shards.len() * SHARD_LOAD / 4
+    }
+
+    /// The number of shards at the start of this round of splits, which is a power of two.
+    fn round(&self) -> usize {
+        1 << self.shards.len().ilog2()
+    }
+
+    /// The next shard to split this round. Shards before it have already been split, so they are
+    /// found with one more bit of the hash.
+    fn next(&self) -> usize {
+        self.shards.len() - self.round()
+    }
+
+    /// The index of the shard for `hash`.
+    fn shard(&self, hash: u64) -> usize {
+        let round = self.round();
+        let bits = shard_bits(hash);
+        let index = bits & (round - 1);
+        if index < self.next() {
+            bits & (round * 2 - 1)
+        } else {
+            index
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> ShardedMap<K, V> {
+    fn find<Q>(&self, key: &Q) -> Option<&(K, V)>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        self.shards[self.shard(hash)].find(hash, |(k, _)| key.equivalent(k))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        self.find(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        self.find(key).map(|(key, value)| (key, value))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        let index = self.shard(hash);
+        self.shards[index]
+            .find_mut(hash, |(k, _)| key.equivalent(k))
+            .map(|(_, value)| value)
+    }
+
+    /// Get mutable values for two keys at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the keys are the same, like [`HashTable::get_many_mut`].
+    pub fn get_pair_mut<Q>(&mut self, keys: [&Q; 2]) -> [Option<&mut V>; 2]
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let hashes = keys.map(|key| self.hasher.hash_one(key));
+        let [a, b] = hashes.map(|hash| self.shard(hash));
+        if a == b {
+            return self.shards[a]
+                .get_many_mut(hashes, |i, (k, _)| keys[i].equivalent(k))
+                .map(|entry| entry.map(|(_, value)| value));
+        }
+
+        let (first, second) = if a < b {
+            let (left, right) = self.shards.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        } else {
+            let (left, right) = self.shards.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        };
+        [
+            first.find_mut(hashes[0], |(k, _)| keys[0].equivalent(k)),
+            second.find_mut(hashes[1], |(k, _)| keys[1].equivalent(k)),
+        ]
+        .map(|entry| entry.map(|(_, value)| value))
+    }
+
+    /// Keep only the entries for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for shard in &mut self.shards {
+            let len = shard.len();
+            shard.retain(|(key, value)| f(key, value));
+            self.len -= len - shard.len();
+        }
+        while self.underloaded() {
+            self.merge();
+        }
+    }
+
+    /// Get the entry for `key`, to insert or update it in place.
+    pub fn entry<Q>(&mut self, key: &Q) -> Entry<'_, K, V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        // Split or merge before handing out an entry, since either one would move it.
+        if self.len >= self.shards.len() * SHARD_LOAD && !self.contains_key(key) {
+            self.split();
+        } else if self.underloaded() {
+            self.merge();
+        }
+
+        let hash = self.hasher.hash_one(key);
+        let index = self.shard(hash);
+        let ShardedMap {
+            hasher,
+            shards,
+            len,
+            ..
+        } = self;
+        let entry = shards[index].entry(
+            hash,
+            |(k, _)| key.equivalent(k),
+            |(k, _)| hasher.hash_one(k),
+        );
+        match entry {
+            hash_table::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry { entry, len }),
+            hash_table::Entry::Vacant(entry) => Entry::Vacant(VacantEntry { entry, len }),
+        }
+    }
+
+    /// Insert `value` for `key`, returning the value it replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(&key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(key, value);
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: KeyRef<K> + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        let index = self.shard(hash);
+        let entry = self.shards[index]
+            .find_entry(hash, |(k, _)| key.equivalent(k))
+            .ok()?;
+        self.len -= 1;
+        let (_, value) = entry.remove().0;
+        if self.underloaded() {
+            self.merge();
+        }
+        Some(value)
+    }
+
+    /// Call `f` with the entries in the slots from `cursor` on, until at least `count` entries have
+    /// been seen, and return the cursor to continue from. A scan starts and ends at zero.
+    ///
+    /// Slots are the shards the map will have once this round of splits is done, and they are
+    /// visited in reverse bit order like Redis's `dictScan`. Splits and merges only ever add or
+    /// remove the highest bit, so an entry that is in the map for the whole scan is seen at least
+    /// once, even if shards are split or merged between calls. Entries may be seen more than
+    /// once.
+    pub fn scan(&self, mut cursor: usize, count: usize, mut f: impl FnMut(&K, &V)) -> usize {
+        let (round, next) = (self.round(), self.next());
+        let mask = if next == 0 { round - 1 } else { round * 2 - 1 };
+        let mut seen = 0;
+        loop {
+            let slot = cursor & mask;
+            let index = slot & (round - 1);
+            if next == 0 || index < next {
+                // This shard holds exactly the entries in the slot.
+                for (key, value) in &self.shards[slot] {
+                    f(key, value);
+                    seen += 1;
+                }
+            } else {
+                // This shard hasn't been split yet, so it also holds the entries for its other
+                // half.
+                for (key, value) in &self.shards[index] {
+                    if shard_bits(self.hasher.hash_one(key)) & mask == slot {
+                        f(key, value);
+                        seen += 1;
+                    }
+                }
+            }
+
+            cursor |= !mask;
+            cursor = cursor.reverse_bits().wrapping_add(1).reverse_bits();
+            if cursor == 0 || seen >= count {
+                return cursor;
+            }
+        }
+    }
+
+    /// Split the next shard, moving the entries with the next bit of their hash set to a new
+    /// shard at the end.
+    fn split(&mut self) {
+        let (round, next) = (self.round(), self.next());
+        let ShardedMap { hasher, shards, .. } = self;
+
+        let source = &mut shards[next];
+        let mut shard = HashTable::with_capacity(source.len() / 2);
+        for entry in source.extract_if(|(key, _)| shard_bits(hasher.hash_one(key)) & round != 0) {
+            let hash = hasher.hash_one(&entry.0);
+            shard.insert_unique(hash, entry, |(key, _)| hasher.hash_one(key));
+        }
+        shards.push(shard);
+    }
+
+    /// Undo the last split, moving the entries in the last shard back to the one it was split
+    /// from.
+    fn merge(&mut self) {
+        let source = self.shards.pop().expect("a shard to merge");
+        let next = self.next();
+        let ShardedMap { hasher, shards, .. } = self;
+
+        let shard = &mut shards[next];
+        shard.reserve(source.len(), |(key, _)| hasher.hash_one(key));
+        for entry in source {
+            let hash = hasher.hash_one(&entry.0);
+            shard.insert_unique(hash, entry, |(key, _)| hasher.hash_one(key));
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq> PartialEq for ShardedMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K: Eq + Hash, V: Eq> Eq for ShardedMap<K, V> {}
+
+impl<'a, K, V> IntoIterator for &'a ShardedMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries in a [`ShardedMap`].
+pub struct Iter<'a, K, V>(Flatten<slice::Iter<'a, HashTable<(K, V)>>>);
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (key, value))
+    }
+}
+
+/// An iterator over the keys in a [`ShardedMap`].
+pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, _)| key)
+    }
+}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    entry: hash_table::OccupiedEntry<'a, (K, V)>,
+    len: &'a mut usize,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.entry.get().0
+    }
+
+    pub fn get(&self) -> &V {
+        &self.entry.get().1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.entry.into_mut().1
+    }
+
+    /// Replace the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(&mut self.entry.get_mut().1, value)
+    }
+
+    pub fn remove(self) -> (K, V) {
+        *self.len -= 1;
+        self.entry.remove().0
+    }
+}
+
+pub struct VacantEntry<'a, K, V> {
+    entry: hash_table::VacantEntry<'a, (K, V)>,
+    len: &'a mut usize,
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn insert(self, key: K, value: V) -> &'a mut V {
+        *self.len += 1;
+        &mut self.entry.insert((key, value)).into_mut().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn split() {
+        let mut map = ShardedMap::default();
+        let mut expected = HashMap::new();
+        for i in 0..SHARD_LOAD * 10 {
+            assert_eq!(map.insert(i, i), None);
+            expected.insert(i, i);
+        }
+        assert!(map.shards.len() > 8);
+        assert_eq!(map.len(), expected.len());
+        for (key, value) in &expected {
+            assert_eq!(map.get(key), Some(value));
+        }
+
+        // Every entry is in the shard its hash points to.
+        for (index, shard) in map.shards.iter().enumerate() {
+            for (key, _) in shard {
+                assert_eq!(map.shard(map.hasher.hash_one(key)), index);
+            }
+        }
+
+        for i in (0..SHARD_LOAD * 10).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i));
+            assert_eq!(map.remove(&i), None);
+        }
+        assert_eq!(map.len(), SHARD_LOAD * 5);
+        assert_eq!(map.iter().count(), SHARD_LOAD * 5);
+        assert!(map.iter().all(|(key, _)| key % 2 == 1));
+    }
+
+    #[test]
+    fn entry() {
+        let mut map = ShardedMap::default();
+        match map.entry(&1) {
+            Entry::Vacant(entry) => *entry.insert(1, 1) += 1,
+            Entry::Occupied(_) => panic!("expected a vacant entry"),
+        }
+        match map.entry(&1) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &1);
+                assert_eq!(entry.insert(3), 2);
+                assert_eq!(entry.remove(), (1, 3));
+            }
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn get_pair_mut() {
+        let mut map = ShardedMap::default();
+        for i in 0..SHARD_LOAD * 4 {
+            map.insert(i, i);
+        }
+        for i in 0..SHARD_LOAD * 4 {
+            let [a, b] = map.get_pair_mut([&i, &(i + 1)]);
+            *a.unwrap() += 1;
+            if let Some(b) = b {
+                *b -= 1;
+            }
+        }
+        assert_eq!(map.get(&0), Some(&1));
+        assert_eq!(map.get(&(SHARD_LOAD * 4 - 1)), Some(&(SHARD_LOAD * 4 - 1)));
+    }
+
+    #[test]
+    fn scan() {
+        let mut map = ShardedMap::default();
+        for i in 0..SHARD_LOAD * 5 {
+            map.insert(i, i);
+        }
+
+        // Without splits, every entry is seen exactly once.
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            cursor = map.scan(cursor, 100, |key, _| seen.push(*key));
+            if cursor == 0 {
+                break;
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (0..SHARD_LOAD * 5).collect::<Vec<_>>());
+
+        // Splitting shards mid scan doesn't skip any entry that was there from the start.
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut next = SHARD_LOAD * 5;
+        loop {
+            cursor = map.scan(cursor, 100, |key, _| {
+                seen.insert(*key);
+            });
+            for _ in 0..SHARD_LOAD / 2 {
+                map.insert(next, next);
+                next += 1;
+            }
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert!(map.shards.len() > 8);
+        assert!((0..SHARD_LOAD * 5).all(|key| seen.contains(&key)));
+    }
+
+    #[test]
+    fn retain() {
+        let mut map = ShardedMap::default();
+        for i in 0..SHARD_LOAD * 3 {
+            map.insert(i, i);
+        }
+        map.retain(|key, _| key % 3 == 0);
+        assert_eq!(map.len(), SHARD_LOAD);
+        assert_eq!(map.iter().count(), SHARD_LOAD);
+
+        map.retain(|key, _| key % 24 == 0);
+        assert_eq!(map.shards.len(), 1);
+        assert_eq!(map.len(), SHARD_LOAD / 8);
+        assert!(
+            (0..SHARD_LOAD * 3)
+                .step_by(24)
+                .all(|key| map.contains_key(&key))
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut map = ShardedMap::default();
+        for i in 0..SHARD_LOAD * 10 {
+            map.insert(i, i);
+        }
+        let shards = map.shards.len();
+
+        // Every entry is still found as shards are merged away.
+        for i in 0..SHARD_LOAD * 10 - 10 {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        assert!(shards > 8);
+        assert_eq!(map.shards.len(), 1);
+        assert_eq!(map.len(), 10);
+        for i in SHARD_LOAD * 10 - 10..SHARD_LOAD * 10 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+
+        // And the map splits again from there.
+        for i in 0..SHARD_LOAD * 10 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.shards.len(), shards);
+        for (index, shard) in map.shards.iter().enumerate() {
+            for (key, _) in shard {
+                assert_eq!(map.shard(map.hasher.hash_one(key)), index);
+            }
+        }
+    }
+
+    #[test]
+    fn scan_while_merging() {
+        let mut map = ShardedMap::default();
+        for i in 0..SHARD_LOAD * 8 {
+            map.insert(i, i);
+        }
+
+        // Merging shards mid scan doesn't skip any entry that is there until the end.
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut next = 0;
+        loop {
+            cursor = map.scan(cursor, 100, |key, _| {
+                seen.insert(*key);
+            });
+            for _ in 0..SHARD_LOAD {
+                if next % 8 != 0 {
+                    map.remove(&next);
+                }
+                next += 1;
+            }
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert!(map.shards.len() < 8);
+        assert!(
+            (0..SHARD_LOAD * 8)
+                .step_by(8)
+                .all(|key| seen.contains(&key))
+        );
+    }
+}
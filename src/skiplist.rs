@@ -52,6 +52,9 @@ struct StepMut<'a> {
 
     /// The node for this step.
     node: &'a mut Node<[Lane]>,
+
+    /// The rank of the node.
+    rank: usize,
 }
 
 /// The result of one mutable step, directing the next one.
@@ -435,6 +438,37 @@ impl Skiplist {
         count
     }
 
+    /// Remove all elements with ranks in `range` and call `f` with each.
+    pub fn remove_range_rank<F>(&mut self, range: Range<usize>, mut f: F) -> usize
+    where
+        F: FnMut(&StringValue),
+    {
+        let end = std::cmp::min(range.end, self.len);
+        if range.start >= end {
+            return 0;
+        }
+
+        let (mut route, _) = self.walk_mut(|step| {
+            if step.rank < range.start {
+                return WalkMut::NextNode;
+            }
+
+            WalkMut::NextLevel
+        });
+
+        let mut next = unsafe { &*route[0] }.next;
+
+        for _ in range.start..end {
+            let link = next.expect("incorrect skiplist length");
+            let node = unsafe { link.as_ref() };
+            f(&node.value);
+            next = node.lanes[0].next;
+            self.unlink(link, &mut route);
+        }
+
+        end - range.start
+    }
+
     /// Return the rank of a `score` `value` pair.
     pub fn rank(&self, score: f64, value: &StringValue) -> Option<usize> {
         self.walk(|step| {
@@ -559,35 +593,69 @@ impl Skiplist {
     pub fn rev_range<'a>(&'a self, range: Range<usize>) -> Iter<'a> {
         let end = std::cmp::min(range.end, self.len);
         let len = end.saturating_sub(range.start);
-        Iter::rev(self.nth(range.end.saturating_sub(1)), len)
+        Iter::rev(self.nth(end.saturating_sub(1)), len)
     }
 
-    /// Return an iterator over all elements in `bounds`.
-    pub fn range_score<'a, R>(&'a self, bounds: &R) -> Iter<'a>
+    /// Return an iterator over all elements in `bounds`, skipping `offset` elements and
+    /// returning at most `count` if `limit` is provided.
+    pub fn range_score<'a, R>(&'a self, bounds: &R, limit: Option<(usize, usize)>) -> Iter<'a>
     where
         R: RangeBounds<f64>,
     {
-        let (first, len) = match self.first_and_last(bounds) {
-            Some((first, _, len)) => (Some(first), len),
-            None => (None, 0),
+        let Some(((first, start), (_, end))) = self.first(bounds).zip(self.last(bounds)) else {
+            return Iter::new(None, 0);
+        };
+
+        let (offset, len) = Self::limit(start, end, limit);
+
+        if len == 0 {
+            return Iter::new(None, 0);
+        }
+
+        // Skip via spans rather than iterating over `offset` elements.
+        let first = if offset == 0 {
+            Some(first)
+        } else {
+            self.nth(start + offset)
         };
 
         Iter::new(first, len)
     }
 
-    /// Return a reverse iterator over all elements in `bounds`.
-    pub fn rev_range_score<'a, R>(&'a self, bounds: &R) -> Iter<'a>
+    /// Return a reverse iterator over all elements in `bounds`, skipping `offset` elements and
+    /// returning at most `count` if `limit` is provided.
+    pub fn rev_range_score<'a, R>(&'a self, bounds: &R, limit: Option<(usize, usize)>) -> Iter<'a>
     where
         R: RangeBounds<f64>,
     {
-        let (last, len) = match self.first_and_last(bounds) {
-            Some((_, last, len)) => (Some(last), len),
-            None => (None, 0),
+        let Some(((_, start), (last, end))) = self.first(bounds).zip(self.last(bounds)) else {
+            return Iter::rev(None, 0);
+        };
+
+        let (offset, len) = Self::limit(start, end, limit);
+
+        if len == 0 {
+            return Iter::rev(None, 0);
+        }
+
+        // Skip via spans rather than iterating over `offset` elements.
+        let last = if offset == 0 {
+            Some(last)
+        } else {
+            self.nth(end - offset)
         };
 
         Iter::rev(last, len)
     }
 
+    /// Apply an `(offset, count)` `limit` to the ranks `start..=end`, returning the offset
+    /// and the number of elements remaining.
+    fn limit(start: usize, end: usize, limit: Option<(usize, usize)>) -> (usize, usize) {
+        let (offset, count) = limit.unwrap_or((0, usize::MAX));
+        let len = (1 + end - start).saturating_sub(offset);
+        (offset, std::cmp::min(len, count))
+    }
+
     /// Walk the list, calling `f` for each step and continuing
     /// according to the result.
     fn walk<F, T>(&self, mut f: F) -> Option<T>
@@ -638,7 +706,11 @@ impl Skiplist {
             while let Some(mut link) = lanes[level].next {
                 let span = lanes[level].span;
                 let node = unsafe { link.as_mut() };
-                let step = StepMut { link, node };
+                let step = StepMut {
+                    link,
+                    node,
+                    rank: rank + span - 1,
+                };
                 use WalkMut::*;
                 lanes = match f(step) {
                     NextLevel => break,
@@ -810,29 +882,117 @@ mod tests {
         assert_skiplist_eq!(list.range(3..6), (3f64, b"x"), (4f64, b"y"), (5f64, b"z"));
         assert_skiplist_eq!(list.range(3..5), (3f64, b"x"), (4f64, b"y"));
         assert_skiplist_eq!(list.rev_range(3..5), (4f64, b"y"), (3f64, b"x"));
-        assert_skiplist_eq!(list.range_score(&(0f64..2f64)), (0f64, b"a"), (1f64, b"b"));
+        assert_skiplist_eq!(
+            list.range_score(&(0f64..2f64), None),
+            (0f64, b"a"),
+            (1f64, b"b")
+        );
 
         assert_skiplist_eq!(
-            list.range_score(&(0f64..=2f64)),
+            list.range_score(&(0f64..=2f64), None),
             (0f64, b"a"),
             (1f64, b"b"),
             (2f64, b"c"),
         );
 
         assert_skiplist_eq!(
-            list.rev_range_score(&(0f64..2f64)),
+            list.rev_range_score(&(0f64..2f64), None),
             (1f64, b"b"),
             (0f64, b"a"),
         );
 
         assert_skiplist_eq!(
-            list.rev_range_score(&(0f64..=2f64)),
+            list.rev_range_score(&(0f64..=2f64), None),
             (2f64, b"c"),
             (1f64, b"b"),
             (0f64, b"a"),
         );
     }
 
+    #[test]
+    fn range_score_limit() {
+        let list = skiplist!(
+            (0f64, b"a"),
+            (1f64, b"b"),
+            (2f64, b"c"),
+            (3f64, b"d"),
+            (4f64, b"e"),
+            (5f64, b"f"),
+        );
+
+        assert_skiplist_eq!(
+            list.range_score(&(1f64..), Some((1, 2))),
+            (2f64, b"c"),
+            (3f64, b"d"),
+        );
+        assert_skiplist_eq!(list.range_score(&(1f64..=3f64), Some((2, 5))), (3f64, b"d"),);
+        assert_eq!(0, list.range_score(&(1f64..=3f64), Some((0, 0))).count());
+        assert_eq!(0, list.range_score(&(1f64..=3f64), Some((3, 1))).count());
+        assert_eq!(
+            0,
+            list.range_score(&(1f64..=3f64), Some((100_000, 10)))
+                .count()
+        );
+        assert_skiplist_eq!(
+            list.rev_range_score(&(1f64..), Some((1, 2))),
+            (4f64, b"e"),
+            (3f64, b"d"),
+        );
+        assert_skiplist_eq!(list.rev_range_score(&(..4f64), Some((3, 5))), (0f64, b"a"),);
+        assert_eq!(0, list.rev_range_score(&(..4f64), Some((4, 5))).count());
+    }
+
+    #[test]
+    fn remove_range_rank() {
+        let mut list = skiplist!(
+            (0f64, b"a"),
+            (1f64, b"b"),
+            (2f64, b"c"),
+            (3f64, b"d"),
+            (4f64, b"e"),
+            (5f64, b"f"),
+        );
+
+        let mut removed = Vec::new();
+        let mut buffer = Vec::new();
+        let count = list.remove_range_rank(1..4, |value| {
+            removed.push(value.as_bytes(&mut buffer).to_vec());
+        });
+        assert_eq!(count, 3);
+        assert_eq!(removed, vec![b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]);
+        assert_skiplist_eq!(list.iter(), (0f64, b"a"), (4f64, b"e"), (5f64, b"f"));
+        assert_skiplist_eq!(list.iter_rev(), (5f64, b"f"), (4f64, b"e"), (0f64, b"a"));
+
+        assert_eq!(list.remove_range_rank(3..10, |_| {}), 0);
+        assert_eq!(list.remove_range_rank(2..10, |_| {}), 1);
+        assert_eq!(list.remove_range_rank(0..2, |_| {}), 2);
+        assert_eq!(list.len(), 0);
+        assert_eq!(0, list.iter().count());
+    }
+
+    #[test]
+    fn remove_range_rank_large() {
+        let mut list = Skiplist::default();
+        for i in 0..1000 {
+            list.insert(
+                NotNan::new(f64::from(i)).unwrap(),
+                i.to_string().as_bytes().into(),
+            );
+        }
+
+        assert_eq!(list.remove_range_rank(100..900, |_| {}), 800);
+        assert_eq!(list.len(), 200);
+        assert_eq!(list.rank(99f64, &b"99".into()), Some(99));
+        assert_eq!(list.rank(900f64, &b"900".into()), Some(100));
+        assert_eq!(list.rank(500f64, &b"500".into()), None);
+        assert_eq!(
+            list.range_score(&(0f64..), Some((150, 10)))
+                .next()
+                .map(|(score, _)| score),
+            Some(950f64)
+        );
+    }
+
     #[test]
     fn remove_range_score() {
         let mut list = skiplist!(
@@ -848,3 +1008,136 @@ mod tests {
         assert_skiplist_eq!(list.iter(), (0f64, b"a"), (4f64, b"e"), (5f64, b"f"));
     }
 }
+
+#[cfg(test)]
+#[cfg(not(miri))]
+mod proptests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+    use std::collections::BTreeSet;
+
+    type Model = BTreeSet<(NotNan<f64>, Vec<u8>)>;
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert(i8, Vec<u8>),
+        Remove(i8, Vec<u8>),
+        RemoveRangeScore(i8, i8),
+        RemoveRangeRank(usize, usize),
+        Pop(bool),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let value = || vec(b'a'..=b'd', 1..3);
+        prop_oneof![
+            4 => (-4i8..4, value()).prop_map(|(score, value)| Op::Insert(score, value)),
+            2 => (-4i8..4, value()).prop_map(|(score, value)| Op::Remove(score, value)),
+            1 => (-4i8..4, -4i8..4).prop_map(|(min, max)| Op::RemoveRangeScore(min, max)),
+            1 => (0usize..20, 0usize..20).prop_map(|(start, end)| Op::RemoveRangeRank(start, end)),
+            1 => any::<bool>().prop_map(Op::Pop),
+        ]
+    }
+
+    fn score(score: i8) -> NotNan<f64> {
+        NotNan::new(f64::from(score)).unwrap()
+    }
+
+    fn entries<'a>(iter: impl Iterator<Item = (f64, &'a StringValue)>) -> Vec<(f64, Vec<u8>)> {
+        let mut buffer = Vec::new();
+        iter.map(|(score, value)| (score, value.as_bytes(&mut buffer).to_vec()))
+            .collect()
+    }
+
+    fn expected<'a>(iter: impl Iterator<Item = &'a (NotNan<f64>, Vec<u8>)>) -> Vec<(f64, Vec<u8>)> {
+        iter.map(|(score, value)| (**score, value.clone()))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn model(ops in vec(op(), 1..100)) {
+            let mut list = Skiplist::default();
+            let mut model = Model::new();
+
+            for op in ops {
+                match op {
+                    Op::Insert(s, value) => {
+                        // Sorted sets always remove a member before inserting a new score.
+                        if !model.iter().any(|(_, v)| *v == value) {
+                            list.insert(score(s), value[..].into());
+                            model.insert((score(s), value));
+                        }
+                    }
+                    Op::Remove(s, value) => {
+                        let removed = list.remove(f64::from(s), &value[..].into());
+                        prop_assert_eq!(removed, model.remove(&(score(s), value)));
+                    }
+                    Op::RemoveRangeScore(min, max) => {
+                        let range = f64::from(min)..=f64::from(max);
+                        let count = list.remove_range_score(&range, |_| {});
+                        let before = model.len();
+                        model.retain(|(score, _)| !range.contains(score));
+                        prop_assert_eq!(count, before - model.len());
+                    }
+                    Op::RemoveRangeRank(start, end) => {
+                        let count = list.remove_range_rank(start..end, |_| {});
+                        let before = model.len();
+                        model = model
+                            .into_iter()
+                            .enumerate()
+                            .filter(|(rank, _)| !(start..end).contains(rank))
+                            .map(|(_, entry)| entry)
+                            .collect();
+                        prop_assert_eq!(count, before - model.len());
+                    }
+                    Op::Pop(max) => {
+                        let (actual, expected) = if max {
+                            (list.pop(Extreme::Max), model.pop_last())
+                        } else {
+                            (list.pop(Extreme::Min), model.pop_first())
+                        };
+                        let mut buffer = Vec::new();
+                        let actual = actual.map(|(s, v)| (s, v.as_bytes(&mut buffer).to_vec()));
+                        prop_assert_eq!(actual, expected.map(|(s, v)| (*s, v)));
+                    }
+                }
+
+                prop_assert_eq!(list.len(), model.len());
+                prop_assert_eq!(entries(list.iter()), expected(model.iter()));
+                prop_assert_eq!(entries(list.iter_rev()), expected(model.iter().rev()));
+            }
+
+            for (rank, (s, value)) in model.iter().enumerate() {
+                prop_assert_eq!(list.rank(**s, &value[..].into()), Some(rank));
+            }
+
+            for min in -4i8..4 {
+                for max in min..4 {
+                    let range = f64::from(min)..=f64::from(max);
+                    let within: Vec<_> = model.iter().filter(|(s, _)| range.contains(s)).collect();
+                    prop_assert_eq!(list.count(&range), within.len());
+                    prop_assert_eq!(
+                        entries(list.range_score(&range, Some((1, 2)))),
+                        expected(within.iter().copied().skip(1).take(2))
+                    );
+                    prop_assert_eq!(
+                        entries(list.rev_range_score(&range, None)),
+                        expected(within.iter().copied().rev())
+                    );
+                }
+            }
+
+            for start in 0..model.len() {
+                let end = start + 2;
+                prop_assert_eq!(
+                    entries(list.range(start..end)),
+                    expected(model.iter().skip(start).take(2))
+                );
+                prop_assert_eq!(
+                    entries(list.rev_range(start..end)),
+                    expected(model.iter().skip(start).take(2).rev())
+                );
+            }
+        }
+    }
+}
@@ -25,12 +25,25 @@ pub fn slice(len: usize, mut start: i64, mut end: i64) -> Option<Range<usize>> {
 
     end = end.clamp(0, len - 1) + 1;
 
+    if start >= end {
+        return None;
+    }
+
     let start = start.try_into().ok()?;
     let end = end.try_into().ok()?;
 
     Some(start..end)
 }
 
+/// Like [`slice`], but an end before the beginning is empty instead of including the first
+/// element, as in LRANGE, LTRIM, and ZRANGE.
+pub fn list_slice(len: usize, start: i64, end: i64) -> Option<Range<usize>> {
+    if end < -i64::try_from(len).ok()? {
+        return None;
+    }
+    slice(len, start, end)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,6 +69,14 @@ mod tests {
         assert_eq!(slice(9, -10, -12), None);
         assert_eq!(slice(9, 5, 4), None);
 
+        // Starting after the end returns nothing.
+        assert_eq!(slice(3, 5, 10), None);
+
+        // An end before the beginning includes the first element, except in lists.
+        assert_eq!(slice(1, -3, -2), Some(0..1));
+        assert_eq!(list_slice(1, -3, -2), None);
+        assert_eq!(list_slice(1, -3, -1), Some(0..1));
+
         // If length is 0, return nothing.
         assert_eq!(slice(0, 1, 4), None);
 
@@ -3,14 +3,15 @@ use futures::{
     future::RemoteHandle,
     task::SpawnExt,
 };
-use std::{cell::RefCell, future::Future};
+use std::{cell::RefCell, fmt::Display, future::Future};
 
 thread_local! {
     static POOL: RefCell<LocalPool> = RefCell::new(LocalPool::new());
     static SPAWNER: RefCell<LocalSpawner> = RefCell::new(POOL.with(|pool| pool.borrow().spawner()));
 }
 
-pub fn spawn_with_handle<F>(f: F) -> TaskHandle<F::Output>
+/// Spawn a task. Names are ignored on a local pool.
+pub fn spawn_with_handle<F>(_name: impl Display, f: F) -> TaskHandle<F::Output>
 where
     F: Future + Send + 'static,
     F::Output: Send + 'static,
@@ -19,13 +20,19 @@ where
     TaskHandle(Some(handle.unwrap()))
 }
 
-pub fn spawn<F>(f: F)
+/// Spawn a task, detached.
+pub fn spawn<F>(_name: impl Display, f: F)
 where
     F: Future<Output = ()> + Send + 'static,
 {
     SPAWNER.with(|s| s.borrow_mut().spawn(f).unwrap());
 }
 
+/// Run blocking work in a task. Nothing else runs on a local pool in the meantime.
+pub fn block_in_place<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
 pub struct TaskHandle<T>(Option<RemoteHandle<T>>);
 
 impl<T: Send + 'static> TaskHandle<T> {
@@ -1,3 +1,40 @@
-pub use tokio::spawn;
-pub use tokio::spawn as spawn_with_handle;
+use std::{fmt::Display, future::Future};
 pub use tokio::task::JoinHandle as TaskHandle;
+
+/// Spawn a named task. Names are only recorded with the `console` feature and
+/// `--cfg tokio_unstable`, so that `tokio-console` can show which tasks are alive.
+pub fn spawn_with_handle<F>(name: impl Display, future: F) -> TaskHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(feature = "console", tokio_unstable))]
+    return tokio::task::Builder::new()
+        .name(&name.to_string())
+        .spawn(future)
+        .expect("failed to spawn task");
+
+    #[cfg(not(all(feature = "console", tokio_unstable)))]
+    {
+        _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// Spawn a named task, detached.
+pub fn spawn<F>(name: impl Display, future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    spawn_with_handle(name, future);
+}
+
+/// Run blocking work in a task, moving the other tasks on this worker to a new one when the
+/// runtime supports it.
+pub fn block_in_place<R>(f: impl FnOnce() -> R) -> R {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+    match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(f),
+        _ => f(),
+    }
+}
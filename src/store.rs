@@ -1,48 +1,100 @@
 mod blocking;
+mod connections;
+mod export;
+#[cfg(feature = "key-events")]
+mod key_events;
+mod lanes;
 mod monitor;
+mod string_limit;
+mod timeouts;
 mod watching;
+mod write_effect;
 
 use crate::{
     BlockResult,
     client::{Client, ClientId, ClientInfo},
-    db::{DB, DBIndex, KeyRef, StringValue, Value},
+    config::{self, Config, ConfigValue, EnableDebugCommand},
+    db::{DB, DBIndex, Expiry, ExpiryChange, KeyRef, StringValue, Value},
     drop::{self, DropMessage},
+    epoch,
     linked_hash_set::LinkedHashSet,
     pubsub::Pubsub,
     reply::{Reply, ReplyError},
+    time,
 };
 use blocking::Blocking;
 use bytes::Bytes;
+pub use connections::Connections;
+pub use export::{EXPORT_BUFFER, Export, ExportEntry, ExportReceiver, ExportValue};
 use hashbrown::{HashMap, hash_map::Entry};
+#[cfg(feature = "key-events")]
+pub use key_events::{KeyEvent, KeyEventReceiver, KeyEvents};
+#[cfg(feature = "tokio-runtime")]
+use lanes::Recv;
+use lanes::{LaneReceiver, LaneSender, Priority};
 pub use monitor::Monitor;
 use respite::RespConfig;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{cmp::Reverse, collections::BTreeMap};
+pub use string_limit::StringLimit;
 use tokio::sync::mpsc;
 use triomphe::Arc;
 use watching::Watching;
+#[cfg(feature = "tokio-runtime")]
+use web_time::Duration;
+use web_time::Instant;
+pub use write_effect::WriteEffect;
 
 pub const DATABASES: usize = 16;
 
 /// Large values can be dropped on a separate thread to prevent long pauses.
 const MAX_DROP_EFFORT: usize = 64;
 
+/// How often the active expire cycle runs, like the default `hz` of 10 in Redis.
+#[cfg(feature = "tokio-runtime")]
+const EXPIRE_CYCLE_INTERVAL: Duration = Duration::from_millis(100);
+
 /// A message to the store.
 pub enum StoreMessage {
     /// A client is ready to execute some commands.
     Ready(Box<Client>),
 
     /// A client has connected.
-    Connect(ClientInfo),
+    Connect(Box<ClientInfo>),
 
     /// A client has disconnected.
     Disconnect(ClientId),
 
-    /// A blocking client has timed out.
-    Timeout(ClientId, Arc<AtomicBool>),
+    /// The embedding application wants to export a database.
+    Export(Box<Export>),
+
+    /// The embedding application wants to load entries into a database.
+    Load(DBIndex, Vec<ExportEntry>),
+
+    /// The embedding application wants to receive key events.
+    #[cfg(feature = "key-events")]
+    KeyEvents(mpsc::UnboundedSender<KeyEvent>),
+}
+
+impl Priority for StoreMessage {
+    /// Connects and disconnects skip ahead of ready clients, so that a flood of commands can't
+    /// hold up cleaning up after a client. A client only sends `Ready` after its `Connect`, and
+    /// its `Disconnect` is sent when it's dropped, so each client's messages stay in order.
+    /// Exports wait their turn with ready clients, so that each batch lets them all run.
+    fn priority(&self) -> bool {
+        !matches!(self, StoreMessage::Ready(_) | StoreMessage::Export(_))
+    }
+}
+
+/// The sending half of the store's channel.
+pub type StoreSender = LaneSender<StoreMessage>;
+
+/// Create a channel for messages to the store.
+pub fn channel() -> (StoreSender, LaneReceiver<StoreMessage>) {
+    lanes::lanes()
 }
 
 /// Configuration for sets.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct SetConfig {
     /// The maxumum number of entries in an intset
     pub max_intset_entries: usize,
@@ -90,6 +142,32 @@ pub struct Store {
     /// Total conncetions accepted since CONFIG RESETSTAT
     pub numconnections: usize,
 
+    /// Total keys removed after expiring since CONFIG RESETSTAT, not counting those still held
+    /// by each database.
+    pub expired_keys: usize,
+
+    /// Total keys evicted to stay under maxmemory since CONFIG RESETSTAT.
+    pub evicted_keys: usize,
+
+    /// Total clients evicted to stay under maxmemory-clients since CONFIG RESETSTAT.
+    pub evicted_clients: usize,
+
+    /// The most memory queued replies can use before clients are evicted, or zero for no limit.
+    pub maxmemory_clients: usize,
+
+    /// Error replies by prefix, like `ERR` or `WRONGTYPE`, since CONFIG RESETSTAT.
+    pub errorstats: BTreeMap<String, usize>,
+
+    /// A moving average of the percentage of volatile keys found expired by the active expire
+    /// cycle.
+    pub expired_stale_perc: f64,
+
+    /// Should expired keys be removed in the background, or only when they're accessed?
+    pub active_expire: bool,
+
+    /// When the active expire cycle last ran.
+    pub last_expire_cycle: Instant,
+
     /// The maximum number of entries in a listpack hash
     pub hash_max_listpack_entries: usize,
 
@@ -119,12 +197,47 @@ pub struct Store {
 
     /// Resp reader config.
     pub reader_config: RespConfig,
+
+    /// Connection counts and limits, shared with the server.
+    pub connections: Arc<Connections>,
+
+    /// How long a script can run, in milliseconds, before other clients are told the server is
+    /// busy.
+    pub busy_reply_threshold: u64,
+
+    /// Who may run DEBUG?
+    pub enable_debug_command: EnableDebugCommand,
+
+    /// Has a script run past the busy reply threshold?
+    pub busy: bool,
+
+    /// Has SCRIPT KILL asked the busy script to stop?
+    pub kill_script: bool,
+
+    /// The value of `dirty` when the running script started, so that SCRIPT KILL can tell
+    /// whether it has written anything.
+    pub script_dirty: usize,
+
+    /// Has SHUTDOWN asked the store to stop?
+    pub shutdown: bool,
+
+    /// Channels for sending key events to the embedding application.
+    #[cfg(feature = "key-events")]
+    pub key_events: KeyEvents,
+
+    /// A channel for receiving messages from clients.
+    receiver: LaneReceiver<StoreMessage>,
 }
 
 impl Store {
-    /// Spawn a store and return its config.
-    pub fn spawn(mut store_receiver: mpsc::UnboundedReceiver<StoreMessage>) -> RespConfig {
+    /// Spawn a store with the defaults for every option, then `options`, and return its config and
+    /// connection counts.
+    pub fn spawn(
+        receiver: LaneReceiver<StoreMessage>,
+        options: Vec<(&'static Config, ConfigValue)>,
+    ) -> (RespConfig, Arc<Connections>) {
         let config = RespConfig::default();
+        let connections = Arc::new(Connections::default());
 
         let mut store = Store {
             clients: HashMap::new(),
@@ -137,29 +250,144 @@ impl Store {
             dirty: 0,
             numcommands: 0,
             numconnections: 0,
-            hash_max_listpack_entries: 512,
-            hash_max_listpack_value: 64,
-            zset_max_listpack_entries: 128,
-            zset_max_listpack_value: 64,
-            set_config: SetConfig {
-                max_intset_entries: 512,
-                max_listpack_entries: 128,
-                max_listpack_value: 64,
-            },
+            expired_keys: 0,
+            evicted_keys: 0,
+            evicted_clients: 0,
+            errorstats: BTreeMap::new(),
+            expired_stale_perc: 0.0,
+            active_expire: true,
+            last_expire_cycle: Instant::now(),
+            // Options are set from their defaults below.
+            hash_max_listpack_entries: 0,
+            hash_max_listpack_value: 0,
+            zset_max_listpack_entries: 0,
+            zset_max_listpack_value: 0,
+            set_config: SetConfig::default(),
             lazy_expire: false,
             lazy_user_del: false,
             lazy_user_flush: false,
-            list_max_listpack_size: -2,
+            list_max_listpack_size: 0,
+            maxmemory_clients: 0,
             reader_config: config.clone(),
+            connections: connections.clone(),
+            busy_reply_threshold: 0,
+            enable_debug_command: EnableDebugCommand::No,
+            busy: false,
+            kill_script: false,
+            script_dirty: 0,
+            shutdown: false,
+            #[cfg(feature = "key-events")]
+            key_events: KeyEvents::default(),
+            receiver,
         };
 
-        crate::spawn(async move {
-            while let Some(message) = store_receiver.recv().await {
+        config::reset(&mut store);
+        for (option, value) in options {
+            option.apply(value, &mut store);
+        }
+
+        crate::spawn("store", async move {
+            while let Some(message) = store.recv().await {
                 store.message(message);
+                if store.shutdown {
+                    break;
+                }
             }
         });
 
-        config
+        (config, connections)
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    /// Wait for the next message, expiring blocked client timeouts and volatile keys in the
+    /// meantime. Connects and disconnects come first, then timeouts, then ready clients, so that
+    /// a flood of commands can't hold up timeouts.
+    async fn recv(&mut self) -> Option<StoreMessage> {
+        loop {
+            let expire_cycle = self.next_expire_cycle();
+            let Some(deadline) = self
+                .blocking
+                .next_timeout()
+                .into_iter()
+                .chain(expire_cycle)
+                .min()
+            else {
+                return self.receiver.recv().await;
+            };
+            match self.receiver.recv_until(deadline.into()).await {
+                Recv::Message(message) => return message,
+                Recv::Deadline => {
+                    self.blocking.expire();
+                    if expire_cycle.is_some_and(|at| at <= Instant::now()) {
+                        self.expire_cycle();
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "tokio-runtime"))]
+    /// Wait for the next message. Blocked clients never time out and expired keys are only
+    /// removed when accessed without a timer.
+    async fn recv(&mut self) -> Option<StoreMessage> {
+        self.receiver.recv().await
+    }
+
+    /// Handle any messages that have already arrived while a script is busy. Clients get a BUSY
+    /// reply for everything except SCRIPT KILL and SHUTDOWN NOSAVE.
+    pub fn busy_poll(&mut self) {
+        self.busy = true;
+        while !self.shutdown
+            && let Ok(message) = self.receiver.try_recv()
+        {
+            self.message(message);
+        }
+    }
+
+    /// Disconnect every client and stop handling messages.
+    pub fn shutdown(&mut self) {
+        for info in self.clients.values_mut() {
+            info.quit();
+        }
+        self.shutdown = true;
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    /// When the active expire cycle should run next, if there are any volatile keys to check.
+    fn next_expire_cycle(&self) -> Option<Instant> {
+        if self.active_expire && self.dbs.iter().any(|db| db.volatile() > 0) {
+            Some(self.last_expire_cycle + EXPIRE_CYCLE_INTERVAL)
+        } else {
+            None
+        }
+    }
+
+    /// Remove expired keys from every database.
+    pub fn expire_cycle(&mut self) {
+        let mut checked = 0;
+        let mut expired = 0;
+        for index in 0..self.dbs.len() {
+            let (volatile, values) = self.dbs[index].remove_expired();
+            checked += volatile;
+            expired += values.len();
+            for value in values {
+                self.drop_value(value, self.lazy_expire);
+            }
+        }
+
+        if checked > 0 {
+            #[allow(clippy::cast_precision_loss)]
+            let current = 100.0 * expired as f64 / checked as f64;
+            self.expired_stale_perc = current * 0.05 + self.expired_stale_perc * 0.95;
+        }
+        self.last_expire_cycle = Instant::now();
+    }
+
+    /// Collect the expired keys counted by each database into `expired_keys`.
+    pub fn collect_expired(&mut self) {
+        for db in &mut self.dbs {
+            self.expired_keys += db.take_expired();
+        }
     }
 
     /// Get a reference to the database at a particular index.
@@ -187,6 +415,21 @@ impl Store {
         self.watching.dirty.remove(&id);
     }
 
+    /// The maximum size of a string value, from `proto-max-bulk-len`.
+    pub fn string_limit(&self) -> StringLimit {
+        StringLimit(self.reader_config.blob_limit())
+    }
+
+    /// Count an error reply towards INFO errorstats.
+    pub fn count_error(&mut self, error: &ReplyError) {
+        let prefix = error.prefix();
+        if let Some(count) = self.errorstats.get_mut(&*prefix) {
+            *count += 1;
+        } else {
+            self.errorstats.insert(prefix.into_owned(), 1);
+        }
+    }
+
     /// Mark a key as ready to fulfill blocking requests.
     pub fn mark_ready<Q>(&mut self, db: DBIndex, key: &Q)
     where
@@ -195,6 +438,75 @@ impl Store {
         self.blocking.mark_ready(db, key);
     }
 
+    /// Swap the contents of two databases, waking clients blocked on keys that now exist and
+    /// invalidating transactions watching keys whose values may have changed.
+    pub fn swap_dbs(&mut self, a: DBIndex, b: DBIndex) {
+        if a == b {
+            return;
+        }
+
+        self.dbs.swap(a.0, b.0);
+
+        let dbs = &self.dbs;
+        let exists = |index: DBIndex, key: &StringValue| dbs[index.0].exists(key);
+        self.blocking.swap(a, b, exists);
+        self.watching.swap(a, b, exists);
+    }
+
+    /// Apply the side effects of writing to `key`: count the changes towards `dirty`, invalidate
+    /// transactions watching it, wake clients blocked on it if it's ready, and send a key event
+    /// to the embedding application.
+    pub fn write<Q>(&mut self, db: DBIndex, key: &Q, effect: WriteEffect)
+    where
+        Q: KeyRef<StringValue> + ?Sized,
+        for<'q> &'q Q: Into<StringValue>,
+    {
+        if effect.changes == 0 {
+            return;
+        }
+        self.dirty += effect.changes;
+        self.touch(db, key);
+        if effect.ready {
+            self.mark_ready(db, key);
+        }
+        #[cfg(feature = "key-events")]
+        self.key_events.send(db, key, effect.event);
+    }
+
+    /// Apply the side effects of the same write to each of `keys`, like calling [`Store::write`]
+    /// for each one, but in a single pass that skips looking for watchers when nobody is
+    /// watching a key in the database.
+    pub fn write_all<I>(&mut self, db: DBIndex, keys: I, effect: WriteEffect)
+    where
+        I: IntoIterator,
+        I::Item: KeyRef<StringValue>,
+        for<'k> &'k I::Item: Into<StringValue>,
+    {
+        if effect.changes == 0 {
+            return;
+        }
+        let watched = self.watching.any(db);
+        for key in keys {
+            self.dirty += effect.changes;
+            if watched {
+                self.touch(db, &key);
+            }
+            if effect.ready {
+                self.mark_ready(db, &key);
+            }
+            #[cfg(feature = "key-events")]
+            self.key_events.send(db, &key, effect.event);
+        }
+    }
+
+    /// Mark clients watching any key in `db` that hasn't expired as dirty, before it's flushed.
+    pub fn touch_db(&mut self, db: DBIndex) {
+        let Some(keys) = self.dbs.get(db.0) else {
+            return;
+        };
+        self.watching.touch_db(db, |key| keys.exists(key));
+    }
+
     /// Mark all clients watching a key as dirty.
     pub fn touch<Q>(&mut self, db: DBIndex, key: &Q)
     where
@@ -205,16 +517,52 @@ impl Store {
 
     // Handle a message from a client.
     pub fn message(&mut self, message: StoreMessage) {
+        time::tick();
         use StoreMessage::*;
         match message {
-            Connect(info) => self.connect(info),
+            Connect(info) => self.connect(*info),
             Disconnect(id) => self.disconnect(id),
-            Ready(client) => client.ready(self),
-            Timeout(id, canceled) => {
-                if !canceled.load(Ordering::Relaxed) {
-                    self.blocking.unblock_with(id, Reply::Nil);
+            Export(export) => {
+                if let Some(db) = self.dbs.get(export.db) {
+                    export.run(db);
                 }
             }
+            Load(db, entries) => _ = self.load_entries(db, entries),
+            #[cfg(feature = "key-events")]
+            KeyEvents(sender) => self.key_events.subscribe(sender),
+            Ready(client) => client.ready(self),
+        }
+        self.evict_clients();
+    }
+
+    /// Evict the clients with the most queued replies until the total is back under
+    /// `maxmemory-clients`. Replies to a client that isn't reading pile up in its queue, so one
+    /// slow subscriber can otherwise use unlimited memory.
+    fn evict_clients(&mut self) {
+        let limit = self.maxmemory_clients;
+        if limit == 0 || self.connections.output() <= limit {
+            return;
+        }
+
+        // Clients that were already evicted release their queues once their repliers stop, so
+        // don't count them again.
+        let (evicted, mut clients): (Vec<_>, Vec<_>) = self
+            .clients
+            .values_mut()
+            .partition(|info| info.evict_sender.is_none());
+        let mut output = self
+            .connections
+            .output()
+            .saturating_sub(evicted.iter().map(|info| info.reply_sender.queued()).sum());
+        clients.sort_unstable_by_key(|info| Reverse(info.reply_sender.queued()));
+
+        for info in clients {
+            if output <= limit {
+                break;
+            }
+            output = output.saturating_sub(info.reply_sender.queued());
+            info.evict();
+            self.evicted_clients += 1;
         }
     }
 
@@ -231,13 +579,15 @@ impl Store {
         self.monitors.remove(&id);
         self.pubsub.disconnect(id);
         self.unwatch(id);
-        self.clients.remove(&id);
+        if self.clients.remove(&id).is_some() {
+            self.connections.release();
+        }
     }
 
-    /// Block this client until the specified keys are ready.
+    /// Block this client until the keys or condition it's waiting for are ready.
     pub fn block(&mut self, mut client: Client, block: BlockResult) {
-        client.block(block.timeout);
-        self.blocking.add(client, block.keys);
+        client.block();
+        self.blocking.add(client, block);
     }
 
     /// Iterate over ready keys and serve blocking clients with as many results as possible.
@@ -255,6 +605,45 @@ impl Store {
             }
             self.blocking.restore_clients(clients);
         }
+
+        self.unblock_conditions();
+    }
+
+    /// Run the commands of clients blocked on a condition that now holds.
+    fn unblock_conditions(&mut self) {
+        let conditions = self.blocking.conditions();
+        if conditions.is_empty() {
+            return;
+        }
+
+        let mut clients = self.blocking.take_clients();
+        for (id, condition) in conditions {
+            let Entry::Occupied(mut entry) = clients.entry(id) else {
+                panic!("missing client");
+            };
+
+            if !condition(entry.get(), self) {
+                continue;
+            }
+
+            let client = entry.get_mut();
+
+            // Reset the request before running.
+            client.request.reset(1);
+
+            // If the client is still blocking then wait for the next check.
+            if let Some(block) = client.run(self) {
+                self.blocking.requeue(client, block);
+                continue;
+            }
+
+            // Remove the client and return it to the normal queue.
+            self.blocking.remove(client.id);
+            let mut client = entry.remove();
+            client.unblock();
+            client.ready(self);
+        }
+        self.blocking.restore_clients(clients);
     }
 
     /// Serve blocked clients for a particular key with as many results as possible.
@@ -265,6 +654,13 @@ impl Store {
         key: &StringValue,
     ) {
         while let Some(id) = self.blocking.front(index, key) {
+            // The key may have expired or been removed since it was marked ready, like when a
+            // key with a millisecond TTL expires in the same script that created it. Running the
+            // command anyway would only requeue the client behind those that blocked after it.
+            if !self.dbs.get(index.0).is_some_and(|db| db.exists(key)) {
+                break;
+            }
+
             let Entry::Occupied(mut entry) = clients.entry(id) else {
                 panic!("missing client");
             };
@@ -274,8 +670,9 @@ impl Store {
             // Reset the request before running.
             client.request.reset(1);
 
-            // If the client is still blocking then we're done.
-            if client.run(self).is_some() {
+            // If the client is still blocking then nothing's left for the others either.
+            if let Some(block) = client.run(self) {
+                self.blocking.requeue(client, block);
                 break;
             }
 
@@ -287,6 +684,61 @@ impl Store {
         }
     }
 
+    /// Set or remove the expiration for `key`, applying the side effects of whatever actually
+    /// changed: `event` for a new or removed expiration, or a delete for a time that has already
+    /// passed. Return `true` if anything changed.
+    pub fn set_expiry(
+        &mut self,
+        db: DBIndex,
+        key: &Bytes,
+        expiry: Expiry,
+        event: &'static str,
+    ) -> Result<bool, Reply> {
+        match self.mut_db(db)?.set_expiry(&key[..], expiry) {
+            ExpiryChange::Unchanged => Ok(false),
+            ExpiryChange::Updated => {
+                self.write(db, key, WriteEffect::new(event));
+                Ok(true)
+            }
+            ExpiryChange::Deleted(value) => {
+                self.drop_value(value, self.lazy_expire);
+                self.write(db, key, WriteEffect::new("del"));
+                Ok(true)
+            }
+        }
+    }
+
+    /// Write `entries` straight into a database without going through any commands, replacing
+    /// keys that already exist. Entries that have already expired or can't be stored (like an
+    /// empty list) are skipped. Return the number of keys written.
+    pub fn load_entries<I>(&mut self, db: DBIndex, entries: I) -> Result<usize, Reply>
+    where
+        I: IntoIterator<Item = ExportEntry>,
+    {
+        self.get_db(db)?;
+        let now = epoch().as_millis();
+        let mut count = 0;
+        for entry in entries {
+            if entry.expires_at.is_some_and(|at| at <= now) {
+                continue;
+            }
+            let Some(value) = entry.value.into_value(self) else {
+                continue;
+            };
+            let key = &entry.key[..];
+            let previous = match entry.expires_at {
+                Some(at) => self.dbs[db.0].setex(key, value, at),
+                None => self.dbs[db.0].set(key, value),
+            };
+            if let Some(previous) = previous {
+                self.drop_value(previous, self.lazy_user_del);
+            }
+            self.write(db, key, WriteEffect::new("restore").ready());
+            count += 1;
+        }
+        Ok(count)
+    }
+
     /// Drop a value, maybe asynchronously.
     pub fn drop_value(&mut self, value: Value, lazy: bool) {
         if lazy && value.drop_effort() > MAX_DROP_EFFORT {
@@ -1,14 +1,19 @@
+use super::timeouts::Timeouts;
 use crate::{
+    BlockResult,
     client::{Client, ClientId},
+    command::{Condition, Wake},
     db::{DBIndex, KeyRef, StringValue},
     linked_hash_set::LinkedHashSet,
-    reply::Reply,
+    ordered_map::OrderedMap,
+    reply::{Reply, ReplyError},
     store::DATABASES,
 };
 use hashbrown::{HashMap, HashSet, hash_map::Entry};
-use std::{iter::StepBy, ops::Range};
+use web_time::Instant;
 
-/// Keep track of blocking clients, the db/key pairs they're waiting for, and keys that are ready.
+/// Keep track of blocking clients, the db/key pairs or conditions they're waiting for, and keys
+/// that are ready.
 pub struct Blocking {
     /// Blocked client instances.
     clients: Option<HashMap<ClientId, Client>>,
@@ -21,6 +26,15 @@ pub struct Blocking {
 
     /// The set of keys that are ready, by database.
     ready: Option<HashMap<DBIndex, LinkedHashSet<StringValue>>>,
+
+    /// Clients waiting for a condition, in the order they blocked.
+    conditions: OrderedMap<ClientId, Condition>,
+
+    /// The reply for each blocked client when its timeout expires.
+    timeout_replies: HashMap<ClientId, Reply>,
+
+    /// Timeouts for blocked clients.
+    timeouts: Timeouts,
 }
 
 impl Default for Blocking {
@@ -30,6 +44,9 @@ impl Default for Blocking {
             keys: HashMap::new(),
             dbs: vec![HashMap::new(); DATABASES],
             ready: None,
+            conditions: OrderedMap::new(),
+            timeout_replies: HashMap::new(),
+            timeouts: Timeouts::default(),
         }
     }
 }
@@ -39,30 +56,77 @@ impl Blocking {
     ///
     /// # Panics
     /// Panics if `clients` has been removed via `take_clients`.
-    pub fn add(&mut self, client: Client, blocking_keys: StepBy<Range<usize>>) {
-        // Get the queues for the current database.
-        let queues = self.dbs.get_mut(client.db().0).unwrap();
+    pub fn add(&mut self, client: Client, block: BlockResult) {
+        let now = Instant::now();
+        let deadline = (!block.timeout.is_zero()).then(|| now + block.timeout);
+        self.register(&client, block, deadline, now);
+        self.clients.as_mut().unwrap().insert(client.id, client);
+    }
 
-        // Get or insert a set of keys for the client.
-        let keys = self.keys.entry(client.id).or_default();
+    /// Block a client again after its command was woken and re-run but couldn't finish, like
+    /// when another client took the value it was waiting for. It moves to the back of its queues
+    /// but keeps its deadline, so being woken doesn't extend its timeout.
+    pub fn requeue(&mut self, client: &Client, block: BlockResult) {
+        let deadline = self.timeouts.deadline(client.id);
+        self.unregister(client.id);
+        self.register(client, block, deadline, Instant::now());
+    }
+
+    /// Track what a client is waiting for, and when it times out.
+    fn register(
+        &mut self,
+        client: &Client,
+        block: BlockResult,
+        deadline: Option<Instant>,
+        now: Instant,
+    ) {
+        match block.wake {
+            Wake::Keys(indices) => {
+                // Get the queues for the current database.
+                let queues = self.dbs.get_mut(client.db().0).unwrap();
+
+                // Get or insert a set of keys for the client.
+                let keys = self.keys.entry(client.id).or_default();
 
-        // Add the client to the queue for each key it's blocked on.
-        for index in blocking_keys {
-            let key = client.request.get(index).unwrap();
-            let mut entry = queues.entry_ref(&key).or_default_entry();
+                // Add the client to the queue for each key it's blocked on.
+                for index in indices {
+                    let key = client.request.get(index).unwrap();
+                    let mut entry = queues.entry_ref(&key).or_default_entry();
 
-            // Add to the queue
-            entry.get_mut().insert_back(client.id);
+                    // Add to the queue
+                    entry.get_mut().insert_back(client.id);
 
-            // Add to the key set for fast removal.
-            keys.insert((client.db(), entry.key().clone()));
+                    // Add to the key set for fast removal.
+                    keys.insert((client.db(), entry.key().clone()));
+                }
+            }
+            Wake::When(condition) => {
+                self.conditions.insert(client.id, condition);
+            }
         }
 
-        self.clients.as_mut().unwrap().insert(client.id, client);
+        self.timeout_replies.insert(client.id, block.timeout_reply);
+
+        match deadline {
+            Some(deadline) => self.timeouts.insert(client.id, deadline, now),
+            None => self.timeouts.remove(client.id),
+        }
     }
 
     /// Remove a particular client from the list of blockers.
     pub fn remove(&mut self, id: ClientId) -> Option<Client> {
+        self.unregister(id);
+        self.clients
+            .as_mut()
+            .and_then(|clients| clients.remove(&id))
+    }
+
+    /// Stop tracking what a client is waiting for, and when it times out.
+    fn unregister(&mut self, id: ClientId) {
+        self.timeouts.remove(id);
+        self.conditions.remove(&id);
+        self.timeout_replies.remove(&id);
+
         // Remove from queues.
         if let Some(mut keys) = self.keys.remove(&id) {
             for (db, key) in keys.drain() {
@@ -78,10 +142,6 @@ impl Blocking {
                 }
             }
         }
-
-        self.clients
-            .as_mut()
-            .and_then(|clients| clients.remove(&id))
     }
 
     /// Get the first client to be unblocked for a particular key.
@@ -110,6 +170,24 @@ impl Blocking {
             .insert_back(key.clone());
     }
 
+    /// After the contents of databases `a` and `b` have been swapped, mark every key that clients
+    /// are blocked on in either one as ready if it now `exists`. Clients stay blocked on the
+    /// database they selected, so they're served from whatever data it now holds.
+    pub fn swap(&mut self, a: DBIndex, b: DBIndex, exists: impl Fn(DBIndex, &StringValue) -> bool) {
+        for index in [a, b] {
+            let Some(db) = self.dbs.get(index.0) else {
+                continue;
+            };
+            for key in db.keys().filter(|key| exists(index, key)) {
+                self.ready
+                    .get_or_insert_with(Default::default)
+                    .entry(index)
+                    .or_default()
+                    .insert_back(key.clone());
+            }
+        }
+    }
+
     /// Return all keys that are ready, and replace the hash of ready keys.
     pub fn ready(&mut self) -> Option<HashMap<DBIndex, LinkedHashSet<StringValue>>> {
         self.ready.take()
@@ -128,11 +206,47 @@ impl Blocking {
         self.clients = Some(clients);
     }
 
+    /// Get the clients waiting for a condition, and the condition each one is waiting for.
+    pub fn conditions(&self) -> Vec<(ClientId, Condition)> {
+        self.conditions
+            .iter()
+            .map(|(id, condition)| (*id, *condition))
+            .collect()
+    }
+
+    /// Handle CLIENT UNBLOCK for a client, sending it `error`, or its timeout reply if there is
+    /// no error. Returns `false` if the client isn't blocked.
+    pub fn unblock(&mut self, id: ClientId, error: Option<ReplyError>) -> bool {
+        match error {
+            Some(error) => self.unblock_with(id, error),
+            None => self.time_out(id),
+        }
+    }
+
+    /// When should [`Blocking::expire`] be called next? Returns `None` if no timeouts are pending.
+    pub fn next_timeout(&self) -> Option<Instant> {
+        self.timeouts.next()
+    }
+
+    /// Unblock every client whose timeout has passed with its timeout reply.
+    pub fn expire(&mut self) {
+        for id in self.timeouts.expire(Instant::now()) {
+            self.time_out(id);
+        }
+    }
+
+    /// Unblock a client with its timeout reply.
+    fn time_out(&mut self, id: ClientId) -> bool {
+        let reply = self.timeout_replies.remove(&id).unwrap_or(Reply::Nil);
+        self.unblock_with(id, reply)
+    }
+
     /// Attempt to unblock a client with a reply, then wait.
     pub fn unblock_with(&mut self, id: ClientId, reply: impl Into<Reply>) -> bool {
         if let Some(mut client) = self.remove(id) {
             client.reply(reply);
             client.unblock();
+            client.finish_reply_mode();
             client.wait();
             true
         } else {
@@ -0,0 +1,121 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+/// Connection counts and settings shared between the server, which accepts connections, the
+/// store, which configures them and sees clients disconnect, and each client.
+#[derive(Debug)]
+pub struct Connections {
+    /// The number of accepted connections that haven't disconnected yet.
+    connected: AtomicUsize,
+
+    /// The maximum number of connected clients (i.e. `maxclients`).
+    max: AtomicUsize,
+
+    /// The number of connections rejected because of the limit.
+    rejected: AtomicUsize,
+
+    /// Bytes waiting in the reply queues of every client, for `maxmemory-clients`.
+    output: AtomicUsize,
+
+    /// Seconds between TCP keepalive probes, or zero to disable them (i.e. `tcp-keepalive`).
+    keepalive: AtomicU64,
+
+    /// Should TCP sockets disable Nagle's algorithm (i.e. `tcp-nodelay`)?
+    nodelay: AtomicBool,
+
+    /// Seconds an idle client can wait before it's disconnected, or zero to wait forever (i.e.
+    /// `timeout`).
+    timeout: AtomicU64,
+}
+
+impl Default for Connections {
+    fn default() -> Self {
+        Connections {
+            connected: AtomicUsize::new(0),
+            max: AtomicUsize::new(10_000),
+            rejected: AtomicUsize::new(0),
+            output: AtomicUsize::new(0),
+            keepalive: AtomicU64::new(300),
+            nodelay: AtomicBool::new(true),
+            timeout: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Connections {
+    /// Count a new connection, or return false if there's no room for it.
+    pub fn accept(&self) -> bool {
+        let max = self.max();
+        let accepted = self
+            .connected
+            .fetch_update(Relaxed, Relaxed, |n| (n < max).then_some(n + 1))
+            .is_ok();
+        if !accepted {
+            self.rejected.fetch_add(1, Relaxed);
+        }
+        accepted
+    }
+
+    /// Make room for another connection after a client disconnects.
+    pub fn release(&self) {
+        self.connected.fetch_sub(1, Relaxed);
+    }
+
+    pub fn max(&self) -> usize {
+        self.max.load(Relaxed)
+    }
+
+    /// Change the limit. Clients that are already connected stay connected.
+    pub fn set_max(&self, max: usize) {
+        self.max.store(max, Relaxed);
+    }
+
+    pub fn rejected(&self) -> usize {
+        self.rejected.load(Relaxed)
+    }
+
+    pub fn reset_rejected(&self) {
+        self.rejected.store(0, Relaxed);
+    }
+
+    /// Count bytes added to a client's reply queue.
+    pub fn queue_output(&self, size: usize) {
+        self.output.fetch_add(size, Relaxed);
+    }
+
+    /// Count bytes taken from a client's reply queue.
+    pub fn release_output(&self, size: usize) {
+        self.output.fetch_sub(size, Relaxed);
+    }
+
+    /// The number of bytes waiting in the reply queues of every client.
+    pub fn output(&self) -> usize {
+        self.output.load(Relaxed)
+    }
+
+    pub fn keepalive(&self) -> Duration {
+        Duration::from_secs(self.keepalive.load(Relaxed))
+    }
+
+    pub fn set_keepalive(&self, seconds: u64) {
+        self.keepalive.store(seconds, Relaxed);
+    }
+
+    pub fn nodelay(&self) -> bool {
+        self.nodelay.load(Relaxed)
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.nodelay.store(nodelay, Relaxed);
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.load(Relaxed))
+    }
+
+    pub fn set_timeout(&self, seconds: u64) {
+        self.timeout.store(seconds, Relaxed);
+    }
+}
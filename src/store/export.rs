@@ -0,0 +1,326 @@
+use crate::{
+    Store,
+    buffer::ArrayBuffer,
+    db::{DB, Edge, Hash, List, Set, SortedSet, StringValue, Value},
+    glob::Pattern,
+    store::{StoreMessage, StoreSender},
+};
+use bytes::Bytes;
+use ordered_float::NotNan;
+use std::collections::VecDeque;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// A key and its value, exported to the application embedding the server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportEntry {
+    /// The key.
+    pub key: Bytes,
+
+    /// The value, copied out of the database.
+    pub value: ExportValue,
+
+    /// When the key expires, in milliseconds since the epoch, if it's volatile.
+    pub expires_at: Option<u128>,
+}
+
+/// A value of any type, as plain Rust types instead of any internal encoding.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportValue {
+    /// A hash's fields and values, in the order they iterate in.
+    Hash(Vec<(Bytes, Bytes)>),
+
+    /// A list's elements, from left to right.
+    List(Vec<Bytes>),
+
+    /// A set's members, in no particular order.
+    Set(Vec<Bytes>),
+
+    /// A sorted set's members and scores, from lowest to highest score.
+    SortedSet(Vec<(Bytes, f64)>),
+
+    /// A string.
+    String(Bytes),
+}
+
+impl ExportValue {
+    /// The name of this value's type, as reported by `TYPE`.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ExportValue::Hash(_) => "hash",
+            ExportValue::List(_) => "list",
+            ExportValue::Set(_) => "set",
+            ExportValue::SortedSet(_) => "zset",
+            ExportValue::String(_) => "string",
+        }
+    }
+
+    /// Convert this back into a value, picking each encoding with the same limits that commands
+    /// use. Return `None` for an empty collection or a score that isn't a number, since neither
+    /// can be stored.
+    pub fn into_value(self, store: &Store) -> Option<Value> {
+        let empty = match &self {
+            ExportValue::Hash(pairs) => pairs.is_empty(),
+            ExportValue::List(elements) | ExportValue::Set(elements) => elements.is_empty(),
+            ExportValue::SortedSet(members) => members.is_empty(),
+            ExportValue::String(_) => false,
+        };
+        if empty {
+            return None;
+        }
+
+        let value = match self {
+            ExportValue::Hash(pairs) => {
+                let max_len = store.hash_max_listpack_entries;
+                let max_size = store.hash_max_listpack_value;
+                let mut hash = Hash::default();
+                for (field, value) in pairs {
+                    hash.insert(&field[..], &value[..], max_len, max_size);
+                }
+                Value::Hash(Box::new(hash))
+            }
+            ExportValue::List(elements) => {
+                let mut list = List::default();
+                list.push_many(&elements, Edge::Right, store.list_max_listpack_size);
+                Value::List(Box::new(list))
+            }
+            ExportValue::Set(members) => {
+                let mut set = Set::default();
+                for member in members {
+                    set.insert(&member[..], &store.set_config);
+                }
+                Value::Set(Box::new(set))
+            }
+            ExportValue::SortedSet(members) => {
+                let max_len = store.zset_max_listpack_entries;
+                let max_size = store.zset_max_listpack_value;
+                let mut set = SortedSet::default();
+                for (member, score) in members {
+                    set.insert(NotNan::new(score).ok()?, &member[..], max_len, max_size);
+                }
+                Value::SortedSet(Box::new(set))
+            }
+            ExportValue::String(string) => Value::String(StringValue::from(string)),
+        };
+        Some(value)
+    }
+}
+
+impl From<&Value> for ExportValue {
+    fn from(value: &Value) -> Self {
+        let mut buffer = ArrayBuffer::default();
+        let copy = |bytes: &[u8]| Bytes::copy_from_slice(bytes);
+        match value {
+            Value::Hash(hash) => ExportValue::Hash(
+                hash.iter()
+                    .map(|(field, value)| {
+                        let field = copy(field.as_bytes(&mut buffer));
+                        (field, copy(value.as_bytes(&mut buffer)))
+                    })
+                    .collect(),
+            ),
+            Value::List(list) => ExportValue::List(
+                list.iter()
+                    .map(|element| copy(element.as_bytes(&mut buffer)))
+                    .collect(),
+            ),
+            Value::Set(set) => ExportValue::Set(
+                set.iter()
+                    .map(|member| copy(member.as_bytes(&mut buffer)))
+                    .collect(),
+            ),
+            Value::SortedSet(set) => ExportValue::SortedSet(
+                set.range(0..set.len())
+                    .map(|(score, member)| (copy(member.as_bytes(&mut buffer)), score))
+                    .collect(),
+            ),
+            Value::String(string) => ExportValue::String(copy(string.as_bytes(&mut buffer))),
+        }
+    }
+}
+
+/// How many entries can wait in an export's channel before it stops to let the application catch
+/// up.
+pub const EXPORT_BUFFER: usize = 1024;
+
+/// How many keys an export scans before letting the store handle other messages.
+const EXPORT_BATCH: usize = 1024;
+
+/// The receiving half of a channel of exported entries, from [`crate::Server::export`].
+pub type ExportReceiver = mpsc::Receiver<ExportEntry>;
+
+/// A request from the embedding application to export a database, which is sent back to the
+/// store after each batch until every key has been sent.
+pub struct Export {
+    /// The index of the database to export.
+    pub db: usize,
+
+    /// Only export keys matching this pattern, or every key if there isn't one.
+    pub pattern: Option<Pattern>,
+
+    /// Where to send the entries.
+    pub sender: mpsc::Sender<ExportEntry>,
+
+    /// Where to send this export to continue it.
+    store: StoreSender,
+
+    /// Where to continue scanning the database from, once the scan has started.
+    cursor: Option<usize>,
+
+    /// Keys that have been scanned but not sent yet.
+    keys: VecDeque<StringValue>,
+}
+
+impl Export {
+    pub fn new(
+        db: usize,
+        pattern: Option<Pattern>,
+        sender: mpsc::Sender<ExportEntry>,
+        store: StoreSender,
+    ) -> Self {
+        Export {
+            db,
+            pattern,
+            sender,
+            store,
+            cursor: None,
+            keys: VecDeque::new(),
+        }
+    }
+
+    /// Scan a batch of keys in `db` that haven't expired and match the pattern, and send as many
+    /// as the channel has room for. Then send this export back to the store to continue once
+    /// other messages have been handled, or once the application has made room in the channel.
+    ///
+    /// Like SCAN, every key that exists for the whole export is sent, and each value is read
+    /// when it's sent rather than all at once.
+    pub fn run(mut self: Box<Self>, db: &DB) {
+        if self.sender.is_closed() {
+            return;
+        }
+
+        let db = db.snapshot();
+        if self.keys.is_empty() {
+            if self.cursor == Some(0) {
+                return;
+            }
+            let (cursor, keys) = db.scan_bucket(self.cursor.unwrap_or(0), EXPORT_BATCH);
+            self.cursor = Some(cursor);
+            let mut buffer = ArrayBuffer::default();
+            self.keys.extend(keys.into_iter().filter(|key| {
+                self.pattern
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.matches(key.as_bytes(&mut buffer)))
+            }));
+        }
+
+        let mut buffer = ArrayBuffer::default();
+        let mut full = false;
+        while let Some(key) = self.keys.pop_front() {
+            let permit = match self.sender.try_reserve() {
+                Ok(permit) => permit,
+                Err(TrySendError::Full(())) => {
+                    self.keys.push_front(key);
+                    full = true;
+                    break;
+                }
+                Err(TrySendError::Closed(())) => return,
+            };
+            let Some(value) = db.get(&key) else {
+                continue;
+            };
+            let key = key.as_bytes(&mut buffer);
+            permit.send(ExportEntry {
+                key: Bytes::copy_from_slice(key),
+                value: value.into(),
+                expires_at: db.expires_at(key),
+            });
+        }
+
+        if full {
+            // Continue once the application has read something.
+            let sender = self.sender.clone();
+            crate::spawn("export", async move {
+                if sender.reserve_owned().await.is_ok() {
+                    let store = self.store.clone();
+                    _ = store.send(StoreMessage::Export(self));
+                }
+            });
+            return;
+        }
+
+        let store = self.store.clone();
+        _ = store.send(StoreMessage::Export(self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{Edge, List},
+        store,
+    };
+
+    /// Run an export to the end, handling the messages it sends to the store in between.
+    fn export(db: &DB, pattern: Option<Pattern>) -> Vec<ExportEntry> {
+        let (sender, mut receiver) = mpsc::channel(EXPORT_BUFFER);
+        let (store, mut messages) = store::channel();
+        Box::new(Export::new(0, pattern, sender, store)).run(db);
+        while let Ok(StoreMessage::Export(export)) = messages.try_recv() {
+            export.run(db);
+        }
+
+        let mut entries = Vec::new();
+        while let Ok(entry) = receiver.try_recv() {
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        entries
+    }
+
+    #[test]
+    fn export_pattern() {
+        let mut db = DB::default();
+        db.set(b"s", Value::String(5.into()));
+        db.setex(b"x", Value::String("x".into()), u128::MAX);
+        db.setex(b"expired", Value::String("x".into()), 1);
+        let mut list = List::default();
+        list.push(&&b"a"[..], Edge::Right, 128);
+        db.set(b"l", Value::List(Box::new(list)));
+
+        assert_eq!(
+            export(&db, Some(Pattern::compile(b"[sx]"))),
+            vec![
+                ExportEntry {
+                    key: "s".into(),
+                    value: ExportValue::String("5".into()),
+                    expires_at: None,
+                },
+                ExportEntry {
+                    key: "x".into(),
+                    value: ExportValue::String("x".into()),
+                    expires_at: Some(u128::MAX),
+                },
+            ]
+        );
+
+        let lists: Vec<_> = export(&db, None)
+            .into_iter()
+            .filter(|entry| entry.value.type_name() == "list")
+            .map(|entry| entry.value)
+            .collect();
+        assert_eq!(lists, vec![ExportValue::List(vec!["a".into()])]);
+    }
+
+    #[test]
+    fn export_batches() {
+        let mut db = DB::default();
+        for i in 0..EXPORT_BATCH * 3 {
+            db.set(format!("{i}").as_bytes(), "x");
+        }
+        let entries = export(&db, Some(Pattern::compile(b"*7")));
+        assert_eq!(entries.len(), EXPORT_BATCH * 3 / 10);
+        assert!(entries.iter().all(|entry| entry.key.ends_with(b"7")));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    buffer::ArrayBuffer,
+    db::{DBIndex, StringValue},
+};
+use bytes::Bytes;
+use tokio::sync::mpsc;
+
+/// A change to a key, sent to the application embedding the server. Unlike keyspace
+/// notifications, these don't depend on any config and aren't visible to clients.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyEvent {
+    /// The index of the database the key is in.
+    pub db: usize,
+
+    /// The key that changed.
+    pub key: Bytes,
+
+    /// What happened to the key, named like the event in a keyspace notification (e.g. `lpush`).
+    pub event: &'static str,
+}
+
+/// The receiving half of a channel of key events, from [`crate::Server::key_events`].
+pub type KeyEventReceiver = mpsc::UnboundedReceiver<KeyEvent>;
+
+/// The channels that key events are sent to.
+#[derive(Default)]
+pub struct KeyEvents {
+    senders: Vec<mpsc::UnboundedSender<KeyEvent>>,
+}
+
+impl KeyEvents {
+    /// Send every later key event to `sender`, until its receiver is dropped.
+    pub fn subscribe(&mut self, sender: mpsc::UnboundedSender<KeyEvent>) {
+        self.senders.push(sender);
+    }
+
+    /// Send an event to every subscriber, forgetting those that have gone away. The key is only
+    /// copied if someone is listening.
+    pub fn send(&mut self, db: DBIndex, key: impl Into<StringValue>, event: &'static str) {
+        self.senders.retain(|sender| !sender.is_closed());
+        if self.senders.is_empty() {
+            return;
+        }
+
+        let key: StringValue = key.into();
+        let mut buffer = ArrayBuffer::default();
+        let event = KeyEvent {
+            db: db.0,
+            key: Bytes::copy_from_slice(key.as_bytes(&mut buffer)),
+            event,
+        };
+        for sender in &self.senders {
+            _ = sender.send(event.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send() {
+        let mut events = KeyEvents::default();
+        events.send(DBIndex(0), b"ignored", "set");
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        events.subscribe(sender);
+        events.send(DBIndex(1), b"key", "lpush");
+        events.send(DBIndex(0), StringValue::from(5), "incrby");
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(KeyEvent {
+                db: 1,
+                key: Bytes::from_static(b"key"),
+                event: "lpush",
+            })
+        );
+        assert_eq!(receiver.try_recv().map(|event| event.key), Ok("5".into()));
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        events.send(DBIndex(0), b"key", "set");
+        assert!(events.senders.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(feature = "tokio-runtime")]
+use std::{future::Future, pin::pin};
+use std::{future::poll_fn, task::Poll};
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TryRecvError},
+};
+
+/// A message that may need to skip ahead of others in the queue.
+pub trait Priority {
+    fn priority(&self) -> bool;
+}
+
+/// What came first while waiting for a message with a deadline.
+#[cfg(feature = "tokio-runtime")]
+pub enum Recv<T> {
+    /// The next message, or `None` when every sender is gone.
+    Message(Option<T>),
+
+    /// The deadline passed first.
+    Deadline,
+}
+
+/// The sending half of a pair of unbounded lanes. Priority messages go in their own lane, so a
+/// flood of other messages can't delay them.
+pub struct LaneSender<T> {
+    priority: mpsc::UnboundedSender<T>,
+    normal: mpsc::UnboundedSender<T>,
+}
+
+impl<T> Clone for LaneSender<T> {
+    fn clone(&self) -> Self {
+        LaneSender {
+            priority: self.priority.clone(),
+            normal: self.normal.clone(),
+        }
+    }
+}
+
+impl<T: Priority> LaneSender<T> {
+    /// Send a message in the lane for its priority.
+    pub fn send(&self, message: T) -> Result<(), SendError<T>> {
+        if message.priority() {
+            self.priority.send(message)
+        } else {
+            self.normal.send(message)
+        }
+    }
+}
+
+/// The receiving half of a pair of unbounded lanes, which always empties the priority lane first.
+/// Messages in the same lane stay in order.
+pub struct LaneReceiver<T> {
+    priority: mpsc::UnboundedReceiver<T>,
+    normal: mpsc::UnboundedReceiver<T>,
+}
+
+impl<T> LaneReceiver<T> {
+    /// Wait for the next message or for `deadline`. Priority messages come before the deadline,
+    /// and the deadline comes before other messages.
+    #[cfg(feature = "tokio-runtime")]
+    pub async fn recv_until(&mut self, deadline: tokio::time::Instant) -> Recv<T> {
+        let mut sleep = pin!(tokio::time::sleep_until(deadline));
+        poll_fn(|cx| {
+            if let Poll::Ready(Some(message)) = self.priority.poll_recv(cx) {
+                return Poll::Ready(Recv::Message(Some(message)));
+            }
+            // The timer driver may not have caught up with a deadline that has already passed
+            // while the runtime is busy, so check the clock first.
+            if deadline <= tokio::time::Instant::now() || sleep.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Recv::Deadline);
+            }
+            self.normal.poll_recv(cx).map(Recv::Message)
+        })
+        .await
+    }
+
+    /// Wait for the next message from either lane. Returns `None` when every sender is gone.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| match self.priority.poll_recv(cx) {
+            Poll::Ready(Some(message)) => Poll::Ready(Some(message)),
+            Poll::Ready(None) | Poll::Pending => self.normal.poll_recv(cx),
+        })
+        .await
+    }
+
+    /// Take the next message from either lane without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.priority.try_recv().or_else(|_| self.normal.try_recv())
+    }
+}
+
+/// Create a pair of unbounded lanes.
+pub fn lanes<T>() -> (LaneSender<T>, LaneReceiver<T>) {
+    let (priority_sender, priority_receiver) = mpsc::unbounded_channel();
+    let (normal_sender, normal_receiver) = mpsc::unbounded_channel();
+    let sender = LaneSender {
+        priority: priority_sender,
+        normal: normal_sender,
+    };
+    let receiver = LaneReceiver {
+        priority: priority_receiver,
+        normal: normal_receiver,
+    };
+    (sender, receiver)
+}
+
+#[cfg(test)]
+#[cfg(feature = "tokio-runtime")]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    enum Message {
+        High(u8),
+        Low(u8),
+    }
+
+    impl Priority for Message {
+        fn priority(&self) -> bool {
+            matches!(self, Message::High(_))
+        }
+    }
+
+    #[test]
+    fn priority_first() {
+        use Message::*;
+        let (sender, mut receiver) = lanes();
+        sender.send(Low(1)).unwrap();
+        sender.send(High(1)).unwrap();
+        sender.send(Low(2)).unwrap();
+        sender.send(High(2)).unwrap();
+
+        assert_eq!(receiver.try_recv(), Ok(High(1)));
+        assert_eq!(receiver.try_recv(), Ok(High(2)));
+        assert_eq!(receiver.try_recv(), Ok(Low(1)));
+        assert_eq!(receiver.try_recv(), Ok(Low(2)));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        drop(sender);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[tokio::test]
+    async fn recv() {
+        use Message::*;
+        let (sender, mut receiver) = lanes();
+        sender.send(Low(1)).unwrap();
+        sender.send(High(1)).unwrap();
+
+        assert_eq!(receiver.recv().await, Some(High(1)));
+        assert_eq!(receiver.recv().await, Some(Low(1)));
+        drop(sender);
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn recv_until() {
+        use Message::*;
+        let (sender, mut receiver) = lanes();
+        let deadline = tokio::time::Instant::now();
+        sender.send(Low(1)).unwrap();
+        sender.send(High(1)).unwrap();
+
+        let recv = receiver.recv_until(deadline).await;
+        assert!(matches!(recv, Recv::Message(Some(High(1)))));
+        let recv = receiver.recv_until(deadline).await;
+        assert!(matches!(recv, Recv::Deadline));
+        let recv = receiver
+            .recv_until(deadline + Duration::from_secs(60))
+            .await;
+        assert!(matches!(recv, Recv::Message(Some(Low(1)))));
+    }
+}
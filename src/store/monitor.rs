@@ -1,15 +1,14 @@
 use crate::{
-    client::{ClientId, ReplyMessage},
+    client::{ClientId, ReplySender},
     reply::Reply,
 };
 use hashbrown::Equivalent;
 use std::hash::{Hash, Hasher};
-use tokio::sync::mpsc;
 
 #[derive(Clone, Debug)]
 pub struct Monitor {
     id: ClientId,
-    reply_sender: mpsc::UnboundedSender<ReplyMessage>,
+    reply_sender: ReplySender,
 }
 
 impl Eq for Monitor {}
@@ -33,11 +32,11 @@ impl Equivalent<Monitor> for ClientId {
 }
 
 impl Monitor {
-    pub fn new(id: ClientId, reply_sender: mpsc::UnboundedSender<ReplyMessage>) -> Self {
+    pub fn new(id: ClientId, reply_sender: ReplySender) -> Self {
         Self { id, reply_sender }
     }
 
     pub fn reply(&self, reply: impl Into<Reply>) {
-        _ = self.reply_sender.send(reply.into().into());
+        self.reply_sender.send(reply.into());
     }
 }
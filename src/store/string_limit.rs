@@ -0,0 +1,55 @@
+use crate::reply::ReplyError;
+
+/// The maximum size of a string value, from `proto-max-bulk-len`. Every command that grows a
+/// string checks it here, so they agree on the boundary and the error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StringLimit(pub usize);
+
+impl StringLimit {
+    /// Check that a string of `len` bytes fits. `None` is a length that overflowed while
+    /// computing it, which never fits.
+    pub fn check(self, len: Option<usize>) -> Result<(), ReplyError> {
+        match len {
+            Some(len) if len <= self.0 => Ok(()),
+            _ => Err(ReplyError::StringLength),
+        }
+    }
+
+    /// Check that a bit offset falls inside a string of the maximum size.
+    pub fn bit_offset(self, offset: usize) -> Result<usize, ReplyError> {
+        if offset / 8 < self.0 {
+            Ok(offset)
+        } else {
+            Err(ReplyError::BitOffset)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        let limit = StringLimit(10);
+        assert!(limit.check(Some(0)).is_ok());
+        assert!(limit.check(Some(10)).is_ok());
+        assert!(matches!(
+            limit.check(Some(11)),
+            Err(ReplyError::StringLength)
+        ));
+        assert!(matches!(limit.check(None), Err(ReplyError::StringLength)));
+    }
+
+    #[test]
+    fn bit_offset() {
+        let limit = StringLimit(10);
+        assert!(matches!(limit.bit_offset(0), Ok(0)));
+        assert!(matches!(limit.bit_offset(79), Ok(79)));
+        assert!(matches!(limit.bit_offset(80), Err(ReplyError::BitOffset)));
+        assert!(matches!(
+            StringLimit(0).bit_offset(0),
+            Err(ReplyError::BitOffset)
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::client::ClientId;
+use hashbrown::HashMap;
+use web_time::{Duration, Instant};
+
+/// The number of slots in the wheel.
+const SLOTS: usize = 512;
+
+/// The resolution of the wheel in milliseconds. Timeouts fire on the first tick after their
+/// deadline.
+const TICK: u64 = 10;
+
+/// A pending timeout in a wheel slot.
+struct Entry {
+    /// The client to time out.
+    id: ClientId,
+
+    /// The token for the blocking operation that scheduled this entry.
+    token: u64,
+
+    /// The tick this entry expires on.
+    tick: u64,
+}
+
+/// A hashed timer wheel for blocking client timeouts, so that blocked clients share a single timer
+/// rather than spawning one each.
+///
+/// Canceling is lazy: a canceled entry stays in its slot until the wheel passes it, but it won't
+/// fire because its token no longer matches.
+pub struct Timeouts {
+    /// The instant that tick zero starts at.
+    start: Instant,
+
+    /// Entries bucketed by tick modulo the number of slots.
+    slots: Vec<Vec<Entry>>,
+
+    /// The next tick to process.
+    current: u64,
+
+    /// The token and deadline for each client with a live timeout.
+    active: HashMap<ClientId, (u64, Instant)>,
+
+    /// The most recently issued token.
+    token: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            start: Instant::now(),
+            slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+            current: 0,
+            active: HashMap::new(),
+            token: 0,
+        }
+    }
+}
+
+impl Timeouts {
+    /// The number of whole ticks between the start of the wheel and `instant`.
+    fn tick(&self, instant: Instant) -> u64 {
+        let elapsed = instant.saturating_duration_since(self.start);
+        u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX) / TICK
+    }
+
+    /// Schedule a timeout for a client at `deadline`, replacing any timeout it already has.
+    pub fn insert(&mut self, id: ClientId, deadline: Instant, now: Instant) {
+        // Nothing is live, so skip straight to the present rather than walking idle ticks later.
+        if self.active.is_empty() {
+            self.slots.iter_mut().for_each(Vec::clear);
+            self.current = self.tick(now);
+        }
+
+        // Round up so that a timeout never fires early.
+        let tick = self.tick(deadline) + 1;
+        let tick = tick.max(self.current);
+
+        self.token += 1;
+        self.active.insert(id, (self.token, deadline));
+
+        let slot = usize::try_from(tick % SLOTS as u64).unwrap();
+        self.slots[slot].push(Entry {
+            id,
+            token: self.token,
+            tick,
+        });
+    }
+
+    /// When does the timeout for a client expire, if it has one?
+    pub fn deadline(&self, id: ClientId) -> Option<Instant> {
+        self.active.get(&id).map(|(_, deadline)| *deadline)
+    }
+
+    /// Cancel the timeout for a client, if it has one.
+    pub fn remove(&mut self, id: ClientId) {
+        self.active.remove(&id);
+    }
+
+    /// When should the wheel be advanced next? Returns `None` if no timeouts are live.
+    pub fn next(&self) -> Option<Instant> {
+        if self.active.is_empty() {
+            return None;
+        }
+        Some(self.start + Duration::from_millis(self.current * TICK))
+    }
+
+    /// Advance the wheel to `now`, returning the clients that have timed out.
+    pub fn expire(&mut self, now: Instant) -> Vec<ClientId> {
+        let now = self.tick(now);
+        let mut expired = Vec::new();
+
+        if now < self.current {
+            return expired;
+        }
+
+        // Once every slot has been visited there's no need to keep walking.
+        let ticks = (now - self.current + 1).min(SLOTS as u64);
+        for tick in self.current..self.current + ticks {
+            let slot = usize::try_from(tick % SLOTS as u64).unwrap();
+            let active = &mut self.active;
+            self.slots[slot].retain(|entry| {
+                if entry.tick > now {
+                    return true;
+                }
+                if active
+                    .get(&entry.id)
+                    .is_some_and(|(token, _)| *token == entry.token)
+                {
+                    active.remove(&entry.id);
+                    expired.push(entry.id);
+                }
+                false
+            });
+        }
+
+        self.current = now + 1;
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expire() {
+        let mut timeouts = Timeouts::default();
+        let now = timeouts.start;
+        timeouts.insert(ClientId(1), now + Duration::from_millis(20), now);
+        timeouts.insert(ClientId(2), now + Duration::from_millis(50), now);
+        assert!(timeouts.next().is_some());
+
+        assert!(timeouts.expire(now + Duration::from_millis(10)).is_empty());
+        assert_eq!(
+            timeouts.expire(now + Duration::from_millis(30)),
+            [ClientId(1)]
+        );
+        assert_eq!(
+            timeouts.expire(now + Duration::from_millis(60)),
+            [ClientId(2)]
+        );
+        assert!(timeouts.next().is_none());
+    }
+
+    #[test]
+    fn never_early() {
+        let mut timeouts = Timeouts::default();
+        let now = timeouts.start + Duration::from_micros(9900);
+        timeouts.insert(ClientId(1), now + Duration::from_millis(1), now);
+        assert!(timeouts.expire(now).is_empty());
+        assert!(timeouts.expire(now + Duration::from_micros(900)).is_empty());
+        assert_eq!(
+            timeouts.expire(now + Duration::from_millis(20)),
+            [ClientId(1)]
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let mut timeouts = Timeouts::default();
+        let now = timeouts.start;
+        timeouts.insert(ClientId(1), now + Duration::from_millis(20), now);
+        timeouts.remove(ClientId(1));
+        assert!(timeouts.next().is_none());
+        assert!(timeouts.expire(now + Duration::from_secs(1)).is_empty());
+    }
+
+    #[test]
+    fn replace() {
+        let mut timeouts = Timeouts::default();
+        let now = timeouts.start;
+        timeouts.insert(ClientId(1), now + Duration::from_millis(20), now);
+        timeouts.insert(ClientId(1), now + Duration::from_millis(100), now);
+        assert!(timeouts.expire(now + Duration::from_millis(50)).is_empty());
+        assert_eq!(
+            timeouts.expire(now + Duration::from_millis(150)),
+            [ClientId(1)]
+        );
+    }
+
+    #[test]
+    fn deadline() {
+        let mut timeouts = Timeouts::default();
+        let now = timeouts.start;
+        let deadline = now + Duration::from_millis(20);
+        timeouts.insert(ClientId(1), deadline, now);
+        assert_eq!(timeouts.deadline(ClientId(1)), Some(deadline));
+
+        // Rescheduling at the same deadline later doesn't push it back.
+        timeouts.insert(ClientId(1), deadline, now + Duration::from_millis(10));
+        assert_eq!(
+            timeouts.expire(now + Duration::from_millis(40)),
+            [ClientId(1)]
+        );
+        assert_eq!(timeouts.deadline(ClientId(1)), None);
+    }
+
+    #[test]
+    fn wrap() {
+        let mut timeouts = Timeouts::default();
+        let now = timeouts.start;
+        let tick = Duration::from_millis(TICK);
+        let slots = u32::try_from(SLOTS).unwrap();
+        let long = tick * slots * 3;
+        timeouts.insert(ClientId(1), now + long, now);
+        timeouts.insert(ClientId(2), now + Duration::from_millis(1), now);
+        assert_eq!(timeouts.expire(now + long / 2), [ClientId(2)]);
+        assert!(timeouts.expire(now + tick * (slots * 3 - 1)).is_empty());
+        assert_eq!(timeouts.expire(now + long + tick * 2), [ClientId(1)]);
+    }
+}
@@ -65,6 +65,44 @@ impl Watching {
         }
     }
 
+    /// After the contents of databases `a` and `b` have been swapped, mark watchers of any key in
+    /// either one as dirty if that key `exists` in either database, since its value may have
+    /// changed.
+    pub fn swap(&mut self, a: DBIndex, b: DBIndex, exists: impl Fn(DBIndex, &StringValue) -> bool) {
+        let mut touched = Vec::new();
+        for index in [a, b] {
+            let Some(keys) = self.watchers.get(index.0) else {
+                continue;
+            };
+            touched.extend(
+                keys.keys()
+                    .filter(|key| exists(a, key) || exists(b, key))
+                    .map(|key| (index, key.clone())),
+            );
+        }
+
+        for (index, key) in touched {
+            self.touch(index, &key);
+        }
+    }
+
+    /// Is anyone watching a key in `db`?
+    pub fn any(&self, db: DBIndex) -> bool {
+        self.watchers.get(db.0).is_some_and(|keys| !keys.is_empty())
+    }
+
+    /// Mark watchers of any key in `db` as dirty if that key `exists`, like before the database
+    /// is flushed.
+    pub fn touch_db(&mut self, db: DBIndex, exists: impl Fn(&StringValue) -> bool) {
+        let Some(keys) = self.watchers.get(db.0) else {
+            return;
+        };
+        let touched: Vec<_> = keys.keys().filter(|key| exists(key)).cloned().collect();
+        for key in touched {
+            self.touch(db, &key);
+        }
+    }
+
     /// Mark all watchers for a db/key pair as dirty.
     pub fn touch<Q>(&mut self, db: DBIndex, key: &Q)
     where
@@ -0,0 +1,39 @@
+/// The side effects of a write to one key. Write commands describe what they did and
+/// [`Store::write`][`crate::Store::write`] applies every side effect together, so that none of
+/// them can be forgotten.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[must_use]
+pub struct WriteEffect {
+    /// The event name, as it would appear in a keyspace notification (e.g. `lpush`).
+    pub event: &'static str,
+
+    /// The number of changes to count towards `dirty`. Nothing happens if this is zero.
+    pub changes: usize,
+
+    /// Could clients be blocked waiting for this key (e.g. after a list push)?
+    pub ready: bool,
+}
+
+impl WriteEffect {
+    /// A single change to a key.
+    pub fn new(event: &'static str) -> Self {
+        WriteEffect {
+            event,
+            changes: 1,
+            ready: false,
+        }
+    }
+
+    /// Count `changes` changes instead of one.
+    pub fn changes(self, changes: usize) -> Self {
+        WriteEffect { changes, ..self }
+    }
+
+    /// Wake clients blocked on the key.
+    pub fn ready(self) -> Self {
+        WriteEffect {
+            ready: true,
+            ..self
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
 use web_time::{Duration, UNIX_EPOCH};
 
 pub fn epoch() -> Duration {
@@ -5,3 +6,18 @@ pub fn epoch() -> Duration {
         .elapsed()
         .expect("current time is before unix epoch")
 }
+
+/// Seconds since the unix epoch, updated by the store before each message so that reads can
+/// record access times cheaply.
+static CLOCK: AtomicU32 = AtomicU32::new(0);
+
+/// The current store clock in seconds.
+pub fn clock() -> u32 {
+    CLOCK.load(Relaxed)
+}
+
+/// Update the store clock.
+pub fn tick() {
+    let seconds = u32::try_from(epoch().as_secs()).unwrap_or(u32::MAX);
+    CLOCK.store(seconds, Relaxed);
+}
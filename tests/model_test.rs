@@ -0,0 +1,442 @@
+#![cfg(feature = "tokio-runtime")]
+#![cfg(not(miri))]
+
+//! Run random command sequences against bradis and a reference, comparing every reply.
+//!
+//! The reference is a small in-memory model of the commands below. Set `BRADIS_REFERENCE_ADDR`
+//! to the address of a real Redis (e.g. `127.0.0.1:6379`) to compare against that instead.
+//! **The reference is flushed before every case**, so don't point this at data you want to keep.
+
+use bradis::Server;
+use bytes::Bytes;
+use proptest::{collection::vec, prelude::*, sample::select};
+use respite::{RespConfig, RespReader, RespValue, RespWriter};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    ops::Range,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf, duplex, split},
+    net::TcpStream,
+    runtime::Runtime,
+};
+
+const KEYS: &[&str] = &["a", "b", "c"];
+
+const VALUES: &[&str] = &[
+    "",
+    "0",
+    "1",
+    "-1",
+    "12",
+    "007",
+    "-0",
+    "x",
+    "hello",
+    "9223372036854775807",
+    "-9223372036854775808",
+];
+
+const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// A command to run against both servers.
+#[derive(Clone, Debug)]
+enum Op {
+    Append(&'static str, &'static str),
+    Del(&'static str),
+    Exists(&'static str),
+    Get(&'static str),
+    Hdel(&'static str, &'static str),
+    Hexists(&'static str, &'static str),
+    Hget(&'static str, &'static str),
+    Hlen(&'static str),
+    Hset(&'static str, &'static str, &'static str),
+    Incrby(&'static str, i64),
+    Llen(&'static str),
+    Lpop(&'static str),
+    Lpush(&'static str, &'static str),
+    Lrange(&'static str, i64, i64),
+    Ltrim(&'static str, i64, i64),
+    Rpop(&'static str),
+    Rpush(&'static str, &'static str),
+    Sadd(&'static str, &'static str),
+    Scard(&'static str),
+    Set(&'static str, &'static str),
+    Sismember(&'static str, &'static str),
+    Srem(&'static str, &'static str),
+    Strlen(&'static str),
+    Type(&'static str),
+}
+
+impl Op {
+    fn args(&self) -> Vec<Vec<u8>> {
+        use Op::*;
+        let (name, args): (&str, Vec<String>) = match *self {
+            Append(key, value) => ("append", vec![key.into(), value.into()]),
+            Del(key) => ("del", vec![key.into()]),
+            Exists(key) => ("exists", vec![key.into()]),
+            Get(key) => ("get", vec![key.into()]),
+            Hdel(key, field) => ("hdel", vec![key.into(), field.into()]),
+            Hexists(key, field) => ("hexists", vec![key.into(), field.into()]),
+            Hget(key, field) => ("hget", vec![key.into(), field.into()]),
+            Hlen(key) => ("hlen", vec![key.into()]),
+            Hset(key, field, value) => ("hset", vec![key.into(), field.into(), value.into()]),
+            Incrby(key, by) => ("incrby", vec![key.into(), by.to_string()]),
+            Llen(key) => ("llen", vec![key.into()]),
+            Lpop(key) => ("lpop", vec![key.into()]),
+            Lpush(key, value) => ("lpush", vec![key.into(), value.into()]),
+            Lrange(key, start, stop) => (
+                "lrange",
+                vec![key.into(), start.to_string(), stop.to_string()],
+            ),
+            Ltrim(key, start, stop) => (
+                "ltrim",
+                vec![key.into(), start.to_string(), stop.to_string()],
+            ),
+            Rpop(key) => ("rpop", vec![key.into()]),
+            Rpush(key, value) => ("rpush", vec![key.into(), value.into()]),
+            Sadd(key, member) => ("sadd", vec![key.into(), member.into()]),
+            Scard(key) => ("scard", vec![key.into()]),
+            Set(key, value) => ("set", vec![key.into(), value.into()]),
+            Sismember(key, member) => ("sismember", vec![key.into(), member.into()]),
+            Srem(key, member) => ("srem", vec![key.into(), member.into()]),
+            Strlen(key) => ("strlen", vec![key.into()]),
+            Type(key) => ("type", vec![key.into()]),
+        };
+        std::iter::once(name.into())
+            .chain(args)
+            .map(String::into_bytes)
+            .collect()
+    }
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let key = || select(KEYS);
+    let value = || select(VALUES);
+    prop_oneof![
+        (key(), value()).prop_map(|(k, v)| Op::Append(k, v)),
+        key().prop_map(Op::Del),
+        key().prop_map(Op::Exists),
+        key().prop_map(Op::Get),
+        (key(), value()).prop_map(|(k, f)| Op::Hdel(k, f)),
+        (key(), value()).prop_map(|(k, f)| Op::Hexists(k, f)),
+        (key(), value()).prop_map(|(k, f)| Op::Hget(k, f)),
+        key().prop_map(Op::Hlen),
+        (key(), value(), value()).prop_map(|(k, f, v)| Op::Hset(k, f, v)),
+        (key(), -2i64..=2).prop_map(|(k, by)| Op::Incrby(k, by)),
+        key().prop_map(Op::Llen),
+        key().prop_map(Op::Lpop),
+        (key(), value()).prop_map(|(k, v)| Op::Lpush(k, v)),
+        (key(), -4i64..4, -4i64..4).prop_map(|(k, start, stop)| Op::Lrange(k, start, stop)),
+        (key(), -4i64..4, -4i64..4).prop_map(|(k, start, stop)| Op::Ltrim(k, start, stop)),
+        key().prop_map(Op::Rpop),
+        (key(), value()).prop_map(|(k, v)| Op::Rpush(k, v)),
+        (key(), value()).prop_map(|(k, m)| Op::Sadd(k, m)),
+        key().prop_map(Op::Scard),
+        (key(), value()).prop_map(|(k, v)| Op::Set(k, v)),
+        (key(), value()).prop_map(|(k, m)| Op::Sismember(k, m)),
+        (key(), value()).prop_map(|(k, m)| Op::Srem(k, m)),
+        key().prop_map(Op::Strlen),
+        key().prop_map(Op::Type),
+    ]
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Set(BTreeSet<Vec<u8>>),
+    Hash(BTreeMap<Vec<u8>, Vec<u8>>),
+}
+
+/// The expected behavior of each [`Op`], following Redis.
+#[derive(Debug, Default)]
+struct Model {
+    keys: BTreeMap<Vec<u8>, Value>,
+}
+
+fn ok() -> RespValue {
+    RespValue::String("OK".into())
+}
+
+fn bulk(value: &[u8]) -> RespValue {
+    RespValue::String(Bytes::copy_from_slice(value))
+}
+
+fn error(message: &str) -> RespValue {
+    RespValue::Error(Bytes::copy_from_slice(message.as_bytes()))
+}
+
+fn int(n: usize) -> RespValue {
+    RespValue::Integer(i64::try_from(n).unwrap())
+}
+
+/// Parse an integer the way Redis does, without signs, spaces, or leading zeros.
+fn parse_i64(value: &[u8]) -> Option<i64> {
+    let digits = value.strip_prefix(b"-").unwrap_or(value);
+    if digits.is_empty() || (digits[0] == b'0' && value != b"0") {
+        return None;
+    }
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+/// Resolve an inclusive range, where negative indexes count from the end, like LRANGE.
+fn range(len: usize, start: i64, stop: i64) -> Option<Range<usize>> {
+    let len = i64::try_from(len).unwrap();
+    let start = if start < 0 { len + start } else { start }.max(0);
+    let stop = if stop < 0 { len + stop } else { stop };
+    if start > stop || start >= len {
+        return None;
+    }
+    let stop = stop.min(len - 1) + 1;
+    Some(usize::try_from(start).unwrap()..usize::try_from(stop).unwrap())
+}
+
+impl Model {
+    fn apply(&mut self, op: &Op) -> RespValue {
+        use Op::*;
+        match *op {
+            Append(key, value) => match self.string(key) {
+                Ok(current) => {
+                    let mut current = current.unwrap_or_default();
+                    current.extend_from_slice(value.as_bytes());
+                    let len = current.len();
+                    self.insert(key, Value::String(current));
+                    int(len)
+                }
+                Err(error) => error,
+            },
+            Del(key) | Exists(key) => {
+                let exists = self.keys.contains_key(key.as_bytes());
+                if matches!(op, Del(_)) {
+                    self.keys.remove(key.as_bytes());
+                }
+                int(exists.into())
+            }
+            Get(key) => match self.string(key) {
+                Ok(value) => value.map_or(RespValue::Nil, |value| bulk(&value)),
+                Err(error) => error,
+            },
+            Hdel(key, field) => self.update_hash(key, |hash| {
+                int(hash.remove(field.as_bytes()).is_some().into())
+            }),
+            Hexists(key, field) => {
+                self.read_hash(key, |hash| int(hash.contains_key(field.as_bytes()).into()))
+            }
+            Hget(key, field) => self.read_hash(key, |hash| {
+                hash.get(field.as_bytes())
+                    .map_or(RespValue::Nil, |value| bulk(value))
+            }),
+            Hlen(key) => self.read_hash(key, |hash| int(hash.len())),
+            Hset(key, field, value) => self.update_hash(key, |hash| {
+                let added = hash.insert(field.into(), value.into()).is_none();
+                int(added.into())
+            }),
+            Incrby(key, by) => match self.string(key) {
+                Ok(current) => {
+                    let current = current.unwrap_or_else(|| b"0".to_vec());
+                    let Some(current) = parse_i64(&current) else {
+                        return error("ERR value is not an integer or out of range");
+                    };
+                    let Some(n) = current.checked_add(by) else {
+                        return error("ERR increment or decrement would overflow");
+                    };
+                    self.insert(key, Value::String(n.to_string().into_bytes()));
+                    RespValue::Integer(n)
+                }
+                Err(error) => error,
+            },
+            Llen(key) => self.read_list(key, |list| int(list.len())),
+            Lpop(key) => self.update_list(key, |list| {
+                list.pop_front()
+                    .map_or(RespValue::Nil, |value| bulk(&value))
+            }),
+            Lpush(key, value) => self.update_list(key, |list| {
+                list.push_front(value.into());
+                int(list.len())
+            }),
+            Lrange(key, start, stop) => self.read_list(key, |list| {
+                let range = range(list.len(), start, stop).unwrap_or_default();
+                RespValue::Array(list.range(range).map(|value| bulk(value)).collect())
+            }),
+            Ltrim(key, start, stop) => self.update_list(key, |list| {
+                let range = range(list.len(), start, stop).unwrap_or_default();
+                list.truncate(range.end);
+                list.drain(..range.start);
+                ok()
+            }),
+            Rpop(key) => self.update_list(key, |list| {
+                list.pop_back().map_or(RespValue::Nil, |value| bulk(&value))
+            }),
+            Rpush(key, value) => self.update_list(key, |list| {
+                list.push_back(value.into());
+                int(list.len())
+            }),
+            Sadd(key, member) => self.update_set(key, |set| int(set.insert(member.into()).into())),
+            Scard(key) => self.read_set(key, |set| int(set.len())),
+            Set(key, value) => {
+                self.insert(key, Value::String(value.into()));
+                ok()
+            }
+            Sismember(key, member) => {
+                self.read_set(key, |set| int(set.contains(member.as_bytes()).into()))
+            }
+            Srem(key, member) => {
+                self.update_set(key, |set| int(set.remove(member.as_bytes()).into()))
+            }
+            Strlen(key) => match self.string(key) {
+                Ok(value) => int(value.map_or(0, |value| value.len())),
+                Err(error) => error,
+            },
+            Type(key) => RespValue::String(
+                match self.keys.get(key.as_bytes()) {
+                    None => "none",
+                    Some(Value::String(_)) => "string",
+                    Some(Value::List(_)) => "list",
+                    Some(Value::Set(_)) => "set",
+                    Some(Value::Hash(_)) => "hash",
+                }
+                .into(),
+            ),
+        }
+    }
+
+    fn insert(&mut self, key: &str, value: Value) {
+        self.keys.insert(key.into(), value);
+    }
+
+    fn string(&self, key: &str) -> Result<Option<Vec<u8>>, RespValue> {
+        match self.keys.get(key.as_bytes()) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(error(WRONGTYPE)),
+        }
+    }
+}
+
+/// Read and update helpers for each collection type. Missing keys read as empty collections, and
+/// collections that are empty after an update are removed, like in Redis.
+macro_rules! collection {
+    ($read:ident, $update:ident, $variant:ident, $type:ty) => {
+        impl Model {
+            fn $read(&self, key: &str, f: impl FnOnce(&$type) -> RespValue) -> RespValue {
+                match self.keys.get(key.as_bytes()) {
+                    None => f(&<$type>::default()),
+                    Some(Value::$variant(value)) => f(value),
+                    Some(_) => error(WRONGTYPE),
+                }
+            }
+
+            fn $update(&mut self, key: &str, f: impl FnOnce(&mut $type) -> RespValue) -> RespValue {
+                let mut value = match self.keys.remove(key.as_bytes()) {
+                    None => <$type>::default(),
+                    Some(Value::$variant(value)) => value,
+                    Some(other) => {
+                        self.insert(key, other);
+                        return error(WRONGTYPE);
+                    }
+                };
+                let reply = f(&mut value);
+                if !value.is_empty() {
+                    self.insert(key, Value::$variant(value));
+                }
+                reply
+            }
+        }
+    };
+}
+
+collection!(read_hash, update_hash, Hash, BTreeMap<Vec<u8>, Vec<u8>>);
+collection!(read_list, update_list, List, VecDeque<Vec<u8>>);
+collection!(read_set, update_set, Set, BTreeSet<Vec<u8>>);
+
+/// A RESP2 connection to a server.
+struct Connection<S: AsyncRead + AsyncWrite + Send + 'static> {
+    reader: RespReader<ReadHalf<S>>,
+    writer: RespWriter<WriteHalf<S>>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + 'static> Connection<S> {
+    fn new(stream: S) -> Self {
+        let (reader, writer) = split(stream);
+        Connection {
+            reader: RespReader::new(reader, RespConfig::default()),
+            writer: RespWriter::new(writer),
+        }
+    }
+
+    async fn call(&mut self, args: &[Vec<u8>]) -> RespValue {
+        self.writer.write_array(args.len()).await.unwrap();
+        for arg in args {
+            self.writer.write_blob_string(arg).await.unwrap();
+        }
+        self.writer.flush().await.unwrap();
+        self.reader
+            .value()
+            .await
+            .unwrap()
+            .expect("connection closed")
+    }
+}
+
+/// Where the expected replies come from.
+enum Reference {
+    Model(Model),
+    Redis(Connection<TcpStream>),
+}
+
+impl Reference {
+    async fn connect() -> Self {
+        let Ok(addr) = std::env::var("BRADIS_REFERENCE_ADDR") else {
+            return Reference::Model(Model::default());
+        };
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut redis = Connection::new(stream);
+        assert_eq!(redis.call(&[b"flushall".to_vec()]).await, ok());
+        Reference::Redis(redis)
+    }
+
+    async fn call(&mut self, op: &Op) -> RespValue {
+        match self {
+            Reference::Model(model) => model.apply(op),
+            Reference::Redis(redis) => redis.call(&op.args()).await,
+        }
+    }
+}
+
+/// Run `ops` against a new server and the reference, returning the first op whose replies differ.
+async fn compare(ops: &[Op]) -> Option<(usize, RespValue, RespValue)> {
+    let server = Server::default();
+    let (local, remote) = duplex(100_000);
+    server.connect(remote, None);
+    let mut bradis = Connection::new(local);
+    let mut reference = Reference::connect().await;
+
+    for (index, op) in ops.iter().enumerate() {
+        let actual = bradis.call(&op.args()).await;
+        let expected = reference.call(op).await;
+        if actual != expected {
+            return Some((index, actual, expected));
+        }
+    }
+    None
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn matches_reference(ops in vec(op(), 1..64)) {
+        let runtime = Runtime::new().unwrap();
+        let mismatch = runtime.block_on(compare(&ops));
+        if let Some((index, actual, expected)) = mismatch {
+            prop_assert_eq!(
+                actual,
+                expected,
+                "replies differ for {:?} after {:?}",
+                &ops[index],
+                &ops[..index]
+            );
+        }
+    }
+}
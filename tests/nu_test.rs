@@ -20,6 +20,7 @@ nu_test!(bitops, "bitops.nu");
 nu_test!(client, "client.nu");
 nu_test!(config, "config.nu");
 nu_test!(db, "db.nu");
+nu_test!(errors, "errors.nu");
 nu_test!(eval, "eval.nu");
 nu_test!(expire, "expire.nu");
 nu_test!(hash, "hash.nu");
@@ -28,6 +29,7 @@ nu_test!(list, "list.nu");
 nu_test!(multi, "multi.nu");
 nu_test!(protocol, "protocol.nu");
 nu_test!(pubsub, "pubsub.nu");
+nu_test!(scripting, "scripting.nu");
 nu_test!(server, "server.nu");
 nu_test!(set, "set.nu");
 nu_test!(sorted_set, "sorted_set.nu");
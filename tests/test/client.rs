@@ -1,5 +1,5 @@
 use crate::test::{TestError, TestResult};
-use respite::{RespConfig, RespReader, RespWriter};
+use respite::{RespConfig, RespReader, RespValue, RespWriter};
 use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
 
 #[derive(Debug)]
@@ -15,9 +15,11 @@ impl TestClient {
         let mut writer = RespWriter::new(writer);
         let mut reader = RespReader::new(reader, RespConfig::default());
 
-        writer.write_inline(b"client id").await?;
+        // A rejected connection replies with an error and closes, which may fail the write.
+        let written = writer.write_inline(b"client id").await;
         let value = reader.value().await?.ok_or(TestError::ReaderClosed)?;
         let id = value.integer().ok_or(TestError::UnexpectedValue(value))?;
+        written?;
 
         Ok(Self {
             id,
@@ -25,4 +27,70 @@ impl TestClient {
             writer: Some(writer),
         })
     }
+
+    /// Send a command without waiting for its reply.
+    pub async fn send(&mut self, args: &[&[u8]]) -> TestResult<()> {
+        let writer = self.writer.as_mut().ok_or(TestError::WriterDisconnected)?;
+        writer.write_array(args.len()).await?;
+        for arg in args {
+            writer.write_blob_string(arg).await?;
+        }
+        Ok(())
+    }
+
+    /// Read the next reply.
+    pub async fn read(&mut self) -> TestResult<RespValue> {
+        self.reader.value().await?.ok_or(TestError::ReaderClosed)
+    }
+
+    /// Send a command and read its reply.
+    pub async fn command(&mut self, args: &[&[u8]]) -> TestResult<RespValue> {
+        self.send(args).await?;
+        self.read().await
+    }
+
+    /// Send every command before reading any replies, then read a reply for each of them.
+    pub async fn pipeline(&mut self, commands: &[Vec<&[u8]>]) -> TestResult<Vec<RespValue>> {
+        for args in commands {
+            self.send(args).await?;
+        }
+        let mut replies = Vec::with_capacity(commands.len());
+        for _ in commands {
+            replies.push(self.read().await?);
+        }
+        Ok(replies)
+    }
+
+    /// The encoding of a key, or `None` if it doesn't exist.
+    pub async fn encoding(&mut self, key: &[u8]) -> TestResult<Option<String>> {
+        match self.command(&[b"object", b"encoding", key]).await? {
+            RespValue::Nil => Ok(None),
+            value => match value.text() {
+                Some(text) => Ok(Some(text.to_owned())),
+                None => Err(TestError::UnexpectedValue(value)),
+            },
+        }
+    }
+
+    /// The length of a key, using the length command for its type, or zero if it doesn't exist.
+    pub async fn len(&mut self, key: &[u8]) -> TestResult<i64> {
+        let value = self.command(&[b"type", key]).await?;
+        let command: &[u8] = match value.text() {
+            Some("none") => return Ok(0),
+            Some("string") => b"strlen",
+            Some("list") => b"llen",
+            Some("set") => b"scard",
+            Some("hash") => b"hlen",
+            Some("zset") => b"zcard",
+            _ => return Err(TestError::UnexpectedValue(value)),
+        };
+        let value = self.command(&[command, key]).await?;
+        value.integer().ok_or(TestError::UnexpectedValue(value))
+    }
+
+    /// The TTL of a key in milliseconds, with `-1` for no TTL and `-2` for a missing key.
+    pub async fn pttl(&mut self, key: &[u8]) -> TestResult<i64> {
+        let value = self.command(&[b"pttl", key]).await?;
+        value.integer().ok_or(TestError::UnexpectedValue(value))
+    }
 }
@@ -1,15 +1,56 @@
 mod client;
 mod client_closed;
 mod client_id;
+mod encoding;
+mod len;
+mod pipeline;
 mod read_value;
 mod run;
 mod run_inline;
 mod test;
+mod ttl;
+mod with_timeout;
+
+use crate::test::{Test, TestClient, TestError, TestResult};
+use nu_protocol::{ShellError, engine::Call};
+use std::sync::Mutex;
+use tokio::{runtime::Handle, time::timeout};
 
 pub use client::ClientCommand;
 pub use client_closed::ClientClosedCommand;
 pub use client_id::ClientIdCommand;
+pub use encoding::EncodingCommand;
+pub use len::LenCommand;
+pub use pipeline::PipelineCommand;
 pub use read_value::ReadValueCommand;
 pub use run::RunCommand;
 pub use run_inline::RunInlineCommand;
 pub use test::TestCommand;
+pub use ttl::TtlCommand;
+pub use with_timeout::WithTimeoutCommand;
+
+/// Run `f` with the current client and wait for it, failing `call` if it times out.
+fn query<T>(
+    test: &Mutex<Option<Test>>,
+    call: &Call,
+    f: impl AsyncFnOnce(&mut TestClient) -> TestResult<T>,
+) -> TestResult<T> {
+    let mut guard = test.lock().unwrap();
+    let test = guard.as_mut().unwrap();
+    let duration = test.timeout;
+    let client = test.client()?;
+    let Ok(result) = Handle::current().block_on(timeout(duration, f(client))) else {
+        return Err(TestError::Timeout(call.span()));
+    };
+    result
+}
+
+/// Fail `call` because it expected one value and got another.
+fn mismatch(expected: &impl ToString, actual: &impl ToString, call: &Call) -> ShellError {
+    TestError::Mismatch {
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+        span: call.span(),
+    }
+    .into()
+}
@@ -44,7 +44,10 @@ impl Command for ClientCommand {
         let current = test.current;
         test.current = index;
         let handle = Handle::current();
-        handle.block_on(test.connect())?;
+        if let Err(error) = handle.block_on(test.connect()) {
+            test.current = current;
+            return Err(error.into());
+        }
         drop(guard);
 
         let eval_block = get_eval_block(state);
@@ -1,4 +1,4 @@
-use crate::test::{TIMEOUT, Test, TestError};
+use crate::test::{Test, TestError};
 use std::sync::Mutex;
 
 use nu_engine::CallExt;
@@ -39,12 +39,13 @@ impl Command for ClientClosedCommand {
 
         let mut guard = self.0.lock().unwrap();
         let test = guard.as_mut().unwrap();
+        let duration = test.timeout;
         let client = test
             .clients
             .get_mut(&index)
             .ok_or(TestError::MissingClient)?;
         let handle = Handle::current();
-        let Ok(value) = handle.block_on(timeout(TIMEOUT, client.reader.value())) else {
+        let Ok(value) = handle.block_on(timeout(duration, client.reader.value())) else {
             return Err(TestError::Timeout(call.span()).into());
         };
         let closed = value.map_err(TestError::from)?.is_none();
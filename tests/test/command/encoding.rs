@@ -0,0 +1,53 @@
+use super::{mismatch, query};
+use crate::test::Test;
+use std::sync::Mutex;
+
+use nu_engine::CallExt;
+use nu_protocol::{
+    Category, PipelineData, ShellError, Signature, SyntaxShape, Type,
+    engine::{Call, Command, EngineState, Stack},
+};
+use triomphe::Arc;
+
+#[derive(Clone)]
+pub struct EncodingCommand(pub Arc<Mutex<Option<Test>>>);
+
+impl Command for EncodingCommand {
+    fn name(&self) -> &'static str {
+        "encoding"
+    }
+
+    fn description(&self) -> &'static str {
+        "expect the encoding of a key"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("encoding")
+            .input_output_types(vec![(Type::Any, Type::Nothing)])
+            .required("key", SyntaxShape::String, "the key to check")
+            .required("expected", SyntaxShape::String, "the expected encoding")
+            .category(Category::Custom("bradis".into()))
+    }
+
+    fn run(
+        &self,
+        state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let key: String = call.req(state, stack, 0)?;
+        let expected: String = call.req(state, stack, 1)?;
+
+        let actual = query(&self.0, call, async |client| {
+            client.encoding(key.as_bytes()).await
+        })?
+        .unwrap_or_else(|| "nil".into());
+
+        if actual != expected {
+            return Err(mismatch(&expected, &actual, call));
+        }
+
+        Ok(PipelineData::Empty)
+    }
+}
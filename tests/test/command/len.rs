@@ -0,0 +1,52 @@
+use super::{mismatch, query};
+use crate::test::Test;
+use std::sync::Mutex;
+
+use nu_engine::CallExt;
+use nu_protocol::{
+    Category, PipelineData, ShellError, Signature, SyntaxShape, Type,
+    engine::{Call, Command, EngineState, Stack},
+};
+use triomphe::Arc;
+
+#[derive(Clone)]
+pub struct LenCommand(pub Arc<Mutex<Option<Test>>>);
+
+impl Command for LenCommand {
+    fn name(&self) -> &'static str {
+        "len"
+    }
+
+    fn description(&self) -> &'static str {
+        "expect the length of a key, whatever its type"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("len")
+            .input_output_types(vec![(Type::Any, Type::Nothing)])
+            .required("key", SyntaxShape::String, "the key to check")
+            .required("expected", SyntaxShape::Int, "the expected length")
+            .category(Category::Custom("bradis".into()))
+    }
+
+    fn run(
+        &self,
+        state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let key: String = call.req(state, stack, 0)?;
+        let expected: i64 = call.req(state, stack, 1)?;
+
+        let actual = query(&self.0, call, async |client| {
+            client.len(key.as_bytes()).await
+        })?;
+
+        if actual != expected {
+            return Err(mismatch(&expected, &actual, call));
+        }
+
+        Ok(PipelineData::Empty)
+    }
+}
@@ -0,0 +1,85 @@
+use super::read_value::to_value;
+use crate::test::{Test, TestError};
+use std::sync::Mutex;
+use tokio::time::timeout;
+
+use nu_engine::CallExt;
+use nu_protocol::{
+    Category, PipelineData, ShellError, Signature, SyntaxShape, Type, Value,
+    engine::{Call, Command, EngineState, Stack},
+};
+use tokio::runtime::Handle;
+use triomphe::Arc;
+
+#[derive(Clone)]
+pub struct PipelineCommand(pub Arc<Mutex<Option<Test>>>);
+
+/// The bytes of each command's arguments. Integers are allowed too, since `[rpush x 1]` parses
+/// `1` as an integer.
+#[allow(clippy::result_large_err)]
+fn arguments(commands: &[Vec<Value>]) -> Result<Vec<Vec<Vec<u8>>>, ShellError> {
+    let mut result = Vec::with_capacity(commands.len());
+    for args in commands {
+        let mut bytes = Vec::with_capacity(args.len());
+        for arg in args {
+            bytes.push(match arg {
+                Value::Int { val, .. } => val.to_string().into_bytes(),
+                arg => arg.coerce_binary()?.to_vec(),
+            });
+        }
+        result.push(bytes);
+    }
+    Ok(result)
+}
+
+impl Command for PipelineCommand {
+    fn name(&self) -> &'static str {
+        "pipeline"
+    }
+
+    fn description(&self) -> &'static str {
+        "send several redis commands, then read all of their replies"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("pipeline")
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .required(
+                "commands",
+                SyntaxShape::List(Box::new(SyntaxShape::List(Box::new(SyntaxShape::Any)))),
+                "the commands to send, each a list of arguments",
+            )
+            .category(Category::Custom("bradis".into()))
+    }
+
+    fn run(
+        &self,
+        state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let commands: Vec<Vec<Value>> = call.req(state, stack, 0)?;
+        let commands = arguments(&commands)?;
+        let commands: Vec<Vec<&[u8]>> = commands
+            .iter()
+            .map(|args| args.iter().map(Vec::as_slice).collect())
+            .collect();
+
+        let mut guard = self.0.lock().unwrap();
+        let test = guard.as_mut().unwrap();
+        let duration = test.timeout;
+        let client = test.client()?;
+        let handle = Handle::current();
+        let Ok(replies) = handle.block_on(timeout(duration, client.pipeline(&commands))) else {
+            return Err(TestError::Timeout(call.span()).into());
+        };
+        let replies = replies?
+            .iter()
+            .map(|reply| to_value(reply, call.span()))
+            .collect();
+        drop(guard);
+
+        Ok(PipelineData::Value(Value::list(replies, call.span()), None))
+    }
+}
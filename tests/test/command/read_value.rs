@@ -1,4 +1,4 @@
-use crate::test::{TIMEOUT, Test, TestError};
+use crate::test::{Test, TestError};
 use std::{str::from_utf8, sync::Mutex};
 use tokio::time::timeout;
 
@@ -37,7 +37,7 @@ fn primitive_to_value(resp: &RespPrimitive, internal_span: Span) -> Value {
     }
 }
 
-fn to_value(resp: &RespValue, internal_span: Span) -> Value {
+pub fn to_value(resp: &RespValue, internal_span: Span) -> Value {
     use RespValue::*;
     match resp {
         Nil => Value::Nothing { internal_span },
@@ -62,6 +62,32 @@ fn to_value(resp: &RespValue, internal_span: Span) -> Value {
             val: **f,
             internal_span,
         },
+        Boolean(b) => Value::Bool {
+            val: *b,
+            internal_span,
+        },
+        Bignum(value) => {
+            let mut record = Record::new();
+            let value = from_utf8(value).unwrap().into();
+            record.insert(
+                "type",
+                Value::String {
+                    val: "bignum".into(),
+                    internal_span,
+                },
+            );
+            record.insert(
+                "value",
+                Value::String {
+                    val: value,
+                    internal_span,
+                },
+            );
+            Value::Record {
+                val: record.into(),
+                internal_span,
+            }
+        }
         Verbatim(encoding, value) => {
             let mut record = Record::new();
             let encoding = from_utf8(encoding).unwrap().into();
@@ -202,7 +228,7 @@ fn to_value(resp: &RespValue, internal_span: Span) -> Value {
                 internal_span,
             }
         }
-        _ => {
+        Attribute(_) => {
             let mut record = Record::new();
             record.insert(
                 "type",
@@ -244,7 +270,7 @@ impl Command for ReadValueCommand {
         let mut guard = self.0.lock().unwrap();
         let test = guard.as_mut().unwrap();
         let handle = Handle::current();
-        let Ok(value) = handle.block_on(timeout(TIMEOUT, test.read_value())) else {
+        let Ok(value) = handle.block_on(timeout(test.timeout, test.read_value())) else {
             return Err(TestError::Timeout(call.span()).into());
         };
         let value = to_value(&value?, call.span());
@@ -0,0 +1,57 @@
+use super::{mismatch, query};
+use crate::test::Test;
+use std::sync::Mutex;
+
+use nu_engine::CallExt;
+use nu_protocol::{
+    Category, PipelineData, ShellError, Signature, SyntaxShape, Type,
+    engine::{Call, Command, EngineState, Stack},
+};
+use triomphe::Arc;
+
+/// How far, in milliseconds, a TTL may drift from the expected value.
+const DELTA: i64 = 50;
+
+#[derive(Clone)]
+pub struct TtlCommand(pub Arc<Mutex<Option<Test>>>);
+
+impl Command for TtlCommand {
+    fn name(&self) -> &'static str {
+        "ttl"
+    }
+
+    fn description(&self) -> &'static str {
+        "expect the ttl of a key, with a little room for timing"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("ttl")
+            .input_output_types(vec![(Type::Any, Type::Nothing)])
+            .required("key", SyntaxShape::String, "the key to check")
+            .required("ttl", SyntaxShape::Int, "the expected ttl in seconds")
+            .category(Category::Custom("bradis".into()))
+    }
+
+    fn run(
+        &self,
+        state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let key: String = call.req(state, stack, 0)?;
+        let ttl: i64 = call.req(state, stack, 1)?;
+
+        let actual = query(&self.0, call, async |client| {
+            client.pttl(key.as_bytes()).await
+        })?;
+
+        let expected = ttl * 1000;
+        if !(expected - DELTA..=expected + DELTA).contains(&actual) {
+            let expected = format!("{}..{}", expected - DELTA, expected + DELTA);
+            return Err(mismatch(&expected, &actual, call));
+        }
+
+        Ok(PipelineData::Empty)
+    }
+}
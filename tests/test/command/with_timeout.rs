@@ -0,0 +1,62 @@
+use crate::test::Test;
+use std::{sync::Mutex, time::Duration};
+
+use nu_engine::{CallExt, get_eval_block};
+use nu_protocol::{
+    Category, PipelineData, ShellError, Signature, SyntaxShape, Type,
+    engine::{Call, Closure, Command, EngineState, Stack},
+};
+use triomphe::Arc;
+
+#[derive(Clone)]
+pub struct WithTimeoutCommand(pub Arc<Mutex<Option<Test>>>);
+
+impl Command for WithTimeoutCommand {
+    fn name(&self) -> &'static str {
+        "with-timeout"
+    }
+
+    fn description(&self) -> &'static str {
+        "wait longer for replies, for commands with large arguments or replies"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("with-timeout")
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .required(
+                "timeout",
+                SyntaxShape::Duration,
+                "how long to wait for each reply",
+            )
+            .required("body", SyntaxShape::Closure(None), "body to execute")
+            .category(Category::Custom("bradis".into()))
+    }
+
+    fn run(
+        &self,
+        state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let nanos: i64 = call.req(state, stack, 0)?;
+        let block: Closure = call.req(state, stack, 1)?;
+
+        let mut guard = self.0.lock().unwrap();
+        let test = guard.as_mut().unwrap();
+        let previous = test.timeout;
+        test.timeout = Duration::from_nanos(nanos.try_into().unwrap_or_default());
+        drop(guard);
+
+        let eval_block = get_eval_block(state);
+        let block = state.get_block(block.block_id);
+        let result = eval_block(state, stack, block, input);
+
+        // Restore the timeout even if the body failed.
+        let mut guard = self.0.lock().unwrap();
+        guard.as_mut().unwrap().timeout = previous;
+        drop(guard);
+
+        result
+    }
+}
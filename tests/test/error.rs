@@ -10,6 +10,14 @@ pub enum TestError {
     #[error(transparent)]
     Resp(#[from] RespError),
 
+    #[error("expected {expected} but got {actual}")]
+    Mismatch {
+        expected: String,
+        actual: String,
+        #[label("here")]
+        span: Span,
+    },
+
     #[error("timed out")]
     Timeout(#[label("here")] Span),
 
@@ -22,7 +30,7 @@ pub enum TestError {
     #[error("invalid info")]
     InvalidInfo,
 
-    #[error("unexpected value")]
+    #[error("unexpected value {0:?}")]
     UnexpectedValue(RespValue),
 
     #[error("running only a subset of tests")]
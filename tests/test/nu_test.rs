@@ -1,7 +1,7 @@
-use crate::test::{TestClient, TestError, TestResult, command::*};
-use std::{env::current_dir, sync::Mutex};
+use crate::test::{TIMEOUT, TestClient, TestError, TestResult, command::*};
+use std::{env::current_dir, sync::Mutex, time::Duration};
 
-use bradis::{Addr, Server};
+use bradis::{Addr, Endpoint, Server};
 use hashbrown::HashMap;
 use miette::{Diagnostic, MietteError, SourceSpan, SpanContents};
 use nu_cli::Print;
@@ -26,7 +26,7 @@ impl From<TestError> for ShellError {
             error: format!("{value}"),
             msg: format!("{value}"),
             span: match value {
-                TestError::Timeout(span) => Some(span),
+                TestError::Mismatch { span, .. } | TestError::Timeout(span) => Some(span),
                 _ => None,
             },
             help: None,
@@ -148,10 +148,15 @@ fn run_inner(state: &mut EngineState, name: &str, source: &str) -> Result<(), Nu
     working_set.add_decl(Box::new(ClientCommand(test.clone())));
     working_set.add_decl(Box::new(ClientClosedCommand(test.clone())));
     working_set.add_decl(Box::new(ClientIdCommand(test.clone())));
+    working_set.add_decl(Box::new(EncodingCommand(test.clone())));
+    working_set.add_decl(Box::new(LenCommand(test.clone())));
+    working_set.add_decl(Box::new(PipelineCommand(test.clone())));
     working_set.add_decl(Box::new(ReadValueCommand(test.clone())));
     working_set.add_decl(Box::new(RunCommand(test.clone())));
     working_set.add_decl(Box::new(RunInlineCommand(test.clone())));
     working_set.add_decl(Box::new(TestCommand(test.clone())));
+    working_set.add_decl(Box::new(TtlCommand(test.clone())));
+    working_set.add_decl(Box::new(WithTimeoutCommand(test.clone())));
     working_set.add_decl(Box::new(Print));
     let file_id = working_set.add_file("bradis".into(), include_bytes!("../bradis.nu"));
     _ = working_set.add_virtual_path("bradis".into(), VirtualPath::File(file_id));
@@ -174,6 +179,9 @@ pub struct Test {
     pub clients: HashMap<usize, TestClient>,
     pub current: usize,
     pub server: Server,
+
+    /// How long to wait for a reply, which `with-timeout` can raise for slow commands.
+    pub timeout: Duration,
 }
 
 impl Default for Test {
@@ -182,6 +190,7 @@ impl Default for Test {
             clients: HashMap::new(),
             current: 1,
             server: Server::default(),
+            timeout: TIMEOUT,
         }
     }
 }
@@ -200,8 +209,8 @@ impl Test {
         }
         let (remote, local) = duplex(2usize.pow(8));
         let addr = Addr {
-            local: format!("127.0.0.1:{index}").parse().unwrap(),
-            peer: format!("1.2.3.4:{index}").parse().unwrap(),
+            local: Endpoint::Tcp(format!("127.0.0.1:{index}").parse().unwrap()),
+            peer: Endpoint::Tcp(format!("1.2.3.4:{index}").parse().unwrap()),
         };
         self.server.connect(local, Some(addr));
         let client = TestClient::connect(remote).await?;
@@ -217,8 +226,6 @@ impl Test {
     }
 
     pub async fn read_value(&mut self) -> TestResult<RespValue> {
-        let reader = &mut self.client()?.reader;
-        let value = reader.value();
-        value.await?.ok_or(TestError::ReaderClosed)
+        self.client()?.read().await
     }
 }